@@ -30,7 +30,7 @@ fi
 mkdir "$DEST"
 mkdir "$GIT_ISH"
 
-for artifact in $(find artifacts/ -type f); do
+find artifacts/ -type f -print0 | while IFS= read -r -d '' artifact; do
   chmod +x "$artifact"
   cp "$artifact" "$DEST"/
   cp "$artifact" "$GIT_ISH"/
@@ -40,7 +40,7 @@ done
 check_reupload() {
   dest="$1"
 
-  for file in $(find "$dest" -type f); do
+  while IFS= read -r -d '' artifact; do
     artifact_path="$dest"/"$(basename "$artifact")"
     md5="$(md5sum "$artifact" | cut -d' ' -f1)"
     obj="$(aws s3api head-object --bucket "$AWS_BUCKET" --key "$artifact_path" || echo '{}')"
@@ -51,7 +51,7 @@ check_reupload() {
       # If we already uploaded to a tag, that's probably bad
       is_tag && exit 1 || exit 0
     fi
-  done
+  done < <(find "$dest" -type f -print0)
 }
 
 check_reupload "$DEST"
@@ -65,7 +65,7 @@ if ! is_tag; then
 fi
 
 
-cat <<-EOF >> $GITHUB_STEP_SUMMARY
+cat <<-EOF >> "$GITHUB_STEP_SUMMARY"
 This commit's flake-checker binaries can be fetched from:
 
 Intel macOS:
@@ -86,7 +86,7 @@
               name = "check-nixpkgs-fmt";
               runtimeInputs = with pkgs; [ git nixpkgs-fmt ];
               text = ''
-                nixpkgs-fmt --check "$(git ls-files '*.nix')"
+                git ls-files -z '*.nix' | xargs -0 nixpkgs-fmt --check
               '';
             };
             check-rustfmt = pkgs.writeShellApplication {
@@ -97,7 +97,7 @@
             get-ref-statuses = pkgs.writeShellApplication {
               name = "get-ref-statuses";
               runtimeInputs = with pkgs; [ rustToolchain ];
-              text = "cargo run --features ref-statuses -- --get-ref-statuses";
+              text = "cargo run -- refs fetch";
             };
           in
           pkgs.mkShell {
@@ -10,10 +10,11 @@
 //! [detsys]: https://determinate.systems
 //! [lock]: https://zero-to-nix.com/concepts/flakes#lockfile
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
@@ -30,6 +31,44 @@ pub enum FlakeLockParseError {
     /// The specified `flake.lock` file couldn't be parsed as JSON.
     #[error("couldn't parse the flake.lock file as json: {0}")]
     Json(#[from] serde_json::Error),
+    /// The `root` of the `flake.lock` is missing from `nodes` or isn't a [RootNode].
+    #[error("the flake.lock appears corrupt: {0}; try regenerating it with `nix flake lock`")]
+    CorruptRoot(String),
+}
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// The number of whole days between a `lastModified` timestamp and `now`, both expressed in seconds
+/// since the Unix epoch.
+///
+/// ```
+/// use parse_flake_lock::num_days_old;
+///
+/// let last_modified = 1689078114; // 2023-07-11T12:21:54Z
+/// let now = last_modified + 45 * 24 * 60 * 60 + 3600;
+/// assert_eq!(num_days_old(last_modified, now), 45);
+/// ```
+pub fn num_days_old(timestamp: i64, now: i64) -> i64 {
+    (now - timestamp) / SECONDS_PER_DAY
+}
+
+/// The contents of a `flake.lock` re-serialized the way that Nix writes them: pretty-printed with
+/// two-space indentation, object keys in sorted order, and a trailing newline. A `flake.lock` that
+/// was last written by Nix is identical to its canonical format.
+///
+/// ```
+/// use parse_flake_lock::canonical_format;
+///
+/// let contents = r#"{"version": 7, "root": "root", "nodes": {"root": {}}}"#;
+/// assert_eq!(
+///     canonical_format(contents).unwrap(),
+///     "{\n  \"nodes\": {\n    \"root\": {}\n  },\n  \"root\": \"root\",\n  \"version\": 7\n}\n"
+/// );
+/// ```
+pub fn canonical_format(contents: &str) -> Result<String, FlakeLockParseError> {
+    // Without serde_json's `preserve_order` feature, object keys are sorted
+    let value: serde_json::Value = serde_json::from_str(contents)?;
+    Ok(format!("{}\n", serde_json::to_string_pretty(&value)?))
 }
 
 /// A Rust representation of a Nix [`flake.lock`
@@ -41,6 +80,8 @@ pub struct FlakeLock {
     /// The `root` of the `flake.lock` with all input references resolved into the corresponding
     /// [Node]s represented by the `nodes` field.
     pub root: HashMap<String, Node>,
+    /// The key of the root node in `nodes` (usually `"root"`).
+    pub root_key: String,
     /// The version of the `flake.lock` (incremented whenever the `flake.nix` dependencies are
     /// updated).
     pub version: usize,
@@ -104,30 +145,12 @@ impl<'de> Deserialize<'de> for FlakeLock {
                 let root: String = root.ok_or_else(|| de::Error::missing_field("root"))?;
                 let version: usize = version.ok_or_else(|| de::Error::missing_field("version"))?;
 
-                let mut root_nodes = HashMap::new();
-                let root_node = &nodes[&root];
-                let Node::Root(root_node) = root_node else {
-                    return Err(de::Error::custom(format!(
-                        "root node was not a Root node, but was a {} node",
-                        root_node.variant()
-                    )));
-                };
-
-                for (root_name, root_input) in root_node.inputs.iter() {
-                    let inputs: VecDeque<String> = match root_input.clone() {
-                        Input::String(s) => [s].into(),
-                        Input::List(keys) => keys.into(),
-                    };
-
-                    let real_node = chase_input_node(&nodes, inputs).map_err(|e| {
-                        de::Error::custom(format!("failed to chase input {}: {:?}", root_name, e))
-                    })?;
-                    root_nodes.insert(root_name.clone(), real_node.clone());
-                }
+                let root_nodes = resolve_root(&nodes, &root).map_err(de::Error::custom)?;
 
                 Ok(FlakeLock {
                     nodes,
                     root: root_nodes,
+                    root_key: root,
                     version,
                 })
             }
@@ -137,30 +160,69 @@ impl<'de> Deserialize<'de> for FlakeLock {
     }
 }
 
+// The root's inputs in `nodes`, with each input resolved to the node that it refers to.
+fn resolve_root(
+    nodes: &HashMap<String, Node>,
+    root: &str,
+) -> Result<HashMap<String, Node>, String> {
+    let Some(root_node) = nodes.get(root) else {
+        return Err(format!("root node {root} is missing from nodes"));
+    };
+    let Node::Root(root_node) = root_node else {
+        return Err(format!(
+            "root node was not a Root node, but was a {} node",
+            root_node.variant()
+        ));
+    };
+
+    let mut root_nodes = HashMap::new();
+    for (root_name, root_input) in root_node.inputs.iter() {
+        let real_node = resolve_root_input(nodes, root_input)
+            .map_err(|e| format!("failed to chase input {}: {:?}", root_name, e))?;
+        if let Some(real_node) = real_node {
+            root_nodes.insert(root_name.clone(), real_node.clone());
+        }
+    }
+
+    Ok(root_nodes)
+}
+
+// The node in `nodes` that an input of the root refers to, or `None` for an empty `follows`, which
+// refers to the flake itself rather than to an input.
+fn resolve_root_input<'a>(
+    nodes: &'a HashMap<String, Node>,
+    root_input: &Input,
+) -> Result<Option<&'a Node>, FlakeLockParseError> {
+    let inputs: VecDeque<String> = match root_input.clone() {
+        Input::String(s) => [s].into(),
+        Input::List(keys) if keys.is_empty() => return Ok(None),
+        Input::List(keys) => keys.into(),
+    };
+
+    chase_input_node(nodes, inputs).map(Some)
+}
+
 fn chase_input_node(
     nodes: &HashMap<String, Node>,
-    mut inputs: VecDeque<String>,
+    inputs: VecDeque<String>,
 ) -> Result<&Node, FlakeLockParseError> {
-    let Some(next_input) = inputs.pop_front() else {
+    let key = chase_input_key(nodes, inputs)?;
+    nodes.get(&key).ok_or_else(|| missing_node(&key))
+}
+
+// Follow a chain of input names (as in `follows` declarations) to the key of the node in `nodes`
+// that it resolves to.
+fn chase_input_key(
+    nodes: &HashMap<String, Node>,
+    mut inputs: VecDeque<String>,
+) -> Result<String, FlakeLockParseError> {
+    let Some(mut key) = inputs.pop_front() else {
         unreachable!("there should always be at least one input");
     };
 
-    let mut node = &nodes[&next_input];
     for input in inputs {
-        let maybe_node_inputs = match node {
-            Node::Root(_) => None,
-            Node::Repo(node) => node.inputs.to_owned(),
-            Node::Indirect(node) => node.inputs.to_owned(),
-            Node::Path(node) => node.inputs.to_owned(),
-            Node::Tarball(node) => node.inputs.to_owned(),
-            Node::Fallthrough(node) => match node.get("inputs") {
-                Some(node_inputs) => serde_json::from_value(node_inputs.clone())
-                    .map_err(FlakeLockParseError::Json)?,
-                None => None,
-            },
-        };
-
-        let node_inputs = match maybe_node_inputs {
+        let node = nodes.get(&key).ok_or_else(|| missing_node(&key))?;
+        let node_inputs = match node.inputs()? {
             Some(node_inputs) => node_inputs,
             None => {
                 return Err(FlakeLockParseError::Invalid(format!(
@@ -170,25 +232,592 @@ fn chase_input_node(
             }
         };
 
-        let next_inputs = &node_inputs[&input];
-        node = match next_inputs {
-            Input::String(s) => &nodes[s],
-            Input::List(inputs) => chase_input_node(nodes, inputs.to_owned().into())?,
+        key = match node_inputs.get(&input) {
+            Some(Input::String(s)) => s.clone(),
+            Some(Input::List(inputs)) => chase_input_key(nodes, inputs.to_owned().into())?,
+            None => {
+                return Err(FlakeLockParseError::Invalid(format!(
+                    "the node `{key}` has no input named `{input}`"
+                )));
+            }
         };
     }
 
+    Ok(key)
+}
+
+fn missing_node(key: &str) -> FlakeLockParseError {
+    FlakeLockParseError::Invalid(format!("the node `{key}` is missing from `nodes`"))
+}
+
+// A node parsed from its JSON `value` on its own, for [FlakeLock::from_str_lenient]. Besides being
+// a JSON object, the node's inputs have to be well-formed, since following inputs relies on them.
+fn parse_node(value: serde_json::Value) -> Result<Node, FlakeLockParseError> {
+    if !value.is_object() {
+        return Err(FlakeLockParseError::Invalid(String::from(
+            "the node isn't a JSON object",
+        )));
+    }
+
+    let node: Node = serde_json::from_value(value)?;
+    node.inputs()?;
     Ok(node)
 }
 
+// Remove the inputs of `nodes` that can't be resolved, like those that lead to a node that
+// [FlakeLock::from_str_lenient] left out, so that walking the graph doesn't fail on them. Removing
+// an input can leave another input that follows it dangling in turn, so this repeats until every
+// input that's left can be resolved.
+fn remove_dangling_inputs(nodes: &mut HashMap<String, Node>) {
+    loop {
+        let mut dangling = vec![];
+        for (key, node) in nodes.iter() {
+            let Ok(Some(node_inputs)) = node.inputs() else {
+                continue;
+            };
+            for (name, input) in node_inputs {
+                let resolves = match input {
+                    Input::String(s) => nodes.contains_key(&s),
+                    Input::List(inputs) if inputs.is_empty() => true,
+                    Input::List(inputs) => chase_input_key(nodes, inputs.into())
+                        .is_ok_and(|input_key| nodes.contains_key(&input_key)),
+                };
+                if !resolves {
+                    dangling.push((key.clone(), name));
+                }
+            }
+        }
+
+        if dangling.is_empty() {
+            return;
+        }
+
+        for (key, name) in dangling {
+            if let Some(node) = nodes.get_mut(&key) {
+                node.remove_input(&name);
+            }
+        }
+    }
+}
+
+/// The top-level fields of a `flake.lock` with the nodes left unparsed.
+#[derive(Deserialize)]
+struct RawFlakeLock {
+    nodes: serde_json::Map<String, serde_json::Value>,
+    root: String,
+    version: usize,
+}
+
 impl FlakeLock {
     /// Instantiate a new [FlakeLock] from the provided [Path].
     pub fn new(path: &Path) -> Result<Self, FlakeLockParseError> {
         let flake_lock_file = read_to_string(path)?;
-        let flake_lock: FlakeLock = serde_json::from_str(&flake_lock_file)?;
+        flake_lock_file.parse()
+    }
+
+    /// Like [FlakeLock::new], but tolerant of nodes that can't be parsed (see
+    /// [FlakeLock::from_str_lenient]).
+    pub fn new_lenient(
+        path: &Path,
+    ) -> Result<(Self, Vec<(String, FlakeLockParseError)>), FlakeLockParseError> {
+        let flake_lock_file = read_to_string(path)?;
+        Self::from_str_lenient(&flake_lock_file)
+    }
+
+    /// Parse a [FlakeLock] from the raw contents of a `flake.lock` file as its [FromStr]
+    /// implementation does, but rather than failing on a node that can't be parsed, leave the
+    /// node out and carry on. The keys of the nodes left out are returned along with why they
+    /// couldn't be parsed, so that callers can report them and still check the rest of the
+    /// lock. Inputs that lead to a node that was left out (or that can't be resolved at all) are
+    /// removed from the nodes that declare them, and so from [FlakeLock::root], so that the rest
+    /// of the input graph can still be walked. The lock as a whole still has to be valid JSON with
+    /// a valid root node.
+    ///
+    /// ```
+    /// use parse_flake_lock::FlakeLock;
+    ///
+    /// // The root's `flake-utils` input follows `home-manager/flake-utils`, but `home-manager`'s
+    /// // inputs are malformed
+    /// let contents = r#"{
+    ///   "nodes": {
+    ///     "home-manager": {"inputs": {"flake-utils": 5}, "locked": {"type": "github"}},
+    ///     "nixpkgs": {"locked": {"type": "github"}},
+    ///     "systems": {"locked": {"type": "github"}},
+    ///     "root": {
+    ///       "inputs": {
+    ///         "flake-utils": ["home-manager", "flake-utils"],
+    ///         "home-manager": "home-manager",
+    ///         "nixpkgs": "nixpkgs",
+    ///         "systems": "systems"
+    ///       }
+    ///     }
+    ///   },
+    ///   "root": "root",
+    ///   "version": 7
+    /// }"#;
+    /// assert!(contents.parse::<FlakeLock>().is_err());
+    ///
+    /// let (flake_lock, errors) = FlakeLock::from_str_lenient(contents).unwrap();
+    /// let bad_nodes: Vec<&str> = errors.iter().map(|(key, _)| key.as_str()).collect();
+    /// assert_eq!(bad_nodes, vec!["home-manager"]);
+    ///
+    /// // The other nodes and the root's inputs that don't depend on `home-manager` are still there
+    /// let mut nodes: Vec<&str> = flake_lock.nodes.keys().map(String::as_str).collect();
+    /// nodes.sort();
+    /// assert_eq!(nodes, vec!["nixpkgs", "root", "systems"]);
+    /// let mut root_inputs: Vec<&str> = flake_lock.root.keys().map(String::as_str).collect();
+    /// root_inputs.sort();
+    /// assert_eq!(root_inputs, vec!["nixpkgs", "systems"]);
+    /// assert_eq!(flake_lock.walk().unwrap().len(), 2);
+    /// ```
+    pub fn from_str_lenient(
+        s: &str,
+    ) -> Result<(Self, Vec<(String, FlakeLockParseError)>), FlakeLockParseError> {
+        let raw: serde_json::Value = serde_json::from_str(s)?;
+        validate_root(&raw)?;
+        let RawFlakeLock {
+            nodes: raw_nodes,
+            root,
+            version,
+        } = serde_json::from_value(raw)?;
+
+        let mut nodes = HashMap::new();
+        let mut errors = vec![];
+        for (key, value) in raw_nodes {
+            match parse_node(value) {
+                Ok(node) => {
+                    nodes.insert(key, node);
+                }
+                Err(e) => errors.push((key, e)),
+            }
+        }
+        errors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        remove_dangling_inputs(&mut nodes);
+
+        let mut root_nodes = HashMap::new();
+        if let Some(Node::Root(root_node)) = nodes.get(&root) {
+            for (root_name, root_input) in root_node.inputs.iter() {
+                if let Ok(Some(real_node)) = resolve_root_input(&nodes, root_input) {
+                    root_nodes.insert(root_name.clone(), real_node.clone());
+                }
+            }
+        }
+
+        Ok((
+            FlakeLock {
+                nodes,
+                root: root_nodes,
+                root_key: root,
+                version,
+            },
+            errors,
+        ))
+    }
+
+    /// Build a [FlakeLock] from its `nodes`, the key of its `root` node, and its format `version`,
+    /// resolving the root's inputs as when parsing a `flake.lock`. This lets tests build locks
+    /// programmatically rather than from JSON fixtures.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn from_parts(
+        nodes: HashMap<String, Node>,
+        root: &str,
+        version: usize,
+    ) -> Result<Self, FlakeLockParseError> {
+        let root_nodes = resolve_root(&nodes, root).map_err(FlakeLockParseError::Invalid)?;
+
+        Ok(FlakeLock {
+            nodes,
+            root: root_nodes,
+            root_key: root.to_string(),
+            version,
+        })
+    }
+
+    /// Walk the input graph breadth first, starting with the root's inputs, and return every
+    /// unique node once along with the shortest path of input names that leads to it. Inputs that
+    /// `follows` another input resolve to that input's node and so aren't returned twice. The root
+    /// itself is never returned, not even for inputs that follow it (with an empty `follows`).
+    pub fn walk(&self) -> Result<Vec<WalkedInput<'_>>, FlakeLockParseError> {
+        self.walk_to_depth(usize::MAX)
+    }
+
+    /// Like [FlakeLock::walk], but only walks `max_depth` levels of inputs deep, where the root's
+    /// inputs are at depth 1. Inputs of the nodes at `max_depth` aren't returned.
+    pub fn walk_to_depth(
+        &self,
+        max_depth: usize,
+    ) -> Result<Vec<WalkedInput<'_>>, FlakeLockParseError> {
+        let mut walked = vec![];
+        let mut seen = HashSet::from([self.root_key.clone()]);
+        let mut queue = VecDeque::from([(vec![], self.root_key.clone())]);
+
+        while let Some((path, key)) = queue.pop_front() {
+            if path.len() >= max_depth {
+                continue;
+            }
+
+            let Some(node_inputs) = self.node(&key)?.inputs()? else {
+                continue;
+            };
+
+            let mut node_inputs: Vec<(String, Input)> = node_inputs.into_iter().collect();
+            node_inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            for (name, input) in node_inputs {
+                let input_key = match input {
+                    Input::String(s) => s,
+                    Input::List(inputs) if inputs.is_empty() => continue,
+                    Input::List(inputs) => chase_input_key(&self.nodes, inputs.into())?,
+                };
+
+                if seen.insert(input_key.clone()) {
+                    let mut input_path = path.clone();
+                    input_path.push(name);
+
+                    walked.push(WalkedInput {
+                        path: input_path.clone(),
+                        key: input_key.clone(),
+                        node: self.node(&input_key)?,
+                    });
+                    queue.push_back((input_path, input_key));
+                }
+            }
+        }
+
+        Ok(walked)
+    }
+
+    /// The flake's direct inputs that appear to be Nixpkgs (see [Node::is_nixpkgs]), sorted by
+    /// input name.
+    ///
+    /// ```
+    /// use parse_flake_lock::FlakeLock;
+    ///
+    /// let flake_lock: FlakeLock = r#"{
+    ///   "nodes": {
+    ///     "nixpkgs": {
+    ///       "locked": {
+    ///         "lastModified": 1689078114,
+    ///         "narHash": "sha256-AAAA",
+    ///         "owner": "NixOS",
+    ///         "repo": "nixpkgs",
+    ///         "rev": "b969a89c3e84a121c9b3af2e4ef277cd822b988a",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "NixOS", "repo": "nixpkgs", "type": "github" }
+    ///     },
+    ///     "root": { "inputs": { "pkgs": "nixpkgs" } }
+    ///   },
+    ///   "root": "root",
+    ///   "version": 7
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let names: Vec<&str> = flake_lock.nixpkgs_nodes().into_iter().map(|(name, _)| name).collect();
+    /// assert_eq!(names, vec!["pkgs"]);
+    /// ```
+    pub fn nixpkgs_nodes(&self) -> Vec<(&str, &Node)> {
+        let mut nodes: Vec<(&str, &Node)> = self
+            .root
+            .iter()
+            .filter(|(_, node)| node.is_nixpkgs())
+            .map(|(name, node)| (name.as_str(), node))
+            .collect();
+        nodes.sort_by_key(|(name, _)| *name);
+        nodes
+    }
+
+    /// Every distinct revision that the `flake.lock` locks an input to (see [Node::locked_rev]),
+    /// across all nodes and in sorted order.
+    ///
+    /// ```
+    /// use parse_flake_lock::FlakeLock;
+    ///
+    /// let flake_lock: FlakeLock = r#"{
+    ///   "nodes": {
+    ///     "nixpkgs": {
+    ///       "locked": {
+    ///         "lastModified": 1689078114,
+    ///         "narHash": "sha256-AAAA",
+    ///         "owner": "NixOS",
+    ///         "repo": "nixpkgs",
+    ///         "rev": "b969a89c3e84a121c9b3af2e4ef277cd822b988a",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "NixOS", "repo": "nixpkgs", "type": "github" }
+    ///     },
+    ///     "nixpkgs_2": {
+    ///       "locked": {
+    ///         "lastModified": 1689078114,
+    ///         "narHash": "sha256-AAAA",
+    ///         "owner": "NixOS",
+    ///         "repo": "nixpkgs",
+    ///         "rev": "b969a89c3e84a121c9b3af2e4ef277cd822b988a",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "NixOS", "ref": "nixos-unstable", "repo": "nixpkgs", "type": "github" }
+    ///     },
+    ///     "home-manager": {
+    ///       "inputs": { "nixpkgs": "nixpkgs_2" },
+    ///       "locked": {
+    ///         "lastModified": 1689078114,
+    ///         "narHash": "sha256-BBBB",
+    ///         "owner": "nix-community",
+    ///         "repo": "home-manager",
+    ///         "rev": "07c347bb50994691d7b0095f45ebd8838cf6bc38",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "nix-community", "repo": "home-manager", "type": "github" }
+    ///     },
+    ///     "assets": {
+    ///       "locked": {
+    ///         "narHash": "sha256-CCCC",
+    ///         "type": "tarball",
+    ///         "url": "https://example.com/assets.tar.gz"
+    ///       },
+    ///       "original": { "type": "tarball", "url": "https://example.com/assets.tar.gz" }
+    ///     },
+    ///     "root": {
+    ///       "inputs": { "assets": "assets", "home-manager": "home-manager", "nixpkgs": "nixpkgs" }
+    ///     }
+    ///   },
+    ///   "root": "root",
+    ///   "version": 7
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let revs: Vec<String> = flake_lock.distinct_revs().into_iter().collect();
+    /// assert_eq!(
+    ///     revs,
+    ///     vec![
+    ///         "07c347bb50994691d7b0095f45ebd8838cf6bc38",
+    ///         "b969a89c3e84a121c9b3af2e4ef277cd822b988a",
+    ///     ]
+    /// );
+    /// ```
+    pub fn distinct_revs(&self) -> BTreeSet<String> {
+        self.nodes
+            .values()
+            .filter_map(Node::locked_rev)
+            .map(String::from)
+            .collect()
+    }
+
+    /// Every `follows` edge in the input graph, i.e. every input of a node that refers to another
+    /// input by its path of input names rather than to a node key, sorted by node key and input
+    /// name. Each edge records the node key that the path resolves to, if it resolves at all.
+    ///
+    /// ```
+    /// use parse_flake_lock::FlakeLock;
+    ///
+    /// let flake_lock: FlakeLock = r#"{
+    ///   "nodes": {
+    ///     "nixpkgs": {
+    ///       "locked": {
+    ///         "lastModified": 1689078114,
+    ///         "narHash": "sha256-AAAA",
+    ///         "owner": "NixOS",
+    ///         "repo": "nixpkgs",
+    ///         "rev": "b969a89c3e84a121c9b3af2e4ef277cd822b988a",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "NixOS", "repo": "nixpkgs", "type": "github" }
+    ///     },
+    ///     "systems": {
+    ///       "locked": {
+    ///         "lastModified": 1681028828,
+    ///         "narHash": "sha256-DDDD",
+    ///         "owner": "nix-systems",
+    ///         "repo": "default",
+    ///         "rev": "da67096a3b9bf56a91d16901293e51ba5b49a27e",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "nix-systems", "repo": "default", "type": "github" }
+    ///     },
+    ///     "flake-utils": {
+    ///       "inputs": { "systems": ["systems"] },
+    ///       "locked": {
+    ///         "lastModified": 1689068808,
+    ///         "narHash": "sha256-EEEE",
+    ///         "owner": "numtide",
+    ///         "repo": "flake-utils",
+    ///         "rev": "919d646de7be200f3bf08cb76ae1f09402b6f9b4",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "numtide", "repo": "flake-utils", "type": "github" }
+    ///     },
+    ///     "home-manager": {
+    ///       "inputs": {
+    ///         "flake-utils": ["flake-utils"],
+    ///         "nixpkgs": ["nixpkgs"],
+    ///         "systems": ["flake-utils", "systems"],
+    ///         "gone": ["missing"]
+    ///       },
+    ///       "locked": {
+    ///         "lastModified": 1689078114,
+    ///         "narHash": "sha256-BBBB",
+    ///         "owner": "nix-community",
+    ///         "repo": "home-manager",
+    ///         "rev": "07c347bb50994691d7b0095f45ebd8838cf6bc38",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "nix-community", "repo": "home-manager", "type": "github" }
+    ///     },
+    ///     "root": {
+    ///       "inputs": {
+    ///         "flake-utils": "flake-utils",
+    ///         "home-manager": "home-manager",
+    ///         "nixpkgs": "nixpkgs",
+    ///         "systems": "systems"
+    ///       }
+    ///     }
+    ///   },
+    ///   "root": "root",
+    ///   "version": 7
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let edges: Vec<(String, String, Option<String>)> = flake_lock
+    ///     .follows()
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|edge| (edge.node, edge.input, edge.target))
+    ///     .collect();
+    /// let edge = |node: &str, input: &str, target: Option<&str>| {
+    ///     (node.to_string(), input.to_string(), target.map(String::from))
+    /// };
+    /// assert_eq!(
+    ///     edges,
+    ///     vec![
+    ///         edge("flake-utils", "systems", Some("systems")),
+    ///         edge("home-manager", "flake-utils", Some("flake-utils")),
+    ///         // This input's follows path doesn't lead anywhere
+    ///         edge("home-manager", "gone", None),
+    ///         edge("home-manager", "nixpkgs", Some("nixpkgs")),
+    ///         // Follows paths can go through other follows
+    ///         edge("home-manager", "systems", Some("systems")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn follows(&self) -> Result<Vec<Follows>, FlakeLockParseError> {
+        let mut edges = vec![];
+        for (key, node) in &self.nodes {
+            let Some(node_inputs) = node.inputs()? else {
+                continue;
+            };
+
+            for (name, input) in node_inputs {
+                if let Input::List(path) = input {
+                    edges.push(Follows {
+                        node: key.clone(),
+                        input: name,
+                        target: self.resolve_follows(&path, 0),
+                        path,
+                    });
+                }
+            }
+        }
+
+        edges.sort_by(|a, b| (&a.node, &a.input).cmp(&(&b.node, &b.input)));
+        Ok(edges)
+    }
+
+    // The key of the node that a `follows` path of input names, starting at the root, leads to.
+    // `depth` counts the nested follows paths so far, which guards against cycles.
+    fn resolve_follows(&self, path: &[String], depth: usize) -> Option<String> {
+        if depth > self.nodes.len() {
+            return None;
+        }
+
+        let mut key = self.root_key.clone();
+        for name in path {
+            let node_inputs = self.nodes.get(&key)?.inputs().ok()??;
+            key = match node_inputs.get(name)? {
+                Input::String(s) => s.clone(),
+                Input::List(path) => self.resolve_follows(path, depth + 1)?,
+            };
+        }
+
+        self.nodes.contains_key(&key).then_some(key)
+    }
+
+    fn node(&self, key: &str) -> Result<&Node, FlakeLockParseError> {
+        self.nodes.get(key).ok_or_else(|| missing_node(key))
+    }
+}
+
+/// A [Node] reached by [FlakeLock::walk].
+#[derive(Clone, Debug)]
+pub struct WalkedInput<'a> {
+    /// The input names that lead from the root to the node, e.g. `["home-manager", "nixpkgs"]`.
+    pub path: Vec<String>,
+    /// The key of the node in [FlakeLock::nodes].
+    pub key: String,
+    /// The node itself.
+    pub node: &'a Node,
+}
+
+/// A `follows` edge reported by [FlakeLock::follows], like the one that `home-manager`'s
+/// `inputs.nixpkgs.follows = "nixpkgs";` declaration results in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Follows {
+    /// The key of the node whose input follows another input, e.g. `home-manager`.
+    pub node: String,
+    /// The name of the node's input that follows another input, e.g. `nixpkgs`.
+    pub input: String,
+    /// The input names that lead from the root to the followed input, e.g. `["nixpkgs"]`.
+    pub path: Vec<String>,
+    /// The key of the node that `path` resolves to, or `None` if it doesn't resolve (a broken
+    /// `follows`).
+    pub target: Option<String>,
+}
+
+impl WalkedInput<'_> {
+    /// Whether the node is a direct input of the root (declared in the flake's own `flake.nix`).
+    pub fn is_direct(&self) -> bool {
+        self.path.len() == 1
+    }
+}
+
+/// Parse a [FlakeLock] from the raw contents of a `flake.lock` file.
+impl FromStr for FlakeLock {
+    type Err = FlakeLockParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw: serde_json::Value = serde_json::from_str(s)?;
+        validate_root(&raw)?;
+        let flake_lock: FlakeLock = serde_json::from_value(raw)?;
         Ok(flake_lock)
     }
 }
 
+// Make sure that the `root` key points to an actual root node before deserializing so that corrupt
+// lockfiles produce a clear error rather than a generic deserialization failure.
+fn validate_root(raw: &serde_json::Value) -> Result<(), FlakeLockParseError> {
+    let (Some(root), Some(nodes)) = (raw.get("root").and_then(|r| r.as_str()), raw.get("nodes"))
+    else {
+        // Missing or mistyped fields are reported by the deserializer
+        return Ok(());
+    };
+
+    let Some(root_node) = nodes.get(root) else {
+        return Err(FlakeLockParseError::CorruptRoot(format!(
+            "the root node `{root}` is missing from `nodes`"
+        )));
+    };
+
+    match serde_json::from_value::<Node>(root_node.clone())? {
+        Node::Root(_) => Ok(()),
+        node => Err(FlakeLockParseError::CorruptRoot(format!(
+            "the root node `{root}` is a {} node rather than a Root node",
+            node.variant()
+        ))),
+    }
+}
+
 /// A flake input [node]. This enum represents two concrete node types, [RepoNode] and [RootNode],
 /// and uses the `Fallthrough` variant to capture node types that don't have explicitly defined
 /// structs in this library, representing them as raw [Value][serde_json::value::Value]s.
@@ -214,8 +843,295 @@ pub enum Node {
     Fallthrough(serde_json::value::Value), // Covers all other node types
 }
 
-// A string representation of the node variant (for logging).
 impl Node {
+    /// The NAR hash of the node's locked input (if it has one).
+    pub fn nar_hash(&self) -> Option<&str> {
+        match self {
+            Node::Root(_) => None,
+            Node::Repo(node) => Some(&node.locked.nar_hash),
+            Node::Indirect(node) => Some(&node.locked.nar_hash),
+            Node::Path(node) => Some(&node.locked.nar_hash),
+            Node::Tarball(node) => Some(&node.locked.nar_hash),
+            Node::Fallthrough(node) => node.get("locked")?.get("narHash")?.as_str(),
+        }
+    }
+
+    /// The raw `type` of the node's locked input, e.g. `github`, `path`, or `tarball`, for every
+    /// kind of node, including [Fallthrough][Node::Fallthrough] nodes. Only the root node has no
+    /// type.
+    ///
+    /// ```
+    /// use parse_flake_lock::{FlakeLock, Node};
+    ///
+    /// let flake_lock: FlakeLock = r#"{
+    ///   "nodes": {
+    ///     "nixpkgs": {
+    ///       "locked": {
+    ///         "lastModified": 1,
+    ///         "narHash": "sha256-AAAA",
+    ///         "owner": "NixOS",
+    ///         "repo": "nixpkgs",
+    ///         "rev": "abc",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "owner": "NixOS", "repo": "nixpkgs", "type": "github" }
+    ///     },
+    ///     "registry": {
+    ///       "locked": {
+    ///         "lastModified": 1,
+    ///         "narHash": "sha256-AAAA",
+    ///         "owner": "NixOS",
+    ///         "repo": "nixpkgs",
+    ///         "rev": "abc",
+    ///         "type": "github"
+    ///       },
+    ///       "original": { "id": "nixpkgs", "type": "indirect" }
+    ///     },
+    ///     "local": {
+    ///       "locked": {
+    ///         "lastModified": 1,
+    ///         "narHash": "sha256-AAAA",
+    ///         "path": "./sub",
+    ///         "type": "path"
+    ///       },
+    ///       "original": { "path": "./sub", "type": "path" }
+    ///     },
+    ///     "channel": {
+    ///       "locked": {
+    ///         "narHash": "sha256-AAAA",
+    ///         "type": "tarball",
+    ///         "url": "https://nixos.org/channels/nixos-24.11/nixexprs.tar.xz"
+    ///       },
+    ///       "original": {
+    ///         "type": "tarball",
+    ///         "url": "https://nixos.org/channels/nixos-24.11/nixexprs.tar.xz"
+    ///       }
+    ///     },
+    ///     "relative": {
+    ///       "locked": {
+    ///         "lastModified": 1,
+    ///         "narHash": "sha256-AAAA",
+    ///         "path": "./sub",
+    ///         "type": "path"
+    ///       },
+    ///       "original": { "path": "./sub", "type": "path" },
+    ///       "parent": []
+    ///     },
+    ///     "root": {
+    ///       "inputs": {
+    ///         "channel": "channel",
+    ///         "local": "local",
+    ///         "nixpkgs": "nixpkgs",
+    ///         "registry": "registry",
+    ///         "relative": "relative"
+    ///       }
+    ///     }
+    ///   },
+    ///   "root": "root",
+    ///   "version": 7
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let node_type = |key: &str| flake_lock.nodes[key].node_type();
+    /// assert!(matches!(flake_lock.nodes["nixpkgs"], Node::Repo(_)));
+    /// assert_eq!(node_type("nixpkgs"), Some("github"));
+    /// // Indirect inputs are locked to what the registry resolved them to
+    /// assert!(matches!(flake_lock.nodes["registry"], Node::Indirect(_)));
+    /// assert_eq!(node_type("registry"), Some("github"));
+    /// assert!(matches!(flake_lock.nodes["local"], Node::Path(_)));
+    /// assert_eq!(node_type("local"), Some("path"));
+    /// assert!(matches!(flake_lock.nodes["channel"], Node::Tarball(_)));
+    /// assert_eq!(node_type("channel"), Some("tarball"));
+    /// // Relative path inputs have a `parent`, which no node struct has a field for
+    /// assert!(matches!(flake_lock.nodes["relative"], Node::Fallthrough(_)));
+    /// assert_eq!(node_type("relative"), Some("path"));
+    /// assert!(matches!(flake_lock.nodes["root"], Node::Root(_)));
+    /// assert_eq!(node_type("root"), None);
+    /// ```
+    pub fn node_type(&self) -> Option<&str> {
+        match self {
+            Node::Root(_) => None,
+            Node::Repo(node) => Some(&node.locked.node_type),
+            Node::Indirect(node) => Some(&node.locked.node_type),
+            Node::Path(node) => Some(&node.locked.node_type),
+            Node::Tarball(node) => Some(&node.locked.node_type),
+            Node::Fallthrough(node) => node.get("locked")?.get("type")?.as_str(),
+        }
+    }
+
+    /// The revision that the node's input is locked to (if it has one). Path inputs and most
+    /// tarball inputs aren't locked to a revision.
+    pub fn locked_rev(&self) -> Option<&str> {
+        match self {
+            Node::Repo(node) => Some(&node.locked.rev),
+            Node::Indirect(node) => Some(&node.locked.rev),
+            Node::Fallthrough(node) => node.get("locked")?.get("rev")?.as_str(),
+            Node::Root(_) | Node::Path(_) | Node::Tarball(_) => None,
+        }
+    }
+
+    /// Whether the node's input is a flake, which it is unless it's declared with `flake = false`
+    /// (like a source tree that's used as is).
+    pub fn is_flake(&self) -> bool {
+        self.flake() != Some(false)
+    }
+
+    /// The node's `flake` attribute (if it has one). Nix only records the attribute for inputs
+    /// declared with `flake = false`, so `None` means that the input is a flake.
+    ///
+    /// ```
+    /// use parse_flake_lock::FlakeLock;
+    ///
+    /// let flake_lock: FlakeLock = r#"{
+    ///   "nodes": {
+    ///     "patch": {
+    ///       "flake": false,
+    ///       "locked": {
+    ///         "narHash": "sha256-AAAA",
+    ///         "type": "file",
+    ///         "url": "https://example.com/fix.patch"
+    ///       },
+    ///       "original": { "type": "file", "url": "https://example.com/fix.patch" }
+    ///     },
+    ///     "root": { "inputs": { "patch": "patch" } }
+    ///   },
+    ///   "root": "root",
+    ///   "version": 7
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let patch = &flake_lock.nodes["patch"];
+    /// assert_eq!(patch.flake(), Some(false));
+    /// assert!(!patch.is_flake());
+    /// assert_eq!(flake_lock.nodes["root"].flake(), None);
+    /// ```
+    pub fn flake(&self) -> Option<bool> {
+        match self {
+            Node::Repo(node) => node.flake,
+            Node::Tarball(node) => node.flake,
+            Node::Fallthrough(node) => node.get("flake")?.as_bool(),
+            Node::Root(_) | Node::Indirect(_) | Node::Path(_) => None,
+        }
+    }
+
+    /// The node's inputs (if it has any).
+    pub fn inputs(&self) -> Result<Option<HashMap<String, Input>>, FlakeLockParseError> {
+        Ok(match self {
+            Node::Root(node) => Some(node.inputs.to_owned()),
+            Node::Repo(node) => node.inputs.to_owned(),
+            Node::Indirect(node) => node.inputs.to_owned(),
+            Node::Path(node) => node.inputs.to_owned(),
+            Node::Tarball(node) => node.inputs.to_owned(),
+            Node::Fallthrough(node) => match node.get("inputs") {
+                Some(node_inputs) => serde_json::from_value(node_inputs.clone())?,
+                None => None,
+            },
+        })
+    }
+
+    // Remove the input called `name` from the node's inputs (if it has one).
+    fn remove_input(&mut self, name: &str) {
+        let node_inputs = match self {
+            Node::Root(node) => Some(&mut node.inputs),
+            Node::Repo(node) => node.inputs.as_mut(),
+            Node::Indirect(node) => node.inputs.as_mut(),
+            Node::Path(node) => node.inputs.as_mut(),
+            Node::Tarball(node) => node.inputs.as_mut(),
+            Node::Fallthrough(node) => {
+                if let Some(node_inputs) = node.get_mut("inputs").and_then(|i| i.as_object_mut()) {
+                    node_inputs.remove(name);
+                }
+                return;
+            }
+        };
+        if let Some(node_inputs) = node_inputs {
+            node_inputs.remove(name);
+        }
+    }
+
+    /// The URL that the node's locked input is fetched from (if it has one). Repo nodes like
+    /// `github:` inputs are fetched using the forge's API and thus have no URL.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Node::Tarball(node) => Some(&node.locked.url),
+            Node::Fallthrough(node) => node.get("locked")?.get("url")?.as_str(),
+            _ => None,
+        }
+    }
+
+    /// The host of the forge that the node's locked input is fetched from, e.g. `github.example.com`
+    /// for a GitHub Enterprise input. This is `None` for inputs on the forge's public host (like
+    /// `github.com`) and for node types that aren't fetched from a forge.
+    pub fn host(&self) -> Option<&str> {
+        match self {
+            Node::Repo(node) => node.locked.host.as_deref(),
+            Node::Indirect(node) => node.locked.host.as_deref(),
+            Node::Fallthrough(node) => node.get("locked")?.get("host")?.as_str(),
+            Node::Root(_) | Node::Path(_) | Node::Tarball(_) => None,
+        }
+    }
+
+    /// Whether the node appears to be Nixpkgs: a repository named `nixpkgs`, the `nixpkgs` flake
+    /// registry entry, or a URL that points to Nixpkgs or a NixOS channel.
+    pub fn is_nixpkgs(&self) -> bool {
+        match self {
+            Node::Repo(node) => node.locked.repo.eq_ignore_ascii_case("nixpkgs"),
+            Node::Indirect(node) => node.original.id == "nixpkgs",
+            Node::Root(_) | Node::Path(_) => false,
+            Node::Tarball(_) | Node::Fallthrough(_) => self.url().is_some_and(|url| {
+                let url = url.to_ascii_lowercase();
+                url.contains("/nixpkgs") || url.contains("nixos.org/channels")
+            }),
+        }
+    }
+
+    /// The node's `original` (user-supplied) flake reference in URL form, e.g.
+    /// `github:NixOS/nixpkgs/nixos-unstable` or `path:./subflake`. For node types that store their
+    /// original reference as attributes, the URL is reconstructed from them.
+    pub fn original_url(&self) -> Option<String> {
+        match self {
+            Node::Root(_) => None,
+            Node::Repo(node) => {
+                let original = &node.original;
+                let mut url = format!(
+                    "{}:{}/{}",
+                    original.node_type, original.owner, original.repo
+                );
+                if let Some(git_ref) = &original.git_ref {
+                    url.push('/');
+                    url.push_str(git_ref);
+                }
+                Some(url)
+            }
+            Node::Indirect(node) => {
+                let original = &node.original;
+                let mut url = format!("flake:{}", original.id);
+                for part in [&original.git_ref, &original.rev].into_iter().flatten() {
+                    url.push('/');
+                    url.push_str(part);
+                }
+                Some(url)
+            }
+            Node::Path(node) => Some(format!("path:{}", node.original.path.display())),
+            Node::Tarball(node) => Some(url_reference(
+                &node.original.node_type,
+                &node.original.url,
+                node.original.git_ref.as_deref(),
+            )),
+            Node::Fallthrough(node) => {
+                let original = node.get("original")?;
+                Some(url_reference(
+                    original.get("type")?.as_str()?,
+                    original.get("url")?.as_str()?,
+                    original.get("ref").and_then(|r| r.as_str()),
+                ))
+            }
+        }
+    }
+
+    // A string representation of the node variant (for logging).
     fn variant(&self) -> &'static str {
         match self {
             Node::Root(_) => "Root",
@@ -228,6 +1144,20 @@ impl Node {
     }
 }
 
+// A URL-style flake reference, e.g. `git+https://example.com/repo?ref=main`. Version control types
+// prefix the URL with the type.
+fn url_reference(node_type: &str, url: &str, git_ref: Option<&str>) -> String {
+    let mut reference = match node_type {
+        "git" | "hg" if !url.starts_with(&format!("{node_type}+")) => format!("{node_type}+{url}"),
+        _ => url.to_string(),
+    };
+    if let Some(git_ref) = git_ref {
+        reference.push_str("?ref=");
+        reference.push_str(git_ref);
+    }
+    reference
+}
+
 /// An enum type representing node input references.
 #[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
@@ -276,6 +1206,8 @@ pub struct RepoLocked {
     pub repo: String,
     /// The Git revision.
     pub rev: String,
+    /// The forge's host for GitHub or GitLab Enterprise installs (absent for the public host).
+    pub host: Option<String>,
     /// The type of the node (either `"repo"` or `"indirect"`).
     #[serde(alias = "type")]
     pub node_type: String,
@@ -318,6 +1250,11 @@ pub struct IndirectOriginal {
     /// The type of the node (always `"indirect"`).
     #[serde(alias = "type")]
     pub node_type: String,
+    /// The Git reference of the input, e.g. `nixos-24.11` for `nixpkgs/nixos-24.11`.
+    #[serde(alias = "ref")]
+    pub git_ref: Option<String>,
+    /// The revision of the input, if the reference pins one.
+    pub rev: Option<String>,
 }
 
 /// A flake input as a filesystem path, e.g. `inputs.local.url = "path:./subdir";`.
@@ -364,6 +1301,8 @@ pub struct PathOriginal {
 /// A flake input as a tarball URL.
 #[derive(Clone, Debug, Deserialize)]
 pub struct TarballNode {
+    /// Whether the input is itself a flake.
+    pub flake: Option<bool>,
     /// The "locked" attributes of the input (set by Nix).
     pub locked: TarballLocked,
     /// The node's inputs.
@@ -393,7 +1332,10 @@ pub struct TarballLocked {
 pub struct TarballOriginal {
     /// The URL for the tarball input.
     pub url: String,
-    /// The type of the node (always `"tarball"`).
+    /// The Git reference of the input (for `git` inputs, which share this structure).
+    #[serde(alias = "ref")]
+    pub git_ref: Option<String>,
+    /// The type of the node (`"tarball"`, or another URL-based type like `"file"` or `"git"`).
     #[serde(alias = "type")]
     pub node_type: String,
 }
@@ -0,0 +1,142 @@
+//! Support for `--baseline-age`, which compares the age of a `flake.lock`'s oldest input with the
+//! one recorded by the last run to tell whether the lock's staleness improved or regressed.
+
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::FlakeCheckerError;
+use crate::report::InputAges;
+
+/// The age of a `flake.lock`'s oldest input as of the last run.
+#[derive(Deserialize, Serialize)]
+struct Snapshot {
+    /// The path of the `flake.lock`, which is only for the people looking at the file.
+    flake_lock: String,
+    oldest_days: i64,
+}
+
+/// Whether a lock got fresher or staler since the last run.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Trend {
+    Improved,
+    Unchanged,
+    Regressed,
+}
+
+/// How the age of the oldest input changed since the last run, in whole days.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct AgeChange {
+    pub previous_days: i64,
+    pub current_days: i64,
+    pub trend: Trend,
+}
+
+impl AgeChange {
+    fn new(previous_days: i64, current_days: i64) -> Self {
+        Self {
+            previous_days,
+            current_days,
+            trend: match current_days.cmp(&previous_days) {
+                std::cmp::Ordering::Less => Trend::Improved,
+                std::cmp::Ordering::Equal => Trend::Unchanged,
+                std::cmp::Ordering::Greater => Trend::Regressed,
+            },
+        }
+    }
+}
+
+/// Compare the current input ages of the `flake.lock` at `flake_lock_path` with the snapshot of
+/// the last run in `dir`, then store the current ages as the snapshot for the next run. The first
+/// run has nothing to compare with, and neither do locks without dated inputs, whose snapshots
+/// are left alone.
+pub(crate) fn compare_and_store(
+    dir: &Path,
+    flake_lock_path: &Path,
+    input_ages: Option<InputAges>,
+) -> Result<Option<AgeChange>, FlakeCheckerError> {
+    let Some(input_ages) = input_ages else {
+        return Ok(None);
+    };
+
+    let path = snapshot_path(dir, flake_lock_path);
+    // An unreadable snapshot is as good as none, since it's overwritten anyway
+    let previous = read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Snapshot>(&contents).ok());
+
+    create_dir_all(dir)?;
+    let snapshot = Snapshot {
+        flake_lock: flake_lock_path.display().to_string(),
+        oldest_days: input_ages.oldest_days,
+    };
+    write(&path, serde_json::to_string_pretty(&snapshot)?)?;
+
+    Ok(previous.map(|previous| AgeChange::new(previous.oldest_days, input_ages.oldest_days)))
+}
+
+/// The path of the snapshot for the `flake.lock` at `flake_lock_path` (as given) in `dir`, which
+/// is named after a hash of the path so that every lockfile gets its own.
+fn snapshot_path(dir: &Path, flake_lock_path: &Path) -> PathBuf {
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(flake_lock_path.as_os_str().as_encoded_bytes())
+    );
+    dir.join(format!("{}.json", &hash[..16]))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::remove_dir_all;
+    use std::path::Path;
+
+    use crate::report::InputAges;
+
+    use super::{compare_and_store, AgeChange, Trend};
+
+    #[test]
+    fn changing_ages() {
+        let dir =
+            std::env::temp_dir().join(format!("flake-checker-baseline-{}", std::process::id()));
+        let flake_lock = Path::new("a/flake.lock");
+        let ages = |oldest_days| {
+            Some(InputAges {
+                oldest_days,
+                newest_days: 1,
+            })
+        };
+
+        // The first run only records the ages
+        assert_eq!(compare_and_store(&dir, flake_lock, ages(30)).unwrap(), None);
+
+        // The oldest input went from 30 to 45 days
+        assert_eq!(
+            compare_and_store(&dir, flake_lock, ages(45)).unwrap(),
+            Some(AgeChange {
+                previous_days: 30,
+                current_days: 45,
+                trend: Trend::Regressed,
+            })
+        );
+
+        // Without dated inputs, the snapshot is kept for the next run
+        assert_eq!(compare_and_store(&dir, flake_lock, None).unwrap(), None);
+        assert_eq!(
+            compare_and_store(&dir, flake_lock, ages(10))
+                .unwrap()
+                .map(|change| change.trend),
+            Some(Trend::Improved)
+        );
+
+        // Each lockfile has its own snapshot
+        assert_eq!(
+            compare_and_store(&dir, Path::new("b/flake.lock"), ages(10)).unwrap(),
+            None
+        );
+
+        remove_dir_all(&dir).unwrap();
+    }
+}
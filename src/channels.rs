@@ -0,0 +1,155 @@
+use crate::error::FlakeCheckerError;
+use crate::flake::{nixpkgs_deps, FlakeCheckConfig};
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use parse_flake_lock::{FlakeLock, Node};
+use serde::Serialize;
+
+/// The format in which to report a channel distribution.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum ChannelsFormat {
+    /// One line per channel with its number of inputs.
+    Text,
+    /// An object with the number of inputs per channel.
+    Json,
+}
+
+/// How many Nixpkgs inputs across a set of lockfiles follow each channel (or, more precisely, each
+/// Git ref, so refs that aren't NixOS channels are tallied as well).
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct ChannelDistribution {
+    pub(crate) lockfiles: usize,
+    /// The number of inputs that follow each channel, keyed by channel (e.g. `nixos-24.11`).
+    pub(crate) channels: BTreeMap<String, usize>,
+    /// The number of inputs that don't follow a channel, like inputs pinned to a revision.
+    pub(crate) no_channel: usize,
+}
+
+impl ChannelDistribution {
+    /// Tally the channels of the Nixpkgs inputs of a lockfile, selected as when checking it.
+    pub(crate) fn add(
+        &mut self,
+        flake_lock: &FlakeLock,
+        config: &FlakeCheckConfig,
+    ) -> Result<(), FlakeCheckerError> {
+        let deps = nixpkgs_deps(
+            flake_lock,
+            &config.nixpkgs_keys,
+            config.input_name_regex.as_ref(),
+            config.transitive,
+            config.max_transitive_depth,
+        )?;
+
+        for node in deps.values() {
+            let git_ref = match node {
+                Node::Repo(repo) => repo.original.git_ref.as_deref(),
+                _ => None,
+            };
+            match git_ref {
+                Some(channel) => *self.channels.entry(channel.to_string()).or_default() += 1,
+                None => self.no_channel += 1,
+            }
+        }
+
+        self.lockfiles += 1;
+        Ok(())
+    }
+
+    /// Render the distribution in the given format.
+    pub(crate) fn render(&self, format: ChannelsFormat) -> Result<String, FlakeCheckerError> {
+        Ok(match format {
+            ChannelsFormat::Text => self.text(),
+            ChannelsFormat::Json => format!("{}\n", serde_json::to_string_pretty(self)?),
+        })
+    }
+
+    fn text(&self) -> String {
+        let total = self.channels.values().sum::<usize>() + self.no_channel;
+        let lockfile_word = if self.lockfiles == 1 {
+            "lockfile"
+        } else {
+            "lockfiles"
+        };
+
+        let mut rows: Vec<(&str, usize)> = self
+            .channels
+            .iter()
+            .map(|(channel, count)| (channel.as_str(), *count))
+            .collect();
+        // The most common channels first
+        rows.sort_by_key(|&(_, count)| Reverse(count));
+        if self.no_channel > 0 {
+            rows.push(("(no channel)", self.no_channel));
+        }
+
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        let mut text = format!(
+            "Channels of {total} Nixpkgs inputs across {} {lockfile_word}:\n",
+            self.lockfiles
+        );
+        for (name, count) in rows {
+            let percent = count * 100 / total;
+            text.push_str(&format!("{name:<width$}  {count} ({percent}%)\n"));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use parse_flake_lock::FlakeLock;
+
+    use crate::flake::FlakeCheckConfig;
+
+    use super::{ChannelDistribution, ChannelsFormat};
+
+    #[test]
+    fn channel_distribution() {
+        let config = FlakeCheckConfig::default();
+        let mut distribution = ChannelDistribution::default();
+        for file in [
+            "flake.clean.0.lock",
+            "flake.clean.1.lock",
+            "flake.clean.2.lock",
+            "flake.clean.4.lock",
+            "flake.rev-only.0.lock",
+        ] {
+            let flake_lock = FlakeLock::new(&PathBuf::from(format!("tests/{file}"))).unwrap();
+            distribution.add(&flake_lock, &config).unwrap();
+        }
+
+        assert_eq!(
+            distribution,
+            ChannelDistribution {
+                lockfiles: 5,
+                channels: BTreeMap::from([
+                    (String::from("nixos-24.11"), 1),
+                    (String::from("nixos-unstable"), 2),
+                    (String::from("nixpkgs-unstable"), 1),
+                ]),
+                no_channel: 1,
+            }
+        );
+
+        let text = distribution.render(ChannelsFormat::Text).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some("Channels of 5 Nixpkgs inputs across 5 lockfiles:")
+        );
+        assert_eq!(lines.next(), Some("nixos-unstable    2 (40%)"));
+        assert_eq!(lines.last(), Some("(no channel)      1 (20%)"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&distribution.render(ChannelsFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["lockfiles"], 5);
+        assert_eq!(json["channels"]["nixos-unstable"], 2);
+        assert_eq!(json["no_channel"], 1);
+    }
+}
@@ -0,0 +1,316 @@
+//! The registry of checks, which the summary's list of checks performed, the clean inputs report,
+//! and `--list-checks` are all derived from.
+
+use crate::flake::FlakeCheckConfig;
+
+/// A check that Flake Checker can perform.
+pub(crate) struct Check {
+    pub(crate) name: &'static str,
+    /// The flag that controls the check.
+    pub(crate) flag: &'static str,
+    /// The environment variable that controls the check.
+    pub(crate) env: &'static str,
+    /// What the check flags.
+    pub(crate) description: &'static str,
+    /// The kinds of issues that the check reports (see [`crate::issue::IssueKind::name`]).
+    pub(crate) issue_kinds: &'static [&'static str],
+    /// Whether the check applies to individual Nixpkgs inputs rather than to the flake.lock (or
+    /// flake.nix) as a whole.
+    pub(crate) per_input: bool,
+    /// Whether the check is enabled in the given config.
+    pub(crate) enabled: fn(&FlakeCheckConfig) -> bool,
+}
+
+/// Every check, in the order in which they're listed.
+pub(crate) const CHECKS: &[Check] = &[
+    Check {
+        name: "Supported branches",
+        flag: "--check-supported",
+        env: "NIX_FLAKE_CHECKER_CHECK_SUPPORTED",
+        description: "Nixpkgs inputs follow a supported branch",
+        issue_kinds: &["disallowed"],
+        per_input: true,
+        enabled: |config| config.check_supported || config.strict_supported,
+    },
+    Check {
+        name: "Denied branches",
+        flag: "--deny-ref-pattern",
+        env: "NIX_FLAKE_CHECKER_DENY_REF_PATTERN",
+        description: "Nixpkgs inputs don't follow a ref that matches a denied pattern",
+        issue_kinds: &["disallowed"],
+        per_input: true,
+        enabled: |config| !config.deny_ref_patterns.is_empty(),
+    },
+    Check {
+        name: "Temporary refs",
+        flag: "--check-temporary-refs",
+        env: "NIX_FLAKE_CHECKER_CHECK_TEMPORARY_REFS",
+        description: "Nixpkgs inputs don't follow a pull request or a work-in-progress branch",
+        issue_kinds: &["temporary-ref"],
+        per_input: true,
+        enabled: |config| config.check_temporary_refs,
+    },
+    Check {
+        name: "Outdated inputs",
+        flag: "--check-outdated",
+        env: "NIX_FLAKE_CHECKER_CHECK_OUTDATED",
+        description: "Nixpkgs inputs were updated within --max-days",
+        issue_kinds: &["outdated", "future-timestamp"],
+        per_input: true,
+        enabled: |config| config.check_outdated,
+    },
+    Check {
+        name: "End of life",
+        flag: "--warn-eol",
+        env: "NIX_FLAKE_CHECKER_WARN_EOL",
+        description: "Stable channels aren't about to reach their end of life",
+        issue_kinds: &["approaching-eol"],
+        per_input: true,
+        enabled: |config| config.warn_eol,
+    },
+    Check {
+        name: "Upstream owner",
+        flag: "--check-owner",
+        env: "NIX_FLAKE_CHECKER_CHECK_OWNER",
+        description: "Nixpkgs inputs come from the upstream GitHub owner (NixOS by default)",
+        issue_kinds: &["non-upstream", "possible-typosquat"],
+        per_input: true,
+        enabled: |config| config.check_owner || config.strict_nixpkgs_owner,
+    },
+    Check {
+        name: "Hash algorithms",
+        flag: "--check-hash-algo",
+        env: "NIX_FLAKE_CHECKER_CHECK_HASH_ALGO",
+        description: "The NAR hashes of all inputs use sha256",
+        issue_kinds: &["unexpected-hash-algo"],
+        per_input: true,
+        enabled: |config| config.check_hash_algo,
+    },
+    Check {
+        name: "NAR hashes",
+        flag: "--require-hashes",
+        env: "NIX_FLAKE_CHECKER_REQUIRE_HASHES",
+        description: "All locked inputs have a NAR hash",
+        issue_kinds: &["missing-hash"],
+        per_input: true,
+        enabled: |config| config.require_hashes,
+    },
+    Check {
+        name: "Locked revisions",
+        flag: "--check-locked-revs",
+        env: "NIX_FLAKE_CHECKER_CHECK_LOCKED_REVS",
+        description: "Nixpkgs inputs are locked to a Git revision",
+        issue_kinds: &["unlocked-rev"],
+        per_input: true,
+        enabled: |config| config.check_locked_revs,
+    },
+    Check {
+        name: "Hash consistency",
+        flag: "--check-nar-hash-consistency",
+        env: "NIX_FLAKE_CHECKER_CHECK_NAR_HASH_CONSISTENCY",
+        description: "Nodes locked to the same revision record the same NAR hash",
+        issue_kinds: &["hash-conflict"],
+        per_input: true,
+        enabled: |config| config.check_nar_hash_consistency,
+    },
+    Check {
+        name: "Unlocked inputs",
+        flag: "--check-unlocked-inputs",
+        env: "NIX_FLAKE_CHECKER_CHECK_UNLOCKED_INPUTS",
+        description: "Every input declared in the flake.nix is in the flake.lock",
+        issue_kinds: &["unlocked-input"],
+        per_input: false,
+        enabled: |config| config.check_unlocked_inputs,
+    },
+    Check {
+        name: "Original URLs",
+        flag: "--check-original-urls",
+        env: "NIX_FLAKE_CHECKER_CHECK_ORIGINAL_URLS",
+        description: "The flake.nix URLs of inputs match what the flake.lock was locked from",
+        issue_kinds: &["original-mismatch"],
+        per_input: true,
+        enabled: |config| config.check_original_urls,
+    },
+    Check {
+        name: "Declared refs",
+        flag: "--check-declared-refs",
+        env: "NIX_FLAKE_CHECKER_CHECK_DECLARED_REFS",
+        description: "Nixpkgs inputs are locked from the branch that the flake.nix declares",
+        issue_kinds: &["declared-ref-mismatch"],
+        per_input: true,
+        enabled: |config| config.check_declared_refs,
+    },
+    Check {
+        name: "Lock format",
+        flag: "--enforce-lock-format",
+        env: "NIX_FLAKE_CHECKER_ENFORCE_LOCK_FORMAT",
+        description: "The flake.lock is formatted exactly the way Nix writes it",
+        issue_kinds: &["lock-format-drift"],
+        per_input: false,
+        enabled: |config| config.enforce_lock_format,
+    },
+    Check {
+        name: "Indirect inputs",
+        flag: "--deny-indirect",
+        env: "NIX_FLAKE_CHECKER_DENY_INDIRECT",
+        description: "Nixpkgs inputs aren't resolved using the flake registry",
+        issue_kinds: &["indirect-input"],
+        per_input: true,
+        enabled: |config| config.deny_indirect,
+    },
+    Check {
+        name: "Transitively indirect inputs",
+        flag: "--check-transitive-indirect",
+        env: "NIX_FLAKE_CHECKER_CHECK_TRANSITIVE_INDIRECT",
+        description: "Inputs don't follow inputs that are resolved using the flake registry",
+        issue_kinds: &["transitively-indirect"],
+        per_input: true,
+        enabled: |config| config.check_transitive_indirect,
+    },
+    Check {
+        name: "TLS transport",
+        flag: "--require-https",
+        env: "NIX_FLAKE_CHECKER_REQUIRE_HTTPS",
+        description: "Inputs aren't fetched over a transport without TLS, like http://",
+        issue_kinds: &["insecure-transport"],
+        per_input: true,
+        enabled: |config| config.require_https,
+    },
+    Check {
+        name: "Data inputs",
+        flag: "--check-data-inputs",
+        env: "NIX_FLAKE_CHECKER_CHECK_DATA_INPUTS",
+        description: "Data-only inputs, like patches, are locked with flake = false",
+        issue_kinds: &["unexpected-flake"],
+        per_input: true,
+        enabled: |config| config.check_data_inputs,
+    },
+    Check {
+        name: "Maximum inputs",
+        flag: "--max-inputs",
+        env: "NIX_FLAKE_CHECKER_MAX_INPUTS",
+        description: "The flake.lock has at most this many unique inputs",
+        issue_kinds: &["too-many-inputs"],
+        per_input: false,
+        enabled: |config| config.max_inputs.is_some(),
+    },
+    Check {
+        name: "Maximum depth",
+        flag: "--max-transitive-depth",
+        env: "NIX_FLAKE_CHECKER_MAX_TRANSITIVE_DEPTH",
+        description: "The input graph is at most this many levels deep",
+        issue_kinds: &["excessive-depth"],
+        per_input: false,
+        enabled: |config| config.max_transitive_depth.is_some(),
+    },
+    Check {
+        name: "Transitive Nixpkgs channels",
+        flag: "--require-consistent-transitive-nixpkgs",
+        env: "NIX_FLAKE_CHECKER_REQUIRE_CONSISTENT_TRANSITIVE_NIXPKGS",
+        description: "Transitive Nixpkgs inputs follow the same channel as the flake's own",
+        issue_kinds: &["mixed-nixpkgs-channels"],
+        per_input: false,
+        enabled: |config| config.require_consistent_transitive_nixpkgs,
+    },
+    Check {
+        name: "Approved revisions",
+        flag: "--approved-revs-file",
+        env: "NIX_FLAKE_CHECKER_APPROVED_REVS_FILE",
+        description: "Inputs are locked to a revision in the approved revisions file",
+        issue_kinds: &["unapproved-rev"],
+        per_input: true,
+        enabled: |config| config.approved_revs.is_some(),
+    },
+];
+
+/// A table of every check with the flag and environment variable that control it, whether it's
+/// enabled by default, and what it flags.
+pub(crate) fn list_checks() -> String {
+    let defaults = FlakeCheckConfig::default();
+    let rows: Vec<[&str; 5]> = CHECKS
+        .iter()
+        .map(|check| {
+            let default = if (check.enabled)(&defaults) {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            [
+                check.name,
+                check.flag,
+                check.env,
+                default,
+                check.description,
+            ]
+        })
+        .collect();
+
+    let width = |column: usize| rows.iter().map(|row| row[column].len()).max().unwrap_or(0);
+    let widths = [width(0), width(1), width(2), width(3)];
+
+    let mut text = String::new();
+    for row in rows {
+        for (cell, width) in row.iter().zip(widths) {
+            text.push_str(&format!("{cell:<width$}  "));
+        }
+        text.push_str(row[4]);
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use clap::CommandFactory;
+
+    use crate::issue::ISSUE_KINDS;
+    use crate::Cli;
+
+    use super::{list_checks, CHECKS};
+
+    #[test]
+    fn every_check_is_listed() {
+        // Each issue kind is reported by a check, except for violations of --condition, which is
+        // a policy of your own rather than a check, and nodes that can't be parsed, which are
+        // always reported
+        let covered: HashSet<&str> = CHECKS
+            .iter()
+            .flat_map(|check| check.issue_kinds.iter().copied())
+            .collect();
+        for kind in ISSUE_KINDS {
+            assert!(
+                covered.contains(kind) || ["violation", "unparsable-node"].contains(kind),
+                "no check reports {kind} issues"
+            );
+        }
+
+        // The flags and environment variables are the real ones
+        let command = Cli::command();
+        for check in CHECKS {
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == check.flag.strip_prefix("--"))
+                .unwrap_or_else(|| panic!("{} has no flag {}", check.name, check.flag));
+            assert_eq!(
+                arg.get_env().and_then(|env| env.to_str()),
+                Some(check.env),
+                "{}",
+                check.name
+            );
+        }
+
+        let listing = list_checks();
+        for check in CHECKS {
+            assert!(listing.contains(check.name), "{}", check.name);
+            assert!(listing.contains(check.env), "{}", check.env);
+        }
+        assert!(listing
+            .lines()
+            .any(|line| line.starts_with("Outdated inputs") && line.contains("enabled")));
+        assert!(listing
+            .lines()
+            .any(|line| line.starts_with("TLS transport") && line.contains("disabled")));
+    }
+}
@@ -0,0 +1,126 @@
+//! Colored terminal output, which follows `--color` as well as the `NO_COLOR`, `CLICOLOR`, and
+//! `CLICOLOR_FORCE` conventions for environment variables.
+
+use clap::ValueEnum;
+
+use crate::severity::Severity;
+
+/// When to color terminal output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum ColorChoice {
+    /// Color output written to a terminal, unless the environment says otherwise.
+    #[default]
+    Auto,
+    /// Always color output, regardless of the environment.
+    Always,
+    /// Never color output, regardless of the environment.
+    Never,
+}
+
+/// Whether to color output, given the `--color` choice, a lookup of environment variables, and
+/// whether the output is a terminal. An explicit `always` or `never` wins. Otherwise, a non-empty
+/// `NO_COLOR` disables color, a `CLICOLOR_FORCE` other than `0` forces it, and `CLICOLOR=0`
+/// disables it, in that order, before falling back to whether the output is a terminal.
+pub(crate) fn use_color(
+    choice: ColorChoice,
+    env: impl Fn(&str) -> Option<String>,
+    is_terminal: bool,
+) -> bool {
+    let set = |name: &str| env(name).filter(|value| !value.is_empty());
+
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            if set("NO_COLOR").is_some() {
+                false
+            } else if set("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+                true
+            } else if set("CLICOLOR").is_some_and(|value| value == "0") {
+                false
+            } else {
+                is_terminal
+            }
+        }
+    }
+}
+
+/// The label of a severity, like `ERROR`, colored by severity if `color` is set.
+pub(crate) fn severity_label(severity: Severity, color: bool) -> String {
+    let label = severity.to_string().to_uppercase();
+    if !color {
+        return label;
+    }
+
+    let code = match severity {
+        Severity::Error => "31",
+        Severity::Warning => "33",
+        Severity::Info => "36",
+    };
+    format!("\x1b[1;{code}m{label}\x1b[0m")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::severity::Severity;
+
+    use super::{severity_label, use_color, ColorChoice};
+
+    #[test]
+    fn color_resolution() {
+        // (choice, environment, terminal, expected)
+        type Env = &'static [(&'static str, &'static str)];
+        let cases: Vec<(ColorChoice, Env, bool, bool)> = vec![
+            (ColorChoice::Auto, &[], true, true),
+            (ColorChoice::Auto, &[], false, false),
+            (ColorChoice::Auto, &[("NO_COLOR", "1")], true, false),
+            // An empty NO_COLOR doesn't count
+            (ColorChoice::Auto, &[("NO_COLOR", "")], true, true),
+            (ColorChoice::Auto, &[("CLICOLOR", "0")], true, false),
+            (ColorChoice::Auto, &[("CLICOLOR", "1")], false, false),
+            (ColorChoice::Auto, &[("CLICOLOR_FORCE", "1")], false, true),
+            (ColorChoice::Auto, &[("CLICOLOR_FORCE", "0")], false, false),
+            (
+                ColorChoice::Auto,
+                &[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")],
+                false,
+                true,
+            ),
+            (
+                ColorChoice::Auto,
+                &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")],
+                true,
+                false,
+            ),
+            // The flag overrides the environment
+            (ColorChoice::Always, &[("NO_COLOR", "1")], false, true),
+            (ColorChoice::Always, &[("CLICOLOR", "0")], false, true),
+            (ColorChoice::Never, &[("CLICOLOR_FORCE", "1")], true, false),
+            (ColorChoice::Never, &[], true, false),
+        ];
+
+        for (choice, env, is_terminal, expected) in cases {
+            let env: HashMap<&str, &str> = env.iter().copied().collect();
+            assert_eq!(
+                use_color(
+                    choice,
+                    |name| env.get(name).map(|value| value.to_string()),
+                    is_terminal
+                ),
+                expected,
+                "unexpected result for {choice:?} with {env:?} (terminal: {is_terminal})"
+            );
+        }
+    }
+
+    #[test]
+    fn severity_labels() {
+        assert_eq!(severity_label(Severity::Error, false), "ERROR");
+        assert_eq!(
+            severity_label(Severity::Warning, true),
+            "\x1b[1;33mWARNING\x1b[0m"
+        );
+    }
+}
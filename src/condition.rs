@@ -1,23 +1,124 @@
 use cel_interpreter::{Context, Program, Value};
+use clap::ValueEnum;
 use parse_flake_lock::{FlakeLock, Node};
+use serde::Deserialize;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
 
 use crate::{
     error::FlakeCheckerError,
-    flake::{nixpkgs_deps, num_days_old},
-    issue::{Issue, IssueKind},
+    flake::{canonical_repo, is_outdated, is_supported, is_upstream, nixpkgs_deps, num_days_old},
+    issue::{Issue, IssueKind, Violation, ViolationSnapshot},
+    FlakeCheckConfig,
 };
 
 const KEY_GIT_REF: &str = "gitRef";
+const KEY_IS_NIXPKGS: &str = "isNixpkgs";
+const KEY_IS_OUTDATED: &str = "isOutdated";
+const KEY_IS_SUPPORTED: &str = "isSupported";
+const KEY_IS_UPSTREAM: &str = "isUpstream";
 const KEY_NUM_DAYS_OLD: &str = "numDaysOld";
 const KEY_OWNER: &str = "owner";
 const KEY_REF_STATUSES: &str = "refStatuses";
+const KEY_REV: &str = "rev";
 const KEY_SUPPORTED_REFS: &str = "supportedRefs";
 
+/// The inputs that a CEL condition is evaluated against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum ConditionScope {
+    /// The selected Nixpkgs inputs.
+    #[default]
+    Nixpkgs,
+    /// Every input of the flake, plus the selected Nixpkgs inputs of its inputs with
+    /// `--transitive`.
+    All,
+}
+
+/// The format of `--condition`: a CEL condition itself or a structured policy file that's compiled
+/// to one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum ConditionFormat {
+    /// A CEL condition.
+    #[default]
+    Cel,
+    /// The path of a JSON policy file.
+    Json,
+    /// The path of a YAML policy file.
+    Yaml,
+}
+
+/// A structured policy, which is a simpler alternative to writing CEL. Each field that's set adds a
+/// requirement, and an input must meet all of them.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Policy {
+    /// The maximum number of days old that an input may be (`numDaysOld <= max_days`).
+    max_days: Option<i64>,
+    /// The refs that inputs may follow (`[...].contains(gitRef)`).
+    allowed_refs: Option<Vec<String>>,
+    /// The owner that inputs must have (`owner == required_owner`).
+    required_owner: Option<String>,
+}
+
+impl Policy {
+    /// Parse a policy in the given structured format.
+    pub(crate) fn parse(contents: &str, format: ConditionFormat) -> Result<Self, String> {
+        match format {
+            ConditionFormat::Cel => Err(String::from("a CEL condition isn't a structured policy")),
+            ConditionFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+            ConditionFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// The CEL condition that's equivalent to the policy, which is the conjunction of its
+    /// requirements, or `None` if the policy has none.
+    pub(crate) fn to_cel(&self) -> Option<String> {
+        let mut requirements = vec![];
+        if let Some(max_days) = self.max_days {
+            requirements.push(format!("{KEY_NUM_DAYS_OLD} <= {max_days}"));
+        }
+        if let Some(allowed_refs) = &self.allowed_refs {
+            let refs: Vec<String> = allowed_refs.iter().map(|r| cel_string(r)).collect();
+            requirements.push(format!("[{}].contains({KEY_GIT_REF})", refs.join(", ")));
+        }
+        if let Some(owner) = &self.required_owner {
+            requirements.push(format!("{KEY_OWNER} == {}", cel_string(owner)));
+        }
+
+        (!requirements.is_empty()).then(|| requirements.join(" && "))
+    }
+}
+
+/// Read the structured policy file at `path` and compile it to a CEL condition.
+pub(crate) fn read_policy(
+    path: &Path,
+    format: ConditionFormat,
+) -> Result<String, FlakeCheckerError> {
+    let invalid =
+        |message: String| FlakeCheckerError::Policy(format!("{}: {message}", path.display()));
+
+    let contents = read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    Policy::parse(&contents, format)
+        .map_err(invalid)?
+        .to_cel()
+        .ok_or_else(|| invalid(String::from("it has no requirements")))
+}
+
+// A CEL string literal, which (for the characters that refs and owners use) is written like a JSON
+// string.
+fn cel_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+/// Evaluate the CEL `condition` for each input in `config.condition_scope`. Besides the input's own
+/// fields, the condition can use whether it's a selected Nixpkgs input (`isNixpkgs`) and the results
+/// of the standard checks (`isSupported`, `isOutdated`, and `isUpstream`), which are computed using
+/// the same settings in `config` as `check_flake_lock`.
 pub(super) fn evaluate_condition(
     flake_lock: &FlakeLock,
-    nixpkgs_keys: &[String],
+    config: &FlakeCheckConfig,
     condition: &str,
     ref_statuses: HashMap<String, String>,
     supported_refs: Vec<String>,
@@ -25,63 +126,248 @@ pub(super) fn evaluate_condition(
     let mut issues: Vec<Issue> = vec![];
     let mut ctx = Context::default();
     ctx.add_variable_from_value(KEY_REF_STATUSES, ref_statuses);
-    ctx.add_variable_from_value(KEY_SUPPORTED_REFS, supported_refs);
+    ctx.add_variable_from_value(KEY_SUPPORTED_REFS, supported_refs.clone());
 
-    let deps = nixpkgs_deps(flake_lock, nixpkgs_keys)?;
+    let program = compile_condition(condition)?;
 
-    for (name, node) in deps {
-        let (git_ref, last_modified, owner) = match node {
-            Node::Repo(repo) => (
-                repo.original.git_ref,
-                Some(repo.locked.last_modified),
-                Some(repo.original.owner),
-            ),
-            Node::Tarball(tarball) => (None, tarball.locked.last_modified, None),
-            _ => (None, None, None),
+    for (name, (node, is_nixpkgs)) in condition_inputs(flake_lock, config)? {
+        let (git_ref, last_modified, owner, canonical, rev) = match node {
+            Node::Repo(repo) => {
+                let canonical = canonical_repo(
+                    &repo.original.owner,
+                    &repo.original.repo,
+                    &config.repo_renames,
+                );
+                (
+                    repo.original.git_ref,
+                    Some(repo.locked.last_modified),
+                    Some(repo.original.owner),
+                    Some(canonical),
+                    Some(repo.locked.rev),
+                )
+            }
+            Node::Tarball(tarball) => (None, tarball.locked.last_modified, None, None, None),
+            _ => (None, None, None, None, None),
         };
 
-        add_cel_variables(&mut ctx, git_ref, last_modified, owner);
+        ctx.add_variable_from_value(KEY_IS_NIXPKGS, is_nixpkgs);
+        ctx.add_variable_from_value(
+            KEY_IS_SUPPORTED,
+            is_supported(
+                git_ref.as_deref(),
+                &supported_refs,
+                config.accept_small_channels,
+                &config.deny_ref_patterns,
+                config.strict_supported,
+            ),
+        );
+        ctx.add_variable_from_value(
+            KEY_IS_OUTDATED,
+            is_outdated(
+                last_modified,
+                git_ref.as_deref(),
+                config.grace_days,
+                config.max_days,
+            ),
+        );
+        ctx.add_variable_from_value(
+            KEY_IS_UPSTREAM,
+            is_upstream(
+                canonical
+                    .as_ref()
+                    .map(|(owner, repo)| (owner.as_str(), repo.as_str())),
+                &config.upstream_owner,
+                config.upstream_repo.as_deref(),
+            ),
+        );
+
+        // The snapshot records exactly the values that the condition sees
+        let snapshot = ViolationSnapshot {
+            git_ref: git_ref.unwrap_or_default(),
+            owner: owner.unwrap_or_default(),
+            num_days_old: last_modified.map(num_days_old).unwrap_or(0),
+            rev: rev.unwrap_or_default(),
+        };
+        add_cel_variables(&mut ctx, &snapshot);
 
-        match Program::compile(condition)?.execute(&ctx) {
+        match program.execute(&ctx) {
             Ok(result) => match result {
                 Value::Bool(b) if !b => {
                     issues.push(Issue {
                         input: name.clone(),
-                        kind: IssueKind::Violation,
+                        kind: IssueKind::Violation(Violation {
+                            snapshot,
+                            rule: None,
+                        }),
+                        location: None,
                     });
                 }
                 Value::Bool(b) if b => continue,
                 result => {
-                    return Err(FlakeCheckerError::NonBooleanCondition(
-                        result.type_of().to_string(),
-                    ))
+                    return Err(FlakeCheckerError::NonBooleanCondition {
+                        input: name,
+                        condition: condition.to_string(),
+                        kind: result.type_of().to_string(),
+                    })
                 }
             },
-            Err(e) => return Err(FlakeCheckerError::CelExecution(e)),
+            Err(e) => {
+                return Err(FlakeCheckerError::CelExecution {
+                    input: name,
+                    condition: condition.to_string(),
+                    source: e,
+                })
+            }
         }
     }
 
     Ok(issues)
 }
 
-fn add_cel_variables(
-    ctx: &mut Context,
-    git_ref: Option<String>,
-    last_modified: Option<i64>,
-    owner: Option<String>,
-) {
-    ctx.add_variable_from_value(KEY_GIT_REF, value_or_empty_string(git_ref));
-    ctx.add_variable_from_value(
-        KEY_NUM_DAYS_OLD,
-        value_or_zero(last_modified.map(num_days_old)),
-    );
-    ctx.add_variable_from_value(KEY_OWNER, value_or_empty_string(owner));
+/// Evaluate each of the named CEL `rules` like [evaluate_condition], with violations naming the
+/// rule that they violate.
+pub(super) fn evaluate_rules(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    rules: &[(String, String)],
+    ref_statuses: HashMap<String, String>,
+    supported_refs: Vec<String>,
+) -> Result<Vec<Issue>, FlakeCheckerError> {
+    let mut issues = vec![];
+    for (name, rule) in rules {
+        for mut issue in evaluate_condition(
+            flake_lock,
+            config,
+            rule,
+            ref_statuses.clone(),
+            supported_refs.clone(),
+        )? {
+            if let IssueKind::Violation(violation) = &mut issue.kind {
+                violation.rule = Some(name.clone());
+            }
+            issues.push(issue);
+        }
+    }
+    Ok(issues)
+}
+
+/// Compile the CEL `condition`, pointing to the location of any error in it.
+pub(crate) fn compile_condition(condition: &str) -> Result<Program, FlakeCheckerError> {
+    Program::compile(condition).map_err(|e| FlakeCheckerError::CelParse {
+        condition: point_to_error(condition, &e.to_string()),
+        source: e,
+    })
+}
+
+/// The rules in a directory of `.cel` files as `(name, condition)` pairs sorted by name, where
+/// each rule is named after its file (without the extension). Other files are ignored, but the
+/// directory must have at least one rule, and each rule must be a valid, non-empty condition.
+pub(crate) fn read_condition_dir(dir: &Path) -> Result<Vec<(String, String)>, FlakeCheckerError> {
+    let invalid =
+        |message: String| FlakeCheckerError::ConditionDir(format!("{}: {message}", dir.display()));
+
+    let mut rules = vec![];
+    for entry in read_dir(dir).map_err(|e| invalid(e.to_string()))? {
+        let path = entry.map_err(|e| invalid(e.to_string()))?.path();
+        if !path.is_file()
+            || path.extension().and_then(|extension| extension.to_str()) != Some("cel")
+        {
+            continue;
+        }
+        let Some(name) = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+
+        let rule = read_to_string(&path)
+            .map_err(|e| invalid(format!("couldn't read {name}.cel: {e}")))?
+            .trim()
+            .to_string();
+        if rule.is_empty() {
+            return Err(invalid(format!("{name}.cel is empty")));
+        }
+        Program::compile(&rule).map_err(|e| FlakeCheckerError::CelParse {
+            condition: format!(
+                "  in {}\n{}",
+                path.display(),
+                point_to_error(&rule, &e.to_string())
+            ),
+            source: e,
+        })?;
+
+        rules.push((name, rule));
+    }
+
+    if rules.is_empty() {
+        return Err(invalid(String::from("there are no .cel files in it")));
+    }
+    rules.sort();
+    Ok(rules)
 }
 
-fn value_or_empty_string(value: Option<String>) -> Value {
-    Value::from(value.unwrap_or(String::from("")))
+/// The inputs in `config.condition_scope`, by name, and whether each is a selected Nixpkgs input.
+pub(crate) fn condition_inputs(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+) -> Result<BTreeMap<String, (Node, bool)>, FlakeCheckerError> {
+    let deps = nixpkgs_deps(
+        flake_lock,
+        &config.nixpkgs_keys,
+        config.input_name_regex.as_ref(),
+        config.transitive,
+        config.max_transitive_depth,
+    )?;
+
+    let mut inputs: BTreeMap<String, (Node, bool)> = BTreeMap::new();
+    if config.condition_scope == ConditionScope::All {
+        for (name, node) in flake_lock.root.clone() {
+            inputs.insert(name, (node, false));
+        }
+    }
+    for (name, node) in deps {
+        inputs.insert(name, (node, true));
+    }
+
+    Ok(inputs)
+}
+
+// Show the line of the condition that a parse error occurred on with a caret under the error's
+// location, which the parser reports as a byte offset like `found at 9:10`. Without a location,
+// the whole condition is shown.
+fn point_to_error(condition: &str, message: &str) -> String {
+    let offset = message
+        .split_once(" at ")
+        .and_then(|(_, location)| {
+            location
+                .split(|c: char| !c.is_ascii_digit())
+                .next()?
+                .parse::<usize>()
+                .ok()
+        })
+        .filter(|offset| condition.is_char_boundary(*offset));
+
+    let Some(offset) = offset else {
+        return format!("  {condition}");
+    };
+
+    let line_start = condition[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = condition[offset..]
+        .find('\n')
+        .map_or(condition.len(), |i| offset + i);
+    let column = condition[line_start..offset].chars().count();
+
+    format!(
+        "  {}\n  {}^",
+        &condition[line_start..line_end],
+        " ".repeat(column)
+    )
 }
 
-fn value_or_zero(value: Option<i64>) -> Value {
-    Value::from(value.unwrap_or(0))
+fn add_cel_variables(ctx: &mut Context, snapshot: &ViolationSnapshot) {
+    ctx.add_variable_from_value(KEY_GIT_REF, snapshot.git_ref.clone());
+    ctx.add_variable_from_value(KEY_NUM_DAYS_OLD, snapshot.num_days_old);
+    ctx.add_variable_from_value(KEY_OWNER, snapshot.owner.clone());
+    ctx.add_variable_from_value(KEY_REV, snapshot.rev.clone());
 }
@@ -0,0 +1,88 @@
+//! Decompression of flake.lock files kept in compressed form, like `flake.lock.zst`.
+
+use crate::error::FlakeCheckerError;
+
+use std::path::Path;
+
+use clap::ValueEnum;
+
+/// A compression format that flake.lock files can be read from.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum Decompression {
+    /// Zstandard (requires building with the `zstd` feature).
+    Zstd,
+}
+
+impl Decompression {
+    /// The compression format implied by the file extension of `location`, e.g. `zstd` for
+    /// `flake.lock.zst`.
+    pub(crate) fn detect(location: &Path) -> Option<Self> {
+        match location.extension()?.to_str()? {
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Decompress a flake.lock's contents, which are decoded separately.
+    pub(crate) fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>, FlakeCheckerError> {
+        match self {
+            Self::Zstd => zstd(bytes),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd(bytes: &[u8]) -> Result<Vec<u8>, FlakeCheckerError> {
+    zstd::decode_all(bytes)
+        .map_err(|e| FlakeCheckerError::Decompress(format!("invalid zstd data ({e})")))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd(_bytes: &[u8]) -> Result<Vec<u8>, FlakeCheckerError> {
+    Err(FlakeCheckerError::Decompress(String::from(
+        "reading zstd-compressed files requires building with the zstd feature",
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::Decompression;
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            Decompression::detect(Path::new("flake.lock.zst")),
+            Some(Decompression::Zstd)
+        );
+        assert_eq!(
+            Decompression::detect(Path::new("https://example.com/flake.lock.zst")),
+            Some(Decompression::Zstd)
+        );
+        assert_eq!(Decompression::detect(Path::new("flake.lock")), None);
+        assert_eq!(Decompression::detect(Path::new("zst")), None);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        let compressed = std::fs::read("tests/flake.small.0.lock.zst").unwrap();
+        assert_eq!(
+            Decompression::Zstd.decompress(&compressed).unwrap(),
+            include_bytes!("../tests/flake.small.0.lock")
+        );
+
+        assert!(Decompression::Zstd
+            .decompress(include_bytes!("../tests/flake.small.0.lock"))
+            .is_err());
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_requires_feature() {
+        let compressed = std::fs::read("tests/flake.small.0.lock.zst").unwrap();
+        let error = Decompression::Zstd.decompress(&compressed).unwrap_err();
+        assert!(error.to_string().contains("zstd feature"), "{error}");
+    }
+}
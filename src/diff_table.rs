@@ -0,0 +1,164 @@
+//! Support for `--diff-base-ref`, which renders the inputs that changed since a base Git ref as a
+//! compact Markdown table, ready to be posted as a (sticky) pull request comment.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use parse_flake_lock::{FlakeLock, Node};
+
+use crate::error::FlakeCheckerError;
+use crate::flake::{check_flake_lock, num_days_old, FlakeCheckConfig, TRANSITIVE_INPUT_SEPARATOR};
+use crate::issue::Issue;
+use crate::since_commit::{diff, lock_at_revision};
+
+/// The length that revisions are shortened to in the table, as on GitHub.
+const SHORT_REV_LENGTH: usize = 7;
+
+/// The table of inputs of the `flake.lock` at `flake_lock_path` (whose contents are
+/// `lock_contents`) that were added, relocked, or removed since Git ref `base_ref`, with their old
+/// and new revisions, how much their age changed, and the issues that the change introduced or
+/// cleared. Both versions of the lock are checked using `config`. If the `flake.lock` didn't exist
+/// at `base_ref`, every input is new.
+pub(crate) fn changed_inputs_table(
+    flake_lock_path: &Path,
+    lock_contents: &str,
+    base_ref: &str,
+    config: &FlakeCheckConfig,
+    allowed_refs: Vec<String>,
+) -> Result<String, FlakeCheckerError> {
+    let flake_lock = FlakeLock::from_str_lenient(lock_contents)?.0;
+    let base = match lock_at_revision(flake_lock_path, base_ref)? {
+        Some(base_contents) => Some(FlakeLock::from_str_lenient(&base_contents)?.0),
+        None => None,
+    };
+
+    let issues = check_flake_lock(&flake_lock, config, allowed_refs.clone())?;
+    let base_issues = match &base {
+        Some(base) => check_flake_lock(base, config, allowed_refs)?,
+        None => vec![],
+    };
+
+    let inputs = walked_nodes(&flake_lock)?;
+    let base_inputs = match &base {
+        Some(base) => walked_nodes(base)?,
+        None => HashMap::new(),
+    };
+
+    // Inputs are new or relocked if they differ from the base, and removed if they're only there
+    let mut changed: BTreeSet<String> = match &base {
+        Some(base) => diff(&flake_lock, base)?.into_iter().collect(),
+        None => inputs.keys().cloned().collect(),
+    };
+    changed.extend(
+        base_inputs
+            .keys()
+            .filter(|name| !inputs.contains_key(*name))
+            .cloned(),
+    );
+
+    if changed.is_empty() {
+        return Ok(format!("No inputs changed since `{base_ref}`.\n"));
+    }
+
+    let mut table = format!(
+        "### Inputs changed since `{base_ref}`\n\n\
+         | Input | Old rev | New rev | Age | Issues |\n\
+         | :---- | :------ | :------ | :-- | :----- |\n"
+    );
+    for name in changed {
+        let node = inputs.get(&name).copied();
+        let base_node = base_inputs.get(&name).copied();
+        table.push_str(&format!(
+            "| `{name}` | {} | {} | {} | {} |\n",
+            rev_cell(base_node, "new"),
+            rev_cell(node, "removed"),
+            age_cell(base_node, node),
+            issues_cell(&kinds(&base_issues, &name), &kinds(&issues, &name)),
+        ));
+    }
+
+    Ok(table)
+}
+
+// The nodes of the inputs of `flake_lock`, keyed by input name as in issues.
+fn walked_nodes(flake_lock: &FlakeLock) -> Result<HashMap<String, &Node>, FlakeCheckerError> {
+    Ok(flake_lock
+        .walk()?
+        .into_iter()
+        .map(|input| (input.path.join(TRANSITIVE_INPUT_SEPARATOR), input.node))
+        .collect())
+}
+
+// The shortened revision of the node (if it has one), or `missing` if there's no node.
+fn rev_cell(node: Option<&Node>, missing: &str) -> String {
+    match node {
+        Some(node) => match node.locked_rev() {
+            Some(rev) => format!("`{}`", &rev[..rev.len().min(SHORT_REV_LENGTH)]),
+            None => String::from("—"),
+        },
+        None => format!("_{missing}_"),
+    }
+}
+
+// The node's age in days and, if it replaces a node, how much newer or older it is.
+fn age_cell(base_node: Option<&Node>, node: Option<&Node>) -> String {
+    let Some(timestamp) = node.and_then(last_modified) else {
+        return String::from("—");
+    };
+    let age = format!("{} days", num_days_old(timestamp));
+
+    match base_node.and_then(last_modified) {
+        Some(base_timestamp) => {
+            let delta = (timestamp - base_timestamp) / (24 * 60 * 60);
+            match delta {
+                0 => age,
+                delta if delta > 0 => format!("{age} ({delta} days newer)"),
+                delta => format!("{age} ({} days older)", -delta),
+            }
+        }
+        None => age,
+    }
+}
+
+// The issue kinds that the change introduced and cleared.
+fn issues_cell(base_kinds: &BTreeSet<&str>, kinds: &BTreeSet<&str>) -> String {
+    let list = |kinds: Vec<&&str>| {
+        kinds
+            .iter()
+            .map(|kind| format!("`{kind}`"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let new: Vec<&&str> = kinds.difference(base_kinds).collect();
+    let cleared: Vec<&&str> = base_kinds.difference(kinds).collect();
+
+    let mut cell = vec![];
+    if !new.is_empty() {
+        cell.push(format!("❌ new {}", list(new)));
+    }
+    if !cleared.is_empty() {
+        cell.push(format!("✅ cleared {}", list(cleared)));
+    }
+    if cell.is_empty() {
+        String::from("—")
+    } else {
+        cell.join("; ")
+    }
+}
+
+// The kinds of the issues with `input`.
+fn kinds<'a>(issues: &'a [Issue], input: &str) -> BTreeSet<&'a str> {
+    issues
+        .iter()
+        .filter(|issue| issue.input == input)
+        .map(|issue| issue.kind.name())
+        .collect()
+}
+
+fn last_modified(node: &Node) -> Option<i64> {
+    match node {
+        Node::Repo(repo) => Some(repo.locked.last_modified),
+        Node::Tarball(tarball) => tarball.locked.last_modified,
+        _ => None,
+    }
+}
@@ -0,0 +1,179 @@
+//! Support for `--dry-run`, which reports what a run would check, and whether it would make
+//! network requests, without checking anything or making any requests.
+
+use std::path::{Path, PathBuf};
+
+use parse_flake_lock::FlakeLock;
+
+use crate::checks::CHECKS;
+use crate::error::FlakeCheckerError;
+use crate::flake::{nixpkgs_deps, FlakeCheckConfig};
+use crate::lock_source::LockSource;
+
+/// A lockfile that a run would check.
+#[derive(Debug, PartialEq)]
+pub(crate) enum PlannedLock {
+    /// A local lockfile, with the names of the Nixpkgs inputs that would be checked, sorted.
+    Local { path: PathBuf, inputs: Vec<String> },
+    /// A lockfile at an `https://` URL, which is only fetched with `--online`.
+    Remote { url: String, online: bool },
+}
+
+/// What a run would do.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Plan {
+    pub(crate) lockfiles: Vec<PlannedLock>,
+    /// The names of the checks, and whether each of them would run.
+    pub(crate) checks: Vec<(&'static str, bool)>,
+    /// Whether telemetry would be sent once each lockfile is checked.
+    pub(crate) telemetry: bool,
+}
+
+impl Plan {
+    /// The plan for checking the lockfiles at `flake_lock_paths` using `config`. Local lockfiles
+    /// are read using `read_lock` to find their Nixpkgs inputs, while remote ones aren't fetched.
+    pub(crate) fn new(
+        flake_lock_paths: &[PathBuf],
+        config: &FlakeCheckConfig,
+        read_lock: impl Fn(&Path) -> Result<String, FlakeCheckerError>,
+        online: bool,
+        telemetry: bool,
+    ) -> Result<Self, FlakeCheckerError> {
+        let mut lockfiles = vec![];
+        for flake_lock_path in flake_lock_paths {
+            lockfiles.push(match LockSource::parse(flake_lock_path)? {
+                LockSource::File(path) => {
+                    let flake_lock: FlakeLock = read_lock(flake_lock_path)?.parse()?;
+                    let mut inputs: Vec<String> = nixpkgs_deps(
+                        &flake_lock,
+                        &config.nixpkgs_keys,
+                        config.input_name_regex.as_ref(),
+                        config.transitive,
+                        config.max_transitive_depth,
+                    )?
+                    .into_keys()
+                    .collect();
+                    inputs.sort();
+                    PlannedLock::Local { path, inputs }
+                }
+                LockSource::Https(url) => PlannedLock::Remote { url, online },
+            });
+        }
+
+        Ok(Self {
+            lockfiles,
+            checks: CHECKS
+                .iter()
+                .map(|check| (check.name, (check.enabled)(config)))
+                .collect(),
+            telemetry,
+        })
+    }
+
+    /// The plan as text, in the style of the text summary.
+    pub(crate) fn render(&self) -> String {
+        let mut text =
+            String::from("Dry run: nothing is checked and no network requests are made.\n\n");
+
+        text.push_str("Lockfiles:\n\n");
+        for lockfile in &self.lockfiles {
+            text.push_str(&match lockfile {
+                PlannedLock::Local { path, inputs } if inputs.is_empty() => {
+                    format!("* {}, with no Nixpkgs inputs to check\n", path.display())
+                }
+                PlannedLock::Local { path, inputs } => format!(
+                    "* {}, with {} Nixpkgs {} to check: {}\n",
+                    path.display(),
+                    inputs.len(),
+                    if inputs.len() == 1 { "input" } else { "inputs" },
+                    inputs.join(", ")
+                ),
+                PlannedLock::Remote { url, online: true } => {
+                    format!("* {url}, which would be fetched\n")
+                }
+                PlannedLock::Remote { url, online: false } => {
+                    format!("* {url}, which can't be fetched without --online\n")
+                }
+            });
+        }
+
+        text.push_str("\nChecks:\n\n");
+        for (name, enabled) in &self.checks {
+            let status = if *enabled { "would run" } else { "skipped" };
+            text.push_str(&format!("* {name}: {status}\n"));
+        }
+
+        text.push_str("\nNetwork requests:\n\n");
+        let fetched = self
+            .lockfiles
+            .iter()
+            .filter(|lockfile| matches!(lockfile, PlannedLock::Remote { online: true, .. }))
+            .count();
+        if fetched > 0 {
+            text.push_str(&format!(
+                "* Fetching {fetched} {}\n",
+                if fetched == 1 {
+                    "lockfile"
+                } else {
+                    "lockfiles"
+                }
+            ));
+        }
+        if self.telemetry {
+            text.push_str("* Sending telemetry (turn it off using --no-telemetry)\n");
+        }
+        if fetched == 0 && !self.telemetry {
+            text.push_str("* None\n");
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::flake::FlakeCheckConfig;
+
+    use super::{Plan, PlannedLock};
+
+    #[test]
+    fn plan_without_reading_remote_lockfiles() {
+        let paths = vec![
+            PathBuf::from("tests/flake.clean.0.lock"),
+            PathBuf::from("https://example.com/flake.lock"),
+        ];
+        let plan = Plan::new(
+            &paths,
+            &FlakeCheckConfig::default(),
+            |path: &Path| Ok(std::fs::read_to_string(path)?),
+            true,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            plan.lockfiles,
+            vec![
+                PlannedLock::Local {
+                    path: PathBuf::from("tests/flake.clean.0.lock"),
+                    inputs: vec![String::from("nixpkgs")],
+                },
+                PlannedLock::Remote {
+                    url: String::from("https://example.com/flake.lock"),
+                    online: true,
+                },
+            ]
+        );
+        assert!(plan.checks.contains(&("Supported branches", true)));
+        assert!(plan.checks.contains(&("Hash algorithms", false)));
+
+        let text = plan.render();
+        assert!(text.contains(
+            "* tests/flake.clean.0.lock, with 1 Nixpkgs input to check: nixpkgs\n\
+             * https://example.com/flake.lock, which would be fetched\n"
+        ));
+        assert!(text.ends_with("Network requests:\n\n* Fetching 1 lockfile\n"));
+    }
+}
@@ -0,0 +1,163 @@
+/// The units accepted in durations and their lengths in days. Months and years are approximate.
+const UNITS: &[(&[&str], i64)] = &[
+    (&["d", "day", "days"], 1),
+    (&["w", "wk", "week", "weeks"], 7),
+    (&["mo", "month", "months"], 30),
+    (&["y", "yr", "year", "years"], 365),
+];
+
+/// The designators of ISO 8601 durations in the order they have to appear in, and their lengths
+/// in days. Durations with a time part (like `PT12H`) aren't supported.
+const ISO_DESIGNATORS: &[(char, i64)] = &[('Y', 365), ('M', 30), ('W', 7), ('D', 1)];
+
+/// Parse a humanized duration like `45d`, `2w`, `3mo`, or `1 year`, or an ISO 8601 duration like
+/// `P2W` or `P1Y6M`, into a number of days.
+pub(crate) fn parse_days(duration: &str) -> Result<i64, String> {
+    let invalid = || {
+        format!(
+            "invalid duration {duration:?} (expected a number of days, weeks, months, or years, \
+             e.g. 45d, 2w, or 3mo, or an ISO 8601 duration like P2W)"
+        )
+    };
+
+    let duration = duration.trim();
+    if let Some(components) = duration.strip_prefix(['P', 'p']) {
+        return parse_iso_days(components).ok_or_else(invalid);
+    }
+
+    let unit_start = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (count, unit) = duration.split_at(unit_start);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let unit = unit.trim_start().to_ascii_lowercase();
+
+    let (_, days) = UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&unit.as_str()))
+        .ok_or_else(invalid)?;
+
+    count.checked_mul(*days).ok_or_else(invalid)
+}
+
+/// Parse a day count, which is either a plain number of days like `45` or a duration as accepted
+/// by [parse_days]. This is the parser for all options that take a number of days.
+pub(crate) fn parse_day_count<T: TryFrom<i64>>(days: &str) -> Result<T, String> {
+    let count = match days.trim().parse::<i64>() {
+        Ok(count) if count >= 0 => count,
+        Ok(_) => return Err(format!("invalid day count {days:?} (it can't be negative)")),
+        Err(_) => parse_days(days)?,
+    };
+
+    T::try_from(count).map_err(|_| format!("invalid day count {days:?} (it's too large)"))
+}
+
+// The number of days in the components of an ISO 8601 duration after the `P`, like `1Y6M`.
+fn parse_iso_days(components: &str) -> Option<i64> {
+    let components = components.to_ascii_uppercase();
+    let mut designators = ISO_DESIGNATORS.iter();
+    let mut rest = components.as_str();
+    let mut total: i64 = 0;
+
+    // At least one component is required
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let designator_start = rest.find(|c: char| !c.is_ascii_digit())?;
+        let (count, tail) = rest.split_at(designator_start);
+        let count: i64 = count.parse().ok()?;
+        let designator = tail.chars().next()?;
+
+        // Each designator may appear once, in order
+        let (_, days) = designators.find(|(d, _)| *d == designator)?;
+        total = total.checked_add(count.checked_mul(*days)?)?;
+        rest = &tail[designator.len_utf8()..];
+    }
+
+    Some(total)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_day_count, parse_days};
+
+    #[test]
+    fn durations() {
+        let cases: Vec<(&str, i64)> = vec![
+            ("45d", 45),
+            ("1 day", 1),
+            ("2w", 14),
+            ("2 weeks", 14),
+            ("3mo", 90),
+            ("1 Month", 30),
+            ("1y", 365),
+            (" 0d ", 0),
+        ];
+
+        for (duration, expected) in cases {
+            assert_eq!(parse_days(duration), Ok(expected), "{duration}");
+        }
+    }
+
+    #[test]
+    fn iso_durations() {
+        let cases: Vec<(&str, i64)> = vec![
+            ("P45D", 45),
+            ("P2W", 14),
+            ("P3M", 90),
+            ("P1Y", 365),
+            ("P1Y6M", 545),
+            ("P1M2W3D", 47),
+            ("p2w", 14),
+            ("P0D", 0),
+        ];
+
+        for (duration, expected) in cases {
+            assert_eq!(parse_days(duration), Ok(expected), "{duration}");
+        }
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for duration in [
+            "",
+            "45",
+            "d",
+            "2x",
+            "-3d",
+            "1.5w",
+            "3m",
+            "99999999999999999999d",
+            "P",
+            "P2",
+            "PW",
+            "P2D3M",
+            "P1M1M",
+            "PT12H",
+            "P1DT12H",
+            "P1.5W",
+            "P99999999999999999999D",
+        ] {
+            let error = parse_days(duration).unwrap_err();
+            assert!(error.starts_with("invalid duration"), "{duration}: {error}");
+        }
+    }
+
+    #[test]
+    fn day_counts() {
+        assert_eq!(parse_day_count::<i64>("45"), Ok(45));
+        assert_eq!(parse_day_count::<i64>("2w"), Ok(14));
+        assert_eq!(parse_day_count::<u32>("P3M"), Ok(90));
+        assert!(parse_day_count::<i64>("-3")
+            .unwrap_err()
+            .contains("can't be negative"));
+        assert!(parse_day_count::<u32>("P99999999Y")
+            .unwrap_err()
+            .contains("too large"));
+        assert!(parse_day_count::<u32>("soon")
+            .unwrap_err()
+            .starts_with("invalid duration"));
+    }
+}
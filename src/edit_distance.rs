@@ -0,0 +1,51 @@
+//! Edit distances between short strings, like GitHub owners, for spotting near misses.
+
+/// The number of single-character insertions, deletions, substitutions, and transpositions of
+/// adjacent characters that turn `a` into `b` (the optimal string alignment distance), so that
+/// `nixso` is as close to `nixos` as `nixoss` is.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // distances[i][j] is the distance between the first i characters of a and the first j of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::edit_distance;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("nixos", "nixos"), 0);
+        assert_eq!(edit_distance("nixso", "nixos"), 1);
+        assert_eq!(edit_distance("nixoss", "nixos"), 1);
+        assert_eq!(edit_distance("nxos", "nixos"), 1);
+        assert_eq!(edit_distance("nixus", "nixos"), 1);
+        assert!(edit_distance("nix-community", "nixos") > 1);
+        assert_eq!(edit_distance("", "nixos"), 5);
+        // Case matters; callers that don't care compare lowercased strings
+        assert_eq!(edit_distance("NixOS", "nixos"), 3);
+    }
+}
@@ -0,0 +1,71 @@
+//! Decoding of flake.lock files that aren't UTF-8, like UTF-16 files written by some editors on
+//! Windows.
+
+use crate::error::FlakeCheckerError;
+
+use encoding_rs::{Encoding, UTF_8};
+
+/// Parse an encoding label for `--lockfile-encoding`, like `utf-16le` or `windows-1252`. Any label
+/// in the [WHATWG Encoding Standard](https://encoding.spec.whatwg.org/#names-and-labels) works.
+pub(crate) fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("unknown encoding {label:?} (expected a label like utf-16le)"))
+}
+
+/// Decode a flake.lock's contents. A byte order mark determines the encoding if there is one and
+/// is removed; otherwise the contents are decoded as `encoding`, which is UTF-8 by default.
+/// Malformed contents are an error rather than being decoded with replacement characters.
+pub(crate) fn decode(
+    bytes: &[u8],
+    encoding: Option<&'static Encoding>,
+) -> Result<String, FlakeCheckerError> {
+    let (encoding, bytes) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (encoding, &bytes[bom_length..]),
+        None => (encoding.unwrap_or(UTF_8), bytes),
+    };
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|contents| contents.into_owned())
+        .ok_or_else(|| FlakeCheckerError::Encoding(format!("it isn't valid {}", encoding.name())))
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::{UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+    use super::{decode, parse_encoding};
+
+    #[test]
+    fn utf16_lock() {
+        let expected = include_str!("../tests/flake.small.0.lock");
+
+        // The byte order mark is enough to tell UTF-16 apart, whatever the encoding says
+        let bytes = include_bytes!("../tests/flake.utf16.0.lock");
+        assert_eq!(decode(bytes, None).unwrap(), expected);
+        assert_eq!(decode(bytes, Some(WINDOWS_1252)).unwrap(), expected);
+
+        // Without a byte order mark, the encoding has to be given
+        let without_bom = &bytes[2..];
+        assert_ne!(decode(without_bom, None).ok().as_deref(), Some(expected));
+        assert_eq!(decode(without_bom, Some(UTF_16LE)).unwrap(), expected);
+
+        // UTF-8 stays the default, with or without a byte order mark
+        assert_eq!(decode(expected.as_bytes(), None).unwrap(), expected);
+        let with_bom = [b"\xef\xbb\xbf", expected.as_bytes()].concat();
+        assert_eq!(decode(&with_bom, None).unwrap(), expected);
+
+        assert_eq!(
+            decode(b"\xff\xff{}", None).unwrap_err().to_string(),
+            "couldn't decode flake.lock: it isn't valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn encoding_labels() {
+        assert_eq!(parse_encoding("utf-16le"), Ok(UTF_16LE));
+        assert_eq!(parse_encoding("UTF-16BE"), Ok(UTF_16BE));
+        assert_eq!(parse_encoding("latin1"), Ok(WINDOWS_1252));
+        assert!(parse_encoding("utf-32").is_err());
+    }
+}
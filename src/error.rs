@@ -1,17 +1,75 @@
 #[derive(Debug, thiserror::Error)]
 pub enum FlakeCheckerError {
-    #[error("CEL execution error: {0}")]
-    CelExecution(#[from] cel_interpreter::ExecutionError),
-    #[error("CEL parsing error: {0}")]
-    CelParse(#[from] cel_interpreter::ParseError),
+    #[error("couldn't extract flake.lock from archive JSON: {0}")]
+    ArchiveJson(String),
+    #[error("CEL execution error for input `{input}`: {source}\n  condition: {condition}")]
+    CelExecution {
+        input: String,
+        condition: String,
+        source: cel_interpreter::ExecutionError,
+    },
+    #[error("CEL parsing error: {source}\n{condition}")]
+    CelParse {
+        /// The condition, with a caret under the location of the error if it's known.
+        condition: String,
+        source: cel_interpreter::ParseError,
+    },
+    #[error("couldn't decompress flake.lock: {0}")]
+    Decompress(String),
+    #[error("couldn't decode flake.lock: {0}")]
+    Encoding(String),
     #[error("env var error: {0}")]
     EnvVar(#[from] std::env::VarError),
     #[error("couldn't parse flake.lock: {0}")]
     FlakeLock(#[from] parse_flake_lock::FlakeLockParseError),
+    #[error("git error: {0}")]
+    Git(String),
+    #[error("{0} isn't in a Git repository")]
+    NotGitRepo(String),
     #[error("http client error: {0}")]
     Http(#[from] reqwest::Error),
-    #[error("CEL conditions must return a Boolean but returned {0} instead")]
-    NonBooleanCondition(String),
+    #[error("{0} needs network access, which --strict-telemetry-off rules out")]
+    StrictTelemetryOff(String),
+    #[error("couldn't read flake.lock URL: {0}")]
+    LockUrl(String),
+    #[error("invalid approved revisions file: {0}")]
+    ApprovedRevsFile(String),
+    #[error("invalid output format: {0}")]
+    OutputFormat(String),
+    #[error("invalid pins file {0}")]
+    PinsFile(String),
+    #[error("invalid channels file {0}")]
+    ChannelsFile(String),
+    #[error("invalid end-of-life dates file {0}")]
+    EolDatesFile(String),
+    #[error("invalid tiers file {0}")]
+    TiersFile(String),
+    #[error("invalid condition directory {0}")]
+    ConditionDir(String),
+    #[error("invalid policy file {0}")]
+    Policy(String),
+    #[error("invalid Nixpkgs keys file: {0}")]
+    NixpkgsKeysFile(String),
+    #[error("CEL conditions must return a Boolean but returned {kind} instead for input `{input}`\n  condition: {condition}")]
+    NonBooleanCondition {
+        input: String,
+        condition: String,
+        kind: String,
+    },
+    #[error("invalid settings: {0}")]
+    Settings(String),
+    #[error("invalid config file {0}")]
+    ConfigFile(String),
+    #[error("{0} already exists; pass --force to overwrite it")]
+    ConfigExists(String),
+    #[error("couldn't initialize flake-checker: {0}")]
+    Init(String),
+    #[error("no flake lockfile found at {0:?}")]
+    MissingFlakeLock(std::path::PathBuf),
+    #[error("no flake lockfiles match {0:?}")]
+    NoMatchingFlakeLocks(String),
+    #[error("timed out after {0:?} fetching ref statuses")]
+    RefStatusTimeout(std::time::Duration),
     #[error("couldn't access flake.lock: {0}")]
     Io(#[from] std::io::Error),
     #[error("couldn't parse flake.lock: {0}")]
@@ -23,3 +81,121 @@ pub enum FlakeCheckerError {
     #[error("invalid flake.lock: {0}")]
     Invalid(String),
 }
+
+impl FlakeCheckerError {
+    /// The exit code for the error, which tells kinds of failures apart in CI. Exit code 1 is
+    /// reserved for issues found in a `flake.lock`, while errors exit with:
+    ///
+    /// * 2: invalid configuration, like flags, settings, CEL conditions, or the files they name
+    /// * 3: a `flake.lock` that can't be read as one
+    /// * 4: a filesystem or Git failure, like a missing `flake.lock` that isn't skipped
+    /// * 5: a network failure
+    /// * 6: a failure to render the summary, which is a bug in Flake Checker
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::CelExecution { .. }
+            | Self::CelParse { .. }
+            | Self::ConditionDir(_)
+            | Self::Policy(_)
+            | Self::EnvVar(_)
+            | Self::StrictTelemetryOff(_)
+            | Self::ApprovedRevsFile(_)
+            | Self::OutputFormat(_)
+            | Self::PinsFile(_)
+            | Self::ChannelsFile(_)
+            | Self::EolDatesFile(_)
+            | Self::TiersFile(_)
+            | Self::NixpkgsKeysFile(_)
+            | Self::NonBooleanCondition { .. }
+            | Self::Settings(_)
+            | Self::ConfigFile(_)
+            | Self::ConfigExists(_) => 2,
+            Self::ArchiveJson(_)
+            | Self::Decompress(_)
+            | Self::Encoding(_)
+            | Self::FlakeLock(_)
+            | Self::Json(_)
+            | Self::Invalid(_) => 3,
+            Self::Git(_)
+            | Self::NotGitRepo(_)
+            | Self::Init(_)
+            | Self::MissingFlakeLock(_)
+            | Self::NoMatchingFlakeLocks(_)
+            | Self::Io(_) => 4,
+            Self::Http(_) | Self::LockUrl(_) | Self::RefStatusTimeout(_) => 5,
+            Self::Render(_) | Self::Template(_) => 6,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use cel_interpreter::Program;
+
+    use super::FlakeCheckerError;
+
+    #[test]
+    fn exit_codes() {
+        let cases = vec![
+            (
+                FlakeCheckerError::CelParse {
+                    condition: String::from("owner =="),
+                    source: Program::compile("owner ==").unwrap_err(),
+                },
+                2,
+            ),
+            (FlakeCheckerError::Settings(String::from("bad")), 2),
+            (FlakeCheckerError::ConfigFile(String::from("bad")), 2),
+            (FlakeCheckerError::ConfigExists(String::from("bad")), 2),
+            (FlakeCheckerError::PinsFile(String::from("bad")), 2),
+            (FlakeCheckerError::ChannelsFile(String::from("bad")), 2),
+            (FlakeCheckerError::EolDatesFile(String::from("bad")), 2),
+            (FlakeCheckerError::TiersFile(String::from("bad")), 2),
+            (
+                FlakeCheckerError::NonBooleanCondition {
+                    input: String::from("nixpkgs"),
+                    condition: String::from("owner"),
+                    kind: String::from("string"),
+                },
+                2,
+            ),
+            (
+                FlakeCheckerError::FlakeLock(
+                    "{".parse::<parse_flake_lock::FlakeLock>().unwrap_err(),
+                ),
+                3,
+            ),
+            (
+                FlakeCheckerError::Json(serde_json::from_str::<u8>("{").unwrap_err()),
+                3,
+            ),
+            (FlakeCheckerError::Invalid(String::from("bad")), 3),
+            (FlakeCheckerError::Decompress(String::from("bad")), 3),
+            (
+                FlakeCheckerError::Io(std::io::Error::from(std::io::ErrorKind::NotFound)),
+                4,
+            ),
+            (FlakeCheckerError::Git(String::from("bad")), 4),
+            (FlakeCheckerError::Init(String::from("bad")), 4),
+            (
+                FlakeCheckerError::MissingFlakeLock(std::path::PathBuf::from("flake.lock")),
+                4,
+            ),
+            (
+                FlakeCheckerError::NoMatchingFlakeLocks(String::from("*/flake.lock")),
+                4,
+            ),
+            (FlakeCheckerError::LockUrl(String::from("bad")), 5),
+            (
+                FlakeCheckerError::RefStatusTimeout(Duration::from_secs(5)),
+                5,
+            ),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{error}");
+        }
+    }
+}
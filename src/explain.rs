@@ -0,0 +1,219 @@
+/// A description of the check behind an issue kind, why it matters, and how to fix issues of that
+/// kind, keyed by the kind's name (see [`crate::issue::IssueKind::name`]). `max_days` is the
+/// maximum age of Nixpkgs inputs that the outdated check is configured with.
+pub(crate) fn explanation(kind: &str, max_days: i64) -> Option<String> {
+    let explanation = match kind {
+        "disallowed" => {
+            "A Nixpkgs input uses a Git ref that isn't a supported branch of Nixpkgs, like an \
+             end-of-life release or a branch that doesn't receive binary builds. Unsupported \
+             branches don't get security fixes, and packages on them may not be cached. To fix \
+             this, point the input at a supported branch (run `flake-checker refs fetch` to see \
+             the current statuses), e.g. `nixpkgs.url = \"github:NixOS/nixpkgs/nixos-unstable\"`, \
+             and run `nix flake update nixpkgs`."
+        }
+        "outdated" => {
+            return Some(format!(
+                "A Nixpkgs input hasn't been updated in more than {max_days} days, the maximum \
+                 age set using `--max-days` (or `--max-age`). Older revisions miss security \
+                 fixes and package updates that have landed since. To fix this, run \
+                 `nix flake update nixpkgs` (or the name of the affected input), ideally on a \
+                 schedule using something like the update-flake-lock GitHub Action."
+            ));
+        }
+        "non-upstream" => {
+            "A Nixpkgs input is fetched from a GitHub owner other than NixOS, which usually means \
+             it's a fork. Forks can lag behind upstream and don't benefit from its binary cache \
+             or security fixes. To fix this, use `github:NixOS/nixpkgs` as the input URL, or set \
+             `--upstream-owner` (and `--upstream-repo`) if you intentionally maintain your own \
+             Nixpkgs."
+        }
+        "violation" => {
+            "A Nixpkgs input doesn't satisfy the Common Expression Language (CEL) policy supplied \
+             using `--condition`. The policy is specific to your project, so check the condition \
+             to see which requirement the input fails, then update or repin the input so that it \
+             satisfies the policy."
+        }
+        "unexpected-hash-algo" => {
+            "An input's NAR hash uses an algorithm other than sha256. Nix locks inputs using \
+             sha256 by default, so another algorithm usually means the lock was edited by hand or \
+             written by an unusual tool. To fix this, relock the input using \
+             `nix flake update <input>`."
+        }
+        "indirect-input" => {
+            "A Nixpkgs input is resolved using the flake registry (e.g. \
+             `inputs.nixpkgs.url = \"nixpkgs\"`) rather than an explicit URL. What the registry \
+             resolves to depends on the machine evaluating the flake, which makes the flake less \
+             reproducible. To fix this, use an explicit URL like \
+             `github:NixOS/nixpkgs/nixos-unstable`."
+        }
+        "transitively-indirect" => {
+            "An input of the flake has a dependency that follows (possibly through a chain of \
+             `follows`) an input that's resolved using the flake registry, like a \
+             `home-manager` input whose `nixpkgs` follows the flake's own \
+             `inputs.nixpkgs.url = \"nixpkgs\"`. The dependency then depends on the machine \
+             evaluating the flake just like the indirect input does, which is easy to miss since \
+             the dependency's own flake.nix pins it. To fix this, give the followed input an \
+             explicit URL like `github:NixOS/nixpkgs/nixos-unstable`."
+        }
+        "insecure-transport" => {
+            "An input is fetched over a transport without TLS, like `http://` or `git://`, so its \
+             contents can be tampered with in transit when the lock is updated. To fix this, \
+             switch the input URL to `https://` (or `git+https://`) and relock it."
+        }
+        "too-many-inputs" => {
+            "The flake.lock has more unique inputs, including transitive inputs, than allowed by \
+             `--max-inputs`. Every input adds evaluation time and something to keep up to date. \
+             To fix this, deduplicate inputs using `follows` (e.g. \
+             `inputs.home-manager.inputs.nixpkgs.follows = \"nixpkgs\"`) or drop unused inputs."
+        }
+        "future-timestamp" => {
+            "An input's `lastModified` timestamp is in the future, usually because of clock skew \
+             on the machine that locked it or a hand-edited flake.lock. The outdated check can't \
+             trust the input's age, so it treats the input as 0 days old. This is informational \
+             by default. To fix this, check the machine's clock and relock the input using \
+             `nix flake update <input>`."
+        }
+        "unapproved-rev" => {
+            "An input is locked to a Git revision that isn't in the file of approved \
+             `owner/repo rev` pins given by `--approved-revs-file`, so the pin hasn't been \
+             audited. To fix this, review the revision and add it to the approved revisions file, \
+             or relock the input to an approved revision."
+        }
+        "missing-hash" => {
+            "A locked input has no `narHash`, so Nix can't verify the input's contents against \
+             the lock. This usually means that the flake.lock was edited by hand or is malformed. \
+             To fix this, relock the input using `nix flake update <input>` so that Nix records \
+             its hash."
+        }
+        "unlocked-input" => {
+            "The flake.nix next to the flake.lock declares an input that the flake.lock doesn't \
+             have, so the lock is stale and Nix will update it the next time the flake is used. \
+             To fix this, run `nix flake lock` and commit the updated flake.lock. Inputs are found \
+             by a best-effort scan of flake.nix rather than by evaluating it."
+        }
+        "excessive-depth" => {
+            "The flake.lock's input graph is nested more deeply than `--max-transitive-depth` \
+             allows, counting the flake's own inputs as depth 1. Deep graphs are slow to walk and \
+             usually mean that inputs bring in their own copies of dependencies. To fix this, use \
+             `follows` to point nested inputs at the flake's own inputs, e.g. \
+             `inputs.home-manager.inputs.nixpkgs.follows = \"nixpkgs\"`."
+        }
+        "mixed-nixpkgs-channels" => {
+            "One of the flake's inputs brings in its own copy of Nixpkgs on a different channel \
+             than the flake's own Nixpkgs, like a `home-manager/nixpkgs` on `nixos-unstable` when \
+             the flake's `nixpkgs` is on `nixos-24.11`. Each copy is evaluated separately, which \
+             slows down evaluation, and packages from the two can be subtly incompatible. To fix \
+             this, make the input's Nixpkgs follow the flake's own, e.g. \
+             `inputs.home-manager.inputs.nixpkgs.follows = \"nixpkgs\"`."
+        }
+        "unparsable-node" => {
+            "A node in the flake.lock couldn't be parsed, like one with a malformed `inputs` or \
+             `locked` attribute, so it was left out and the rest of the flake.lock was checked \
+             without it (along with any inputs that lead to it). This is always reported, since \
+             nothing about the node can be checked. To fix this, regenerate the flake.lock using \
+             `nix flake lock` rather than editing it by hand."
+        }
+        "lock-format-drift" => {
+            "The flake.lock isn't formatted exactly the way Nix writes it (two-space indentation, \
+             sorted keys, and a trailing newline), which means that it was edited by hand or \
+             reformatted by other tooling. This is only checked with `--enforce-lock-format`. To \
+             fix this, regenerate the flake.lock using `nix flake lock` and exclude it from \
+             formatters."
+        }
+        "original-mismatch" => {
+            "The flake.nix next to the flake.lock declares a different URL for an input than the \
+             one that the flake.lock was locked from, so the lock is stale or was edited by hand. \
+             To fix this, run `nix flake lock` (or `nix flake update <input>`) and commit the \
+             updated flake.lock. URLs are found by a best-effort scan of flake.nix rather than by \
+             evaluating it, so only URLs given as plain strings are compared."
+        }
+        "unexpected-flake" => {
+            "An input that's clearly data rather than a flake, like a single file or a patch, is \
+             locked as a flake. Nix then looks for a flake.nix in it, which can fail or surprise \
+             you at evaluation time. This is only checked with `--check-data-inputs`. To fix \
+             this, declare the input with `flake = false` and run `nix flake lock`."
+        }
+        "declared-ref-mismatch" => {
+            "A Nixpkgs input's URL in flake.nix names a different branch (like `nixos-24.11`) \
+             than the one the flake.lock was locked from, usually because flake.nix was updated \
+             to a new channel without relocking. The flake then keeps using the old channel. This \
+             is only checked with `--check-declared-refs`. To fix this, run \
+             `nix flake update <input>` and commit the updated flake.lock. Refs are found by a \
+             best-effort scan of flake.nix rather than by evaluating it."
+        }
+        "temporary-ref" => {
+            "A Nixpkgs input follows a pull request (like `refs/pull/123/head`) or a branch that's \
+             named as work in progress (like `wip/openssl` or `fix-openssl`). Such pins are meant \
+             to be temporary, until a fix is merged, but are easily forgotten, leaving the flake on \
+             code that never gets updates. This is only checked with `--check-temporary-refs`, \
+             which `--temporary-refs-all-inputs` extends to every input. To fix this, switch the \
+             input back to a regular branch once the change is merged."
+        }
+        "hash-conflict" => {
+            "Several nodes in the flake.lock are locked to the same revision of the same \
+             repository but record different NAR hashes for it. A revision always has the same \
+             contents, so at most one of the hashes can be right, which points to a corrupted or \
+             tampered-with flake.lock (or an attempt at a hash collision). This is only checked \
+             with `--check-nar-hash-consistency`. To fix this, find out how the flake.lock came to \
+             be this way, then relock the affected inputs using `nix flake update <input>`."
+        }
+        "approaching-eol" => {
+            "A Nixpkgs input follows a stable channel (like `nixos-24.11`) that reaches its end of \
+             life soon, after which it's no longer supported and fails the supported-ref check. \
+             This is only checked with `--warn-eol`, and is a warning by default so that you get \
+             lead time to migrate. `--deprecation-window` sets how many days ahead to warn. To fix \
+             this, switch the input to the next release's channel and run \
+             `nix flake update <input>`."
+        }
+        "possible-typosquat" => {
+            "A Nixpkgs input comes from a GitHub owner that's a single typo away from the upstream \
+             owner, like `nixso` or `nixoss` rather than `NixOS`. Attackers register such \
+             look-alike organizations to get malicious forks into flakes, so this is far more \
+             suspicious than an ordinary fork. This is only checked with \
+             `--strict-nixpkgs-owner`, which also requires the owner's case to match exactly. To \
+             fix this, check where the input's URL came from and point it at the upstream owner, \
+             like `github:NixOS/nixpkgs`."
+        }
+        "unlocked-rev" => {
+            "A Nixpkgs input isn't locked to a Git revision, so the flake.lock doesn't pin down \
+             which Nixpkgs the input is. This mostly happens with indirect inputs (like \
+             `inputs.nixpkgs.url = \"nixpkgs\";`), which resolve to whatever the flake registry \
+             points to, when the flake.lock was written by an old or unusual tool. Tarball and path \
+             inputs are locked by their NAR hash instead and aren't flagged. To fix this, point the \
+             input at an explicit URL like `github:NixOS/nixpkgs/nixos-unstable` and run \
+             `nix flake update <input>`."
+        }
+        _ => return None,
+    };
+
+    Some(explanation.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::issue::ISSUE_KINDS;
+
+    use super::explanation;
+
+    #[test]
+    fn every_kind_is_explained() {
+        for kind in ISSUE_KINDS {
+            assert!(
+                explanation(kind, 30).is_some_and(|text| !text.is_empty()),
+                "no explanation for {kind}"
+            );
+        }
+
+        assert_eq!(explanation("unknown", 30), None);
+    }
+
+    #[test]
+    fn configured_max_age() {
+        assert!(explanation("outdated", 30)
+            .unwrap()
+            .contains("in more than 30 days"));
+        assert!(explanation("outdated", 45)
+            .unwrap()
+            .contains("in more than 45 days"));
+    }
+}
@@ -1,66 +1,551 @@
 #![allow(dead_code)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::LazyLock;
 
-use crate::issue::{Disallowed, Issue, IssueKind, NonUpstream, Outdated};
+use crate::checks::CHECKS;
+use crate::condition::{condition_inputs, ConditionScope};
+use crate::edit_distance::edit_distance;
+use crate::issue::{
+    ApproachingEol, Disallowed, DisallowedReason, ExcessiveDepth, FutureTimestamp, HashConflict,
+    IndirectInput, InsecureTransport, Issue, IssueKind, LockFormatDrift, MissingHash,
+    MixedNixpkgsChannels, NonUpstream, Outdated, PossibleTyposquat, TemporaryRef, TooManyInputs,
+    TransitivelyIndirect, UnapprovedRev, UnexpectedFlake, UnexpectedHashAlgo, UnlockedRev,
+};
+use crate::pins::Pins;
+use crate::ref_statuses::EOL_DATES;
+use crate::report::{CheckOutcome, ChecksRun, CleanInput, InputAges, InputChecks};
+use crate::severity::{has_errors, SeverityMap};
+use crate::tiers::Tiers;
 use crate::FlakeCheckerError;
 
-use chrono::{Duration, Utc};
-use parse_flake_lock::{FlakeLock, Node};
+use chrono::{NaiveDate, Utc};
+use parse_flake_lock::{canonical_format, FlakeLock, Input, Node, RepoNode};
+use regex::Regex;
 
 pub const MAX_DAYS: i64 = 30;
 
+/// How many days before a stable channel's end of life `--warn-eol` starts warning about it.
+pub const DEPRECATION_WINDOW: u32 = 30;
+
+/// The GitHub owner of upstream Nixpkgs.
+pub const UPSTREAM_OWNER: &str = "NixOS";
+
+/// The hash algorithm that Nix uses for the `narHash` of locked inputs.
+pub const EXPECTED_HASH_ALGO: &str = "sha256";
+
+/// The separator between input names in the names of transitive inputs, e.g. `home-manager/nixpkgs`.
+pub const TRANSITIVE_INPUT_SEPARATOR: &str = "/";
+
+/// The release dates of NixOS versions, used for the `--grace-days` grace period. They're parsed
+/// once, the first time that they're needed.
+static RELEASE_DATES: LazyLock<HashMap<String, NaiveDate>> = LazyLock::new(|| {
+    let dates: HashMap<String, String> =
+        serde_json::from_str(include_str!("../release-dates.json"))
+            .expect("release-dates.json should be a map of releases to dates");
+    dates
+        .into_iter()
+        .map(|(release, date)| {
+            let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .expect("release-dates.json should have YYYY-MM-DD dates");
+            (release, date)
+        })
+        .collect()
+});
+
+/// The first parts of branch names (before a `/`, `-`, or `_`) that mark branches as work in
+/// progress rather than as long-lived branches.
+const TEMPORARY_BRANCH_PREFIXES: &[&str] = &[
+    "backport", "bugfix", "draft", "feat", "feature", "fix", "hotfix", "pr", "temp", "tmp", "wip",
+];
+
+/// URL schemes (or the transport part of schemes like `git+http`) that don't use TLS.
+const INSECURE_SCHEMES: &[&str] = &["http", "git"];
+
+#[derive(Clone)]
 pub(crate) struct FlakeCheckConfig {
     pub check_supported: bool,
+    /// Like `check_supported`, but inputs without a Git ref (like rev-only pins) fail too.
+    pub strict_supported: bool,
     pub check_outdated: bool,
     pub check_owner: bool,
+    /// Like `check_owner`, but the owner's case has to match too, and owners that are a single
+    /// edit away from the upstream owner are flagged as possible typosquats.
+    pub strict_nixpkgs_owner: bool,
+    pub check_hash_algo: bool,
+    /// Flag locked inputs without a NAR hash.
+    pub require_hashes: bool,
+    /// Flag Nixpkgs inputs that aren't locked to a Git revision.
+    pub check_locked_revs: bool,
+    /// Flag nodes that are locked to the same revision of the same repository but record
+    /// different NAR hashes for it.
+    pub check_nar_hash_consistency: bool,
+    /// Warn about Nixpkgs inputs that follow a stable channel whose end of life is within
+    /// `deprecation_window` days.
+    pub warn_eol: bool,
+    pub deprecation_window: u32,
+    /// Flag inputs declared in a `flake.nix` next to the `flake.lock` that the lock doesn't have.
+    pub check_unlocked_inputs: bool,
+    /// Flag inputs whose URL in a `flake.nix` next to the `flake.lock` doesn't match the original
+    /// reference that the lock records.
+    pub check_original_urls: bool,
+    /// Flag a `flake.lock` that isn't formatted exactly the way Nix writes it.
+    pub enforce_lock_format: bool,
+    pub deny_indirect: bool,
+    /// Flag inputs whose dependencies follow an input that's resolved using the flake registry.
+    pub check_transitive_indirect: bool,
+    /// Flag transitive Nixpkgs inputs that follow a different channel than the flake's own.
+    pub require_consistent_transitive_nixpkgs: bool,
+    pub require_https: bool,
+    /// Flag data-only inputs (like patches) that are locked as flakes.
+    pub check_data_inputs: bool,
+    /// Check that the Nixpkgs inputs are locked from the Git ref that a `flake.nix` next to the
+    /// `flake.lock` declares for them.
+    pub check_declared_refs: bool,
+    /// Flag Nixpkgs inputs that follow a pull request or a work-in-progress branch.
+    pub check_temporary_refs: bool,
+    /// Apply `check_temporary_refs` to every input rather than only to Nixpkgs inputs.
+    pub temporary_refs_all_inputs: bool,
+    pub accept_small_channels: bool,
+    pub max_inputs: Option<usize>,
+    /// How many levels of inputs deep the input graph may go, where the flake's own inputs are at
+    /// depth 1. This also bounds the walk for `transitive`.
+    pub max_transitive_depth: Option<usize>,
+    pub max_days: i64,
+    pub grace_days: u32,
+    pub transitive: bool,
+    pub first_party_only: bool,
+    /// Show issues with transitive inputs in the human-readable summary. They're always part of
+    /// the check report.
+    pub include_transitive_in_summary: bool,
     pub fail_mode: bool,
+    pub fail_on_warnings: bool,
+    /// Demote every error to a warning, so that no issue fails the check. This overrides
+    /// `fail_mode`, `fail_on_warnings`, and the severity map.
+    pub warn_only: bool,
+    /// Stop checking as soon as an issue that fails the check (an error) is found.
+    pub fail_fast: bool,
+    /// How many errors a lockfile may have before they fail the check.
+    pub fail_threshold: usize,
     pub nixpkgs_keys: Vec<String>,
+    pub upstream_owner: String,
+    /// The GitHub repository considered upstream by the owner check, if the repository matters.
+    pub upstream_repo: Option<String>,
+    pub input_name_regex: Option<Regex>,
+    pub deny_ref_patterns: Vec<Regex>,
+    /// The approved `(owner/repo, rev)` pins. If set, inputs locked to any other revision are
+    /// flagged.
+    pub approved_revs: Option<HashSet<(String, String)>>,
+    pub severity_map: SeverityMap,
+    /// Inputs that are deliberately pinned and thus exempt from the outdated check.
+    pub pins: Pins,
+    /// With `--since-commit`, the inputs that changed since that commit (see
+    /// [crate::since_commit::changed_inputs]). Issues with other inputs are only informational.
+    pub changed_inputs: Option<HashSet<String>>,
+    /// The statuses of Nixpkgs refs (as in `ref-statuses.json`), which tell end-of-life channels
+    /// apart from refs that aren't channels at all. Without them, disallowed refs get no more
+    /// specific reason.
+    pub ref_statuses: HashMap<String, String>,
+    /// The end-of-life dates of NixOS releases (as in `eol-dates.json`), keyed by release (e.g.
+    /// `24.11`), for `--warn-eol`.
+    pub eol_dates: HashMap<String, NaiveDate>,
+    /// Renamed repositories (like mirrors of Nixpkgs) that the owner and approved revision checks
+    /// treat as their canonical repositories.
+    pub repo_renames: Vec<RepoRename>,
+    /// The inputs that `--condition` is evaluated against.
+    pub condition_scope: ConditionScope,
+    /// The tiers whose policies apply to their inputs in place of this config's.
+    pub tiers: Tiers,
+}
+
+impl FlakeCheckConfig {
+    /// The config with `--ci-strict` applied. In CI, a Nixpkgs input without a ref follows
+    /// whatever the default branch is at the time it's locked, which makes builds unreproducible,
+    /// so such inputs are flagged as if `strict_supported` were set. Outside of CI, nothing
+    /// changes.
+    pub(crate) fn with_ci_strict(self, is_ci: bool) -> Self {
+        Self {
+            strict_supported: self.strict_supported || is_ci,
+            ..self
+        }
+    }
+
+    /// A description of every setting, which keys cached reports (see
+    /// [crate::report::cached_or_check]) so that changing any setting invalidates the cache. Sets
+    /// and maps are sorted, so the same settings always have the same fingerprint.
+    pub(crate) fn fingerprint(&self) -> String {
+        // Destructured so that each new setting has to be added here
+        let Self {
+            check_supported,
+            strict_supported,
+            check_outdated,
+            check_owner,
+            strict_nixpkgs_owner,
+            check_hash_algo,
+            require_hashes,
+            check_locked_revs,
+            check_nar_hash_consistency,
+            warn_eol,
+            deprecation_window,
+            check_unlocked_inputs,
+            check_original_urls,
+            enforce_lock_format,
+            deny_indirect,
+            check_transitive_indirect,
+            require_consistent_transitive_nixpkgs,
+            require_https,
+            check_data_inputs,
+            check_declared_refs,
+            check_temporary_refs,
+            temporary_refs_all_inputs,
+            accept_small_channels,
+            max_inputs,
+            max_transitive_depth,
+            max_days,
+            grace_days,
+            transitive,
+            first_party_only,
+            include_transitive_in_summary,
+            fail_mode,
+            fail_on_warnings,
+            warn_only,
+            fail_fast,
+            fail_threshold,
+            nixpkgs_keys,
+            upstream_owner,
+            upstream_repo,
+            input_name_regex,
+            deny_ref_patterns,
+            approved_revs,
+            severity_map,
+            pins,
+            changed_inputs,
+            ref_statuses,
+            eol_dates,
+            repo_renames,
+            condition_scope,
+            tiers,
+        } = self;
+        let approved_revs: Option<BTreeSet<&(String, String)>> =
+            approved_revs.as_ref().map(|revs| revs.iter().collect());
+        let changed_inputs: Option<BTreeSet<&String>> = changed_inputs
+            .as_ref()
+            .map(|inputs| inputs.iter().collect());
+        let ref_statuses: BTreeMap<&String, &String> = ref_statuses.iter().collect();
+        let eol_dates: BTreeMap<&String, &NaiveDate> = eol_dates.iter().collect();
+
+        let settings: [&dyn std::fmt::Debug; 49] = [
+            check_supported,
+            strict_supported,
+            check_outdated,
+            check_owner,
+            strict_nixpkgs_owner,
+            check_hash_algo,
+            require_hashes,
+            check_locked_revs,
+            check_nar_hash_consistency,
+            warn_eol,
+            deprecation_window,
+            check_unlocked_inputs,
+            check_original_urls,
+            enforce_lock_format,
+            deny_indirect,
+            check_transitive_indirect,
+            require_consistent_transitive_nixpkgs,
+            require_https,
+            check_data_inputs,
+            check_declared_refs,
+            check_temporary_refs,
+            temporary_refs_all_inputs,
+            accept_small_channels,
+            max_inputs,
+            max_transitive_depth,
+            max_days,
+            grace_days,
+            transitive,
+            first_party_only,
+            include_transitive_in_summary,
+            fail_mode,
+            fail_on_warnings,
+            warn_only,
+            fail_fast,
+            fail_threshold,
+            nixpkgs_keys,
+            upstream_owner,
+            upstream_repo,
+            input_name_regex,
+            deny_ref_patterns,
+            &approved_revs,
+            severity_map,
+            pins,
+            &changed_inputs,
+            &ref_statuses,
+            &eol_dates,
+            repo_renames,
+            condition_scope,
+            tiers,
+        ];
+        format!("{settings:?}")
+    }
 }
 
 impl Default for FlakeCheckConfig {
     fn default() -> Self {
         Self {
             check_supported: true,
+            strict_supported: false,
             check_outdated: true,
             check_owner: true,
+            strict_nixpkgs_owner: false,
+            check_hash_algo: false,
+            require_hashes: false,
+            check_locked_revs: false,
+            check_nar_hash_consistency: false,
+            warn_eol: false,
+            deprecation_window: DEPRECATION_WINDOW,
+            check_unlocked_inputs: false,
+            check_original_urls: false,
+            enforce_lock_format: false,
+            deny_indirect: false,
+            check_transitive_indirect: false,
+            require_consistent_transitive_nixpkgs: false,
+            require_https: false,
+            check_data_inputs: false,
+            check_declared_refs: false,
+            check_temporary_refs: false,
+            temporary_refs_all_inputs: false,
+            accept_small_channels: false,
+            max_inputs: None,
+            max_transitive_depth: None,
+            max_days: MAX_DAYS,
+            grace_days: 0,
+            transitive: false,
+            first_party_only: true,
+            include_transitive_in_summary: false,
             fail_mode: false,
+            fail_on_warnings: false,
+            warn_only: false,
+            fail_fast: false,
+            fail_threshold: 0,
             nixpkgs_keys: vec![String::from("nixpkgs")],
+            upstream_owner: String::from(UPSTREAM_OWNER),
+            upstream_repo: None,
+            input_name_regex: None,
+            deny_ref_patterns: vec![],
+            approved_revs: None,
+            severity_map: SeverityMap::default(),
+            pins: Pins::default(),
+            changed_inputs: None,
+            ref_statuses: HashMap::new(),
+            eol_dates: EOL_DATES.clone(),
+            repo_renames: vec![],
+            condition_scope: ConditionScope::default(),
+            tiers: Tiers::default(),
+        }
+    }
+}
+
+/// Read Nixpkgs input keys from a file, accepting both newline- and comma-separated lists. Keys are
+/// returned as listed, duplicates included, since they're merged with `--nixpkgs-keys` anyway.
+pub(crate) fn read_nixpkgs_keys_file(path: &Path) -> Result<Vec<String>, FlakeCheckerError> {
+    let contents = read_to_string(path)?;
+    let mut keys: Vec<String> = vec![];
+
+    for key in contents
+        .split(['\n', ','])
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+    {
+        if key.contains(char::is_whitespace) {
+            return Err(FlakeCheckerError::NixpkgsKeysFile(format!(
+                "{key:?} in {} isn't a valid key",
+                path.display()
+            )));
+        }
+        keys.push(key.to_string());
+    }
+
+    Ok(keys)
+}
+
+/// Read approved pins from a file of `owner/repo rev` lines. Blank lines and lines starting with `#`
+/// are skipped. Owners and repositories are compared case-insensitively, like on GitHub.
+pub(crate) fn read_approved_revs_file(
+    path: &Path,
+) -> Result<HashSet<(String, String)>, FlakeCheckerError> {
+    let contents = read_to_string(path)?;
+    let mut approved = HashSet::new();
+
+    for line in contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    {
+        let pin = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [repo, rev] if repo.split('/').filter(|part| !part.is_empty()).count() == 2 => {
+                (repo.to_lowercase(), rev.to_string())
+            }
+            _ => {
+                return Err(FlakeCheckerError::ApprovedRevsFile(format!(
+                    "{line:?} in {} isn't an `owner/repo rev` line",
+                    path.display()
+                )))
+            }
+        };
+        approved.insert(pin);
+    }
+
+    Ok(approved)
+}
+
+/// Extract the `flake.lock` from `nix flake archive --json` output, wherever it's nested.
+pub(crate) fn lock_from_archive_json(contents: &str) -> Result<String, FlakeCheckerError> {
+    let archive: serde_json::Value = serde_json::from_str(contents)?;
+
+    find_lock(&archive)
+        .map(|lock| lock.to_string())
+        .ok_or_else(|| {
+            FlakeCheckerError::ArchiveJson(String::from(
+                "no object with nodes, root, and version fields found",
+            ))
+        })
+}
+
+fn find_lock(value: &serde_json::Value) -> Option<&serde_json::Value> {
+    match value {
+        serde_json::Value::Object(map) => {
+            if ["nodes", "root", "version"]
+                .iter()
+                .all(|key| map.contains_key(*key))
+            {
+                Some(value)
+            } else {
+                map.values().find_map(find_lock)
+            }
         }
+        serde_json::Value::Array(values) => values.iter().find_map(find_lock),
+        _ => None,
     }
 }
 
+/// Compile a regular expression that must match an entire input name.
+pub(crate) fn input_name_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+/// Whether an input name refers to a transitive input (an input of one of the flake's inputs).
+pub(crate) fn is_transitive_input(input: &str) -> bool {
+    input.contains(TRANSITIVE_INPUT_SEPARATOR)
+}
+
+/// The Nixpkgs inputs of the flake, keyed by input name. With `transitive`, Nixpkgs inputs of
+/// inputs are included as well, keyed by their path of input names, e.g. `home-manager/nixpkgs`.
+/// Inputs that aren't flakes are left out (see [non_flake_nixpkgs]).
 pub(super) fn nixpkgs_deps(
     flake_lock: &FlakeLock,
     keys: &[String],
+    input_name_regex: Option<&Regex>,
+    transitive: bool,
+    max_depth: Option<usize>,
 ) -> Result<HashMap<String, Node>, FlakeCheckerError> {
+    let (deps, _) = select_nixpkgs(
+        flake_lock,
+        keys,
+        input_name_regex,
+        transitive,
+        max_depth,
+        false,
+    )?;
+    Ok(deps)
+}
+
+/// The names (or paths) of the flake's Nixpkgs inputs that are declared with `flake = false`. Such
+/// inputs are used as plain source trees rather than as flakes, so they're skipped rather than
+/// checked like Nixpkgs flakes, even if they're explicitly selected.
+pub(super) fn non_flake_nixpkgs(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+) -> Result<Vec<String>, FlakeCheckerError> {
+    let (_, mut non_flake) = select_nixpkgs(
+        flake_lock,
+        &config.nixpkgs_keys,
+        config.input_name_regex.as_ref(),
+        config.transitive,
+        config.max_transitive_depth,
+        false,
+    )?;
+    non_flake.sort();
+    Ok(non_flake)
+}
+
+// The selected Nixpkgs inputs that are flakes (as for nixpkgs_deps) and the names of those that
+// aren't. With `fallthrough`, explicitly selected inputs whose nodes couldn't be parsed as any
+// known kind are selected too, which only the locked-revision check handles.
+fn select_nixpkgs(
+    flake_lock: &FlakeLock,
+    keys: &[String],
+    input_name_regex: Option<&Regex>,
+    transitive: bool,
+    max_depth: Option<usize>,
+    fallthrough: bool,
+) -> Result<(HashMap<String, Node>, Vec<String>), FlakeCheckerError> {
     let mut deps: HashMap<String, Node> = HashMap::new();
+    let mut non_flake: Vec<String> = vec![];
 
-    for (ref key, node) in flake_lock.root.clone() {
-        match &node {
-            Node::Repo(_) => {
-                if keys.contains(key) {
-                    deps.insert(key.to_string(), node);
-                }
+    // A regex replaces the explicit list of keys. Inputs named by key are taken to be Nixpkgs,
+    // which lets renamed mirrors and base flakes be checked, but inputs that merely match a regex
+    // must also look like Nixpkgs, so that e.g. `.*` doesn't select every input of the flake.
+    let is_nixpkgs = |key: &String, node: &Node| match input_name_regex {
+        Some(regex) => regex.is_match(key) && node.is_nixpkgs(),
+        None => match node {
+            Node::Repo(_) | Node::Tarball(_) => keys.contains(key),
+            Node::Fallthrough(_) => fallthrough && keys.contains(key),
+            Node::Indirect(indirect_node) => {
+                keys.contains(key) && &indirect_node.original.id == key
             }
-            Node::Tarball(_) => {
-                if keys.contains(key) {
-                    deps.insert(key.to_string(), node);
-                }
+            // NOTE: it's unclear that a path node for Nixpkgs should be accepted
+            _ => false,
+        },
+    };
+
+    for (ref key, node) in flake_lock.root.clone() {
+        if is_nixpkgs(key, &node) {
+            if node.is_flake() {
+                deps.insert(key.to_string(), node);
+            } else {
+                non_flake.push(key.to_string());
             }
-            Node::Indirect(indirect_node) => {
-                if keys.contains(key) && &indirect_node.original.id == key {
-                    deps.insert(key.to_string(), node);
+        }
+    }
+
+    if transitive {
+        for input in flake_lock.walk_to_depth(max_depth.unwrap_or(usize::MAX))? {
+            let Some(key) = input.path.last() else {
+                continue;
+            };
+
+            if !input.is_direct() && is_nixpkgs(key, input.node) {
+                let name = input.path.join(TRANSITIVE_INPUT_SEPARATOR);
+                if input.node.is_flake() {
+                    deps.insert(name, input.node.clone());
+                } else {
+                    non_flake.push(name);
                 }
             }
-            _ => {
-                // NOTE: it's unclear that a path node for Nixpkgs should be accepted
-            }
         }
     }
+
+    if input_name_regex.is_some() {
+        return Ok((deps, non_flake));
+    }
+
+    // Keys of non-flake inputs are deliberately skipped rather than missing
     let missing: Vec<String> = keys
         .iter()
-        .filter(|k| !deps.contains_key(*k))
+        .filter(|k| !deps.contains_key(*k) && !non_flake.contains(k))
         .map(String::from)
         .collect();
 
@@ -73,268 +558,4034 @@ pub(super) fn nixpkgs_deps(
         return Err(FlakeCheckerError::Invalid(error_msg));
     }
 
-    Ok(deps)
+    Ok((deps, non_flake))
 }
 
+/// Check the flake's Nixpkgs inputs (and, for some checks, all of its inputs) and collect the
+/// issues. With `fail_fast`, checking stops after the first input (or check of the whole lockfile)
+/// that yields an error, so only the issues found up to that point are returned.
 pub(crate) fn check_flake_lock(
     flake_lock: &FlakeLock,
     config: &FlakeCheckConfig,
     allowed_refs: Vec<String>,
+) -> Result<Vec<Issue>, FlakeCheckerError> {
+    run_checks(flake_lock, config, allowed_refs, &mut ChecksRun::default())
+}
+
+/// Like [check_flake_lock], but also with the checks that ran on each input, which
+/// [clean_inputs] and [input_checks] are derived from.
+pub(crate) fn check_flake_lock_with_checks_run(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    allowed_refs: Vec<String>,
+) -> Result<(Vec<Issue>, ChecksRun), FlakeCheckerError> {
+    let mut checks_run = ChecksRun::default();
+    let issues = run_checks(flake_lock, config, allowed_refs, &mut checks_run)?;
+    Ok((issues, checks_run))
+}
+
+// The checks of check_flake_lock, recording the checks that run on each input in `checks_run`.
+fn run_checks(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    allowed_refs: Vec<String>,
+    checks_run: &mut ChecksRun,
 ) -> Result<Vec<Issue>, FlakeCheckerError> {
     let mut issues = vec![];
 
-    let deps = nixpkgs_deps(flake_lock, &config.nixpkgs_keys)?;
+    let (deps, _) = select_nixpkgs(
+        flake_lock,
+        &config.nixpkgs_keys,
+        config.input_name_regex.as_ref(),
+        config.transitive,
+        config.max_transitive_depth,
+        config.check_locked_revs,
+    )?;
 
     for (name, node) in deps {
-        let (git_ref, last_modified, owner) = match node {
-            Node::Repo(repo) => (
-                repo.original.git_ref,
-                Some(repo.locked.last_modified),
-                Some(repo.original.owner),
-            ),
-            Node::Tarball(tarball) => (None, tarball.locked.last_modified, None),
-            _ => (None, None, None),
-        };
+        // Inputs in a tier are checked using the tier's policy
+        let tier = config.tiers.tier(&name);
+        let tier_config = tier.map(|tier| tier.apply(config));
+        let config = tier_config.as_ref().unwrap_or(config);
+        let allowed_refs = tier
+            .and_then(|tier| tier.allowed_refs.as_deref())
+            .unwrap_or(allowed_refs.as_slice());
 
-        // Check if not explicitly supported
-        if let Some(git_ref) = git_ref {
-            // Check if not explicitly supported
-            if config.check_supported && !allowed_refs.contains(&git_ref) {
+        if config.check_locked_revs && !is_locked_by_hash(&node) {
+            checks_run.record(&name, "Locked revisions");
+            if lacks_locked_rev(&node) {
                 issues.push(Issue {
                     input: name.clone(),
-                    kind: IssueKind::Disallowed(Disallowed {
-                        reference: git_ref.to_string(),
+                    kind: IssueKind::UnlockedRev(UnlockedRev {
+                        unlocked_type: node.node_type().unwrap_or("unknown").to_string(),
+                    }),
+                    location: None,
+                });
+            }
+        }
+
+        // Nodes of unknown kinds are only selected for the locked-revision check
+        if matches!(node, Node::Fallthrough(_)) {
+            continue;
+        }
+
+        if config.check_temporary_refs && !config.temporary_refs_all_inputs {
+            if let Some(git_ref) = original_ref(&node) {
+                checks_run.record(&name, "Temporary refs");
+                if let Some(temporary_ref) = temporary_ref(git_ref) {
+                    issues.push(Issue {
+                        input: name.clone(),
+                        kind: IssueKind::TemporaryRef(temporary_ref),
+                        location: None,
+                    });
+                }
+            }
+        }
+
+        // Check that the input isn't resolved using the flake registry
+        if config.deny_indirect {
+            checks_run.record(&name, "Indirect inputs");
+            if let Node::Indirect(indirect) = &node {
+                issues.push(Issue {
+                    input: name.clone(),
+                    kind: IssueKind::IndirectInput(IndirectInput {
+                        id: indirect.original.id.clone(),
                     }),
+                    location: None,
+                });
+            }
+        }
+
+        let (git_ref, last_modified, origin, canonical) = match node {
+            Node::Repo(repo) => {
+                let canonical = canonical_repo(
+                    &repo.original.owner,
+                    &repo.original.repo,
+                    &config.repo_renames,
+                );
+                (
+                    repo.original.git_ref,
+                    Some(repo.locked.last_modified),
+                    Some((repo.original.owner, repo.original.repo)),
+                    Some(canonical),
+                )
+            }
+            Node::Tarball(tarball) => (None, tarball.locked.last_modified, None, None),
+            _ => (None, None, None, None),
+        };
+
+        if let Some(git_ref) = &git_ref {
+            if config.check_supported || config.strict_supported {
+                checks_run.record(&name, "Supported branches");
+            }
+            if !config.deny_ref_patterns.is_empty() {
+                checks_run.record(&name, "Denied branches");
+            }
+
+            // Check if not explicitly supported or explicitly denied
+            let unsupported = (config.check_supported || config.strict_supported)
+                && !is_allowed_ref(git_ref, allowed_refs, config.accept_small_channels);
+
+            if unsupported || is_denied_ref(git_ref, &config.deny_ref_patterns) {
+                issues.push(Issue {
+                    input: name.clone(),
+                    kind: IssueKind::Disallowed(disallowed(
+                        git_ref,
+                        &config.ref_statuses,
+                        allowed_refs,
+                    )),
+                    location: None,
                 });
             }
+
+            if config.warn_eol {
+                checks_run.record(&name, "End of life");
+                if let Some(approaching_eol) = approaching_eol(
+                    git_ref,
+                    &config.ref_statuses,
+                    &config.eol_dates,
+                    config.deprecation_window,
+                    Utc::now().date_naive(),
+                ) {
+                    issues.push(Issue {
+                        input: name.clone(),
+                        kind: IssueKind::ApproachingEol(approaching_eol),
+                        location: None,
+                    });
+                }
+            }
+        } else if config.strict_supported {
+            // Inputs without a ref aren't following any branch, let alone a supported one
+            checks_run.record(&name, "Supported branches");
+            issues.push(Issue {
+                input: name.clone(),
+                kind: IssueKind::Disallowed(Disallowed::default()),
+                location: None,
+            });
         }
 
         if let Some(last_modified) = last_modified {
             // Check if outdated
             if config.check_outdated {
+                checks_run.record(&name, "Outdated inputs");
                 let num_days_old = num_days_old(last_modified);
 
-                if num_days_old > MAX_DAYS {
+                // Flag timestamps in the future (which count as 0 days old)
+                let now = Utc::now().timestamp();
+                if last_modified > now {
+                    issues.push(Issue {
+                        input: name.clone(),
+                        kind: IssueKind::FutureTimestamp(FutureTimestamp::new(
+                            last_modified,
+                            parse_flake_lock::num_days_old(now, last_modified),
+                        )),
+                        location: None,
+                    });
+                }
+
+                // Deliberately pinned inputs are exempt until their pins expire
+                if is_outdated(
+                    Some(last_modified),
+                    git_ref.as_deref(),
+                    config.grace_days,
+                    config.max_days,
+                ) && !config
+                    .pins
+                    .exempts(&name, "outdated", Utc::now().date_naive())
+                {
                     issues.push(Issue {
                         input: name.clone(),
-                        kind: IssueKind::Outdated(Outdated { num_days_old }),
+                        kind: IssueKind::Outdated(Outdated {
+                            channel: git_ref.clone(),
+                            ..Outdated::new(last_modified, num_days_old)
+                        }),
+                        location: None,
                     });
                 }
             }
         }
 
-        if let Some(owner) = owner {
-            // Check that the GitHub owner is NixOS
-            if config.check_owner && owner.to_lowercase() != "nixos" {
+        if let Some((owner, repo)) = origin {
+            // Check that the GitHub owner is the upstream owner (NixOS by default), and the
+            // repository the upstream repository if there is one, treating renamed repositories
+            // as their canonical ones
+            let canonical = canonical
+                .as_ref()
+                .map(|(owner, repo)| (owner.as_str(), repo.as_str()));
+            let canonical_owner = canonical.map(|(owner, _)| owner);
+            if config.check_owner || config.strict_nixpkgs_owner {
+                checks_run.record(&name, "Upstream owner");
+            }
+            if config.strict_nixpkgs_owner
+                && canonical_owner
+                    .is_some_and(|owner| is_possible_typosquat(owner, &config.upstream_owner))
+            {
+                issues.push(Issue {
+                    input: name.clone(),
+                    kind: IssueKind::PossibleTyposquat(PossibleTyposquat {
+                        suspicious_owner: owner,
+                        resembles: config.upstream_owner.clone(),
+                    }),
+                    location: None,
+                });
+            } else if (config.check_owner || config.strict_nixpkgs_owner)
+                && (!is_upstream(
+                    canonical,
+                    &config.upstream_owner,
+                    config.upstream_repo.as_deref(),
+                ) || (config.strict_nixpkgs_owner
+                    && canonical_owner.is_some_and(|owner| owner != config.upstream_owner)))
+            {
                 issues.push(Issue {
                     input: name.clone(),
-                    kind: IssueKind::NonUpstream(NonUpstream { owner }),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner,
+                        // The repository only matters when there's an upstream one
+                        repo: config.upstream_repo.as_ref().map(|_| repo),
+                    }),
+                    location: None,
                 });
             }
         }
+
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
     }
-    Ok(issues)
-}
 
-pub(super) fn num_days_old(timestamp: i64) -> i64 {
-    let now_timestamp = Utc::now().timestamp();
-    let diff = now_timestamp - timestamp;
-    Duration::seconds(diff).num_days()
-}
+    // The root is the flake itself rather than an input, so the checks of every node skip it
+    let mut nodes: Vec<(&String, &Node)> = flake_lock
+        .nodes
+        .iter()
+        .filter(|(_, node)| !matches!(node, Node::Root(_)))
+        .collect();
+    nodes.sort_by_key(|(key, _)| *key);
 
-#[cfg(test)]
-mod test {
-    use std::collections::HashMap;
-    use std::path::PathBuf;
+    if config.check_hash_algo {
+        for (key, node) in nodes.iter() {
+            if let Some(algo) = node.nar_hash().and_then(hash_algorithm) {
+                checks_run.record(key, "Hash algorithms");
+                if algo != EXPECTED_HASH_ALGO {
+                    issues.push(Issue {
+                        input: key.to_string(),
+                        kind: IssueKind::UnexpectedHashAlgo(UnexpectedHashAlgo {
+                            algo: algo.to_string(),
+                        }),
+                        location: None,
+                    });
+                }
+            }
+        }
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
+    }
 
-    use crate::{
-        check_flake_lock,
-        condition::evaluate_condition,
-        issue::{Disallowed, Issue, IssueKind, NonUpstream},
-        supported_refs, FlakeCheckConfig, FlakeLock,
-    };
+    if config.require_hashes {
+        for (key, node) in nodes.iter() {
+            checks_run.record(key, "NAR hashes");
+            if node.nar_hash().is_none() {
+                issues.push(Issue {
+                    input: key.to_string(),
+                    kind: IssueKind::MissingHash(MissingHash {
+                        locked_type: node.node_type().unwrap_or("unknown").to_string(),
+                    }),
+                    location: None,
+                });
+            }
+        }
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
+    }
 
-    #[test]
-    fn cel_conditions() {
-        // (condition, expected)
-        let cases: Vec<(&str, bool)> = vec![
-            (include_str!("../tests/cel-condition.cel"), true),
-            (
+    if config.check_nar_hash_consistency {
+        let conflicts = hash_conflicts(&nodes, config);
+        // A conflict is reported for one of the conflicting nodes, so the others don't pass
+        let unreported: HashSet<&String> = conflicts
+            .iter()
+            .filter_map(|issue| match &issue.kind {
+                IssueKind::HashConflict(conflict) => Some(&conflict.conflicting_nodes),
+                _ => None,
+            })
+            .flat_map(|keys| keys.iter().skip(1))
+            .collect();
+        for (key, node) in nodes.iter() {
+            if matches!(node, Node::Repo(_)) && !unreported.contains(key) {
+                checks_run.record(key, "Hash consistency");
+            }
+        }
+        issues.extend(conflicts);
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
+    }
 
-                "has(gitRef) && has(numDaysOld) && has(owner) && has(supportedRefs) && supportedRefs.contains(gitRef) && owner != 'NixOS'",
-                false,
-            ),
-            (
+    if config.check_transitive_indirect {
+        if let Some(Node::Root(root)) = flake_lock.nodes.get(&flake_lock.root_key) {
+            for input in root.inputs.keys() {
+                checks_run.record(input, "Transitively indirect inputs");
+            }
+        }
+        issues.extend(transitively_indirect(flake_lock)?);
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
+    }
 
-                "has(gitRef) && has(numDaysOld) && has(owner) && has(supportedRefs) && supportedRefs.contains(gitRef) && owner != 'NixOS'",
-                false,
-            ),
-        ];
+    if config.require_consistent_transitive_nixpkgs {
+        if let Some(issue) = mixed_nixpkgs_channels(flake_lock, config)? {
+            issues.push(issue);
+            if stop_early(config, &issues) {
+                return Ok(issues);
+            }
+        }
+    }
 
-        let ref_statuses: HashMap<String, String> =
-            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
-        let supported_refs = supported_refs(ref_statuses.clone());
-        let path = PathBuf::from("tests/flake.cel.0.lock");
+    if let Some(max) = config.max_inputs {
+        let count = total_inputs(flake_lock);
 
-        for (condition, expected) in cases {
-            let flake_lock = FlakeLock::new(&path).unwrap();
-            let config = FlakeCheckConfig {
-                nixpkgs_keys: vec![String::from("nixpkgs")],
-                ..Default::default()
-            };
+        if count > max {
+            issues.push(Issue {
+                // The issue applies to the flake as a whole rather than to a single input
+                input: String::from("root"),
+                kind: IssueKind::TooManyInputs(TooManyInputs { count, max }),
+                location: None,
+            });
+            if stop_early(config, &issues) {
+                return Ok(issues);
+            }
+        }
+    }
 
-            let result = evaluate_condition(
-                &flake_lock,
-                &config.nixpkgs_keys,
-                condition,
-                ref_statuses.clone(),
-                supported_refs.clone(),
-            );
+    if let Some(max_depth) = config.max_transitive_depth {
+        // Walking one level past the maximum is enough to find an input beyond it
+        let too_deep = flake_lock
+            .walk_to_depth(max_depth.saturating_add(1))?
+            .into_iter()
+            .find(|input| input.path.len() > max_depth);
 
-            if expected {
-                println!("{result:?}");
+        if let Some(input) = too_deep {
+            issues.push(Issue {
+                // The issue applies to the flake as a whole rather than to a single input
+                input: String::from("root"),
+                kind: IssueKind::ExcessiveDepth(ExcessiveDepth {
+                    path: input.path.join(TRANSITIVE_INPUT_SEPARATOR),
+                    depth: input.path.len(),
+                    max_depth,
+                }),
+                location: None,
+            });
+            if stop_early(config, &issues) {
+                return Ok(issues);
+            }
+        }
+    }
 
-                assert!(result.is_ok());
-                assert!(result.unwrap().is_empty());
-            } else {
-                assert!(!result.unwrap().is_empty());
+    if config.require_https {
+        for (key, node) in nodes.iter() {
+            if let Some(url) = node.url() {
+                checks_run.record(key, "TLS transport");
+                if is_insecure_url(url) {
+                    issues.push(Issue {
+                        input: key.to_string(),
+                        kind: IssueKind::InsecureTransport(InsecureTransport {
+                            url: url.to_string(),
+                        }),
+                        location: None,
+                    });
+                }
             }
         }
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
     }
 
-    #[test]
-    fn clean_flake_locks() {
-        let ref_statuses: HashMap<String, String> =
-            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
-        let allowed_refs = supported_refs(ref_statuses);
-        for n in 0..=7 {
-            let path = PathBuf::from(format!("tests/flake.clean.{n}.lock"));
-            let flake_lock = FlakeLock::new(&path).unwrap();
-            let config = FlakeCheckConfig {
-                check_outdated: false,
-                ..Default::default()
+    if config.check_data_inputs {
+        for (key, node) in nodes.iter() {
+            if !is_data_input(node) {
+                continue;
+            }
+            checks_run.record(key, "Data inputs");
+            if node.is_flake() {
+                issues.push(Issue {
+                    input: key.to_string(),
+                    kind: IssueKind::UnexpectedFlake(UnexpectedFlake {
+                        data_url: node.url().unwrap_or_default().to_string(),
+                    }),
+                    location: None,
+                });
+            }
+        }
+        if stop_early(config, &issues) {
+            return Ok(issues);
+        }
+    }
+
+    if config.check_temporary_refs && config.temporary_refs_all_inputs {
+        // Inputs are reported by their paths, e.g. `crane/rust-overlay`, as node keys like
+        // `rust-overlay_2` don't tell which input is meant
+        for walked in flake_lock.walk()? {
+            let Some(git_ref) = original_ref(walked.node) else {
+                continue;
             };
-            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone())
-                .unwrap_or_else(|_| panic!("couldn't run check_flake_lock function in {path:?}"));
-            assert!(
-                issues.is_empty(),
-                "expected clean flake.lock in tests/flake.clean.{n}.lock but encountered an issue"
-            );
+            let input = walked.path.join(TRANSITIVE_INPUT_SEPARATOR);
+            checks_run.record(&input, "Temporary refs");
+            if let Some(temporary_ref) = temporary_ref(git_ref) {
+                issues.push(Issue {
+                    input,
+                    kind: IssueKind::TemporaryRef(temporary_ref),
+                    location: None,
+                });
+            }
+        }
+        if stop_early(config, &issues) {
+            return Ok(issues);
         }
     }
 
-    #[test]
-    fn dirty_flake_locks() {
-        let ref_statuses: HashMap<String, String> =
-            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
-        let allowed_refs = supported_refs(ref_statuses);
+    if let Some(approved_revs) = &config.approved_revs {
+        for (key, node) in nodes.iter() {
+            // Registry inputs are locked to a repository revision too, so they're checked alike
+            let (Some(rev), Some((locked_owner, locked_repo))) =
+                (node.locked_rev(), locked_repo(node))
+            else {
+                continue;
+            };
+            checks_run.record(key, "Approved revisions");
+            let (owner, name) = canonical_repo(locked_owner, locked_repo, &config.repo_renames);
+            let canonical = format!("{owner}/{name}").to_lowercase();
+            if !approved_revs.contains(&(canonical, rev.to_string())) {
+                issues.push(Issue {
+                    input: key.to_string(),
+                    kind: IssueKind::UnapprovedRev(UnapprovedRev {
+                        repo: format!("{locked_owner}/{locked_repo}"),
+                        rev: rev.to_string(),
+                    }),
+                    location: None,
+                });
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+// The owner and repository that the node's input is locked to (if it's locked to a repository on a
+// forge, like `github:` inputs and registry inputs that resolve to them).
+fn locked_repo(node: &Node) -> Option<(&str, &str)> {
+    match node {
+        Node::Repo(repo) => Some((&repo.locked.owner, &repo.locked.repo)),
+        Node::Indirect(indirect) => Some((&indirect.locked.owner, &indirect.locked.repo)),
+        Node::Fallthrough(value) => {
+            let locked = value.get("locked")?;
+            Some((
+                locked.get("owner")?.as_str()?,
+                locked.get("repo")?.as_str()?,
+            ))
+        }
+        Node::Root(_) | Node::Path(_) | Node::Tarball(_) => None,
+    }
+}
+
+// The checked inputs, in order of name. With a `condition`, those are the inputs that the condition
+// is evaluated against.
+fn checked_inputs(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    condition: bool,
+) -> Result<Vec<String>, FlakeCheckerError> {
+    if condition {
+        Ok(condition_inputs(flake_lock, config)?.into_keys().collect())
+    } else {
+        let mut inputs: Vec<String> = nixpkgs_deps(
+            flake_lock,
+            &config.nixpkgs_keys,
+            config.input_name_regex.as_ref(),
+            config.transitive,
+            config.max_transitive_depth,
+        )?
+        .into_keys()
+        .collect();
+        inputs.sort();
+        Ok(inputs)
+    }
+}
+
+/// The checked inputs that have no `issues` and on which at least one check that applies to
+/// individual inputs ran, in order of name, each with the checks that ran on it (as recorded in
+/// `checks_run`). With a `condition`, the checked inputs are those that the condition is evaluated
+/// against and the condition is the only check.
+pub(crate) fn clean_inputs(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    issues: &[Issue],
+    checks_run: &ChecksRun,
+    condition: bool,
+) -> Result<Vec<CleanInput>, FlakeCheckerError> {
+    let with_issues: HashSet<&str> = issues.iter().map(|issue| issue.input.as_str()).collect();
+    Ok(
+        input_checks(flake_lock, config, issues, checks_run, condition)?
+            .into_iter()
+            .filter(|input| {
+                !input.checks.is_empty()
+                    && input.checks.iter().all(|check| check.passed)
+                    && !with_issues.contains(input.input.as_str())
+            })
+            .map(|input| CleanInput {
+                input: input.input,
+                checks: input.checks.into_iter().map(|check| check.name).collect(),
+            })
+            .collect(),
+    )
+}
+
+/// Every checked input, in order of name, with the outcome of each check that applies to
+/// individual inputs and that ran on it (as recorded in `checks_run`): a check fails if it
+/// reported any of the `issues` for the input, and its detail is the details of those issues.
+/// Checks of every node in the `flake.lock`, like the NAR hash check, ran on an input if they ran
+/// on its node. With a `condition`, the condition is the only check.
+pub(crate) fn input_checks(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    issues: &[Issue],
+    checks_run: &ChecksRun,
+    condition: bool,
+) -> Result<Vec<InputChecks>, FlakeCheckerError> {
+    let outcome = |name: &str, issue_kinds: &[&str], reported_as: &str| {
+        let details: Vec<String> = issues
+            .iter()
+            .filter(|issue| issue.input == reported_as && issue_kinds.contains(&issue.kind.name()))
+            .map(|issue| issue.kind.detail())
+            .collect();
+        CheckOutcome {
+            name: name.to_string(),
+            passed: details.is_empty(),
+            detail: (!details.is_empty()).then(|| details.join("; ")),
+        }
+    };
+
+    if condition {
+        return Ok(checked_inputs(flake_lock, config, condition)?
+            .into_iter()
+            .map(|input| InputChecks {
+                checks: vec![outcome("Condition", &["violation"], &input)],
+                input,
+            })
+            .collect());
+    }
+
+    let keys = input_keys(flake_lock, config)?;
+    Ok(checked_inputs(flake_lock, config, condition)?
+        .into_iter()
+        .map(|input| {
+            let names = [Some(input.as_str()), keys.get(&input).map(String::as_str)];
+            let checks = CHECKS
+                .iter()
+                .filter(|check| check.per_input)
+                .filter_map(|check| {
+                    // Issues are reported with the name that the check ran on
+                    let reported_as = names
+                        .into_iter()
+                        .flatten()
+                        .find(|name| checks_run.ran(name, check.name))?;
+                    Some(outcome(check.name, check.issue_kinds, reported_as))
+                })
+                .collect();
+            InputChecks { input, checks }
+        })
+        .collect())
+}
+
+// The keys of the nodes of the inputs that the checks can select, keyed by input name (or path of
+// input names for transitive inputs, e.g. `home-manager/nixpkgs`).
+fn input_keys(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+) -> Result<HashMap<String, String>, FlakeCheckerError> {
+    Ok(flake_lock
+        .walk_to_depth(config.max_transitive_depth.unwrap_or(usize::MAX))?
+        .into_iter()
+        .map(|input| (input.path.join(TRANSITIVE_INPUT_SEPARATOR), input.key))
+        .collect())
+}
+
+/// The names that issues with the root's `input` and its own inputs are reported under (see
+/// `--input`): the input's name, the paths of its transitive inputs (e.g. `nixpkgs/flake-compat`),
+/// and the keys of all of their nodes.
+pub(crate) fn input_scope(
+    flake_lock: &FlakeLock,
+    input: &str,
+) -> Result<HashSet<String>, FlakeCheckerError> {
+    Ok(flake_lock
+        .walk()?
+        .into_iter()
+        .filter(|walked| walked.path.first().is_some_and(|name| name == input))
+        .flat_map(|walked| [walked.path.join(TRANSITIVE_INPUT_SEPARATOR), walked.key])
+        .collect())
+}
+
+/// An issue if the `flake.lock` contents differ from the way Nix writes them, which means that it
+/// was edited by hand or reformatted by other tooling.
+pub(crate) fn lock_format_drift(lock_contents: &str) -> Result<Option<Issue>, FlakeCheckerError> {
+    let canonical = canonical_format(lock_contents)?;
+    if canonical == lock_contents {
+        return Ok(None);
+    }
+
+    // Lines keep their line endings so that a missing trailing newline or CRLF line endings count
+    // as differences. If one is a prefix of the other, the first extra line differs.
+    let lines: Vec<&str> = lock_contents.split_inclusive('\n').collect();
+    let canonical_lines: Vec<&str> = canonical.split_inclusive('\n').collect();
+    let first_differing_line = lines
+        .iter()
+        .zip(&canonical_lines)
+        .position(|(line, canonical_line)| line != canonical_line)
+        .unwrap_or(lines.len().min(canonical_lines.len()))
+        + 1;
+
+    Ok(Some(Issue {
+        input: String::from("root"),
+        kind: IssueKind::LockFormatDrift(LockFormatDrift {
+            first_differing_line,
+        }),
+        location: None,
+    }))
+}
+
+// With `fail_fast`, whether the issues found so far fail the check so that checking can stop.
+fn stop_early(config: &FlakeCheckConfig, issues: &[Issue]) -> bool {
+    config.fail_fast && has_errors(issues, config)
+}
+
+// The Git ref that the input was declared with, if any.
+fn original_ref(node: &Node) -> Option<&str> {
+    match node {
+        Node::Repo(repo) => repo.original.git_ref.as_deref(),
+        Node::Tarball(tarball) => tarball.original.git_ref.as_deref(),
+        Node::Fallthrough(node) => node.get("original")?.get("ref")?.as_str(),
+        _ => None,
+    }
+}
+
+/// The issue details if `git_ref` is a temporary ref: a GitHub pull request (`refs/pull/<n>/head`)
+/// or GitLab merge request (`refs/merge-requests/<n>/head`), or a branch whose name starts with
+/// one of [TEMPORARY_BRANCH_PREFIXES], like `wip/openssl` or `fix-openssl`.
+pub(crate) fn temporary_ref(git_ref: &str) -> Option<TemporaryRef> {
+    let reference = git_ref.strip_prefix("refs/").unwrap_or(git_ref);
+
+    for prefix in ["pull/", "merge-requests/"] {
+        if let Some(rest) = reference.strip_prefix(prefix) {
+            let number = rest.split('/').next().unwrap_or_default();
+            if let Ok(number) = number.parse() {
+                return Some(TemporaryRef {
+                    temporary_ref: git_ref.to_string(),
+                    pull_request: Some(number),
+                });
+            }
+        }
+    }
+
+    let branch = reference.strip_prefix("heads/").unwrap_or(reference);
+    let first_part = branch
+        .split(['/', '-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    TEMPORARY_BRANCH_PREFIXES
+        .contains(&first_part.as_str())
+        .then(|| TemporaryRef {
+            temporary_ref: git_ref.to_string(),
+            pull_request: None,
+        })
+}
+
+/// The groups of `nodes` that are locked to the same revision of the same repository (after
+/// `config.repo_renames`) but record different NAR hashes for it, as one issue per group. The issue
+/// is reported for the first node in the group by key.
+fn hash_conflicts(nodes: &[(&String, &Node)], config: &FlakeCheckConfig) -> Vec<Issue> {
+    let mut groups: BTreeMap<(String, String), Vec<(&String, &RepoNode)>> = BTreeMap::new();
+    for (key, node) in nodes {
+        if let Node::Repo(repo) = node {
+            let locked = &repo.locked;
+            let (owner, name) = canonical_repo(&locked.owner, &locked.repo, &config.repo_renames);
+            let canonical = format!("{owner}/{name}").to_lowercase();
+            groups
+                .entry((canonical, locked.rev.clone()))
+                .or_default()
+                .push((key, repo));
+        }
+    }
+
+    groups
+        .into_values()
+        .filter_map(|group| {
+            let nar_hashes: BTreeSet<&str> = group
+                .iter()
+                .map(|(_, repo)| repo.locked.nar_hash.as_str())
+                .collect();
+            if nar_hashes.len() < 2 {
+                return None;
+            }
+
+            let (key, repo) = group[0];
+            Some(Issue {
+                input: key.to_string(),
+                kind: IssueKind::HashConflict(HashConflict {
+                    locked_repo: format!("{}/{}", repo.locked.owner, repo.locked.repo),
+                    locked_rev: repo.locked.rev.clone(),
+                    conflicting_nodes: group.iter().map(|(key, _)| key.to_string()).collect(),
+                    nar_hashes: nar_hashes.into_iter().map(String::from).collect(),
+                }),
+                location: None,
+            })
+        })
+        .collect()
+}
+
+/// The root inputs that transitively depend on an input resolved using the flake registry through a
+/// `follows`, like a `devshell` input whose `nixpkgs` follows `home-manager/nixpkgs`, which in turn
+/// follows the flake's own indirect `nixpkgs` input. Chains of `follows` are traced to the node
+/// that they end at. Inputs that are indirect themselves are left to `deny_indirect`, and each
+/// root input is reported once, for the first such `follows` found walking its dependencies
+/// breadth first.
+pub(crate) fn transitively_indirect(
+    flake_lock: &FlakeLock,
+) -> Result<Vec<Issue>, FlakeCheckerError> {
+    let targets: HashMap<(String, String), Option<String>> = flake_lock
+        .follows()?
+        .into_iter()
+        .map(|follows| ((follows.node, follows.input), follows.target))
+        .collect();
+    let sorted_inputs = |key: &str| -> Result<Vec<(String, Input)>, FlakeCheckerError> {
+        let mut node_inputs: Vec<(String, Input)> = match flake_lock.nodes.get(key) {
+            Some(node) => node.inputs()?.unwrap_or_default().into_iter().collect(),
+            None => vec![],
+        };
+        node_inputs.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(node_inputs)
+    };
+
+    let mut issues = vec![];
+    for (root_input, input) in sorted_inputs(&flake_lock.root_key)? {
+        // The inputs still to look at, with the input names that lead to them from the root input
+        let mut queue =
+            VecDeque::from([(flake_lock.root_key.clone(), vec![root_input.clone()], input)]);
+        let mut visited = HashSet::new();
+
+        while let Some((node_key, path, input)) = queue.pop_front() {
+            let key = match &input {
+                Input::String(key) => Some(key.clone()),
+                Input::List(_) => targets
+                    .get(&(node_key, path[path.len() - 1].clone()))
+                    .cloned()
+                    .flatten(),
+            };
+            // A broken `follows` doesn't lead anywhere
+            let Some(key) = key else {
+                continue;
+            };
+            let Some(node) = flake_lock.nodes.get(&key) else {
+                continue;
+            };
+
+            if let (Input::List(follows), Some(registry_id)) = (&input, registry_id(node)) {
+                issues.push(Issue {
+                    input: root_input.clone(),
+                    kind: IssueKind::TransitivelyIndirect(TransitivelyIndirect {
+                        via: path.join(TRANSITIVE_INPUT_SEPARATOR),
+                        follows: follows.join(TRANSITIVE_INPUT_SEPARATOR),
+                        registry_id: registry_id.to_string(),
+                    }),
+                    location: None,
+                });
+                break;
+            }
+
+            if visited.insert(key.clone()) {
+                for (name, input) in sorted_inputs(&key)? {
+                    let mut path = path.clone();
+                    path.push(name);
+                    queue.push_back((key.clone(), path, input));
+                }
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+// The flake registry ID that the node is resolved from, if it's an indirect node.
+fn registry_id(node: &Node) -> Option<&str> {
+    match node {
+        Node::Indirect(indirect) => Some(&indirect.original.id),
+        Node::Fallthrough(node) => {
+            let original = node.get("original")?;
+            if original.get("type")?.as_str()? != "indirect" {
+                return None;
+            }
+            original.get("id")?.as_str()
+        }
+        _ => None,
+    }
+}
+
+/// An issue if any of the flake's transitive Nixpkgs inputs follows a channel that none of its own
+/// Nixpkgs inputs follow, like a `home-manager/nixpkgs` on `nixos-unstable` when the flake's
+/// `nixpkgs` is on `nixos-24.11`, since every such copy of Nixpkgs is evaluated separately. The
+/// transitive inputs are selected as for `transitive`, whether or not it's set, and inputs without
+/// a channel, like those locked from a revision, aren't compared.
+pub(crate) fn mixed_nixpkgs_channels(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+) -> Result<Option<Issue>, FlakeCheckerError> {
+    let deps = nixpkgs_deps(
+        flake_lock,
+        &config.nixpkgs_keys,
+        config.input_name_regex.as_ref(),
+        true,
+        config.max_transitive_depth,
+    )?;
+
+    let mut own_channels: BTreeSet<&str> = BTreeSet::new();
+    let mut transitive_channels: BTreeMap<&str, &str> = BTreeMap::new();
+    for (name, node) in &deps {
+        let Some(channel) = original_ref(node) else {
+            continue;
+        };
+        if is_transitive_input(name) {
+            transitive_channels.insert(name, channel);
+        } else {
+            own_channels.insert(channel);
+        }
+    }
+
+    // Without a channel of its own, the flake has nothing for its inputs to be consistent with
+    let inputs: Vec<String> = transitive_channels
+        .iter()
+        .filter(|(_, channel)| !own_channels.is_empty() && !own_channels.contains(*channel))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if inputs.is_empty() {
+        return Ok(None);
+    }
+
+    let channels: BTreeSet<&str> = own_channels
+        .into_iter()
+        .chain(transitive_channels.into_values())
+        .collect();
+    Ok(Some(Issue {
+        // The issue applies to the flake as a whole rather than to a single input
+        input: String::from("root"),
+        kind: IssueKind::MixedNixpkgsChannels(MixedNixpkgsChannels {
+            channels: channels.into_iter().map(String::from).collect(),
+            inputs,
+        }),
+        location: None,
+    }))
+}
+
+/// The supported refs that the flake's Nixpkgs inputs (selected as in [check_flake_lock]) use,
+/// sorted and without duplicates. This tells users which channels they're actually on.
+pub(crate) fn matched_refs(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+    allowed_refs: &[String],
+) -> Result<Vec<String>, FlakeCheckerError> {
+    let deps = nixpkgs_deps(
+        flake_lock,
+        &config.nixpkgs_keys,
+        config.input_name_regex.as_ref(),
+        config.transitive,
+        config.max_transitive_depth,
+    )?;
+
+    let matched: BTreeSet<String> = deps
+        .values()
+        .filter_map(|node| match node {
+            Node::Repo(repo) => repo.original.git_ref.clone(),
+            _ => None,
+        })
+        .filter(|git_ref| {
+            is_allowed_ref(git_ref, allowed_refs, config.accept_small_channels)
+                && !is_denied_ref(git_ref, &config.deny_ref_patterns)
+        })
+        .collect();
+
+    Ok(matched.into_iter().collect())
+}
+
+/// The ages of the oldest and newest of the flake's Nixpkgs inputs (selected as in
+/// [check_flake_lock]), or `None` if none of them has a last-modified time.
+pub(crate) fn input_ages(
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+) -> Result<Option<InputAges>, FlakeCheckerError> {
+    let deps = nixpkgs_deps(
+        flake_lock,
+        &config.nixpkgs_keys,
+        config.input_name_regex.as_ref(),
+        config.transitive,
+        config.max_transitive_depth,
+    )?;
+
+    let ages: Vec<i64> = deps
+        .values()
+        .filter_map(|node| match node {
+            Node::Repo(repo) => Some(repo.locked.last_modified),
+            Node::Tarball(tarball) => tarball.locked.last_modified,
+            _ => None,
+        })
+        .map(num_days_old)
+        .collect();
+
+    Ok(ages
+        .iter()
+        .max()
+        .zip(ages.iter().min())
+        .map(|(&oldest_days, &newest_days)| InputAges {
+            oldest_days,
+            newest_days,
+        }))
+}
+
+/// The total number of unique inputs in a `flake.lock`, including transitive inputs. Inputs that
+/// follow other inputs share a node and are thus counted once.
+pub(crate) fn total_inputs(flake_lock: &FlakeLock) -> usize {
+    flake_lock
+        .nodes
+        .values()
+        .filter(|node| !matches!(node, Node::Root(_)))
+        .count()
+}
+
+/// Compile a regular expression that must match an entire Git ref.
+pub(crate) fn ref_pattern_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+/// Whether a Git ref matches any of the denied ref patterns, which overrides the allowed refs.
+pub(crate) fn is_denied_ref(git_ref: &str, deny_ref_patterns: &[Regex]) -> bool {
+    deny_ref_patterns
+        .iter()
+        .any(|pattern| pattern.is_match(git_ref))
+}
+
+/// A disallowed ref with the reason why, going by the ref statuses: a ref that's unmaintained, or
+/// a release channel older than the newest known release, is end-of-life, with the newest allowed
+/// channel of the same kind to upgrade to, while a ref that's neither a known ref nor a release
+/// channel isn't recognized. Known refs that are otherwise not allowed (e.g. betas or denied refs)
+/// get no more specific reason.
+pub(crate) fn disallowed(
+    git_ref: &str,
+    ref_statuses: &HashMap<String, String>,
+    allowed_refs: &[String],
+) -> Disallowed {
+    let reason = match ref_statuses.get(git_ref).map(String::as_str) {
+        // Without ref statuses, nothing is known about the ref
+        _ if ref_statuses.is_empty() => DisallowedReason::Unsupported,
+        Some("unmaintained") => DisallowedReason::EndOfLife,
+        Some(_) => DisallowedReason::Unsupported,
+        None => match release_channel(git_ref) {
+            Some((_, version, _)) => {
+                let newest_release = ref_statuses
+                    .keys()
+                    .filter_map(|known| release_channel(known))
+                    .map(|(_, version, _)| version)
+                    .max();
+                if newest_release.is_some_and(|newest| version < newest) {
+                    DisallowedReason::EndOfLife
+                } else {
+                    DisallowedReason::Unknown
+                }
+            }
+            None => DisallowedReason::Unknown,
+        },
+    };
+
+    let upgrade_to = match (reason, release_channel(git_ref)) {
+        (DisallowedReason::EndOfLife, Some((prefix, _, suffix))) => allowed_refs
+            .iter()
+            .filter_map(|allowed| {
+                let (allowed_prefix, version, allowed_suffix) = release_channel(allowed)?;
+                (allowed_prefix == prefix && allowed_suffix == suffix).then_some((version, allowed))
+            })
+            .max()
+            .map(|(_, allowed)| allowed.clone()),
+        _ => None,
+    };
+
+    Disallowed {
+        reference: git_ref.to_string(),
+        reason,
+        upgrade_to,
+    }
+}
+
+// A release channel like `nixos-24.11-small` split into its prefix, version, and suffix (here
+// `nixos`, `24.11`, and `-small`).
+fn release_channel(git_ref: &str) -> Option<(&str, &str, &str)> {
+    let (prefix, rest) = git_ref.split_once('-')?;
+    let (version, suffix) = match rest.find('-') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let is_version = version.len() == 5
+        && version
+            .char_indices()
+            .all(|(i, c)| if i == 2 { c == '.' } else { c.is_ascii_digit() });
+
+    ((prefix == "nixos" || prefix == "nixpkgs") && is_version).then_some((prefix, version, suffix))
+}
+
+/// Whether a Git ref is in the list of allowed refs. If `accept_small_channels` is set, a
+/// `<channel>-small` ref is allowed whenever `<channel>` is.
+pub(crate) fn is_allowed_ref(
+    git_ref: &str,
+    allowed_refs: &[String],
+    accept_small_channels: bool,
+) -> bool {
+    let is_allowed = |git_ref: &str| allowed_refs.iter().any(|r| r == git_ref);
+
+    is_allowed(git_ref)
+        || (accept_small_channels && git_ref.strip_suffix("-small").is_some_and(is_allowed))
+}
+
+/// Whether an input with the given Git ref passes the supported-ref check, i.e. its ref is allowed
+/// and doesn't match a denied ref pattern. Inputs without a Git ref pass unless `require_ref` is
+/// set.
+pub(crate) fn is_supported(
+    git_ref: Option<&str>,
+    allowed_refs: &[String],
+    accept_small_channels: bool,
+    deny_ref_patterns: &[Regex],
+    require_ref: bool,
+) -> bool {
+    match git_ref {
+        Some(git_ref) => {
+            is_allowed_ref(git_ref, allowed_refs, accept_small_channels)
+                && !is_denied_ref(git_ref, deny_ref_patterns)
+        }
+        None => !require_ref,
+    }
+}
+
+/// Whether an input last modified at `last_modified` is more than `max_days` old and thus fails
+/// the outdated check, which it doesn't while its Git ref is within a release's grace period.
+/// Inputs without a timestamp never do.
+pub(crate) fn is_outdated(
+    last_modified: Option<i64>,
+    git_ref: Option<&str>,
+    grace_days: u32,
+    max_days: i64,
+) -> bool {
+    let in_grace_period = git_ref
+        .is_some_and(|git_ref| in_grace_period(git_ref, grace_days, Utc::now().date_naive()));
+
+    last_modified.is_some_and(|last_modified| num_days_old(last_modified) > max_days)
+        && !in_grace_period
+}
+
+/// Whether a node is clearly data rather than a flake: a single file (a `file` input), which can't
+/// contain a `flake.nix`, or a patch. This is deliberately conservative, so source trees (which may
+/// or may not be flakes) are never considered data.
+pub(crate) fn is_data_input(node: &Node) -> bool {
+    let is_patch = node.url().is_some_and(|url| {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        path.ends_with(".patch") || path.ends_with(".diff")
+    });
+
+    node.node_type() == Some("file") || is_patch
+}
+
+/// Whether an input from the given GitHub repository (as `(owner, repo)`) passes the upstream owner
+/// check: its owner is `upstream_owner` and, if there's an `upstream_repo`, it's that repository.
+/// Inputs without an owner always pass.
+pub(crate) fn is_upstream(
+    repo: Option<(&str, &str)>,
+    upstream_owner: &str,
+    upstream_repo: Option<&str>,
+) -> bool {
+    repo.is_none_or(|(owner, repo)| {
+        owner.eq_ignore_ascii_case(upstream_owner)
+            && upstream_repo.is_none_or(|upstream_repo| repo.eq_ignore_ascii_case(upstream_repo))
+    })
+}
+
+/// Whether `owner` looks like a typo of `upstream_owner` (e.g. `nixso` or `nixoss` for `NixOS`):
+/// ignoring case, they're different but only a single edit apart.
+pub(crate) fn is_possible_typosquat(owner: &str, upstream_owner: &str) -> bool {
+    let owner = owner.to_lowercase();
+    let upstream_owner = upstream_owner.to_lowercase();
+    owner != upstream_owner && edit_distance(&owner, &upstream_owner) == 1
+}
+
+/// Whether the node's input should be locked to a Git revision but has no (or an empty) `rev`,
+/// which makes it unreproducible. Tarball and path inputs are locked by their NAR hash instead.
+pub(crate) fn lacks_locked_rev(node: &Node) -> bool {
+    !is_locked_by_hash(node) && node.locked_rev().is_none_or(|rev| rev.is_empty())
+}
+
+// Whether the node's input is locked by its NAR hash alone rather than to a Git revision.
+fn is_locked_by_hash(node: &Node) -> bool {
+    matches!(node.node_type(), Some("tarball" | "file" | "path"))
+}
+
+/// A renamed repository, like a mirror of Nixpkgs, and the canonical repository that checks treat
+/// it as, supplied as `<from>=<to>`. Each side is either a repository name (e.g.
+/// `our-nixpkgs=nixpkgs`), which only renames the repository, or `<owner>/<repo>` (e.g.
+/// `my-org/our-nixpkgs=NixOS/nixpkgs`), which renames the owner as well. As the owner check
+/// compares the owner, renaming only the repository only affects it with an `upstream_repo`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RepoRename {
+    from: String,
+    to: String,
+}
+
+impl FromStr for RepoRename {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once('=')
+            .ok_or_else(|| format!("{s:?} isn't a <from>=<to> rename"))?;
+        let (from, to) = (from.trim(), to.trim());
+        let valid = |side: &str| {
+            !side.is_empty() && side.split('/').count() <= 2 && !side.split('/').any(str::is_empty)
+        };
+        if !valid(from) || !valid(to) {
+            return Err(format!(
+                "{s:?} isn't a <from>=<to> rename of repositories (<repo> or <owner>/<repo>)"
+            ));
+        }
+
+        Ok(Self {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+}
+
+/// The canonical `(owner, repo)` of a GitHub repository: the repository that the first matching
+/// rename maps it to or, without one, the repository itself. Names are matched case-insensitively,
+/// as on GitHub.
+pub(crate) fn canonical_repo(owner: &str, repo: &str, renames: &[RepoRename]) -> (String, String) {
+    let full_name = format!("{owner}/{repo}");
+    let rename = renames.iter().find(|rename| {
+        let name = if rename.from.contains('/') {
+            &full_name
+        } else {
+            repo
+        };
+        rename.from.eq_ignore_ascii_case(name)
+    });
+
+    match rename.map(|rename| rename.to.split_once('/').unwrap_or((owner, &rename.to))) {
+        Some((owner, repo)) => (owner.to_string(), repo.to_string()),
+        None => (owner.to_string(), repo.to_string()),
+    }
+}
+
+/// Whether a Git ref follows a NixOS release (e.g. `nixos-24.11` or `nixpkgs-24.11-darwin`) that
+/// came out within `grace_days` of `today`. Refs for releases with unknown dates get no grace period.
+pub(crate) fn in_grace_period(git_ref: &str, grace_days: u32, today: NaiveDate) -> bool {
+    if grace_days == 0 {
+        return false;
+    }
+
+    RELEASE_DATES
+        .iter()
+        .filter(|(release, _)| {
+            git_ref
+                .split('-')
+                .any(|component| component == release.as_str())
+        })
+        .any(|(_, &date)| date <= today && (today - date).num_days() <= i64::from(grace_days))
+}
+
+/// With `--warn-eol`, the end-of-life warning for a Git ref that follows a stable NixOS release
+/// (according to `ref_statuses`) whose end of life (according to `eol_dates`) is at most
+/// `window_days` after `today`, including releases that are already past it but still stable.
+/// Refs for releases with unknown end-of-life dates get no warning.
+pub(crate) fn approaching_eol(
+    git_ref: &str,
+    ref_statuses: &HashMap<String, String>,
+    eol_dates: &HashMap<String, NaiveDate>,
+    window_days: u32,
+    today: NaiveDate,
+) -> Option<ApproachingEol> {
+    if ref_statuses.get(git_ref).map(String::as_str) != Some("stable") {
+        return None;
+    }
+
+    eol_dates
+        .iter()
+        .filter(|(release, _)| {
+            git_ref
+                .split('-')
+                .any(|component| component == release.as_str())
+        })
+        .map(|(_, &date)| (date - today).num_days())
+        .find(|&days_left| days_left <= i64::from(window_days))
+        .map(|days_left| ApproachingEol {
+            channel: git_ref.to_string(),
+            days_left,
+        })
+}
+
+/// The algorithm of a hash in either SRI (`sha256-<base64>`) or Nix (`sha256:<base32>`) format.
+pub(crate) fn hash_algorithm(hash: &str) -> Option<&str> {
+    let (algo, digest) = hash.split_once('-').or_else(|| hash.split_once(':'))?;
+    (!algo.is_empty() && !digest.is_empty()).then_some(algo)
+}
+
+/// Whether a URL is fetched using a transport without TLS, e.g. `http://` or `git+http://`.
+pub(crate) fn is_insecure_url(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once("://") else {
+        return false;
+    };
+    let transport = scheme.rsplit('+').next().unwrap_or(scheme);
+    INSECURE_SCHEMES.contains(&transport.to_ascii_lowercase().as_str())
+}
+
+/// The number of whole days since `timestamp`, which is 0 for timestamps in the future.
+pub(super) fn num_days_old(timestamp: i64) -> i64 {
+    parse_flake_lock::num_days_old(timestamp, Utc::now().timestamp()).max(0)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+    use std::path::PathBuf;
+
+    use chrono::{NaiveDate, Utc};
+    use parse_flake_lock::{
+        FlakeLockParseError, Input, Node, RepoLocked, RepoNode, RepoOriginal, RootNode,
+    };
+
+    use crate::{
+        condition::{
+            evaluate_condition, evaluate_rules, read_condition_dir, ConditionFormat,
+            ConditionScope, Policy,
+        },
+        flake::{
+            approaching_eol, canonical_repo, check_flake_lock, check_flake_lock_with_checks_run,
+            clean_inputs, disallowed, hash_algorithm, in_grace_period, input_checks,
+            input_name_regex, is_allowed_ref, is_data_input, is_insecure_url,
+            is_possible_typosquat, lacks_locked_rev, lock_format_drift, lock_from_archive_json,
+            matched_refs, nixpkgs_deps, non_flake_nixpkgs, num_days_old, read_approved_revs_file,
+            read_nixpkgs_keys_file, ref_pattern_regex, temporary_ref, total_inputs, RepoRename,
+        },
+        issue::{
+            ApproachingEol, Disallowed, DisallowedReason, ExcessiveDepth, FutureTimestamp,
+            HashConflict, IndirectInput, InsecureTransport, Issue, IssueKind, LockFormatDrift,
+            MissingHash, MixedNixpkgsChannels, NonUpstream, PossibleTyposquat, TemporaryRef,
+            TooManyInputs, TransitivelyIndirect, UnapprovedRev, UnexpectedFlake,
+            UnexpectedHashAlgo, UnlockedRev,
+        },
+        ref_statuses::{supported_refs, EOL_DATES},
+        report::{ChecksRun, CleanInput},
+        severity::{has_errors, Severity},
+        FlakeCheckConfig, FlakeCheckerError, FlakeLock,
+    };
+
+    #[test]
+    fn cel_conditions() {
+        // (condition, expected)
+        let cases: Vec<(&str, bool)> = vec![
+            (include_str!("../tests/cel-condition.cel"), true),
+            (
+
+                "has(gitRef) && has(numDaysOld) && has(owner) && has(supportedRefs) && supportedRefs.contains(gitRef) && owner != 'NixOS'",
+                false,
+            ),
+            (
+
+                "has(gitRef) && has(numDaysOld) && has(owner) && has(supportedRefs) && supportedRefs.contains(gitRef) && owner != 'NixOS'",
+                false,
+            ),
+        ];
+
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let path = PathBuf::from("tests/flake.cel.0.lock");
+
+        for (condition, expected) in cases {
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let config = FlakeCheckConfig {
+                nixpkgs_keys: vec![String::from("nixpkgs")],
+                ..Default::default()
+            };
+
+            let result = evaluate_condition(
+                &flake_lock,
+                &config,
+                condition,
+                ref_statuses.clone(),
+                supported_refs.clone(),
+            );
+
+            if expected {
+                println!("{result:?}");
+
+                assert!(result.is_ok());
+                assert!(result.unwrap().is_empty());
+            } else {
+                assert!(!result.unwrap().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn cel_error_context() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let path = PathBuf::from("tests/flake.cel.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig::default();
+
+        let error = |condition: &str| {
+            evaluate_condition(
+                &flake_lock,
+                &config,
+                condition,
+                ref_statuses.clone(),
+                supported_refs.clone(),
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        // Parse errors point to the offending token
+        let message = error("owner == 'NixOS' &&\n    gitRef == )");
+        assert!(message.starts_with("CEL parsing error: "), "{message}");
+        assert!(
+            message.ends_with("\n      gitRef == )\n                ^"),
+            "{message}"
+        );
+
+        let message = error("numDaysOld > missing");
+        assert!(
+            message.starts_with("CEL execution error for input `nixpkgs`: "),
+            "{message}"
+        );
+        assert!(message.contains("'missing'"), "{message}");
+        assert!(
+            message.ends_with("\n  condition: numDaysOld > missing"),
+            "{message}"
+        );
+
+        assert_eq!(
+            error("numDaysOld"),
+            "CEL conditions must return a Boolean but returned int instead for input `nixpkgs`\n  condition: numDaysOld"
+        );
+    }
+
+    #[test]
+    fn cel_violation_snapshot() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.cel.0.lock")).unwrap();
+
+        let issues = evaluate_condition(
+            &flake_lock,
+            &FlakeCheckConfig::default(),
+            "owner != 'NixOS'",
+            ref_statuses,
+            supported_refs,
+        )
+        .unwrap();
+
+        let [Issue {
+            input,
+            kind: IssueKind::Violation(violation),
+            ..
+        }] = issues.as_slice()
+        else {
+            panic!("expected a single violation but got {issues:?}");
+        };
+        assert_eq!(input, "nixpkgs");
+        assert_eq!(violation.snapshot.git_ref, "nixos-unstable");
+        assert_eq!(violation.snapshot.owner, "NixOS");
+        assert_eq!(
+            violation.snapshot.rev,
+            "04af42f3b31dba0ef742d254456dc4c14eedac86"
+        );
+        assert_eq!(violation.snapshot.num_days_old, num_days_old(1686960236));
+    }
+
+    #[test]
+    fn cel_condition_dir() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.cel.0.lock")).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("flake-checker-rules-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Without any .cel files there are no rules to apply
+        std::fs::write(dir.join("README.md"), "Our policy").unwrap();
+        assert!(read_condition_dir(&dir)
+            .unwrap_err()
+            .to_string()
+            .contains("there are no .cel files"));
+
+        std::fs::write(dir.join("upstream.cel"), "owner == 'NixOS'\n").unwrap();
+        std::fs::write(dir.join("stable.cel"), "gitRef.startsWith('nixos-2')").unwrap();
+        let rules = read_condition_dir(&dir).unwrap();
+        assert_eq!(
+            rules
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["stable", "upstream"]
+        );
+
+        // Nixpkgs is upstream but follows nixos-unstable
+        let issues = evaluate_rules(
+            &flake_lock,
+            &FlakeCheckConfig::default(),
+            &rules,
+            ref_statuses,
+            supported_refs,
+        )
+        .unwrap();
+        let [Issue {
+            input,
+            kind: IssueKind::Violation(violation),
+            ..
+        }] = issues.as_slice()
+        else {
+            panic!("expected a single violation but got {issues:?}");
+        };
+        assert_eq!(input, "nixpkgs");
+        assert_eq!(violation.rule.as_deref(), Some("stable"));
+
+        // An invalid rule is reported along with its file
+        std::fs::write(dir.join("broken.cel"), "owner ==").unwrap();
+        let error = read_condition_dir(&dir).unwrap_err().to_string();
+        assert!(error.contains("broken.cel"), "{error}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cel_policy_file() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+        let config = FlakeCheckConfig {
+            condition_scope: ConditionScope::All,
+            ..Default::default()
+        };
+        let evaluate = |condition: &str| {
+            evaluate_condition(
+                &flake_lock,
+                &config,
+                condition,
+                ref_statuses.clone(),
+                supported_refs.clone(),
+            )
+            .unwrap()
+        };
+
+        let yaml = "allowed_refs: [nixos-unstable, master]\nrequired_owner: NixOS\n";
+        let json = r#"{"allowed_refs": ["nixos-unstable", "master"], "required_owner": "NixOS"}"#;
+        let policy = Policy::parse(yaml, ConditionFormat::Yaml).unwrap();
+        assert_eq!(Policy::parse(json, ConditionFormat::Json).unwrap(), policy);
+
+        // The policy reports the same issues as the equivalent hand-written condition
+        let condition = policy.to_cel().unwrap();
+        let issues = evaluate(&condition);
+        assert!(!issues.is_empty());
+        assert_eq!(
+            issues,
+            evaluate("(gitRef == 'nixos-unstable' || gitRef == 'master') && owner == 'NixOS'")
+        );
+
+        // A policy must have requirements, and only known ones
+        assert_eq!(
+            Policy::parse("{}", ConditionFormat::Json).unwrap().to_cel(),
+            None
+        );
+        assert!(Policy::parse("max_age: 30", ConditionFormat::Yaml).is_err());
+    }
+
+    #[test]
+    fn cel_condition_scope() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+
+        let violations = |scope: ConditionScope, condition: &str| -> Vec<String> {
+            let config = FlakeCheckConfig {
+                condition_scope: scope,
+                ..Default::default()
+            };
+            evaluate_condition(
+                &flake_lock,
+                &config,
+                condition,
+                ref_statuses.clone(),
+                supported_refs.clone(),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|i| i.input)
+            .collect()
+        };
+
+        // A policy for a non-Nixpkgs input only applies with the `all` scope
+        let condition = "isNixpkgs || owner != 'ipetkov'";
+        assert!(violations(ConditionScope::Nixpkgs, condition).is_empty());
+        assert_eq!(violations(ConditionScope::All, condition), vec!["crane"]);
+
+        assert_eq!(
+            violations(ConditionScope::All, "isNixpkgs"),
+            vec!["crane", "flake-compat", "flake-utils", "rust-overlay"]
+        );
+        assert!(violations(ConditionScope::Nixpkgs, "isNixpkgs").is_empty());
+    }
+
+    #[test]
+    fn cel_standard_checks() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let supported_refs = supported_refs(ref_statuses.clone());
+        let config = FlakeCheckConfig::default();
+
+        // (condition, the issue kind that the built-in check reports when the condition fails)
+        type IsKind = fn(&IssueKind) -> bool;
+        let cases: Vec<(&str, IsKind)> = vec![
+            ("isSupported", IssueKind::is_disallowed),
+            ("!isOutdated", IssueKind::is_outdated),
+            ("isUpstream", IssueKind::is_non_upstream),
+        ];
+
+        for path in [
+            "tests/flake.clean.0.lock",
+            "tests/flake.dirty.0.lock",
+            "tests/flake.dirty.1.lock",
+            "tests/flake.cel.0.lock",
+        ] {
+            let flake_lock = FlakeLock::new(&PathBuf::from(path)).unwrap();
+            let issues = check_flake_lock(&flake_lock, &config, supported_refs.clone()).unwrap();
+
+            for (condition, is_kind) in &cases {
+                let mut expected: Vec<String> = issues
+                    .iter()
+                    .filter(|i| is_kind(&i.kind))
+                    .map(|i| i.input.clone())
+                    .collect();
+                let mut violations: Vec<String> = evaluate_condition(
+                    &flake_lock,
+                    &config,
+                    condition,
+                    ref_statuses.clone(),
+                    supported_refs.clone(),
+                )
+                .unwrap()
+                .into_iter()
+                .map(|i| i.input)
+                .collect();
+                expected.sort();
+                violations.sort();
+
+                assert_eq!(violations, expected, "{condition} in {path}");
+            }
+
+            // Combining the variables reproduces the built-in checks
+            let violations = evaluate_condition(
+                &flake_lock,
+                &config,
+                "isSupported && !isOutdated && isUpstream",
+                ref_statuses.clone(),
+                supported_refs.clone(),
+            )
+            .unwrap();
+            assert_eq!(violations.is_empty(), issues.is_empty(), "{path}");
+        }
+    }
+
+    #[test]
+    fn clean_flake_locks() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        for n in 0..=7 {
+            let path = PathBuf::from(format!("tests/flake.clean.{n}.lock"));
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone())
+                .unwrap_or_else(|_| panic!("couldn't run check_flake_lock function in {path:?}"));
+            assert!(
+                issues.is_empty(),
+                "expected clean flake.lock in tests/flake.clean.{n}.lock but encountered an issue"
+            );
+        }
+    }
+
+    #[test]
+    fn dirty_flake_locks() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let cases: Vec<(&str, Vec<Issue>)> = vec![
+            (
+                "flake.dirty.0.lock",
+                vec![
+                    Issue {
+                        input: String::from("nixpkgs"),
+                        kind: IssueKind::Disallowed(Disallowed {
+                            reference: String::from("this-should-fail"),
+                            ..Default::default()
+                        }),
+                        location: None,
+                    },
+                    Issue {
+                        input: String::from("nixpkgs"),
+                        kind: IssueKind::NonUpstream(NonUpstream {
+                            owner: String::from("bitcoin-miner-org"),
+                            ..Default::default()
+                        }),
+                        location: None,
+                    },
+                ],
+            ),
+            (
+                "flake.dirty.1.lock",
+                vec![
+                    Issue {
+                        input: String::from("nixpkgs"),
+                        kind: IssueKind::Disallowed(Disallowed {
+                            reference: String::from("probably-nefarious"),
+                            ..Default::default()
+                        }),
+                        location: None,
+                    },
+                    Issue {
+                        input: String::from("nixpkgs"),
+                        kind: IssueKind::NonUpstream(NonUpstream {
+                            owner: String::from("pretty-shady"),
+                            ..Default::default()
+                        }),
+                        location: None,
+                    },
+                ],
+            ),
+        ];
+
+        for (file, expected_issues) in cases {
+            let path = PathBuf::from(format!("tests/{file}"));
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            dbg!(&path);
+            assert_eq!(issues, expected_issues);
+        }
+    }
+
+    #[test]
+    fn overridden_input() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        // Locked using `nix flake lock --override-input nixpkgs github:my-org/nixpkgs/my-fix` for a
+        // flake.nix that declares `github:NixOS/nixpkgs/nixos-unstable`. The lock doesn't record the
+        // override, so the overriding input is what gets checked.
+        let path = PathBuf::from("tests/flake.overridden.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("my-fix"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner: String::from("my-org"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nixpkgs_nodes() {
+        let flake_lock: FlakeLock =
+            r#"{"nodes": {"root": {"inputs": {}}}, "root": "root", "version": 7}"#
+                .parse()
+                .unwrap();
+        assert!(flake_lock.nixpkgs_nodes().is_empty());
+
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            ("flake.clean.0.lock", vec!["nixpkgs"]),
+            // An input that follows the Nixpkgs input of another input
+            ("flake.clean.5.lock", vec!["nixpkgs"]),
+            (
+                "flake.clean.3.lock",
+                vec![
+                    "nixpkgs",
+                    "nixpkgs-ansible",
+                    "nixpkgs-nodejs",
+                    "nixpkgs-packer",
+                    "nixpkgs-unstable",
+                    "nixpkgsMinio",
+                ],
+            ),
+            ("flake.indirect.0.lock", vec!["nixpkgs"]),
+            ("flake.tarball.0.lock", vec!["nixpkgs"]),
+            ("flake.insecure.0.lock", vec!["nixpkgs"]),
+        ];
+
+        for (file, expected) in cases {
+            let path = PathBuf::from(format!("tests/{file}"));
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let names: Vec<&str> = flake_lock
+                .nixpkgs_nodes()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            assert_eq!(names, expected, "unexpected Nixpkgs inputs in {file}");
+        }
+    }
+
+    #[test]
+    fn original_urls() {
+        let cases: Vec<(&str, Option<&str>)> = vec![
+            (r#"{"inputs": {"nixpkgs": "nixpkgs"}}"#, None),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "owner": "NixOS", "repo": "nixpkgs", "rev": "abc", "type": "github"},
+                    "original": {"owner": "NixOS", "ref": "nixos-unstable", "repo": "nixpkgs", "type": "github"}
+                }"#,
+                Some("github:NixOS/nixpkgs/nixos-unstable"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "owner": "numtide", "repo": "flake-utils", "rev": "abc", "type": "github"},
+                    "original": {"owner": "numtide", "repo": "flake-utils", "type": "github"}
+                }"#,
+                Some("github:numtide/flake-utils"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "owner": "NixOS", "repo": "nixpkgs", "rev": "abc", "type": "github"},
+                    "original": {"id": "nixpkgs", "type": "indirect"}
+                }"#,
+                Some("flake:nixpkgs"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "owner": "NixOS", "repo": "nixpkgs", "rev": "abc", "type": "github"},
+                    "original": {"id": "nixpkgs", "ref": "nixos-24.05", "type": "indirect"}
+                }"#,
+                Some("flake:nixpkgs/nixos-24.05"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "owner": "NixOS", "repo": "nixpkgs", "rev": "abc", "type": "github"},
+                    "original": {"id": "nixpkgs", "ref": "nixos-24.05", "rev": "abc", "type": "indirect"}
+                }"#,
+                Some("flake:nixpkgs/nixos-24.05/abc"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "path": "./sub", "type": "path"},
+                    "original": {"path": "./sub", "type": "path"}
+                }"#,
+                Some("path:./sub"),
+            ),
+            (
+                r#"{
+                    "locked": {"narHash": "sha256-AAAA", "type": "tarball", "url": "https://example.com/flake.tar.gz"},
+                    "original": {"type": "tarball", "url": "https://example.com/flake.tar.gz"}
+                }"#,
+                Some("https://example.com/flake.tar.gz"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "rev": "abc", "type": "git", "url": "https://example.com/tools"},
+                    "original": {"ref": "main", "type": "git", "url": "https://example.com/tools"}
+                }"#,
+                Some("git+https://example.com/tools?ref=main"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "type": "file", "url": "https://example.com/data.json"},
+                    "original": {"type": "file", "url": "https://example.com/data.json"}
+                }"#,
+                Some("https://example.com/data.json"),
+            ),
+        ];
+
+        for (json, expected) in cases {
+            let node: Node = serde_json::from_str(json).unwrap();
+            assert_eq!(node.original_url().as_deref(), expected, "for {json}");
+        }
+    }
+
+    #[test]
+    fn future_timestamp() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+
+        // Move the nixpkgs input's lastModified 5 days (and a bit) into the future
+        let future = Utc::now().timestamp() + 5 * 24 * 60 * 60 + 3600;
+        let mut lock: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/flake.clean.0.lock")).unwrap();
+        lock["nodes"]["nixpkgs"]["locked"]["lastModified"] = serde_json::json!(future);
+        let flake_lock: FlakeLock = lock.to_string().parse().unwrap();
+
+        assert_eq!(num_days_old(future), 0);
+
+        let config = FlakeCheckConfig::default();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::FutureTimestamp(FutureTimestamp::new(future, 5)),
+                location: None,
+            }]
+        );
+
+        // The issue is informational and doesn't fail the run by default
+        assert_eq!(issues[0].severity(&config), Severity::Info);
+        let config = FlakeCheckConfig {
+            fail_mode: true,
+            ..Default::default()
+        };
+        assert!(!has_errors(&issues, &config));
+    }
+
+    #[test]
+    fn hosts() {
+        let cases: Vec<(&str, Option<&str>)> = vec![
+            (r#"{"inputs": {"nixpkgs": "nixpkgs"}}"#, None),
+            (
+                r#"{
+                    "locked": {"host": "github.example.com", "lastModified": 1, "narHash": "sha256-AAAA", "owner": "platform", "repo": "nixpkgs", "rev": "abc", "type": "github"},
+                    "original": {"host": "github.example.com", "owner": "platform", "repo": "nixpkgs", "type": "github"}
+                }"#,
+                Some("github.example.com"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "owner": "NixOS", "repo": "nixpkgs", "rev": "abc", "type": "github"},
+                    "original": {"owner": "NixOS", "repo": "nixpkgs", "type": "github"}
+                }"#,
+                None,
+            ),
+            (
+                r#"{
+                    "locked": {"host": "gitlab.example.com", "lastModified": 1, "narHash": "sha256-AAAA", "owner": "platform", "repo": "nixpkgs", "rev": "abc", "type": "gitlab"},
+                    "original": {"id": "nixpkgs", "type": "indirect"}
+                }"#,
+                Some("gitlab.example.com"),
+            ),
+            (
+                r#"{
+                    "locked": {"lastModified": 1, "narHash": "sha256-AAAA", "path": "./sub", "type": "path"},
+                    "original": {"path": "./sub", "type": "path"}
+                }"#,
+                None,
+            ),
+            (
+                r#"{
+                    "locked": {"narHash": "sha256-AAAA", "type": "tarball", "url": "https://example.com/flake.tar.gz"},
+                    "original": {"type": "tarball", "url": "https://example.com/flake.tar.gz"}
+                }"#,
+                None,
+            ),
+            (
+                r#"{
+                    "locked": {"host": "git.example.com", "lastModified": 1, "narHash": "sha256-AAAA", "owner": "platform", "repo": "tools", "type": "sourcehut"},
+                    "original": {"host": "git.example.com", "owner": "platform", "repo": "tools", "type": "sourcehut"}
+                }"#,
+                Some("git.example.com"),
+            ),
+        ];
+
+        for (json, expected) in cases {
+            let node: Node = serde_json::from_str(json).unwrap();
+            assert_eq!(node.host(), expected, "for {json}");
+        }
+    }
+
+    #[test]
+    fn explicit_nixpkgs_keys() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let cases: Vec<(&str, Vec<String>, Vec<Issue>)> = vec![(
+            "flake.explicit-keys.0.lock",
+            vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+            vec![Issue {
+                input: String::from("nixpkgs-alt"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("seems-pretty-shady"),
+                    ..Default::default()
+                }),
+                location: None,
+            }],
+        )];
+
+        for (file, nixpkgs_keys, expected_issues) in cases {
+            let path = PathBuf::from(format!("tests/{file}"));
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                nixpkgs_keys,
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            assert_eq!(issues, expected_issues);
+        }
+    }
+
+    #[test]
+    fn custom_upstream_owner() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.explicit-keys.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            nixpkgs_keys: vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+            upstream_owner: String::from("Seems-Pretty-Shady"),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("NixOS"),
+                    ..Default::default()
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn custom_upstream_repo() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.renamed.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let non_upstream = |repo: Option<&str>| {
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("my-org"),
+                    repo: repo.map(String::from),
+                }),
+                location: None,
+            }]
+        };
+        // (upstream owner, upstream repo, expected issues)
+        let cases: Vec<(&str, Option<&str>, Vec<Issue>)> = vec![
+            // Without an upstream repo, any repository of the upstream owner passes
+            ("my-org", None, vec![]),
+            ("my-org", Some("our-nixpkgs"), vec![]),
+            ("My-Org", Some("Our-Nixpkgs"), vec![]),
+            (
+                "my-org",
+                Some("base-flake"),
+                non_upstream(Some("our-nixpkgs")),
+            ),
+            ("NixOS", Some("nixpkgs"), non_upstream(Some("our-nixpkgs"))),
+            ("NixOS", None, non_upstream(None)),
+        ];
+
+        for (upstream_owner, upstream_repo, expected_issues) in cases {
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                upstream_owner: String::from(upstream_owner),
+                upstream_repo: upstream_repo.map(String::from),
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            assert_eq!(
+                issues, expected_issues,
+                "{upstream_owner}/{upstream_repo:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn renamed_repos() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.renamed.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let non_upstream = vec![Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::NonUpstream(NonUpstream {
+                owner: String::from("my-org"),
+                ..Default::default()
+            }),
+            location: None,
+        }];
+        let cases: Vec<(&str, Vec<Issue>)> = vec![
+            ("", non_upstream.clone()),
+            // Renaming only the repository keeps its owner, which is all that's compared here
+            ("our-nixpkgs=nixpkgs", non_upstream),
+            ("my-org/our-nixpkgs=NixOS/nixpkgs", vec![]),
+            ("My-Org/Our-Nixpkgs=NixOS/nixpkgs", vec![]),
+        ];
+
+        for (renames, expected_issues) in cases {
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                repo_renames: renames
+                    .split(',')
+                    .filter(|rename| !rename.is_empty())
+                    .map(|rename| rename.parse().unwrap())
+                    .collect(),
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            assert_eq!(issues, expected_issues, "renames: {renames:?}");
+        }
+
+        // With an upstream repository, renaming only the repository maps it back to that one
+        let non_upstream_repo = vec![Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::NonUpstream(NonUpstream {
+                owner: String::from("my-org"),
+                repo: Some(String::from("our-nixpkgs")),
+            }),
+            location: None,
+        }];
+        let cases: Vec<(&str, Vec<Issue>)> = vec![
+            ("", non_upstream_repo.clone()),
+            ("our-nixpkgs=nixpkgs", vec![]),
+            ("Our-Nixpkgs=nixpkgs", vec![]),
+            ("our-nixpkgs=nixpkgs-mirror", non_upstream_repo),
+        ];
+        for (renames, expected_issues) in cases {
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                upstream_owner: String::from("my-org"),
+                upstream_repo: Some(String::from("nixpkgs")),
+                repo_renames: renames
+                    .split(',')
+                    .filter(|rename| !rename.is_empty())
+                    .map(|rename| rename.parse().unwrap())
+                    .collect(),
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            assert_eq!(issues, expected_issues, "renames: {renames:?}");
+        }
+
+        // Approved revisions are listed under the canonical repository
+        let approved = HashSet::from([(
+            String::from("nixos/nixpkgs"),
+            String::from("04af42f3b31dba0ef742d254456dc4c14eedac86"),
+        )]);
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_owner: false,
+            approved_revs: Some(approved),
+            repo_renames: vec!["my-org/our-nixpkgs=NixOS/nixpkgs".parse().unwrap()],
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+
+        assert_eq!(
+            canonical_repo("my-org", "our-nixpkgs", &[]),
+            (String::from("my-org"), String::from("our-nixpkgs"))
+        );
+        for invalid in [
+            "our-nixpkgs",
+            "=nixpkgs",
+            "a/b/c=nixpkgs",
+            "my-org/=NixOS/nixpkgs",
+        ] {
+            assert!(invalid.parse::<RepoRename>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn clean_inputs_report() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses.clone());
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/flake.explicit-keys.0.lock")).unwrap();
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            nixpkgs_keys: vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+            upstream_owner: String::from("Seems-Pretty-Shady"),
+            ..Default::default()
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            clean_inputs(&flake_lock, &config, &issues, &checks_run, false).unwrap(),
+            vec![CleanInput {
+                input: String::from("nixpkgs-alt"),
+                checks: vec![
+                    String::from("Supported branches"),
+                    String::from("Upstream owner"),
+                ],
+            }]
+        );
+
+        // Scoped to a single input, the breakdown only covers that input
+        let config = FlakeCheckConfig {
+            nixpkgs_keys: vec![String::from("nixpkgs")],
+            ..config
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.iter().all(|issue| issue.input == "nixpkgs"));
+        assert!(
+            clean_inputs(&flake_lock, &config, &issues, &checks_run, false)
+                .unwrap()
+                .is_empty()
+        );
+        let config = FlakeCheckConfig {
+            nixpkgs_keys: vec![String::from("nixpkgs-alt")],
+            ..config
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(
+            clean_inputs(&flake_lock, &config, &issues, &checks_run, false)
+                .unwrap()
+                .into_iter()
+                .map(|clean| clean.input)
+                .collect::<Vec<_>>(),
+            vec![String::from("nixpkgs-alt")]
+        );
+
+        // Only the checks that apply to an input count: the branch and owner checks don't apply to
+        // a registry input, which isn't listed unless another check ran on it
+        let config = FlakeCheckConfig {
+            nixpkgs_keys: vec![String::from("flake-utils")],
+            ..config
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.is_empty());
+        assert!(
+            clean_inputs(&flake_lock, &config, &issues, &checks_run, false)
+                .unwrap()
+                .is_empty()
+        );
+        let config = FlakeCheckConfig {
+            require_hashes: true,
+            ..config
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            clean_inputs(&flake_lock, &config, &issues, &checks_run, false).unwrap(),
+            vec![CleanInput {
+                input: String::from("flake-utils"),
+                checks: vec![String::from("NAR hashes")],
+            }]
+        );
+
+        // Checks that fail-fast skips don't count either
+        let config = FlakeCheckConfig {
+            nixpkgs_keys: vec![String::from("nixpkgs-alt")],
+            upstream_owner: String::from("NixOS"),
+            require_hashes: false,
+            fail_fast: true,
+            ..config
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            input_checks(&flake_lock, &config, &issues, &checks_run, false).unwrap()[0]
+                .checks
+                .iter()
+                .map(|check| (check.name.as_str(), check.passed))
+                .collect::<Vec<_>>(),
+            vec![("Supported branches", true), ("Upstream owner", false)]
+        );
+
+        // With a condition, the inputs in its scope are checked against it alone
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+        let config = FlakeCheckConfig {
+            condition_scope: ConditionScope::All,
+            ..Default::default()
+        };
+        let issues = evaluate_condition(
+            &flake_lock,
+            &config,
+            "isNixpkgs",
+            ref_statuses,
+            allowed_refs,
+        )
+        .unwrap();
+        assert_eq!(
+            clean_inputs(&flake_lock, &config, &issues, &ChecksRun::default(), true).unwrap(),
+            vec![CleanInput {
+                input: String::from("nixpkgs"),
+                checks: vec![String::from("Condition")],
+            }]
+        );
+    }
+
+    #[test]
+    fn nixpkgs_keys_from_file() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+
+        let nixpkgs_keys =
+            read_nixpkgs_keys_file(&PathBuf::from("tests/nixpkgs-keys.txt")).unwrap();
+        assert_eq!(
+            nixpkgs_keys,
+            vec![
+                String::from("nixpkgs"),
+                String::from("nixpkgs-alt"),
+                String::from("nixpkgs")
+            ]
+        );
+
+        let path = PathBuf::from("tests/flake.explicit-keys.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            nixpkgs_keys,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs-alt"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("seems-pretty-shady"),
+                    ..Default::default()
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn input_name_regexes() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
         let cases: Vec<(&str, Vec<Issue>)> = vec![
             (
-                "flake.dirty.0.lock",
-                vec![
-                    Issue {
-                        input: String::from("nixpkgs"),
-                        kind: IssueKind::Disallowed(Disallowed {
-                            reference: String::from("this-should-fail"),
-                        }),
-                    },
-                    Issue {
-                        input: String::from("nixpkgs"),
-                        kind: IssueKind::NonUpstream(NonUpstream {
-                            owner: String::from("bitcoin-miner-org"),
-                        }),
-                    },
-                ],
-            ),
+                "nixpkgs.*",
+                vec![Issue {
+                    input: String::from("nixpkgs-alt"),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner: String::from("seems-pretty-shady"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                }],
+            ),
+            // Only inputs that look like Nixpkgs are selected, not crane, flake-utils, etc.
+            (
+                ".*",
+                vec![Issue {
+                    input: String::from("nixpkgs-alt"),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner: String::from("seems-pretty-shady"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                }],
+            ),
+            // The regex must match the whole input name
+            ("alt", vec![]),
+            ("nixpkgs", vec![]),
+            ("foo|bar", vec![]),
+        ];
+
+        for (pattern, expected_issues) in cases {
+            let path = PathBuf::from("tests/flake.explicit-keys.0.lock");
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                input_name_regex: Some(input_name_regex(pattern).unwrap()),
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            assert_eq!(issues, expected_issues, "unexpected issues for {pattern:?}");
+        }
+
+        assert!(input_name_regex("nixpkgs_(").is_err());
+    }
+
+    #[test]
+    fn missing_nixpkgs_keys() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let cases: Vec<(&str, Vec<String>, String)> = vec![(
+            "flake.clean.0.lock",
+            vec![String::from("nixpkgs"), String::from("foo"), String::from("bar")],
+            String::from("invalid flake.lock: no nixpkgs dependency found for specified keys: foo, bar"),
+        ),
+        (
+            "flake.clean.1.lock",
+            vec![String::from("nixpkgs"), String::from("nixpkgs-other")],
+            String::from("invalid flake.lock: no nixpkgs dependency found for specified key: nixpkgs-other"),
+        )];
+        for (file, nixpkgs_keys, expected_err) in cases {
+            let path = PathBuf::from(format!("tests/{file}"));
+            let flake_lock = FlakeLock::new(&path).unwrap();
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                nixpkgs_keys,
+                ..Default::default()
+            };
+
+            let result = check_flake_lock(&flake_lock, &config, allowed_refs.clone());
+
+            assert!(result.is_err());
+            assert_eq!(result.unwrap_err().to_string(), expected_err);
+        }
+    }
+
+    #[test]
+    fn tarball_without_last_modified() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.tarball.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let Some(Node::Tarball(tarball)) = flake_lock.root.get("nixpkgs") else {
+            panic!("expected the nixpkgs input in {path:?} to be a tarball");
+        };
+        assert_eq!(tarball.locked.last_modified, None);
+
+        // The outdated check is enabled but there's no timestamp to check
+        let config = FlakeCheckConfig::default();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn grace_days() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        // NixOS 24.11 was released on 2024-11-30
+        assert!(in_grace_period("nixos-24.11", 14, date("2024-12-05")));
+        assert!(in_grace_period("nixos-24.11-small", 14, date("2024-12-14")));
+        assert!(in_grace_period(
+            "nixpkgs-24.11-darwin",
+            14,
+            date("2024-12-14")
+        ));
+        assert!(!in_grace_period("nixos-24.11", 14, date("2024-12-15")));
+        assert!(!in_grace_period("nixos-24.11", 0, date("2024-12-05")));
+        assert!(!in_grace_period("nixos-24.11", 14, date("2024-11-29")));
+        // No grace period without a known release date
+        assert!(!in_grace_period("nixos-unstable", 14, date("2024-12-05")));
+        assert!(!in_grace_period("nixos-19.09", 14, date("2019-10-05")));
+
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        // The nixpkgs input follows nixos-24.11 but was last modified long before its release
+        let path = PathBuf::from("tests/flake.clean.2.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let issues = check_flake_lock(
+            &flake_lock,
+            &FlakeCheckConfig::default(),
+            allowed_refs.clone(),
+        )
+        .unwrap();
+        assert!(issues.iter().any(|issue| issue.kind.is_outdated()));
+
+        // A grace period reaching back to the release suppresses the outdated issue
+        let config = FlakeCheckConfig {
+            grace_days: u32::try_from((Utc::now().date_naive() - date("2024-11-30")).num_days())
+                .unwrap(),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn approaching_eol_warnings() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let ref_statuses: HashMap<String, String> = HashMap::from([
+            (String::from("nixos-24.11"), String::from("stable")),
+            (String::from("nixpkgs-24.11-darwin"), String::from("stable")),
+            (String::from("nixos-24.05"), String::from("deprecated")),
+            (String::from("nixos-unstable"), String::from("rolling")),
+            (String::from("nixos-25.11"), String::from("stable")),
+        ]);
+        let eol_dates = EOL_DATES.clone();
+
+        // NixOS 24.11 reaches its end of life on 2025-06-30
+        assert_eq!(
+            approaching_eol(
+                "nixos-24.11",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2025-06-10")
+            ),
+            Some(ApproachingEol {
+                channel: String::from("nixos-24.11"),
+                days_left: 20,
+            })
+        );
+        assert_eq!(
+            approaching_eol(
+                "nixpkgs-24.11-darwin",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2025-06-30")
+            )
+            .map(|eol| eol.days_left),
+            Some(0)
+        );
+        assert_eq!(
+            approaching_eol(
+                "nixos-24.11",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2025-05-01")
+            ),
+            None
+        );
+        assert_eq!(
+            approaching_eol(
+                "nixos-24.11",
+                &ref_statuses,
+                &eol_dates,
+                90,
+                date("2025-05-01")
+            )
+            .map(|eol| eol.days_left),
+            Some(60)
+        );
+        // Stable channels that are already past their end of life are overdue, while channels
+        // that are no longer stable, or that never reach an end of life, get no warning
+        assert_eq!(
+            approaching_eol(
+                "nixos-24.11",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2025-07-01")
+            )
+            .map(|eol| eol.days_left),
+            Some(-1)
+        );
+        assert_eq!(
+            approaching_eol(
+                "nixos-24.05",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2024-12-20")
+            ),
+            None
+        );
+        assert_eq!(
+            approaching_eol(
+                "nixos-unstable",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2025-06-10")
+            ),
+            None
+        );
+
+        // Newer releases are known too, and a file of end-of-life dates overrides them
+        assert_eq!(
+            approaching_eol(
+                "nixos-25.11",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2026-06-10")
+            )
+            .map(|eol| eol.days_left),
+            Some(20)
+        );
+        let eol_dates = HashMap::from([(String::from("24.11"), date("2025-03-31"))]);
+        assert_eq!(
+            approaching_eol(
+                "nixos-24.11",
+                &ref_statuses,
+                &eol_dates,
+                30,
+                date("2025-03-20")
+            )
+            .map(|eol| eol.days_left),
+            Some(11)
+        );
+
+        // The warning doesn't fail the check, even in fail mode
+        let issue = Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::ApproachingEol(ApproachingEol {
+                channel: String::from("nixos-24.11"),
+                days_left: 20,
+            }),
+            location: None,
+        };
+        let config = FlakeCheckConfig {
+            fail_mode: true,
+            ..Default::default()
+        };
+        assert_eq!(issue.severity(&config), Severity::Warning);
+    }
+
+    #[test]
+    fn temporary_refs() {
+        // (ref, expected pull request number if temporary)
+        let cases: Vec<(&str, Option<Option<u64>>)> = vec![
+            ("refs/pull/123456/head", Some(Some(123456))),
+            ("pull/42/merge", Some(Some(42))),
+            ("refs/merge-requests/7/head", Some(Some(7))),
+            ("wip/openssl", Some(None)),
+            ("refs/heads/fix-openssl", Some(None)),
+            ("backport-123-to-release-24.11", Some(None)),
+            ("nixos-unstable", None),
+            ("nixos-24.11", None),
+            ("release-24.11", None),
+            ("master", None),
+            ("fixtures", None),
+            ("refs/pull/head", None),
+        ];
+        for (git_ref, expected) in cases {
+            assert_eq!(
+                temporary_ref(git_ref).map(|temporary_ref| temporary_ref.pull_request),
+                expected,
+                "unexpected result for {git_ref:?}"
+            );
+        }
+
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/flake.temporary-ref.0.lock")).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_supported: false,
+            check_temporary_refs: true,
+            ..Default::default()
+        };
+
+        let pull_request = Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::TemporaryRef(TemporaryRef {
+                temporary_ref: String::from("refs/pull/123456/head"),
+                pull_request: Some(123456),
+            }),
+            location: None,
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(issues, vec![pull_request.clone()]);
+
+        // Every input is checked on request, and reported by its name rather than its node's key
+        // (`rust-overlay_2`)
+        let config = FlakeCheckConfig {
+            temporary_refs_all_inputs: true,
+            ..config
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(checks_run.ran("rust-overlay", "Temporary refs"));
+        assert!(!checks_run.ran("rust-overlay_2", "Temporary refs"));
+        assert_eq!(
+            issues,
+            vec![
+                pull_request,
+                Issue {
+                    input: String::from("rust-overlay"),
+                    kind: IssueKind::TemporaryRef(TemporaryRef {
+                        temporary_ref: String::from("wip/msrv"),
+                        pull_request: None,
+                    }),
+                    location: None,
+                },
+            ]
+        );
+
+        // Channels aren't temporary
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+        assert!(check_flake_lock(&flake_lock, &config, allowed_refs)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn unexpected_hash_algo() {
+        assert_eq!(hash_algorithm("sha256-AAAA"), Some("sha256"));
+        assert_eq!(hash_algorithm("sha512:0000"), Some("sha512"));
+        assert_eq!(hash_algorithm("AAAA"), None);
+
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.sha512.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_hash_algo: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::UnexpectedHashAlgo(UnexpectedHashAlgo {
+                    algo: String::from("sha512"),
+                }),
+                location: None,
+            }]
+        );
+
+        // The check is opt-in
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn deny_indirect() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.indirect.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            deny_indirect: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::IndirectInput(IndirectInput {
+                    id: String::from("nixpkgs"),
+                }),
+                location: None,
+            }]
+        );
+
+        // Indirect inputs are allowed by default
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn transitively_indirect_inputs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        // `devshell`'s `nixpkgs` follows `home-manager/nixpkgs`, which follows the indirect `nixpkgs`
+        let path = PathBuf::from("tests/flake.transitively-indirect.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_transitive_indirect: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        let issue = |input: &str, follows: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::TransitivelyIndirect(TransitivelyIndirect {
+                via: format!("{input}/nixpkgs"),
+                follows: String::from(follows),
+                registry_id: String::from("nixpkgs"),
+            }),
+            location: None,
+        };
+        // The indirect `nixpkgs` input itself is left to `deny_indirect`
+        assert_eq!(
+            issues,
+            vec![
+                issue("devshell", "home-manager/nixpkgs"),
+                issue("home-manager", "nixpkgs"),
+            ]
+        );
+
+        // The check is disabled by default
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn mixed_nixpkgs_channel_inputs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        // `home-manager/nixpkgs` is on nixos-unstable while `nixpkgs` and `devshell/nixpkgs` are on
+        // nixos-24.05 (which is no longer supported), and `treefmt-nix/nixpkgs` follows `nixpkgs`
+        let path = PathBuf::from("tests/flake.mixed-channels.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        // Transitive inputs are compared without `transitive`
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_supported: false,
+            require_consistent_transitive_nixpkgs: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("root"),
+                kind: IssueKind::MixedNixpkgsChannels(MixedNixpkgsChannels {
+                    channels: vec![String::from("nixos-24.05"), String::from("nixos-unstable")],
+                    inputs: vec![String::from("home-manager/nixpkgs")],
+                }),
+                location: None,
+            }]
+        );
+
+        // A flake without transitive Nixpkgs inputs is consistent
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+
+        // The check is disabled by default
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_supported: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+    }
+
+    #[test]
+    fn unlocked_revs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_locked_revs: true,
+            ..Default::default()
+        };
+
+        // An indirect input resolved to GitHub but locked without a rev
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.unlocked-rev.0.lock")).unwrap();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::UnlockedRev(UnlockedRev {
+                    unlocked_type: String::from("github"),
+                }),
+                location: None,
+            }]
+        );
+
+        // Its node can't be parsed as an indirect one, so other checks don't select it
+        assert!(nixpkgs_deps(&flake_lock, &config.nixpkgs_keys, None, false, None).is_err());
+
+        // Indirect inputs with a rev are fine
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.indirect.0.lock")).unwrap();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+
+        // Tarballs are locked by their NAR hash
+        let tarball: Node = serde_json::from_value(serde_json::json!({
+            "locked": {
+                "lastModified": 1689078114,
+                "narHash": "sha256-osG8BrX5RpKJ7wH+vI6auOU+ctvNOblT4XXCgknK47c=",
+                "type": "tarball",
+                "url": "https://channels.nixos.org/nixos-24.05/nixexprs.tar.xz"
+            },
+            "original": {
+                "type": "tarball",
+                "url": "https://channels.nixos.org/nixos-24.05/nixexprs.tar.xz"
+            }
+        }))
+        .unwrap();
+        assert!(!lacks_locked_rev(&tarball));
+    }
+
+    #[test]
+    fn data_inputs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.data-inputs.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        // Only data inputs that are locked as flakes are flagged
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_data_inputs: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("fix-patch"),
+                kind: IssueKind::UnexpectedFlake(UnexpectedFlake {
+                    data_url: String::from("https://github.com/NixOS/nixpkgs/pull/123456.patch"),
+                }),
+                location: None,
+            }]
+        );
+
+        // Source trees may or may not be flakes, so they're never flagged
+        assert!(is_data_input(&flake_lock.nodes["fix-patch"]));
+        assert!(is_data_input(&flake_lock.nodes["release-notes"]));
+        assert!(!is_data_input(&flake_lock.nodes["tools"]));
+        assert!(!is_data_input(&flake_lock.nodes["nixpkgs"]));
+
+        // The check is opt-in
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+    }
+
+    #[test]
+    fn require_https() {
+        assert!(is_insecure_url("http://example.com/nixpkgs.tar.gz"));
+        assert!(is_insecure_url("git://example.com/nixpkgs"));
+        assert!(is_insecure_url("git+http://example.com/nixpkgs"));
+        assert!(!is_insecure_url("https://example.com/nixpkgs.tar.gz"));
+        assert!(!is_insecure_url("git+https://example.com/nixpkgs"));
+        assert!(!is_insecure_url("ssh://git@example.com/nixpkgs"));
+
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.insecure.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let config = FlakeCheckConfig {
+            require_https: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::InsecureTransport(InsecureTransport {
+                        url: String::from(
+                            "http://nixos.org/channels/nixpkgs-unstable/nixexprs.tar.xz"
+                        ),
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("tools"),
+                    kind: IssueKind::InsecureTransport(InsecureTransport {
+                        url: String::from("git://git.example.com/tools"),
+                    }),
+                    location: None,
+                },
+            ]
+        );
+
+        // The check is opt-in
+        let config = FlakeCheckConfig::default();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn deny_ref_patterns() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        // The nixpkgs input follows the supported `nixos-unstable` branch
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+
+        // (patterns, whether the input is flagged)
+        let cases: Vec<(Vec<&str>, bool)> = vec![
+            (vec![], false),
+            (vec![".*-beta", "wip/.*"], false),
+            (vec![".*-beta", "nixos-.*"], true),
+            (vec!["nixos-unstable"], true),
+            // Patterns must match the entire ref
+            (vec!["unstable"], false),
+        ];
+
+        for (patterns, flagged) in cases {
+            let config = FlakeCheckConfig {
+                check_outdated: false,
+                deny_ref_patterns: patterns
+                    .iter()
+                    .map(|pattern| ref_pattern_regex(pattern).unwrap())
+                    .collect(),
+                ..Default::default()
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            let expected = if flagged {
+                vec![Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("nixos-unstable"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                }]
+            } else {
+                vec![]
+            };
+            assert_eq!(issues, expected, "{patterns:?}");
+
+            // Denied refs are flagged even without the supported-ref check
+            let config = FlakeCheckConfig {
+                check_supported: false,
+                ..config
+            };
+            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+            assert_eq!(!issues.is_empty(), flagged, "{patterns:?}");
+        }
+    }
+
+    #[test]
+    fn accept_small_channels() {
+        let allowed_refs = vec![String::from("nixos-unstable")];
+        assert!(!is_allowed_ref(
+            "nixos-unstable-small",
+            &allowed_refs,
+            false
+        ));
+        assert!(is_allowed_ref("nixos-unstable-small", &allowed_refs, true));
+        assert!(is_allowed_ref("nixos-unstable", &allowed_refs, true));
+        assert!(!is_allowed_ref("nixos-24.11-small", &allowed_refs, true));
+        assert!(!is_allowed_ref("-small", &allowed_refs, true));
+
+        let path = PathBuf::from("tests/flake.small.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("nixos-unstable-small"),
+                    ..Default::default()
+                }),
+                location: None,
+            }]
+        );
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            accept_small_channels: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn max_inputs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.clean.3.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        assert_eq!(total_inputs(&flake_lock), 30);
+
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            check_owner: false,
+            max_inputs: Some(20),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("root"),
+                kind: IssueKind::TooManyInputs(TooManyInputs { count: 30, max: 20 }),
+                location: None,
+            }]
+        );
+
+        // The maximum itself is allowed
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            check_owner: false,
+            max_inputs: Some(30),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn max_transitive_depth() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.clean.3.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        // The deepest inputs, like haskellNix/hydra/nix/lowdown-src, are at depth 4
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            check_owner: false,
+            max_transitive_depth: Some(3),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("root"),
+                kind: IssueKind::ExcessiveDepth(ExcessiveDepth {
+                    path: String::from("haskellNix/hydra/nix/lowdown-src"),
+                    depth: 4,
+                    max_depth: 3,
+                }),
+                location: None,
+            }]
+        );
+
+        // The maximum itself is allowed
+        let config = FlakeCheckConfig {
+            max_transitive_depth: Some(4),
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+
+        // The walk for transitive Nixpkgs inputs stops at the maximum depth
+        let keys = vec![String::from("nixpkgs")];
+        let deps = |max_depth| nixpkgs_deps(&flake_lock, &keys, None, true, max_depth).unwrap();
+        assert!(deps(None).contains_key("haskellNix/hydra/nixpkgs"));
+        assert!(!deps(Some(2)).contains_key("haskellNix/hydra/nixpkgs"));
+        assert!(deps(Some(2)).contains_key("nixpkgs"));
+    }
+
+    #[test]
+    fn transitive_inputs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.transitive.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        // utils/nixpkgs follows tools/nixpkgs and so isn't walked twice
+        let walked: Vec<String> = flake_lock
+            .walk()
+            .unwrap()
+            .iter()
+            .map(|input| input.path.join("/"))
+            .collect();
+        assert_eq!(
+            walked,
+            vec!["nixpkgs", "tools", "tools/nixpkgs", "tools/utils"]
+        );
+
+        let disallowed = |input: &str, reference: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::Disallowed(Disallowed {
+                reference: String::from(reference),
+                ..Default::default()
+            }),
+            location: None,
+        };
+
+        // Only first-party inputs are checked by default
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            fail_mode: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(issues, vec![disallowed("nixpkgs", "nixos-22.05")]);
+
+        let config = FlakeCheckConfig {
+            transitive: true,
+            ..config
+        };
+        let mut issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        issues.sort_by(|a, b| a.input.cmp(&b.input));
+        assert_eq!(
+            issues,
+            vec![
+                disallowed("nixpkgs", "nixos-22.05"),
+                disallowed("tools/nixpkgs", "nixos-22.11"),
+            ]
+        );
+
+        // Third-party issues are reported as info unless first_party_only is unset
+        assert_eq!(issues[0].severity(&config), Severity::Error);
+        assert_eq!(issues[1].severity(&config), Severity::Info);
+        assert!(!has_errors(&issues[1..], &config));
+
+        let config = FlakeCheckConfig {
+            first_party_only: false,
+            ..config
+        };
+        assert_eq!(issues[1].severity(&config), Severity::Error);
+        assert!(has_errors(&issues[1..], &config));
+    }
+
+    #[test]
+    fn root_is_never_an_input() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.follows-self.0.lock")).unwrap();
+
+        // tools/parent follows the flake itself, which isn't walked
+        let walked: Vec<String> = flake_lock
+            .walk()
+            .unwrap()
+            .iter()
+            .map(|input| input.path.join("/"))
+            .collect();
+        assert_eq!(walked, vec!["nixpkgs", "tools"]);
+
+        // The checks of every node skip the root
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            transitive: true,
+            require_hashes: true,
+            require_https: true,
+            check_data_inputs: true,
+            check_temporary_refs: true,
+            temporary_refs_all_inputs: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(
+            !issues.iter().any(|issue| issue.input == "root"),
+            "{issues:?}"
+        );
+    }
+
+    #[test]
+    fn fail_fast() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.transitive.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        // Both disallowed inputs are errors, so all issues are collected by default
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            transitive: true,
+            first_party_only: false,
+            fail_mode: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(issues.len(), 2);
+
+        // With fail_fast, checking stops at the first error
+        let config = FlakeCheckConfig {
+            fail_fast: true,
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert!(has_errors(&issues, &config));
+
+        // Warnings don't fail the check and so don't stop it either
+        let config = FlakeCheckConfig {
+            fail_mode: false,
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert_eq!(issues.len(), 2);
+    }
+
+    #[test]
+    fn approved_revs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.transitive.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+
+        // Every input but nixpkgs_2 is approved, with owners compared case-insensitively
+        let approved_revs =
+            read_approved_revs_file(&PathBuf::from("tests/approved-revs.txt")).unwrap();
+        assert_eq!(approved_revs.len(), 3);
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            approved_revs: Some(approved_revs),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs_2"),
+                kind: IssueKind::UnapprovedRev(UnapprovedRev {
+                    repo: String::from("NixOS/nixpkgs"),
+                    rev: String::from("d4d822f526f1f72a450da88bf35abe132181170f"),
+                }),
+                location: None,
+            }]
+        );
+
+        // Without an approved list, revisions aren't checked
+        let config = FlakeCheckConfig {
+            approved_revs: None,
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.is_empty());
+
+        // Registry inputs are checked against the revision that they're locked to
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.indirect.0.lock")).unwrap();
+        let rev = String::from("b6cc7ff8fee93789bc871a267ab876c3fca042cb");
+        let config = FlakeCheckConfig {
+            approved_revs: Some(HashSet::from([(
+                String::from("nixos/nixpkgs"),
+                String::from("0000000000000000000000000000000000000000"),
+            )])),
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::UnapprovedRev(UnapprovedRev {
+                    repo: String::from("NixOS/nixpkgs"),
+                    rev: rev.clone(),
+                }),
+                location: None,
+            }]
+        );
+        let config = FlakeCheckConfig {
+            approved_revs: Some(HashSet::from([(String::from("nixos/nixpkgs"), rev)])),
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+
+        let path = std::env::temp_dir().join("flake-checker-invalid-approved-revs.txt");
+        std::fs::write(&path, "NixOS/nixpkgs\n").unwrap();
+        let result = read_approved_revs_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(FlakeCheckerError::ApprovedRevsFile(_))
+        ));
+    }
+
+    #[test]
+    fn disallowed_reasons() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses.clone());
+        let reason = |git_ref: &str| {
+            let disallowed = disallowed(git_ref, &ref_statuses, &allowed_refs);
+            (disallowed.reason, disallowed.upgrade_to)
+        };
+
+        // Unmaintained channels, whether still listed or long gone, are end-of-life
+        assert_eq!(
+            reason("nixos-24.05"),
+            (
+                DisallowedReason::EndOfLife,
+                Some(String::from("nixos-24.11"))
+            )
+        );
+        assert_eq!(
+            reason("nixos-22.11-small"),
             (
-                "flake.dirty.1.lock",
-                vec![
-                    Issue {
-                        input: String::from("nixpkgs"),
-                        kind: IssueKind::Disallowed(Disallowed {
-                            reference: String::from("probably-nefarious"),
-                        }),
-                    },
-                    Issue {
-                        input: String::from("nixpkgs"),
-                        kind: IssueKind::NonUpstream(NonUpstream {
-                            owner: String::from("pretty-shady"),
-                        }),
-                    },
-                ],
-            ),
-        ];
+                DisallowedReason::EndOfLife,
+                Some(String::from("nixos-24.11-small"))
+            )
+        );
+        assert_eq!(
+            reason("nixpkgs-22.11-darwin"),
+            (
+                DisallowedReason::EndOfLife,
+                Some(String::from("nixpkgs-24.11-darwin"))
+            )
+        );
 
-        for (file, expected_issues) in cases {
-            let path = PathBuf::from(format!("tests/{file}"));
-            let flake_lock = FlakeLock::new(&path).unwrap();
+        // Refs that aren't channels (yet) aren't recognized
+        assert_eq!(reason("nixos-99.99"), (DisallowedReason::Unknown, None));
+        assert_eq!(reason("nixos-unstabel"), (DisallowedReason::Unknown, None));
+
+        // Known refs that are denied have no more specific reason
+        assert_eq!(
+            reason("nixos-unstable"),
+            (DisallowedReason::Unsupported, None)
+        );
+        assert_eq!(
+            disallowed("nixos-22.11", &HashMap::new(), &allowed_refs).reason,
+            DisallowedReason::Unsupported
+        );
+
+        // A channel policy can make channels that upstream supports end-of-life or unsupported
+        let mut policy_statuses = ref_statuses.clone();
+        policy_statuses.extend([
+            (String::from("nixos-24.11"), String::from("unmaintained")),
+            (String::from("nixos-unstable"), String::from("beta")),
+        ]);
+        let policy_allowed_refs = supported_refs(policy_statuses.clone());
+        assert_eq!(
+            disallowed("nixos-24.11", &policy_statuses, &policy_allowed_refs).reason,
+            DisallowedReason::EndOfLife
+        );
+        assert_eq!(
+            disallowed("nixos-unstable", &policy_statuses, &policy_allowed_refs).reason,
+            DisallowedReason::Unsupported
+        );
+
+        // The reason is part of the issue
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.dirty.0.lock")).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            check_owner: false,
+            ref_statuses,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    reason: DisallowedReason::Unknown,
+                    upgrade_to: None,
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn matched_supported_refs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let config = FlakeCheckConfig {
+            nixpkgs_keys: vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+            ..Default::default()
+        };
+
+        // Both inputs follow nixos-unstable, which is listed once
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/flake.explicit-keys.0.lock")).unwrap();
+        assert_eq!(
+            matched_refs(&flake_lock, &config, &allowed_refs).unwrap(),
+            vec![String::from("nixos-unstable")]
+        );
+
+        // Unsupported refs aren't matched
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.dirty.0.lock")).unwrap();
+        assert!(
+            matched_refs(&flake_lock, &FlakeCheckConfig::default(), &allowed_refs)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn pinned_inputs() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+        let outdated = |pins: &str| {
             let config = FlakeCheckConfig {
-                check_outdated: false,
+                pins: pins.parse().unwrap(),
                 ..Default::default()
             };
-            let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
-            dbg!(&path);
-            assert_eq!(issues, expected_issues);
+            check_flake_lock(&flake_lock, &config, allowed_refs.clone())
+                .unwrap()
+                .iter()
+                .any(|issue| issue.kind.is_outdated())
+        };
+
+        assert!(outdated(""));
+        // An active pin exempts the input
+        assert!(!outdated("[pins.nixpkgs]\nexpires = \"2999-01-01\""));
+        assert!(!outdated("[pins.nixpkgs]"));
+        // After the pin expires, the issue returns
+        assert!(outdated("[pins.nixpkgs]\nexpires = \"2000-01-01\""));
+        // Pins only apply to the pinned inputs
+        assert!(outdated("[pins.nixpkgs-stable]"));
+    }
+
+    #[test]
+    fn input_tiers() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        // Both inputs follow nixos-unstable, but only nixpkgs-alt has a non-upstream owner
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/flake.explicit-keys.0.lock")).unwrap();
+        let kinds = |tiers: &str| {
+            let config = FlakeCheckConfig {
+                nixpkgs_keys: vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+                tiers: tiers.parse().unwrap(),
+                ..Default::default()
+            };
+            let mut kinds: Vec<String> =
+                check_flake_lock(&flake_lock, &config, allowed_refs.clone())
+                    .unwrap()
+                    .iter()
+                    .map(|issue| format!("{} {}", issue.input, issue.kind.name()))
+                    .collect();
+            // Inputs are checked in no particular order
+            kinds.sort();
+            kinds
+        };
+
+        assert_eq!(
+            kinds(""),
+            vec![
+                "nixpkgs outdated",
+                "nixpkgs-alt non-upstream",
+                "nixpkgs-alt outdated"
+            ]
+        );
+        // A critical tier only allows a stable channel, while an experimental one accepts old
+        // inputs from anywhere
+        assert_eq!(
+            kinds(
+                r#"
+                [tiers.critical]
+                inputs = ["nixpkgs"]
+                allowed-refs = ["nixos-24.11"]
+
+                [tiers.experimental]
+                inputs = ["nixpkgs-alt"]
+                max-days = 100000
+                check-owner = false
+                "#
+            ),
+            vec!["nixpkgs disallowed", "nixpkgs outdated"]
+        );
+        // A tier with an owner of its own accepts a fork for its inputs only
+        assert_eq!(
+            kinds(
+                r#"
+                [tiers.forked]
+                inputs = ["nixpkgs-alt"]
+                upstream-owner = "seems-pretty-shady"
+                "#
+            ),
+            vec!["nixpkgs outdated", "nixpkgs-alt outdated"]
+        );
+    }
+
+    #[test]
+    fn lock_format() {
+        // Written by Nix
+        let lock_contents = std::fs::read_to_string("tests/flake.clean.0.lock").unwrap();
+        assert_eq!(lock_format_drift(&lock_contents).unwrap(), None);
+
+        // The same lock, reindented by hand
+        let reformatted = std::fs::read_to_string("tests/flake.reformatted.0.lock").unwrap();
+        let drift = |line| {
+            Some(Issue {
+                input: String::from("root"),
+                kind: IssueKind::LockFormatDrift(LockFormatDrift {
+                    first_differing_line: line,
+                }),
+                location: None,
+            })
+        };
+        assert_eq!(lock_format_drift(&reformatted).unwrap(), drift(2));
+
+        // Without the trailing newline, only the last line differs
+        let last_line = lock_contents.lines().count();
+        assert_eq!(
+            lock_format_drift(lock_contents.trim_end()).unwrap(),
+            drift(last_line)
+        );
+    }
+
+    #[test]
+    fn hash_conflicts() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            check_nar_hash_consistency: true,
+            ..Default::default()
+        };
+
+        // nixpkgs and nixpkgs_2 are locked to the same revision with different hashes
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/flake.hash-conflict.0.lock")).unwrap();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::HashConflict(HashConflict {
+                    locked_repo: String::from("NixOS/nixpkgs"),
+                    locked_rev: String::from("b6cc7ff8fee93789bc871a267ab876c3fca042cb"),
+                    conflicting_nodes: vec![String::from("nixpkgs"), String::from("nixpkgs_2")],
+                    nar_hashes: vec![
+                        String::from("sha256-+qUhj8mkS6BsSFAOMQek346MHTEDkmoaojSBbLefq7w="),
+                        String::from("sha256-osG8BrX5RpKJ7wH+vI6auOU+ctvNOblT4XXCgknK47c="),
+                    ],
+                }),
+                location: None,
+            }]
+        );
+
+        // Different revisions may have different hashes
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/flake.transitive.0.lock")).unwrap();
+        assert!(check_flake_lock(&flake_lock, &config, allowed_refs)
+            .unwrap()
+            .is_empty());
+    }
+
+    // A GitHub node for FlakeLock::from_parts with inputs that refer to other nodes by key
+    fn github_node(owner: &str, repo: &str, rev: &str, inputs: &[(&str, &str)]) -> Node {
+        Node::Repo(Box::new(RepoNode {
+            flake: None,
+            inputs: (!inputs.is_empty()).then(|| {
+                inputs
+                    .iter()
+                    .map(|(name, key)| (name.to_string(), Input::String(key.to_string())))
+                    .collect()
+            }),
+            locked: RepoLocked {
+                last_modified: 1689078114,
+                nar_hash: format!("sha256-{rev}"),
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                rev: rev.to_string(),
+                host: None,
+                node_type: String::from("github"),
+            },
+            original: RepoOriginal {
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                git_ref: None,
+                node_type: String::from("github"),
+            },
+        }))
+    }
+
+    // A lock whose root has a Nixpkgs input and the given other inputs, along with those nodes
+    fn synthetic_lock(inputs: Vec<(String, Node)>, root_inputs: &[&str]) -> FlakeLock {
+        let mut nodes: HashMap<String, Node> = inputs.into_iter().collect();
+        nodes.insert(
+            String::from("nixpkgs"),
+            github_node("NixOS", "nixpkgs", "nixpkgs-rev", &[]),
+        );
+        let root_inputs = root_inputs
+            .iter()
+            .chain(&["nixpkgs"])
+            .map(|key| (key.to_string(), Input::String(key.to_string())))
+            .collect();
+        nodes.insert(
+            String::from("root"),
+            Node::Root(RootNode {
+                inputs: root_inputs,
+            }),
+        );
+        FlakeLock::from_parts(nodes, "root", 7).unwrap()
+    }
+
+    #[test]
+    fn synthetic_deep_graph() {
+        // A chain of 40 inputs, each of which is the only input of the one before it
+        let chain = (0..40)
+            .map(|n| {
+                let next = format!("n{}", n + 1);
+                let inputs = if n < 39 {
+                    vec![("next", next.as_str())]
+                } else {
+                    vec![]
+                };
+                (
+                    format!("n{n}"),
+                    github_node("some-org", "tool", &format!("rev-{n}"), &inputs),
+                )
+            })
+            .collect();
+        let flake_lock = synthetic_lock(chain, &["n0"]);
+        assert_eq!(flake_lock.walk().unwrap().len(), 41);
+
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            max_transitive_depth: Some(32),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, vec![]).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("root"),
+                kind: IssueKind::ExcessiveDepth(ExcessiveDepth {
+                    path: [vec!["n0"], vec!["next"; 32]].concat().join("/"),
+                    depth: 33,
+                    max_depth: 32,
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn synthetic_wide_graph() {
+        let inputs: Vec<(String, Node)> = (0..500)
+            .map(|n| {
+                (
+                    format!("tool-{n}"),
+                    github_node("some-org", &format!("tool-{n}"), "rev", &[]),
+                )
+            })
+            .collect();
+        let names: Vec<String> = inputs.iter().map(|(name, _)| name.clone()).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let flake_lock = synthetic_lock(inputs, &names);
+
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            max_inputs: Some(500),
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, vec![]).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("root"),
+                kind: IssueKind::TooManyInputs(TooManyInputs {
+                    count: 501,
+                    max: 500,
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn synthetic_hash_conflict_across_owner_case() {
+        // GitHub owners are case-insensitive, so these lock the same repository
+        let mut fork = github_node("nixos", "nixpkgs", "nixpkgs-rev", &[]);
+        if let Node::Repo(repo) = &mut fork {
+            repo.locked.nar_hash = String::from("sha256-tampered");
         }
+        let flake_lock = synthetic_lock(vec![(String::from("pinned"), fork)], &["pinned"]);
+
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            check_nar_hash_consistency: true,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, vec![]).unwrap();
+        let [Issue {
+            input,
+            kind: IssueKind::HashConflict(hash_conflict),
+            ..
+        }] = issues.as_slice()
+        else {
+            panic!("expected a single hash conflict but got {issues:?}");
+        };
+        assert_eq!(input, "nixpkgs");
+        assert_eq!(hash_conflict.conflicting_nodes, vec!["nixpkgs", "pinned"]);
     }
 
     #[test]
-    fn explicit_nixpkgs_keys() {
+    fn possible_typosquats() {
+        let owners = [
+            ("transposed", "nixso"),
+            ("doubled", "nixoss"),
+            ("fork", "nix-community"),
+            ("lowercase", "nixos"),
+        ];
+        let flake_lock = synthetic_lock(
+            owners
+                .iter()
+                .map(|(key, owner)| {
+                    (
+                        key.to_string(),
+                        github_node(owner, "nixpkgs", "nixpkgs-rev", &[]),
+                    )
+                })
+                .collect(),
+            &owners.map(|(key, _)| key),
+        );
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            check_outdated: false,
+            nixpkgs_keys: owners
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .chain([String::from("nixpkgs")])
+                .collect(),
+            ..Default::default()
+        };
+        let non_upstream = |input: &str, owner: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::NonUpstream(NonUpstream {
+                owner: String::from(owner),
+                repo: None,
+            }),
+            location: None,
+        };
+        let possible_typosquat = |input: &str, owner: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::PossibleTyposquat(PossibleTyposquat {
+                suspicious_owner: String::from(owner),
+                resembles: String::from("NixOS"),
+            }),
+            location: None,
+        };
+        let sorted_issues = |config: &FlakeCheckConfig| {
+            let mut issues = check_flake_lock(&flake_lock, config, vec![]).unwrap();
+            issues.sort_by(|a, b| a.input.cmp(&b.input));
+            issues
+        };
+
+        // By default, look-alike owners are just other owners, and the owner's case doesn't matter
+        assert_eq!(
+            sorted_issues(&config),
+            vec![
+                non_upstream("doubled", "nixoss"),
+                non_upstream("fork", "nix-community"),
+                non_upstream("transposed", "nixso"),
+            ]
+        );
+
+        // Strictly, owners a typo away are possible typosquats, while legitimately different
+        // owners are still just non-upstream, as are owners whose case differs
+        let config = FlakeCheckConfig {
+            strict_nixpkgs_owner: true,
+            ..config
+        };
+        assert_eq!(
+            sorted_issues(&config),
+            vec![
+                possible_typosquat("doubled", "nixoss"),
+                non_upstream("fork", "nix-community"),
+                non_upstream("lowercase", "nixos"),
+                possible_typosquat("transposed", "nixso"),
+            ]
+        );
+
+        assert!(!is_possible_typosquat("NixOs", "nixos"));
+        assert!(is_possible_typosquat("NxOS", "NixOS"));
+    }
+
+    #[test]
+    fn missing_hashes() {
         let ref_statuses: HashMap<String, String> =
             serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
         let allowed_refs = supported_refs(ref_statuses);
-        let cases: Vec<(&str, Vec<String>, Vec<Issue>)> = vec![(
-            "flake.explicit-keys.0.lock",
-            vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+
+        // The tools input's locked attributes lack a narHash
+        let path = PathBuf::from("tests/flake.missing-hash.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let tools = &flake_lock.nodes["tools"];
+        assert_eq!(tools.nar_hash(), None);
+        assert_eq!(tools.node_type(), Some("github"));
+
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.is_empty());
+
+        let config = FlakeCheckConfig {
+            require_hashes: true,
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
             vec![Issue {
-                input: String::from("nixpkgs-alt"),
-                kind: IssueKind::NonUpstream(NonUpstream {
-                    owner: String::from("seems-pretty-shady"),
+                input: String::from("tools"),
+                kind: IssueKind::MissingHash(MissingHash {
+                    locked_type: String::from("github"),
                 }),
-            }],
-        )];
+                location: None,
+            }]
+        );
 
-        for (file, nixpkgs_keys, expected_issues) in cases {
-            let path = PathBuf::from(format!("tests/{file}"));
+        // Complete lockfiles pass
+        for n in 0..=7 {
+            let path = PathBuf::from(format!("tests/flake.clean.{n}.lock"));
             let flake_lock = FlakeLock::new(&path).unwrap();
-            let config = FlakeCheckConfig {
-                check_outdated: false,
-                nixpkgs_keys,
-                ..Default::default()
-            };
             let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
-            assert_eq!(issues, expected_issues);
+            assert!(issues.is_empty(), "{path:?}: {issues:?}");
         }
     }
 
     #[test]
-    fn missing_nixpkgs_keys() {
+    fn strict_supported() {
         let ref_statuses: HashMap<String, String> =
             serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
         let allowed_refs = supported_refs(ref_statuses);
-        let cases: Vec<(&str, Vec<String>, String)> = vec![(
-            "flake.clean.0.lock",
-            vec![String::from("nixpkgs"), String::from("foo"), String::from("bar")],
-            String::from("invalid flake.lock: no nixpkgs dependency found for specified keys: foo, bar"),
-        ),
-        (
-            "flake.clean.1.lock",
-            vec![String::from("nixpkgs"), String::from("nixpkgs-other")],
-            String::from("invalid flake.lock: no nixpkgs dependency found for specified key: nixpkgs-other"),
-        )];
-        for (file, nixpkgs_keys, expected_err) in cases {
-            let path = PathBuf::from(format!("tests/{file}"));
-            let flake_lock = FlakeLock::new(&path).unwrap();
-            let config = FlakeCheckConfig {
-                check_outdated: false,
-                nixpkgs_keys,
-                ..Default::default()
-            };
 
-            let result = check_flake_lock(&flake_lock, &config, allowed_refs.clone());
+        // The input is pinned to a rev without a ref, which passes the supported check
+        let path = PathBuf::from("tests/flake.rev-only.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert!(issues.is_empty());
 
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err().to_string(), expected_err);
+        // ...but not the strict one, even with the regular check disabled
+        let config = FlakeCheckConfig {
+            check_supported: false,
+            strict_supported: true,
+            ..config
+        };
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.clone()).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed::default()),
+                location: None,
+            }]
+        );
+
+        // Inputs with a supported ref pass the strict check
+        let path = PathBuf::from("tests/flake.clean.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn ci_strict() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.rev-only.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+
+        // Outside of CI, an input without a ref passes
+        let issues = check_flake_lock(
+            &flake_lock,
+            &config.clone().with_ci_strict(false),
+            allowed_refs.clone(),
+        )
+        .unwrap();
+        assert!(issues.is_empty());
+
+        // In CI, it's flagged
+        let issues =
+            check_flake_lock(&flake_lock, &config.with_ci_strict(true), allowed_refs).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed::default()),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn non_flake_nixpkgs_input() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+        let path = PathBuf::from("tests/flake.non-flake.0.lock");
+        let flake_lock = FlakeLock::new(&path).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            ..Default::default()
+        };
+
+        // The nixpkgs input is locked to an unsupported branch but isn't a flake, so it's skipped
+        // rather than checked (or reported as missing)
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs).unwrap();
+        assert!(issues.is_empty());
+        let deps = nixpkgs_deps(&flake_lock, &config.nixpkgs_keys, None, false, None).unwrap();
+        assert!(deps.is_empty());
+        assert_eq!(
+            non_flake_nixpkgs(&flake_lock, &config).unwrap(),
+            vec![String::from("nixpkgs")]
+        );
+    }
+
+    #[test]
+    fn archive_json() {
+        let archive = std::fs::read_to_string("tests/flake-archive.0.json").unwrap();
+        let flake_lock: FlakeLock = lock_from_archive_json(&archive).unwrap().parse().unwrap();
+        assert_eq!(flake_lock.version, 7);
+        assert!(flake_lock.root.contains_key("nixpkgs"));
+
+        let result = lock_from_archive_json(r#"{"path": "/nix/store/foo-source", "inputs": {}}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn corrupt_root() {
+        for n in 0..=1 {
+            let path = PathBuf::from(format!("tests/flake.corrupt-root.{n}.lock"));
+            let result = FlakeLock::new(&path);
+            assert!(
+                matches!(result, Err(FlakeLockParseError::CorruptRoot(_))),
+                "expected a corrupt root error for {path:?} but got {result:?}"
+            );
         }
     }
 }
@@ -0,0 +1,786 @@
+//! A best-effort scanner for the inputs declared in a `flake.nix`. This isn't a Nix parser: it only
+//! understands the flake's top-level attribute set, in which it finds `inputs.<name>...` bindings
+//! and the bindings of an `inputs = { ... };` set. Inputs declared in other ways, like using `let`
+//! bindings, `//`, or an outer `let ... in`, aren't found. Likewise, an input's URL is only found
+//! if it's a plain string, so URLs with interpolations (`${...}`) or built using expressions aren't
+//! compared against the lock, and the same goes for the Git refs named in them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use parse_flake_lock::{FlakeLock, Input, Node};
+
+use crate::issue::{DeclaredRefMismatch, Issue, IssueKind, OriginalMismatch, UnlockedInput};
+use crate::report::ChecksRun;
+
+/// The flake reference types whose `ref` can be given as the last part of the path, as in
+/// `github:NixOS/nixpkgs/nixos-unstable`.
+const REPO_TYPES: &[&str] = &["github", "gitlab", "sourcehut"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// An identifier, which can name an attribute.
+    Name(String),
+    /// A string, which can name an attribute or be a value.
+    Str(String),
+    Dot,
+    Equals,
+    Semicolon,
+    /// `{`, `[`, or `(`.
+    Open(char),
+    /// `}`, `]`, or `)`.
+    Close(char),
+    Other,
+}
+
+/// The names of the inputs declared in a `flake.nix`, sorted by name.
+pub(crate) fn declared_inputs(flake_nix: &str) -> Vec<String> {
+    let tokens = tokenize(flake_nix);
+    let Some(start) = tokens.iter().position(|t| *t == Token::Open('{')) else {
+        return vec![];
+    };
+
+    let mut names = BTreeSet::new();
+    for (path, value) in bindings(&tokens, start + 1) {
+        // `inputs.self` configures the flake itself (like `inputs.self.submodules = true;`)
+        if path[0] != "inputs" || path.get(1).is_some_and(|name| name == "self") {
+            continue;
+        }
+
+        if let Some(name) = path.get(1) {
+            // inputs.<name>.url = "...";
+            names.insert(name.clone());
+        } else if tokens.get(value) == Some(&Token::Open('{')) {
+            // inputs = { <name>.url = "..."; };
+            for (path, _) in bindings(&tokens, value + 1) {
+                if path[0] != "self" {
+                    names.insert(path[0].clone());
+                }
+            }
+        }
+    }
+
+    names.into_iter().collect()
+}
+
+/// An issue for each input that the `flake.nix` declares but that the root of the `flake.lock`
+/// doesn't have, which means that the lock is stale.
+pub(crate) fn unlocked_inputs(flake_lock: &FlakeLock, flake_nix: &str) -> Vec<Issue> {
+    let Some(Node::Root(root)) = flake_lock.nodes.get(&flake_lock.root_key) else {
+        return vec![];
+    };
+
+    declared_inputs(flake_nix)
+        .into_iter()
+        .filter(|name| !root.inputs.contains_key(name))
+        .map(|name| Issue {
+            input: name.clone(),
+            kind: IssueKind::UnlockedInput(UnlockedInput { name }),
+            location: None,
+        })
+        .collect()
+}
+
+/// The URLs that the `flake.nix` declares for its inputs, keyed by input name. Only URLs given as
+/// plain strings are found.
+pub(crate) fn declared_urls(flake_nix: &str) -> BTreeMap<String, String> {
+    let tokens = tokenize(flake_nix);
+    let Some(start) = tokens.iter().position(|t| *t == Token::Open('{')) else {
+        return BTreeMap::new();
+    };
+
+    let mut urls = BTreeMap::new();
+    for (path, value) in bindings(&tokens, start + 1) {
+        if path[0] == "inputs" {
+            input_urls(&tokens, &path[1..], value, &mut urls);
+        }
+    }
+
+    urls
+}
+
+// Collect the URLs of the binding whose attribute path (below `inputs`) is `path` and whose value
+// starts at `value`.
+fn input_urls(
+    tokens: &[Token],
+    path: &[String],
+    value: usize,
+    urls: &mut BTreeMap<String, String>,
+) {
+    let is_set = tokens.get(value) == Some(&Token::Open('{'));
+    match path {
+        // inputs = { ... };
+        [] if is_set => {
+            for (path, value) in bindings(tokens, value + 1) {
+                input_urls(tokens, &path, value, urls);
+            }
+        }
+        // inputs.<name> = { url = "..."; };
+        [name] if is_set => {
+            for (path, value) in bindings(tokens, value + 1) {
+                if path != ["url"] {
+                    continue;
+                }
+                if let Some(url) = plain_string(tokens, value) {
+                    urls.insert(name.clone(), url.to_string());
+                }
+            }
+        }
+        // inputs.<name>.url = "...";
+        [name, url] if url == "url" => {
+            if let Some(url) = plain_string(tokens, value) {
+                urls.insert(name.clone(), url.to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+// The string that the value starting at `value` consists of, unless the value is more than a
+// string or the string has interpolations.
+fn plain_string(tokens: &[Token], value: usize) -> Option<&str> {
+    match (tokens.get(value), tokens.get(value + 1)) {
+        (Some(Token::Str(string)), Some(Token::Semicolon | Token::Close(_)))
+            if !string.contains("${") =>
+        {
+            Some(string)
+        }
+        _ => None,
+    }
+}
+
+/// An issue for each input whose URL in the `flake.nix` doesn't match the original (user-supplied)
+/// reference that the root of the `flake.lock` records for it, which means that the lock is stale
+/// or was tampered with. Inputs that follow other inputs are skipped, and the check is recorded in
+/// `checks_run` for the inputs that it compares.
+pub(crate) fn original_mismatches(
+    flake_lock: &FlakeLock,
+    flake_nix: &str,
+    checks_run: &mut ChecksRun,
+) -> Vec<Issue> {
+    let Some(Node::Root(root)) = flake_lock.nodes.get(&flake_lock.root_key) else {
+        return vec![];
+    };
+
+    declared_urls(flake_nix)
+        .into_iter()
+        .filter_map(|(name, declared_url)| {
+            let Some(Input::String(key)) = root.inputs.get(&name) else {
+                return None;
+            };
+            let original_url = flake_lock.nodes.get(key)?.original_url()?;
+            checks_run.record(&name, "Original URLs");
+            (normalize_url(&declared_url) != normalize_url(&original_url)).then_some(Issue {
+                input: name,
+                kind: IssueKind::OriginalMismatch(OriginalMismatch {
+                    declared_url,
+                    original_url,
+                }),
+                location: None,
+            })
+        })
+        .collect()
+}
+
+/// An issue for each of the Nixpkgs inputs (those named in `nixpkgs_keys`) whose URL in the
+/// `flake.nix` names a Git ref other than the one in the original reference that the root of the
+/// `flake.lock` records for it, which means that the lock wasn't updated after the `flake.nix`
+/// switched branches. Inputs whose URL doesn't name a ref are skipped, and the check is recorded in
+/// `checks_run` for the inputs that it compares.
+pub(crate) fn declared_ref_mismatches(
+    flake_lock: &FlakeLock,
+    flake_nix: &str,
+    nixpkgs_keys: &[String],
+    checks_run: &mut ChecksRun,
+) -> Vec<Issue> {
+    let Some(Node::Root(root)) = flake_lock.nodes.get(&flake_lock.root_key) else {
+        return vec![];
+    };
+
+    declared_urls(flake_nix)
+        .into_iter()
+        .filter(|(name, _)| nixpkgs_keys.contains(name))
+        .filter_map(|(name, declared_url)| {
+            let declared_ref = url_ref(&declared_url)?;
+            let Some(Input::String(key)) = root.inputs.get(&name) else {
+                return None;
+            };
+            let locked_ref = url_ref(&flake_lock.nodes.get(key)?.original_url()?);
+            checks_run.record(&name, "Declared refs");
+            (locked_ref.as_ref() != Some(&declared_ref)).then_some(Issue {
+                input: name,
+                kind: IssueKind::DeclaredRefMismatch(DeclaredRefMismatch {
+                    declared_ref,
+                    locked_ref,
+                }),
+                location: None,
+            })
+        })
+        .collect()
+}
+
+// The Git ref that a flake reference names, either as a `ref` parameter or as the last part of the
+// path of a repository reference like `github:NixOS/nixpkgs/nixos-24.05` or a flake registry
+// reference like `flake:nixpkgs/nixos-24.05`. A revision in the path isn't a ref.
+fn url_ref(url: &str) -> Option<String> {
+    let url = percent_decode(url);
+    let (url, query) = url.split_once('?').unwrap_or((&url, ""));
+    if let Some(git_ref) = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("ref="))
+    {
+        return Some(git_ref.to_string());
+    }
+
+    let (scheme, path) = url.trim_end_matches('/').split_once(':')?;
+    let position = ref_position(scheme)?;
+    let git_ref = path.splitn(position + 1, '/').nth(position)?;
+    (!is_rev(git_ref)).then(|| git_ref.to_string())
+}
+
+// How many parts of the path of a reference with this scheme come before its ref, like the owner
+// and repository of `github:NixOS/nixpkgs/nixos-24.05` or the registry ID of
+// `flake:nixpkgs/nixos-24.05`. References with other schemes only name a ref as a `ref` parameter.
+fn ref_position(scheme: &str) -> Option<usize> {
+    if scheme == "flake" {
+        Some(1)
+    } else if REPO_TYPES.contains(&scheme) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+// Whether a part of a flake reference is a full Git revision rather than a ref.
+fn is_rev(part: &str) -> bool {
+    part.len() == 40 && part.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// A flake reference in a form that's the same however it's written: percent-encoded characters
+// are decoded (Nix writes `*` as `%2A`, for example), bare flake registry IDs get the `flake:`
+// scheme, `tarball+` and `file+` prefixes are dropped, a `ref` parameter of a repository or flake
+// registry reference moves into its path while a revision in its path is dropped (the lock's
+// original reference doesn't include it), and any other parameters, like `dir`, are dropped.
+fn normalize_url(url: &str) -> String {
+    let url = percent_decode(url);
+    let (url, query) = url.split_once('?').unwrap_or((&url, ""));
+    let url = url.trim_end_matches('/');
+    let git_ref = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("ref="));
+
+    let url = if url.contains(':') {
+        url.to_string()
+    } else {
+        format!("flake:{url}")
+    };
+    let url = url
+        .strip_prefix("tarball+")
+        .or_else(|| url.strip_prefix("file+"))
+        .unwrap_or(&url);
+
+    let Some((scheme, path)) = url.split_once(':') else {
+        return url.to_string();
+    };
+    let Some(position) = ref_position(scheme) else {
+        return match git_ref {
+            Some(git_ref) => format!("{url}?ref={git_ref}"),
+            None => url.to_string(),
+        };
+    };
+
+    let mut parts: Vec<&str> = path.splitn(position + 1, '/').collect();
+    if parts.get(position).is_some_and(|part| is_rev(part)) {
+        parts.truncate(position);
+    }
+    if let Some(git_ref) = git_ref {
+        parts.truncate(position);
+        parts.push(git_ref);
+    }
+    format!("{scheme}:{}", parts.join("/"))
+}
+
+// The string with `%XX` escapes decoded. Escapes that aren't valid are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while let Some(&byte) = bytes.get(i) {
+        let escaped = (byte == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(escaped) => {
+                decoded.push(escaped);
+                i += 3;
+            }
+            None => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// The bindings of the attribute set whose contents start at `start`, as the binding's attribute
+// path and the index of the first token of its value.
+fn bindings(tokens: &[Token], start: usize) -> Vec<(Vec<String>, usize)> {
+    let mut bindings = vec![];
+    let mut i = start;
+
+    loop {
+        match tokens.get(i) {
+            None | Some(Token::Close(_)) => break,
+            Some(Token::Semicolon) => {
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let mut path = vec![];
+        while let Some(Token::Name(name) | Token::Str(name)) = tokens.get(i) {
+            path.push(name.clone());
+            i += 1;
+            if tokens.get(i) != Some(&Token::Dot) {
+                break;
+            }
+            i += 1;
+        }
+
+        if !path.is_empty() && path[0] != "inherit" && tokens.get(i) == Some(&Token::Equals) {
+            i += 1;
+            bindings.push((path, i));
+        }
+
+        i = skip_value(tokens, i);
+    }
+
+    bindings
+}
+
+// The index of the `;` that ends the value starting at `start` (or of the bracket that closes the
+// enclosing attribute set if the `;` is missing).
+fn skip_value(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+
+    while let Some(token) = tokens.get(i) {
+        match token {
+            Token::Open(_) => depth += 1,
+            Token::Close(_) if depth == 0 => return i,
+            Token::Close(_) => depth -= 1,
+            Token::Semicolon if depth == 0 => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    i
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while let Some(&c) = chars.get(i) {
+        let next = chars.get(i + 1).copied();
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '#' => {
+                while chars.get(i).is_some_and(|&c| c != '\n') {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            '"' => {
+                let (string, end) = string(&chars, i + 1);
+                tokens.push(Token::Str(string));
+                i = end;
+            }
+            '\'' if next == Some('\'') => {
+                i = indented_string_end(&chars, i + 2);
+                tokens.push(Token::Other);
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | '\''))
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            '=' if next == Some('=') => {
+                tokens.push(Token::Other);
+                i += 2;
+            }
+            _ => {
+                tokens.push(match c {
+                    '.' => Token::Dot,
+                    '=' => Token::Equals,
+                    ';' => Token::Semicolon,
+                    '{' | '[' | '(' => Token::Open(c),
+                    '}' | ']' | ')' => Token::Close(c),
+                    _ => Token::Other,
+                });
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+// The contents of the `"`-delimited string starting at `start` (just after the opening quote) and
+// the index just after its closing quote. Escapes and interpolations are kept as they are.
+fn string(chars: &[char], start: usize) -> (String, usize) {
+    let mut i = start;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '\\' => i += 2,
+            '$' if chars.get(i + 1) == Some(&'{') => i = interpolation_end(chars, i + 2),
+            '"' => return (chars[start..i].iter().collect(), i + 1),
+            _ => i += 1,
+        }
+    }
+    (chars[start..].iter().collect(), chars.len())
+}
+
+// The index just after the `''`-delimited string starting at `start` (just after the opening `''`).
+fn indented_string_end(chars: &[char], start: usize) -> usize {
+    let mut i = start;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '\'' if chars.get(i + 1) == Some(&'\'') => match chars.get(i + 2) {
+                // Escapes: `'''`, `''$`, and `''\<char>`
+                Some('\'') | Some('$') => i += 3,
+                Some('\\') => i += 4,
+                _ => return i + 2,
+            },
+            '$' if chars.get(i + 1) == Some(&'{') => i = interpolation_end(chars, i + 2),
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+// The index just after the `}` that ends the interpolation starting at `start` (just after `${`).
+fn interpolation_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '"' => i = string(chars, i + 1).1,
+            '\'' if chars.get(i + 1) == Some(&'\'') => i = indented_string_end(chars, i + 2),
+            '{' => {
+                depth += 1;
+                i += 1;
+            }
+            '}' if depth == 0 => return i + 1,
+            '}' => {
+                depth -= 1;
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use parse_flake_lock::FlakeLock;
+
+    use crate::issue::{DeclaredRefMismatch, Issue, IssueKind, OriginalMismatch, UnlockedInput};
+    use crate::report::ChecksRun;
+
+    use super::{
+        declared_inputs, declared_ref_mismatches, declared_urls, normalize_url,
+        original_mismatches, unlocked_inputs, url_ref,
+    };
+
+    #[test]
+    fn declared() {
+        let flake_nix = r#"
+            {
+              description = "A flake with { braces } and ; semicolons in strings";
+
+              # inputs.commented-out.url = "github:example/commented-out";
+              inputs = {
+                nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+                flake-utils = {
+                  url = "github:numtide/flake-utils";
+                  inputs.systems.follows = "systems";
+                };
+                "nixpkgs-stable".url = "github:NixOS/nixpkgs/${"nixos"}-24.11";
+                /* disabled.url = "github:example/disabled"; */
+              };
+              inputs.home-manager.url = "github:nix-community/home-manager";
+              inputs.home-manager.inputs.nixpkgs.follows = "nixpkgs";
+              inputs.assets = { url = "path:./assets"; flake = false; };
+              inputs.self.submodules = true;
+
+              outputs = { self, nixpkgs, ... }: {
+                inputs.not-an-input = ''
+                  ${nixpkgs}'''s ''${literal}
+                '';
+              };
+            }
+        "#;
+
+        assert_eq!(
+            declared_inputs(flake_nix),
+            vec![
+                "assets",
+                "flake-utils",
+                "home-manager",
+                "nixpkgs",
+                "nixpkgs-stable"
+            ]
+        );
+        assert!(declared_inputs("{ inputs = { self.lfs = true; }; }").is_empty());
+        assert!(declared_inputs("").is_empty());
+        assert!(declared_inputs("{ outputs = _: { }; }").is_empty());
+    }
+
+    #[test]
+    fn unlocked() {
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/unlocked/flake.lock")).unwrap();
+        let flake_nix = include_str!("../tests/unlocked/flake.nix");
+
+        // The flake.nix declares home-manager, which was never locked
+        assert_eq!(
+            unlocked_inputs(&flake_lock, flake_nix),
+            vec![Issue {
+                input: String::from("home-manager"),
+                kind: IssueKind::UnlockedInput(UnlockedInput {
+                    name: String::from("home-manager"),
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn urls() {
+        let flake_nix = r#"
+            {
+              inputs = {
+                nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+                flake-utils = {
+                  url = "github:numtide/flake-utils";
+                  inputs.systems.follows = "systems";
+                };
+                interpolated.url = "github:NixOS/nixpkgs/${"nixos"}-24.11";
+                concatenated.url = "github:NixOS/" + "nixpkgs";
+              };
+              inputs.home-manager.url = "github:nix-community/home-manager?ref=release-24.05";
+              inputs.assets = { url = "path:./assets"; flake = false; };
+              outputs = _: { };
+            }
+        "#;
+
+        // URLs that aren't plain strings are skipped
+        assert_eq!(
+            declared_urls(flake_nix).into_iter().collect::<Vec<_>>(),
+            vec![
+                (String::from("assets"), String::from("path:./assets")),
+                (
+                    String::from("flake-utils"),
+                    String::from("github:numtide/flake-utils")
+                ),
+                (
+                    String::from("home-manager"),
+                    String::from("github:nix-community/home-manager?ref=release-24.05")
+                ),
+                (
+                    String::from("nixpkgs"),
+                    String::from("github:NixOS/nixpkgs/nixos-24.05")
+                ),
+            ]
+        );
+
+        // Equivalent ways of writing the same reference
+        for (declared, original) in [
+            ("nixpkgs", "flake:nixpkgs"),
+            ("nixpkgs/nixos-24.05", "flake:nixpkgs/nixos-24.05"),
+            ("nixpkgs?ref=nixos-24.05", "flake:nixpkgs/nixos-24.05"),
+            (
+                "github:NixOS/nixpkgs?ref=nixos-24.05&dir=lib",
+                "github:NixOS/nixpkgs/nixos-24.05",
+            ),
+            (
+                "github:NixOS/nixpkgs/b6cc7ff8fee93789bc871a267ab876c3fca042cb",
+                "github:NixOS/nixpkgs",
+            ),
+            (
+                "https://flakehub.com/f/NixOS/nixpkgs/0.2411.*",
+                "https://flakehub.com/f/NixOS/nixpkgs/0.2411.%2A",
+            ),
+            (
+                "git+https://example.com/repo?ref=main&shallow=1",
+                "git+https://example.com/repo?ref=main",
+            ),
+        ] {
+            assert_eq!(
+                normalize_url(declared),
+                normalize_url(original),
+                "{declared}"
+            );
+        }
+        assert_ne!(
+            normalize_url("github:NixOS/nixpkgs/nixos-24.05"),
+            normalize_url("github:NixOS/nixpkgs/nixos-unstable")
+        );
+        assert_ne!(
+            normalize_url("nixpkgs/nixos-24.05"),
+            normalize_url("flake:nixpkgs")
+        );
+    }
+
+    #[test]
+    fn original_mismatch() {
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/original-mismatch/flake.lock")).unwrap();
+        let flake_nix = include_str!("../tests/original-mismatch/flake.nix");
+
+        // nixpkgs was locked from another branch, while fenix only differs in how `*` is encoded
+        assert_eq!(
+            original_mismatches(&flake_lock, flake_nix, &mut ChecksRun::default()),
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::OriginalMismatch(OriginalMismatch {
+                    declared_url: String::from("github:NixOS/nixpkgs/nixos-24.05"),
+                    original_url: String::from("github:NixOS/nixpkgs/nixos-unstable"),
+                }),
+                location: None,
+            }]
+        );
+
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/unlocked/flake.lock")).unwrap();
+        let flake_nix = include_str!("../tests/unlocked/flake.nix");
+        assert!(original_mismatches(&flake_lock, flake_nix, &mut ChecksRun::default()).is_empty());
+
+        // The ref of a flake registry input is part of its original reference
+        let indirect_lock = |git_ref: &str| -> FlakeLock {
+            format!(
+                r#"{{
+                  "nodes": {{
+                    "nixpkgs": {{
+                      "locked": {{
+                        "lastModified": 1,
+                        "narHash": "sha256-AAAA",
+                        "owner": "NixOS",
+                        "repo": "nixpkgs",
+                        "rev": "b6cc7ff8fee93789bc871a267ab876c3fca042cb",
+                        "type": "github"
+                      }},
+                      "original": {{ "id": "nixpkgs", "ref": "{git_ref}", "type": "indirect" }}
+                    }},
+                    "root": {{ "inputs": {{ "nixpkgs": "nixpkgs" }} }}
+                  }},
+                  "root": "root",
+                  "version": 7
+                }}"#
+            )
+            .parse()
+            .unwrap()
+        };
+        let flake_nix = r#"{ inputs.nixpkgs.url = "nixpkgs/nixos-24.05"; }"#;
+        assert!(original_mismatches(
+            &indirect_lock("nixos-24.05"),
+            flake_nix,
+            &mut ChecksRun::default()
+        )
+        .is_empty());
+        assert_eq!(
+            original_mismatches(
+                &indirect_lock("nixos-24.11"),
+                flake_nix,
+                &mut ChecksRun::default()
+            ),
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::OriginalMismatch(OriginalMismatch {
+                    declared_url: String::from("nixpkgs/nixos-24.05"),
+                    original_url: String::from("flake:nixpkgs/nixos-24.11"),
+                }),
+                location: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn declared_ref_mismatch() {
+        for (url, git_ref) in [
+            ("github:NixOS/nixpkgs/nixos-24.05", Some("nixos-24.05")),
+            ("github:NixOS/nixpkgs?ref=nixos-24.05", Some("nixos-24.05")),
+            ("flake:nixpkgs/nixos-24.05", Some("nixos-24.05")),
+            ("nixpkgs", None),
+            ("git+https://example.com/nixpkgs?ref=main", Some("main")),
+            ("github:NixOS/nixpkgs", None),
+            (
+                "github:NixOS/nixpkgs/b6cc7ff8fee93789bc871a267ab876c3fca042cb",
+                None,
+            ),
+            ("https://flakehub.com/f/NixOS/nixpkgs/0.2411.*", None),
+        ] {
+            assert_eq!(url_ref(url).as_deref(), git_ref, "{url}");
+        }
+
+        let flake_lock =
+            FlakeLock::new(&PathBuf::from("tests/original-mismatch/flake.lock")).unwrap();
+        let flake_nix = include_str!("../tests/original-mismatch/flake.nix");
+
+        // flake.nix moved nixpkgs to nixos-24.05, but it's still locked from nixos-unstable
+        let nixpkgs_keys = vec![String::from("nixpkgs"), String::from("fenix")];
+        assert_eq!(
+            declared_ref_mismatches(
+                &flake_lock,
+                flake_nix,
+                &nixpkgs_keys,
+                &mut ChecksRun::default()
+            ),
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::DeclaredRefMismatch(DeclaredRefMismatch {
+                    declared_ref: String::from("nixos-24.05"),
+                    locked_ref: Some(String::from("nixos-unstable")),
+                }),
+                location: None,
+            }]
+        );
+
+        // Only Nixpkgs inputs are checked
+        assert!(declared_ref_mismatches(
+            &flake_lock,
+            flake_nix,
+            &[String::from("fenix")],
+            &mut ChecksRun::default()
+        )
+        .is_empty());
+
+        let flake_lock = FlakeLock::new(&PathBuf::from("tests/unlocked/flake.lock")).unwrap();
+        let flake_nix = include_str!("../tests/unlocked/flake.nix");
+        assert!(declared_ref_mismatches(
+            &flake_lock,
+            flake_nix,
+            &nixpkgs_keys,
+            &mut ChecksRun::default()
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::error::FlakeCheckerError;
+
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+/// Expand a glob pattern like `*/flake.lock` into the paths of the files that match it, sorted.
+/// Relative patterns are relative to the current directory. `*` matches any characters and `?`
+/// matches a single character, both within a single path component. As in shells, wildcards don't
+/// match hidden files and directories unless the pattern component starts with `.`.
+pub(crate) fn expand(pattern: &str) -> Result<Vec<PathBuf>, FlakeCheckerError> {
+    let mut matches = vec![if pattern.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        PathBuf::new()
+    }];
+
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        let mut next = vec![];
+
+        for dir in &matches {
+            if !component.contains(['*', '?']) {
+                let path = dir.join(component);
+                if path.exists() {
+                    next.push(path);
+                }
+                continue;
+            }
+
+            let regex = component_regex(component)?;
+            let dir_path = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir.as_path()
+            };
+            let Ok(entries) = read_dir(dir_path) else {
+                continue;
+            };
+
+            for entry in entries {
+                let file_name = entry?.file_name();
+                let Some(name) = file_name.to_str() else {
+                    continue;
+                };
+                if name.starts_with('.') && !component.starts_with('.') {
+                    continue;
+                }
+                if regex.is_match(name) {
+                    next.push(dir.join(name));
+                }
+            }
+        }
+
+        matches = next;
+    }
+
+    let mut files: Vec<PathBuf> = matches.into_iter().filter(|path| path.is_file()).collect();
+    files.sort();
+    Ok(files)
+}
+
+// Translate a single path component of a glob pattern into an anchored regex.
+fn component_regex(component: &str) -> Result<Regex, FlakeCheckerError> {
+    let mut regex = String::from("^");
+    for c in component.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+
+    Regex::new(&regex)
+        .map_err(|e| FlakeCheckerError::Invalid(format!("invalid glob pattern {component:?}: {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use super::expand;
+
+    #[test]
+    fn expand_flake_lock_glob() {
+        let root = std::env::temp_dir().join(format!("flake-checker-glob-{}", std::process::id()));
+        for dir in ["a", "b", "c", ".hidden", "a/nested"] {
+            create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "flake.lock",
+            "a/flake.lock",
+            "b/flake.lock",
+            "c/other.lock",
+            ".hidden/flake.lock",
+            "a/nested/flake.lock",
+        ] {
+            write(root.join(file), "{}").unwrap();
+        }
+
+        let pattern = format!("{}/*/flake.lock", root.display());
+        assert_eq!(
+            expand(&pattern).unwrap(),
+            vec![root.join("a/flake.lock"), root.join("b/flake.lock")]
+        );
+
+        let pattern = format!("{}/?/*.lock", root.display());
+        assert_eq!(
+            expand(&pattern).unwrap(),
+            vec![
+                root.join("a/flake.lock"),
+                root.join("b/flake.lock"),
+                root.join("c/other.lock")
+            ]
+        );
+
+        // No matches isn't an error
+        let pattern = format!("{}/*/missing.lock", root.display());
+        assert!(expand(&pattern).unwrap().is_empty());
+
+        remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! Support for `--dump-graph`, which reports the resolved input graph of a `flake.lock` as is,
+//! without checking it, for visualizers and audits.
+
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use parse_flake_lock::{FlakeLock, Input, Node};
+use serde::Serialize;
+
+use crate::error::FlakeCheckerError;
+
+/// The format in which to dump an input graph.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum GraphFormat {
+    /// An object with the root's key and inputs and every other node with its children.
+    Json,
+}
+
+/// The input graph of a `flake.lock`.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Graph {
+    /// The key of the root node.
+    pub(crate) root: String,
+    /// The flake's own inputs (the root node's children), sorted by name.
+    pub(crate) inputs: Vec<GraphEdge>,
+    /// Every node in the `flake.lock` except the root, which is the flake itself rather than a
+    /// dependency, sorted by key.
+    pub(crate) nodes: Vec<GraphNode>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct GraphNode {
+    /// The node's key in the `flake.lock`, e.g. `nixpkgs_2`.
+    pub(crate) key: String,
+    /// The `type` of the node's locked input, e.g. `github`.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub(crate) node_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) repo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) url: Option<String>,
+    /// Whether the node's input is a flake (see [`Node::is_flake`]).
+    pub(crate) flake: bool,
+    /// The node's inputs, sorted by name.
+    pub(crate) children: Vec<GraphEdge>,
+}
+
+/// An edge from a node to one of its inputs.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct GraphEdge {
+    /// The name of the input, e.g. `nixpkgs`.
+    pub(crate) input: String,
+    /// The key of the input's node, which is `None` for a `follows` that doesn't resolve or that
+    /// refers to the flake itself (an empty `follows`).
+    pub(crate) node: Option<String>,
+    /// For an input that follows another input, the input names that lead from the root to it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) follows: Option<Vec<String>>,
+}
+
+impl Graph {
+    pub(crate) fn new(flake_lock: &FlakeLock) -> Result<Self, FlakeCheckerError> {
+        let follows: HashMap<(String, String), Option<String>> = flake_lock
+            .follows()?
+            .into_iter()
+            .map(|follows| {
+                let target = follows
+                    .target
+                    .filter(|target| target != &flake_lock.root_key);
+                ((follows.node, follows.input), target)
+            })
+            .collect();
+
+        let edges = |key: &String, node: &Node| -> Result<Vec<GraphEdge>, FlakeCheckerError> {
+            let mut edges: Vec<GraphEdge> = node
+                .inputs()?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(input, target)| match target {
+                    Input::String(target) => GraphEdge {
+                        input,
+                        node: Some(target),
+                        follows: None,
+                    },
+                    Input::List(path) => GraphEdge {
+                        node: follows
+                            .get(&(key.clone(), input.clone()))
+                            .cloned()
+                            .flatten(),
+                        input,
+                        follows: Some(path),
+                    },
+                })
+                .collect();
+            edges.sort_by(|a, b| a.input.cmp(&b.input));
+            Ok(edges)
+        };
+
+        let mut inputs = vec![];
+        let mut nodes = vec![];
+        for (key, node) in &flake_lock.nodes {
+            if matches!(node, Node::Root(_)) {
+                if key == &flake_lock.root_key {
+                    inputs = edges(key, node)?;
+                }
+                continue;
+            }
+
+            let children = edges(key, node)?;
+            let locked = match node {
+                Node::Repo(node) => Some(&node.locked),
+                Node::Indirect(node) => Some(&node.locked),
+                _ => None,
+            };
+
+            nodes.push(GraphNode {
+                key: key.clone(),
+                node_type: node.node_type().map(String::from),
+                owner: locked.map(|locked| locked.owner.clone()),
+                repo: locked.map(|locked| locked.repo.clone()),
+                rev: node.locked_rev().map(String::from),
+                url: node.url().map(String::from),
+                flake: node.is_flake(),
+                children,
+            });
+        }
+        nodes.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(Self {
+            root: flake_lock.root_key.clone(),
+            inputs,
+            nodes,
+        })
+    }
+
+    /// Render the graph in the given format.
+    pub(crate) fn render(&self, format: GraphFormat) -> Result<String, FlakeCheckerError> {
+        Ok(match format {
+            GraphFormat::Json => format!("{}\n", serde_json::to_string_pretty(self)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use parse_flake_lock::FlakeLock;
+
+    use super::{Graph, GraphFormat};
+
+    #[test]
+    fn transitive_graph() {
+        let flake_lock = FlakeLock::new(Path::new("tests/flake.transitive.0.lock")).unwrap();
+        let graph = Graph::new(&flake_lock).unwrap();
+
+        assert_eq!(
+            graph.render(GraphFormat::Json).unwrap(),
+            include_str!("../tests/graph/flake.transitive.0.json")
+        );
+    }
+
+    #[test]
+    fn root_is_not_a_node() {
+        let flake_lock = FlakeLock::new(Path::new("tests/flake.follows-self.0.lock")).unwrap();
+        let graph = Graph::new(&flake_lock).unwrap();
+
+        assert_eq!(graph.inputs.len(), 2);
+        assert!(graph.nodes.iter().all(|node| node.key != "root"));
+
+        // An input that follows the flake itself doesn't lead to the root either
+        let edges = graph
+            .inputs
+            .iter()
+            .chain(graph.nodes.iter().flat_map(|node| &node.children));
+        assert!(edges
+            .clone()
+            .all(|edge| edge.node.as_deref() != Some("root")));
+        let parent = edges.clone().find(|edge| edge.input == "parent").unwrap();
+        assert_eq!(parent.follows, Some(vec![]));
+    }
+}
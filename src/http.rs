@@ -0,0 +1,86 @@
+use crate::error::FlakeCheckerError;
+
+use reqwest::blocking::Client;
+use reqwest::Proxy;
+
+/// The HTTP client used to fetch ref statuses and send telemetry. If `proxy` is set, all requests
+/// go through it; otherwise the `HTTPS_PROXY`, `HTTP_PROXY`, and `NO_PROXY` environment variables
+/// are honored.
+pub(crate) fn client(proxy: Option<&str>) -> Result<Client, FlakeCheckerError> {
+    let mut builder = Client::builder();
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// The HTTP client, or none with `strict_telemetry_off`. Without a client, nothing (telemetry
+/// included) can be sent, which is what `--strict-telemetry-off` guarantees.
+pub(crate) fn optional_client(
+    proxy: Option<&str>,
+    strict_telemetry_off: bool,
+) -> Result<Option<Client>, FlakeCheckerError> {
+    if strict_telemetry_off {
+        return Ok(None);
+    }
+    client(proxy).map(Some)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use super::{client, optional_client};
+
+    #[test]
+    fn requests_go_through_proxy() {
+        // A proxy that records the request line it receives and responds itself
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            tx.send(request_line).unwrap();
+
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .unwrap();
+        });
+
+        let response = client(Some(&proxy))
+            .unwrap()
+            .get("http://prometheus.invalid/api/v1/query")
+            .send()
+            .unwrap();
+        assert_eq!(response.text().unwrap(), "ok");
+        assert_eq!(
+            rx.recv().unwrap().trim_end(),
+            "GET http://prometheus.invalid/api/v1/query HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn invalid_proxy() {
+        assert!(client(Some("not a url")).is_err());
+    }
+
+    #[test]
+    fn no_client_with_strict_telemetry_off() {
+        assert!(optional_client(None, false).unwrap().is_some());
+
+        // Not even an invalid proxy matters, since no client is built
+        assert!(optional_client(None, true).unwrap().is_none());
+        assert!(optional_client(Some("not a url"), true).unwrap().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! Support for `flake-checker init`, which writes a starter config file and prints a CI snippet
+//! that runs Flake Checker with it.
+
+use std::fs::write;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::error::FlakeCheckerError;
+use crate::settings::CONFIG_FILE_NAME;
+
+/// The CI system to print a snippet for.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum CiProvider {
+    /// A GitHub Actions job using the Nix Flake Checker Action.
+    Github,
+    /// A GitLab CI job that runs Flake Checker using Nix.
+    Gitlab,
+}
+
+/// The starter config file, whose settings are the defaults for a typical flake, except that it
+/// enables `fail-mode` so that CI fails when there are issues.
+pub(crate) const STARTER_CONFIG: &str = r#"# Settings for Nix Flake Checker, which reads this file from the directory that it runs in.
+# Each key is the name of a flag without the leading `--` (see `flake-checker --help`). Explicit
+# flags and environment variables take precedence over the settings here.
+
+# The flake.lock to check
+flake-lock-path = "flake.lock"
+
+# The inputs to check as Nixpkgs
+nixpkgs-keys = ["nixpkgs"]
+
+# Exit with a status of 1 when there are issues, even in GitHub Actions
+fail-mode = true
+
+# Flag Nixpkgs inputs that haven't been updated in this many days
+check-outdated = true
+max-days = 30
+
+# Flag Nixpkgs inputs that don't come from NixOS/nixpkgs
+check-owner = true
+
+# Flag Nixpkgs inputs that don't follow a supported branch, like nixos-unstable
+check-supported = true
+
+# Also check the Nixpkgs inputs of your inputs
+transitive = false
+
+# Flag inputs whose NAR hashes don't use sha256
+# check-hash-algo = true
+
+# Flag inputs that the flake registry resolves, like `inputs.nixpkgs.url = "nixpkgs"`
+# deny-indirect = true
+"#;
+
+const GITHUB_SNIPPET: &str = r#"# .github/workflows/flake-checker.yml
+name: Flake Checker
+on:
+  pull_request:
+  push:
+    branches: [main]
+jobs:
+  flake-checker:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      # Reads flake-checker.toml from the root of the repository
+      - name: Check Nix flake Nixpkgs inputs
+        uses: DeterminateSystems/flake-checker-action@main
+"#;
+
+const GITLAB_SNIPPET: &str = r#"# .gitlab-ci.yml
+flake-checker:
+  image: nixos/nix:latest
+  variables:
+    NIX_CONFIG: "experimental-features = nix-command flakes"
+  script:
+    # Reads flake-checker.toml from the root of the repository
+    - nix run github:DeterminateSystems/flake-checker
+"#;
+
+/// Write the starter config file to `dir`, unless there's a config file there already and `force`
+/// isn't set, and return the CI snippet for `ci`.
+pub(crate) fn init(
+    dir: &Path,
+    force: bool,
+    ci: CiProvider,
+) -> Result<&'static str, FlakeCheckerError> {
+    let path = dir.join(CONFIG_FILE_NAME);
+    if path.exists() && !force {
+        return Err(FlakeCheckerError::ConfigExists(path.display().to_string()));
+    }
+
+    write(&path, STARTER_CONFIG)
+        .map_err(|e| FlakeCheckerError::Init(format!("couldn't write {}: {e}", path.display())))?;
+
+    Ok(match ci {
+        CiProvider::Github => GITHUB_SNIPPET,
+        CiProvider::Gitlab => GITLAB_SNIPPET,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use clap::FromArgMatches;
+
+    use crate::settings::{command, config_file_settings, CONFIG_FILE_NAME};
+    use crate::Cli;
+
+    use super::{init, CiProvider};
+
+    #[test]
+    fn starter_config() {
+        let dir = std::env::temp_dir().join(format!("flake-checker-init-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let snippet = init(&dir, false, CiProvider::Github).unwrap();
+        assert!(snippet.contains("DeterminateSystems/flake-checker-action"));
+
+        // The config parses back into the usual defaults, besides failing when there are issues
+        let settings = config_file_settings(&dir.join(CONFIG_FILE_NAME), None)
+            .unwrap()
+            .unwrap();
+        let matches = command(Some(&settings))
+            .unwrap()
+            .try_get_matches_from(["flake-checker"])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        assert!(cli.fail_mode);
+        assert!(cli.check_outdated);
+        assert_eq!(cli.max_days, crate::flake::MAX_DAYS);
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs"]);
+
+        // An existing config is only overwritten with --force
+        fs::write(dir.join(CONFIG_FILE_NAME), "fail-mode = false\n").unwrap();
+        assert!(init(&dir, false, CiProvider::Gitlab).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join(CONFIG_FILE_NAME)).unwrap(),
+            "fail-mode = false\n"
+        );
+        assert!(init(&dir, true, CiProvider::Gitlab)
+            .unwrap()
+            .contains("nix run"));
+        assert_eq!(
+            fs::read_to_string(dir.join(CONFIG_FILE_NAME)).unwrap(),
+            super::STARTER_CONFIG
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
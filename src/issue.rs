@@ -1,36 +1,573 @@
-use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::flake::{is_transitive_input, FlakeCheckConfig};
+use crate::severity::Severity;
+
+/// The names of all issue kinds, as used on the command line.
+pub(crate) const ISSUE_KINDS: &[&str] = &[
+    "disallowed",
+    "outdated",
+    "non-upstream",
+    "violation",
+    "unexpected-hash-algo",
+    "indirect-input",
+    "insecure-transport",
+    "too-many-inputs",
+    "future-timestamp",
+    "unapproved-rev",
+    "missing-hash",
+    "unlocked-input",
+    "excessive-depth",
+    "lock-format-drift",
+    "original-mismatch",
+    "unexpected-flake",
+    "declared-ref-mismatch",
+    "temporary-ref",
+    "hash-conflict",
+    "approaching-eol",
+    "possible-typosquat",
+    "unlocked-rev",
+    "transitively-indirect",
+    "mixed-nixpkgs-channels",
+    "unparsable-node",
+];
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub(crate) struct Issue {
     pub input: String,
     pub kind: IssueKind,
+    /// The 1-based line and column in the `flake.lock` where the input's node is defined, if the
+    /// issue is about a node in the lock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<(usize, usize)>,
+}
+
+impl Issue {
+    /// The severity of the issue under `config`, which every output format and the exit code go by.
+    /// It falls back to `fail_mode` for kinds that aren't in the severity map. Issues with
+    /// transitive inputs are only informational if `first_party_only` is set, as are issues with
+    /// unchanged inputs if `changed_inputs` is set, and future timestamps are informational by
+    /// default, while approaching ends of life are warnings. With `fail_on_warnings`, all warnings
+    /// are errors, while with `warn_only`, all errors are warnings.
+    pub(crate) fn severity(&self, config: &FlakeCheckConfig) -> Severity {
+        if config.transitive && config.first_party_only && is_transitive_input(&self.input) {
+            return Severity::Info;
+        }
+
+        // Only changed inputs can fail the check
+        if config
+            .changed_inputs
+            .as_ref()
+            .is_some_and(|changed| !changed.contains(&self.input))
+        {
+            return Severity::Info;
+        }
+
+        let severity = config.severity_map.get(&self.kind).unwrap_or(
+            // Future timestamps are informational and approaching ends of life are warnings unless
+            // the severity map says otherwise
+            if self.kind.is_future_timestamp() {
+                Severity::Info
+            } else if self.kind.is_approaching_eol() {
+                Severity::Warning
+            } else if config.fail_mode {
+                Severity::Error
+            } else {
+                Severity::Warning
+            },
+        );
+
+        // Warn-only wins over fail-on-warnings, so that nothing fails
+        if config.warn_only {
+            severity.min(Severity::Warning)
+        } else if config.fail_on_warnings && severity == Severity::Warning {
+            Severity::Error
+        } else {
+            severity
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(untagged)]
 pub(crate) enum IssueKind {
     Disallowed(Disallowed),
     Outdated(Outdated),
     NonUpstream(NonUpstream),
-    Violation,
+    Violation(Violation),
+    UnexpectedHashAlgo(UnexpectedHashAlgo),
+    IndirectInput(IndirectInput),
+    InsecureTransport(InsecureTransport),
+    TooManyInputs(TooManyInputs),
+    FutureTimestamp(FutureTimestamp),
+    UnapprovedRev(UnapprovedRev),
+    MissingHash(MissingHash),
+    UnlockedInput(UnlockedInput),
+    ExcessiveDepth(ExcessiveDepth),
+    LockFormatDrift(LockFormatDrift),
+    OriginalMismatch(OriginalMismatch),
+    UnexpectedFlake(UnexpectedFlake),
+    DeclaredRefMismatch(DeclaredRefMismatch),
+    TemporaryRef(TemporaryRef),
+    HashConflict(HashConflict),
+    ApproachingEol(ApproachingEol),
+    PossibleTyposquat(PossibleTyposquat),
+    UnlockedRev(UnlockedRev),
+    TransitivelyIndirect(TransitivelyIndirect),
+    MixedNixpkgsChannels(MixedNixpkgsChannels),
+    UnparsableNode(UnparsableNode),
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub(crate) struct Disallowed {
     pub(crate) reference: String,
+    /// Why the ref isn't allowed.
+    #[serde(default)]
+    pub(crate) reason: DisallowedReason,
+    /// For an end-of-life channel, the supported channel of the same kind to upgrade to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) upgrade_to: Option<String>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+/// Why a Git ref isn't allowed.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum DisallowedReason {
+    /// The ref isn't supported (or is denied), with no more specific reason.
+    #[default]
+    Unsupported,
+    /// The ref is a real channel that's no longer maintained, e.g. `nixos-22.11`.
+    EndOfLife,
+    /// The ref isn't a recognized channel at all, e.g. because of a typo.
+    Unknown,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub(crate) struct Outdated {
     pub(crate) num_days_old: i64,
+    /// The date on which the input was last modified (`YYYY-MM-DD`).
+    pub(crate) last_modified: String,
+    /// The time at which the input was last modified as an ISO 8601 string.
+    pub(crate) last_modified_iso: String,
+    /// The channel (Git ref) that the input follows, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) channel: Option<String>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub(crate) struct NonUpstream {
     pub(crate) owner: String,
+    /// The input's repository, which is only recorded when there's an upstream repository (see
+    /// `--upstream-repo`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) repo: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct Violation {
+    /// The CEL variables describing the input when it violated the condition.
+    pub(crate) snapshot: ViolationSnapshot,
+    /// The name of the rule that the input violated, for conditions from `--condition-dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rule: Option<String>,
+}
+
+/// The values of an input's CEL variables. Missing values are empty (or zero), as in the condition.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct ViolationSnapshot {
+    /// `gitRef`
+    pub(crate) git_ref: String,
+    /// `owner`
+    pub(crate) owner: String,
+    /// `numDaysOld`
+    pub(crate) num_days_old: i64,
+    /// `rev`
+    pub(crate) rev: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct UnexpectedHashAlgo {
+    pub(crate) algo: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct IndirectInput {
+    /// The flake registry ID that the input is resolved from.
+    pub(crate) id: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct InsecureTransport {
+    /// The URL that the input is fetched from without TLS.
+    pub(crate) url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct TooManyInputs {
+    /// The total number of unique inputs in the `flake.lock`.
+    pub(crate) count: usize,
+    /// The maximum allowed number of inputs.
+    pub(crate) max: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct FutureTimestamp {
+    /// The date on which the input was supposedly last modified (`YYYY-MM-DD`).
+    pub(crate) last_modified: String,
+    /// How many whole days in the future that is.
+    pub(crate) num_days_ahead: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct UnapprovedRev {
+    /// The input's repository as `owner/repo`.
+    pub(crate) repo: String,
+    /// The locked Git revision, which isn't in the approved revisions file.
+    pub(crate) rev: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct MissingHash {
+    /// The `type` of the locked input without a NAR hash, e.g. `github`.
+    pub(crate) locked_type: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct UnlockedInput {
+    /// The name of the input that `flake.nix` declares but `flake.lock` doesn't have.
+    pub(crate) name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct ExcessiveDepth {
+    /// The path of input names to the first input found beyond the maximum depth, e.g.
+    /// `home-manager/nixpkgs`.
+    pub(crate) path: String,
+    /// The depth of that input in the input graph, where the root's inputs are at depth 1.
+    pub(crate) depth: usize,
+    /// The maximum allowed depth.
+    pub(crate) max_depth: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct LockFormatDrift {
+    /// The first line (counting from 1) at which the flake.lock differs from the way Nix writes it.
+    pub(crate) first_differing_line: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct OriginalMismatch {
+    /// The URL that `flake.nix` declares for the input.
+    pub(crate) declared_url: String,
+    /// The original (user-supplied) reference that `flake.lock` records for the input, as a URL.
+    pub(crate) original_url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct UnexpectedFlake {
+    /// The URL of the data-only input (like a patch) that's locked as a flake.
+    pub(crate) data_url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct DeclaredRefMismatch {
+    /// The Git ref that the input's URL in `flake.nix` names, e.g. `nixos-24.11`.
+    pub(crate) declared_ref: String,
+    /// The Git ref of the original reference that `flake.lock` records for the input, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) locked_ref: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct TemporaryRef {
+    /// The Git ref that the input follows, e.g. `refs/pull/123/head` or `wip/fix-openssl`.
+    pub(crate) temporary_ref: String,
+    /// The number of the pull (or merge) request, if the ref is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pull_request: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct HashConflict {
+    /// The locked repository as `owner/repo`.
+    pub(crate) locked_repo: String,
+    /// The Git revision that the nodes are all locked to.
+    pub(crate) locked_rev: String,
+    /// The keys of the nodes that lock the revision, sorted.
+    pub(crate) conflicting_nodes: Vec<String>,
+    /// The different NAR hashes that the nodes record for the revision, sorted.
+    pub(crate) nar_hashes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct ApproachingEol {
+    /// The stable channel that the input follows, e.g. `nixos-24.11`.
+    pub(crate) channel: String,
+    /// The number of days until the channel reaches its end of life, which is negative once it's
+    /// past it.
+    pub(crate) days_left: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct PossibleTyposquat {
+    /// The GitHub owner of the input, e.g. `nixso`.
+    pub(crate) suspicious_owner: String,
+    /// The upstream owner that it's a single edit away from, e.g. `NixOS`.
+    pub(crate) resembles: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct UnlockedRev {
+    /// The `type` of the input that isn't locked to a revision, e.g. `indirect`.
+    pub(crate) unlocked_type: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct TransitivelyIndirect {
+    /// The input names that lead from the root input to the input that follows the indirect one,
+    /// e.g. `devshell/nixpkgs`.
+    pub(crate) via: String,
+    /// The `follows` path of that input, e.g. `home-manager/nixpkgs`.
+    pub(crate) follows: String,
+    /// The flake registry ID that the followed input is resolved from.
+    pub(crate) registry_id: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct MixedNixpkgsChannels {
+    /// Every channel that the Nixpkgs inputs follow, sorted, e.g. `nixos-24.11` and
+    /// `nixos-unstable`.
+    pub(crate) channels: Vec<String>,
+    /// The transitive Nixpkgs inputs that follow a channel that the flake's own Nixpkgs inputs
+    /// don't, sorted, e.g. `home-manager/nixpkgs`.
+    pub(crate) inputs: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct UnparsableNode {
+    /// Why the node couldn't be parsed.
+    pub(crate) error: String,
+}
+
+impl Outdated {
+    pub(crate) fn new(last_modified: i64, num_days_old: i64) -> Self {
+        let last_modified = DateTime::from_timestamp(last_modified, 0).unwrap_or_default();
+
+        Self {
+            num_days_old,
+            last_modified: last_modified.format("%Y-%m-%d").to_string(),
+            last_modified_iso: last_modified.to_rfc3339(),
+            channel: None,
+        }
+    }
+}
+
+impl FutureTimestamp {
+    pub(crate) fn new(last_modified: i64, num_days_ahead: i64) -> Self {
+        let last_modified = DateTime::from_timestamp(last_modified, 0).unwrap_or_default();
+
+        Self {
+            last_modified: last_modified.format("%Y-%m-%d").to_string(),
+            num_days_ahead,
+        }
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses an issue kind from its name (as in [`ISSUE_KINDS`]). The kind's details are left empty,
+/// so the result is only useful for identifying the kind.
+impl FromStr for IssueKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "disallowed" => Self::Disallowed(Disallowed::default()),
+            "outdated" => Self::Outdated(Outdated::default()),
+            "non-upstream" => Self::NonUpstream(NonUpstream::default()),
+            "violation" => Self::Violation(Violation::default()),
+            "unexpected-hash-algo" => Self::UnexpectedHashAlgo(UnexpectedHashAlgo::default()),
+            "indirect-input" => Self::IndirectInput(IndirectInput::default()),
+            "insecure-transport" => Self::InsecureTransport(InsecureTransport::default()),
+            "too-many-inputs" => Self::TooManyInputs(TooManyInputs::default()),
+            "future-timestamp" => Self::FutureTimestamp(FutureTimestamp::default()),
+            "unapproved-rev" => Self::UnapprovedRev(UnapprovedRev::default()),
+            "missing-hash" => Self::MissingHash(MissingHash::default()),
+            "unlocked-input" => Self::UnlockedInput(UnlockedInput::default()),
+            "excessive-depth" => Self::ExcessiveDepth(ExcessiveDepth::default()),
+            "lock-format-drift" => Self::LockFormatDrift(LockFormatDrift::default()),
+            "original-mismatch" => Self::OriginalMismatch(OriginalMismatch::default()),
+            "unexpected-flake" => Self::UnexpectedFlake(UnexpectedFlake::default()),
+            "declared-ref-mismatch" => Self::DeclaredRefMismatch(DeclaredRefMismatch::default()),
+            "temporary-ref" => Self::TemporaryRef(TemporaryRef::default()),
+            "hash-conflict" => Self::HashConflict(HashConflict::default()),
+            "approaching-eol" => Self::ApproachingEol(ApproachingEol::default()),
+            "possible-typosquat" => Self::PossibleTyposquat(PossibleTyposquat::default()),
+            "unlocked-rev" => Self::UnlockedRev(UnlockedRev::default()),
+            "transitively-indirect" => Self::TransitivelyIndirect(TransitivelyIndirect::default()),
+            "mixed-nixpkgs-channels" => Self::MixedNixpkgsChannels(MixedNixpkgsChannels::default()),
+            "unparsable-node" => Self::UnparsableNode(UnparsableNode::default()),
+            _ => {
+                return Err(format!(
+                    "unknown issue kind {s:?} (expected one of: {})",
+                    ISSUE_KINDS.join(", ")
+                ))
+            }
+        })
+    }
 }
 
 impl IssueKind {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Disallowed(_) => "disallowed",
+            Self::Outdated(_) => "outdated",
+            Self::NonUpstream(_) => "non-upstream",
+            Self::Violation(_) => "violation",
+            Self::UnexpectedHashAlgo(_) => "unexpected-hash-algo",
+            Self::IndirectInput(_) => "indirect-input",
+            Self::InsecureTransport(_) => "insecure-transport",
+            Self::TooManyInputs(_) => "too-many-inputs",
+            Self::FutureTimestamp(_) => "future-timestamp",
+            Self::UnapprovedRev(_) => "unapproved-rev",
+            Self::MissingHash(_) => "missing-hash",
+            Self::UnlockedInput(_) => "unlocked-input",
+            Self::ExcessiveDepth(_) => "excessive-depth",
+            Self::LockFormatDrift(_) => "lock-format-drift",
+            Self::OriginalMismatch(_) => "original-mismatch",
+            Self::UnexpectedFlake(_) => "unexpected-flake",
+            Self::DeclaredRefMismatch(_) => "declared-ref-mismatch",
+            Self::TemporaryRef(_) => "temporary-ref",
+            Self::HashConflict(_) => "hash-conflict",
+            Self::ApproachingEol(_) => "approaching-eol",
+            Self::PossibleTyposquat(_) => "possible-typosquat",
+            Self::UnlockedRev(_) => "unlocked-rev",
+            Self::TransitivelyIndirect(_) => "transitively-indirect",
+            Self::MixedNixpkgsChannels(_) => "mixed-nixpkgs-channels",
+            Self::UnparsableNode(_) => "unparsable-node",
+        }
+    }
+
+    /// A short, kind-specific detail, e.g. the disallowed Git ref.
+    pub(crate) fn detail(&self) -> String {
+        match self {
+            Self::Disallowed(disallowed) => disallowed.reference.clone(),
+            Self::Outdated(outdated) => format!("{} days old", outdated.num_days_old),
+            Self::NonUpstream(non_upstream) => match &non_upstream.repo {
+                Some(repo) => format!("{}/{repo}", non_upstream.owner),
+                None => non_upstream.owner.clone(),
+            },
+            Self::Violation(violation) => {
+                let snapshot = &violation.snapshot;
+                let detail = format!(
+                    "gitRef: {}, owner: {}, numDaysOld: {}, rev: {}",
+                    snapshot.git_ref, snapshot.owner, snapshot.num_days_old, snapshot.rev
+                );
+                match &violation.rule {
+                    Some(rule) => format!("{rule} ({detail})"),
+                    None => detail,
+                }
+            }
+            Self::UnexpectedHashAlgo(unexpected_hash_algo) => unexpected_hash_algo.algo.clone(),
+            Self::IndirectInput(indirect_input) => indirect_input.id.clone(),
+            Self::InsecureTransport(insecure_transport) => insecure_transport.url.clone(),
+            Self::TooManyInputs(too_many_inputs) => {
+                format!(
+                    "{} inputs (max {})",
+                    too_many_inputs.count, too_many_inputs.max
+                )
+            }
+            Self::FutureTimestamp(future_timestamp) => {
+                format!("{} (in the future)", future_timestamp.last_modified)
+            }
+            Self::UnapprovedRev(unapproved_rev) => {
+                format!("{}@{}", unapproved_rev.repo, unapproved_rev.rev)
+            }
+            Self::MissingHash(missing_hash) => {
+                format!("{} input without a narHash", missing_hash.locked_type)
+            }
+            Self::UnlockedInput(unlocked_input) => {
+                format!("{} isn't in the flake.lock", unlocked_input.name)
+            }
+            Self::ExcessiveDepth(excessive_depth) => {
+                format!(
+                    "{} at depth {} (max {})",
+                    excessive_depth.path, excessive_depth.depth, excessive_depth.max_depth
+                )
+            }
+            Self::LockFormatDrift(lock_format_drift) => {
+                format!(
+                    "reformatted from line {}",
+                    lock_format_drift.first_differing_line
+                )
+            }
+            Self::OriginalMismatch(original_mismatch) => {
+                format!(
+                    "{} in flake.nix, {} in flake.lock",
+                    original_mismatch.declared_url, original_mismatch.original_url
+                )
+            }
+            Self::UnexpectedFlake(unexpected_flake) => {
+                format!("{} locked as a flake", unexpected_flake.data_url)
+            }
+            Self::DeclaredRefMismatch(declared_ref_mismatch) => {
+                format!(
+                    "{} in flake.nix, {} in flake.lock",
+                    declared_ref_mismatch.declared_ref,
+                    declared_ref_mismatch
+                        .locked_ref
+                        .as_deref()
+                        .unwrap_or("no ref")
+                )
+            }
+            Self::TemporaryRef(temporary_ref) => match temporary_ref.pull_request {
+                Some(number) => format!("{} (pull request #{number})", temporary_ref.temporary_ref),
+                None => temporary_ref.temporary_ref.clone(),
+            },
+            Self::HashConflict(hash_conflict) => format!(
+                "{}@{} with {} NAR hashes",
+                hash_conflict.locked_repo,
+                hash_conflict.locked_rev,
+                hash_conflict.nar_hashes.len()
+            ),
+            Self::ApproachingEol(approaching_eol) if approaching_eol.days_left < 0 => format!(
+                "{} {} days ago",
+                approaching_eol.channel, -approaching_eol.days_left
+            ),
+            Self::ApproachingEol(approaching_eol) => format!(
+                "{} in {} days",
+                approaching_eol.channel, approaching_eol.days_left
+            ),
+            Self::PossibleTyposquat(possible_typosquat) => format!(
+                "{} (resembles {})",
+                possible_typosquat.suspicious_owner, possible_typosquat.resembles
+            ),
+            Self::UnlockedRev(unlocked_rev) => {
+                format!("{} input without a locked rev", unlocked_rev.unlocked_type)
+            }
+            Self::TransitivelyIndirect(transitively_indirect) => format!(
+                "{} follows {} ({})",
+                transitively_indirect.via,
+                transitively_indirect.follows,
+                transitively_indirect.registry_id
+            ),
+            Self::MixedNixpkgsChannels(mixed_nixpkgs_channels) => format!(
+                "{} ({})",
+                mixed_nixpkgs_channels.channels.join(", "),
+                mixed_nixpkgs_channels.inputs.join(", ")
+            ),
+            Self::UnparsableNode(unparsable_node) => unparsable_node.error.clone(),
+        }
+    }
+
     pub(crate) fn is_disallowed(&self) -> bool {
         matches!(self, Self::Disallowed(_))
     }
@@ -44,6 +581,253 @@ impl IssueKind {
     }
 
     pub(crate) fn is_violation(&self) -> bool {
-        matches!(self, Self::Violation)
+        matches!(self, Self::Violation(_))
+    }
+
+    pub(crate) fn is_unexpected_hash_algo(&self) -> bool {
+        matches!(self, Self::UnexpectedHashAlgo(_))
+    }
+
+    pub(crate) fn is_indirect_input(&self) -> bool {
+        matches!(self, Self::IndirectInput(_))
+    }
+
+    pub(crate) fn is_insecure_transport(&self) -> bool {
+        matches!(self, Self::InsecureTransport(_))
+    }
+
+    pub(crate) fn is_too_many_inputs(&self) -> bool {
+        matches!(self, Self::TooManyInputs(_))
+    }
+
+    pub(crate) fn is_future_timestamp(&self) -> bool {
+        matches!(self, Self::FutureTimestamp(_))
+    }
+
+    pub(crate) fn is_unapproved_rev(&self) -> bool {
+        matches!(self, Self::UnapprovedRev(_))
+    }
+
+    pub(crate) fn is_missing_hash(&self) -> bool {
+        matches!(self, Self::MissingHash(_))
+    }
+
+    pub(crate) fn is_unlocked_input(&self) -> bool {
+        matches!(self, Self::UnlockedInput(_))
+    }
+
+    pub(crate) fn is_excessive_depth(&self) -> bool {
+        matches!(self, Self::ExcessiveDepth(_))
+    }
+
+    pub(crate) fn is_lock_format_drift(&self) -> bool {
+        matches!(self, Self::LockFormatDrift(_))
+    }
+
+    pub(crate) fn is_original_mismatch(&self) -> bool {
+        matches!(self, Self::OriginalMismatch(_))
+    }
+
+    pub(crate) fn is_unexpected_flake(&self) -> bool {
+        matches!(self, Self::UnexpectedFlake(_))
+    }
+
+    pub(crate) fn is_declared_ref_mismatch(&self) -> bool {
+        matches!(self, Self::DeclaredRefMismatch(_))
+    }
+
+    pub(crate) fn is_temporary_ref(&self) -> bool {
+        matches!(self, Self::TemporaryRef(_))
+    }
+
+    pub(crate) fn is_hash_conflict(&self) -> bool {
+        matches!(self, Self::HashConflict(_))
+    }
+
+    pub(crate) fn is_approaching_eol(&self) -> bool {
+        matches!(self, Self::ApproachingEol(_))
+    }
+
+    pub(crate) fn is_possible_typosquat(&self) -> bool {
+        matches!(self, Self::PossibleTyposquat(_))
+    }
+
+    pub(crate) fn is_unlocked_rev(&self) -> bool {
+        matches!(self, Self::UnlockedRev(_))
+    }
+
+    pub(crate) fn is_transitively_indirect(&self) -> bool {
+        matches!(self, Self::TransitivelyIndirect(_))
+    }
+
+    pub(crate) fn is_mixed_nixpkgs_channels(&self) -> bool {
+        matches!(self, Self::MixedNixpkgsChannels(_))
+    }
+
+    pub(crate) fn is_unparsable_node(&self) -> bool {
+        matches!(self, Self::UnparsableNode(_))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{
+        ApproachingEol, Disallowed, FutureTimestamp, Issue, IssueKind, Outdated, ISSUE_KINDS,
+    };
+    use crate::flake::FlakeCheckConfig;
+    use crate::severity::Severity;
+
+    fn issue(input: &str, kind: IssueKind) -> Issue {
+        Issue {
+            input: String::from(input),
+            kind,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn display_from_str_round_trip() {
+        for name in ISSUE_KINDS {
+            let kind: IssueKind = name.parse().unwrap();
+            assert_eq!(kind.to_string(), *name);
+            assert_eq!(kind.to_string().parse::<IssueKind>().unwrap(), kind);
+        }
+
+        // Display only depends on the kind, not its details
+        let disallowed = IssueKind::Disallowed(Disallowed {
+            reference: String::from("this-should-fail"),
+            ..Default::default()
+        });
+        assert_eq!(disallowed.to_string(), "disallowed");
+        assert_eq!(
+            IssueKind::Outdated(Outdated::new(1689078114, 45)).to_string(),
+            "outdated"
+        );
+
+        assert_eq!(
+            "stale".parse::<IssueKind>().unwrap_err(),
+            format!(
+                "unknown issue kind \"stale\" (expected one of: {})",
+                ISSUE_KINDS.join(", ")
+            )
+        );
+    }
+
+    #[test]
+    fn severity_resolution() {
+        let disallowed = issue(
+            "nixpkgs",
+            IssueKind::Disallowed(Disallowed {
+                reference: String::from("this-should-fail"),
+                ..Default::default()
+            }),
+        );
+        let future = issue(
+            "nixpkgs",
+            IssueKind::FutureTimestamp(FutureTimestamp {
+                last_modified: String::from("2099-01-01"),
+                num_days_ahead: 1,
+            }),
+        );
+        let eol = issue(
+            "nixpkgs",
+            IssueKind::ApproachingEol(ApproachingEol {
+                channel: String::from("nixos-24.11"),
+                days_left: 20,
+            }),
+        );
+        let transitive = issue("home-manager/nixpkgs", disallowed.kind.clone());
+        let severities = |config: &FlakeCheckConfig| {
+            [&disallowed, &future, &eol, &transitive].map(|issue| issue.severity(config))
+        };
+
+        // By default, only the soft kinds aren't plain warnings
+        assert_eq!(
+            severities(&FlakeCheckConfig::default()),
+            [
+                Severity::Warning,
+                Severity::Info,
+                Severity::Warning,
+                Severity::Warning
+            ]
+        );
+
+        // Fail mode makes the other kinds errors
+        let fail_mode = FlakeCheckConfig {
+            fail_mode: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            severities(&fail_mode),
+            [
+                Severity::Error,
+                Severity::Info,
+                Severity::Warning,
+                Severity::Error
+            ]
+        );
+
+        // The severity map takes precedence over both fail mode and the soft kinds
+        let mapped = FlakeCheckConfig {
+            severity_map: "disallowed=info,future-timestamp=error".parse().unwrap(),
+            ..fail_mode.clone()
+        };
+        assert_eq!(
+            severities(&mapped),
+            [
+                Severity::Info,
+                Severity::Error,
+                Severity::Warning,
+                Severity::Info
+            ]
+        );
+
+        // Transitive inputs are informational with first-party-only, and unchanged inputs with a
+        // set of changed inputs, whatever the rest of the config says
+        let first_party = FlakeCheckConfig {
+            transitive: true,
+            first_party_only: true,
+            fail_on_warnings: true,
+            ..fail_mode.clone()
+        };
+        assert_eq!(
+            severities(&first_party),
+            [
+                Severity::Error,
+                Severity::Info,
+                Severity::Error,
+                Severity::Info
+            ]
+        );
+        let changed = FlakeCheckConfig {
+            changed_inputs: Some(HashSet::from([String::from("home-manager/nixpkgs")])),
+            ..fail_mode.clone()
+        };
+        assert_eq!(
+            severities(&changed),
+            [
+                Severity::Info,
+                Severity::Info,
+                Severity::Info,
+                Severity::Error
+            ]
+        );
+
+        // Warn-only turns errors into warnings, even with fail-on-warnings
+        let warn_only = FlakeCheckConfig {
+            warn_only: true,
+            ..first_party
+        };
+        assert_eq!(
+            severities(&warn_only),
+            [
+                Severity::Warning,
+                Severity::Info,
+                Severity::Warning,
+                Severity::Info
+            ]
+        );
     }
 }
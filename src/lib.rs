@@ -0,0 +1,5 @@
+//! The parts of Flake Checker that other tools can use: which Nixpkgs refs are supported, and the
+//! errors that determining them can fail with.
+
+pub mod error;
+pub mod ref_statuses;
@@ -0,0 +1,189 @@
+//! Where in the `flake.lock` the nodes that issues are about are defined, found by a second pass
+//! over the lock's text since the parsed [FlakeLock] doesn't keep track of positions.
+
+use std::collections::HashMap;
+
+use parse_flake_lock::FlakeLock;
+
+use crate::error::FlakeCheckerError;
+use crate::flake::TRANSITIVE_INPUT_SEPARATOR;
+use crate::issue::Issue;
+
+/// Set the location of each issue to the position of its input's node in the `flake.lock` (whose
+/// contents are `lock_contents`). Issues that aren't about a node in the lock, like whole-lock
+/// issues (reported for the `root` input) or issues with inputs that are only in the flake.nix,
+/// get no location.
+pub(crate) fn locate_issues(
+    issues: &mut [Issue],
+    lock_contents: &str,
+) -> Result<(), FlakeCheckerError> {
+    let flake_lock = FlakeLock::from_str_lenient(lock_contents)?.0;
+    let keys: HashMap<String, String> = flake_lock
+        .walk()?
+        .into_iter()
+        .map(|input| (input.path.join(TRANSITIVE_INPUT_SEPARATOR), input.key))
+        .collect();
+    let locations = node_locations(lock_contents);
+
+    for issue in issues.iter_mut().filter(|issue| issue.input != "root") {
+        // Some issues, like hash conflicts, name nodes by key rather than by input
+        let key = keys.get(&issue.input).unwrap_or(&issue.input);
+        issue.location = locations.get(key).copied();
+    }
+
+    Ok(())
+}
+
+/// The 1-based line and column of the key of each node in the `nodes` object of the `flake.lock`
+/// whose contents are `lock_contents`, keyed by node key. Malformed JSON yields whatever nodes
+/// were found before the error.
+pub(crate) fn node_locations(lock_contents: &str) -> HashMap<String, (usize, usize)> {
+    let chars: Vec<char> = lock_contents.chars().collect();
+    let mut locations = HashMap::new();
+
+    let (mut line, mut column) = (1, 1);
+    let mut depth = 0;
+    let mut top_level_key = String::new();
+    let mut in_nodes = false;
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => {
+                let start = (line, column);
+                let Some(end) = string_end(&chars, i) else {
+                    break;
+                };
+                let raw: String = chars[i..=end].iter().collect();
+                column += end + 1 - i;
+                i = end + 1;
+
+                // Only strings followed by a colon are keys
+                let is_key = chars[i..]
+                    .iter()
+                    .find(|c| !c.is_whitespace())
+                    .is_some_and(|c| *c == ':');
+                if !is_key {
+                    continue;
+                }
+                let Ok(key) = serde_json::from_str::<String>(&raw) else {
+                    continue;
+                };
+                if depth == 1 {
+                    top_level_key = key;
+                } else if depth == 2 && in_nodes {
+                    locations.insert(key, start);
+                }
+                continue;
+            }
+            '{' | '[' => {
+                depth += 1;
+                if depth == 2 {
+                    in_nodes = chars[i] == '{' && top_level_key == "nodes";
+                }
+            }
+            '}' | ']' => {
+                if depth == 2 {
+                    in_nodes = false;
+                }
+                depth -= 1;
+            }
+            '\n' => {
+                line += 1;
+                column = 0;
+            }
+            _ => {}
+        }
+
+        column += 1;
+        i += 1;
+    }
+
+    locations
+}
+
+// The index of the quote that closes the string starting at `start`, if it's closed.
+fn string_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in chars.iter().enumerate().skip(start + 1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+
+    use crate::issue::{Issue, IssueKind, Outdated, TooManyInputs};
+
+    use super::{locate_issues, node_locations};
+
+    #[test]
+    fn node_key_locations() {
+        let lock = r#"{
+  "nodes": {
+    "nixpkgs": {
+      "locked": { "nodes": "not a node" }
+    },
+    "root": {
+      "inputs": { "nixpkgs": "nixpkgs" }
+    }
+  },
+  "root": "root",
+  "version": 7
+}"#;
+        let locations = node_locations(lock);
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations["nixpkgs"], (3, 5));
+        assert_eq!(locations["root"], (6, 5));
+    }
+
+    #[test]
+    fn issue_locations() {
+        let lock_contents = read_to_string("tests/flake.transitive.0.lock").unwrap();
+        let locations = node_locations(&lock_contents);
+        let line = |key: &str| {
+            lock_contents
+                .lines()
+                .position(|line| line.trim_start().starts_with(&format!("\"{key}\": {{")))
+                .unwrap()
+                + 1
+        };
+
+        let mut issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+            Issue {
+                input: String::from("root"),
+                kind: IssueKind::TooManyInputs(TooManyInputs { count: 30, max: 20 }),
+                location: None,
+            },
+            Issue {
+                input: String::from("not-in-the-lock"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ];
+        locate_issues(&mut issues, &lock_contents).unwrap();
+
+        // The location points at the key of the input's node
+        let (line_number, column) = issues[0].location.unwrap();
+        assert_eq!(line_number, line("nixpkgs"));
+        assert_eq!(
+            &lock_contents.lines().nth(line_number - 1).unwrap()[column - 1..],
+            "\"nixpkgs\": {"
+        );
+        assert_eq!(Some(locations["nixpkgs"]), issues[0].location);
+
+        assert_eq!(issues[1].location, None);
+        assert_eq!(issues[2].location, None);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::decompress::Decompression;
+use crate::encoding::decode;
+use crate::error::FlakeCheckerError;
+
+use std::path::{Path, PathBuf};
+
+use encoding_rs::Encoding;
+use reqwest::blocking::Client;
+
+/// Where to read a flake.lock from, as given by the flake.lock path argument.
+#[derive(Debug, PartialEq)]
+pub(crate) enum LockSource {
+    /// A local file, given as a path or a `file://` URL.
+    File(PathBuf),
+    /// A remote file, given as an `https://` URL, which is only fetched with `--online`.
+    Https(String),
+}
+
+impl LockSource {
+    /// Detect the kind of source from the argument's URL scheme. Arguments without a scheme are
+    /// paths.
+    pub(crate) fn parse(location: &Path) -> Result<Self, FlakeCheckerError> {
+        let Some((scheme, rest)) = location.to_str().and_then(|l| l.split_once("://")) else {
+            return Ok(Self::File(location.to_path_buf()));
+        };
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "file" => {
+                // Only local files have no host (or `localhost`)
+                let path = rest.strip_prefix("localhost").unwrap_or(rest);
+                if !path.starts_with('/') {
+                    return Err(FlakeCheckerError::LockUrl(format!(
+                        "{} isn't a local file URL",
+                        location.display()
+                    )));
+                }
+                Ok(Self::File(PathBuf::from(percent_decode(path)?)))
+            }
+            "https" => Ok(Self::Https(location.display().to_string())),
+            _ => Err(FlakeCheckerError::LockUrl(format!(
+                "unsupported scheme {scheme}:// in {} (expected file:// or https://)",
+                location.display()
+            ))),
+        }
+    }
+
+    /// Read the flake.lock's contents, decompressing them if `decompression` is set and decoding
+    /// them as `encoding` (see [crate::encoding::decode]). Fetching from an `https://` URL requires
+    /// `online` and a `client`.
+    pub(crate) fn read(
+        &self,
+        client: Option<&Client>,
+        online: bool,
+        decompression: Option<Decompression>,
+        encoding: Option<&'static Encoding>,
+    ) -> Result<String, FlakeCheckerError> {
+        let bytes = match self {
+            Self::File(path) => std::fs::read(path)?,
+            Self::Https(url) if online => match client {
+                Some(client) => fetch(client, url)?,
+                None => {
+                    return Err(FlakeCheckerError::StrictTelemetryOff(format!(
+                        "fetching {url}"
+                    )))
+                }
+            },
+            Self::Https(url) => {
+                return Err(FlakeCheckerError::LockUrl(format!(
+                    "fetching {url} requires --online"
+                )))
+            }
+        };
+
+        let bytes = match decompression {
+            Some(decompression) => decompression.decompress(&bytes)?,
+            None => bytes,
+        };
+        decode(&bytes, encoding)
+    }
+}
+
+fn fetch(client: &Client, url: &str) -> Result<Vec<u8>, FlakeCheckerError> {
+    Ok(client
+        .get(url)
+        .send()?
+        .error_for_status()?
+        .bytes()?
+        .to_vec())
+}
+
+// Decode `%XX` escapes in the path of a `file://` URL, like `%20` for a space.
+fn percent_decode(path: &str) -> Result<String, FlakeCheckerError> {
+    let invalid = || FlakeCheckerError::LockUrl(format!("invalid escape in file URL path {path}"));
+
+    let mut bytes = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+
+    use crate::http::client;
+
+    use super::{fetch, LockSource};
+
+    #[test]
+    fn scheme_detection() {
+        let parse = |location: &str| LockSource::parse(Path::new(location));
+
+        let cases: Vec<(&str, LockSource)> = vec![
+            ("flake.lock", LockSource::File(PathBuf::from("flake.lock"))),
+            (
+                "/src/flake.lock",
+                LockSource::File(PathBuf::from("/src/flake.lock")),
+            ),
+            (
+                "file:///src/flake.lock",
+                LockSource::File(PathBuf::from("/src/flake.lock")),
+            ),
+            (
+                "FILE://localhost/src/my%20flake/flake.lock",
+                LockSource::File(PathBuf::from("/src/my flake/flake.lock")),
+            ),
+            (
+                "https://example.com/flake.lock",
+                LockSource::Https(String::from("https://example.com/flake.lock")),
+            ),
+        ];
+
+        for (location, expected) in cases {
+            assert_eq!(parse(location).unwrap(), expected, "{location}");
+        }
+
+        for location in [
+            "file://example.com/flake.lock",
+            "file:///flake%2.lock",
+            "http://example.com/flake.lock",
+            "s3://bucket/flake.lock",
+        ] {
+            assert!(parse(location).is_err(), "{location}");
+        }
+    }
+
+    #[test]
+    fn file_url() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/flake.clean.0.lock");
+        let url = format!("file://{}", path.display());
+
+        let source = LockSource::parse(Path::new(&url)).unwrap();
+        assert_eq!(
+            source
+                .read(Some(&client(None).unwrap()), false, None, None)
+                .unwrap(),
+            std::fs::read_to_string(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn https_url() {
+        let lock = include_str!("../tests/flake.clean.0.lock");
+
+        // A server standing in for the HTTPS host, which serves the lock to one request
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/flake.lock", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{lock}",
+                lock.len()
+            )
+            .unwrap();
+        });
+
+        assert_eq!(
+            fetch(&client(None).unwrap(), &url).unwrap(),
+            lock.as_bytes()
+        );
+
+        // Without --online, nothing is fetched
+        let source = LockSource::parse(Path::new("https://example.com/flake.lock")).unwrap();
+        let error = source
+            .read(Some(&client(None).unwrap()), false, None, None)
+            .unwrap_err();
+        assert!(error.to_string().contains("requires --online"), "{error}");
+    }
+}
@@ -1,45 +1,163 @@
+mod baseline;
+mod channels;
+mod checks;
+mod color;
 mod condition;
-mod error;
+mod decompress;
+mod diff_table;
+mod dry_run;
+mod duration;
+mod edit_distance;
+mod encoding;
+mod explain;
 mod flake;
+mod flake_nix;
+mod glob;
+mod graph;
+mod http;
+mod init;
 mod issue;
+mod location;
+mod lock_source;
+mod output;
+mod pins;
+mod pool;
+mod report;
+mod settings;
+mod severity;
+mod since_commit;
 mod summary;
 mod telemetry;
+mod tiers;
+mod trend;
+mod validate;
 
-#[cfg(feature = "ref-statuses")]
-mod ref_statuses;
+#[cfg(feature = "protobuf")]
+mod protobuf;
 
+use flake_checker::{error, ref_statuses};
+
+use channels::{ChannelDistribution, ChannelsFormat};
+use color::ColorChoice;
+use decompress::Decompression;
 use error::FlakeCheckerError;
-use flake::{check_flake_lock, FlakeCheckConfig};
-use summary::Summary;
+use flake::{
+    check_flake_lock_with_checks_run, clean_inputs, input_ages, input_checks, input_name_regex,
+    input_scope, lock_format_drift, lock_from_archive_json, matched_refs, nixpkgs_deps,
+    non_flake_nixpkgs, read_approved_revs_file, read_nixpkgs_keys_file, ref_pattern_regex,
+    total_inputs, FlakeCheckConfig, RepoRename,
+};
+use graph::{Graph, GraphFormat};
+use init::CiProvider;
+use issue::{Issue, IssueKind, UnparsableNode, ISSUE_KINDS};
+use lock_source::LockSource;
+use output::{ExplainFormat, OutputFormat};
+use pins::Pins;
+use ref_statuses::{
+    resolve_allowed_refs, EndOfLifeFetcher, PrometheusFetcher, RefStatusSource, RefsCommand,
+    EOL_DATES_URL,
+};
+use report::{CheckReport, ChecksRun};
+use severity::SeverityMap;
+use summary::{Lang, Summary};
+use tiers::Tiers;
+use trend::TrendFormat;
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::Duration;
 
-use clap::Parser;
+use chrono::Utc;
+use clap::builder::PossibleValuesParser;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
+use encoding_rs::Encoding;
 use parse_flake_lock::FlakeLock;
+use regex::Regex;
+use reqwest::blocking::Client;
+use tracing_subscriber::EnvFilter;
 
-use crate::condition::evaluate_condition;
+use crate::condition::{evaluate_condition, evaluate_rules, ConditionFormat, ConditionScope};
 
 /// A flake.lock checker for Nix projects.
-#[cfg(not(feature = "ref-statuses"))]
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Don't send aggregate sums of each issue type.
     ///
     /// See <https://github.com/determinateSystems/flake-checker>.
     #[arg(long, env = "NIX_FLAKE_CHECKER_NO_TELEMETRY", default_value_t = false)]
     no_telemetry: bool,
 
+    /// Guarantee that nothing is sent: telemetry is off and no HTTP client is even built, so
+    /// anything that needs network access, like --online, is an error.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_STRICT_TELEMETRY_OFF",
+        default_value_t = false,
+        conflicts_with = "online"
+    )]
+    strict_telemetry_off: bool,
+
+    /// List every check with the flag and environment variable that control it, whether it's
+    /// enabled by default, and what it flags, and exit.
+    #[arg(long, default_value_t = false)]
+    list_checks: bool,
+
+    /// Apply the settings in this `[profile.<name>]` section of the flake-checker.toml on top of
+    /// the file's top-level settings. Unless NIX_FLAKE_CHECKER_SETTINGS is set, a flake-checker.toml
+    /// in the current directory is always read, with or without a profile, and its settings are
+    /// the defaults for these flags.
+    #[arg(long, global = true, env = "NIX_FLAKE_CHECKER_CONFIG_PROFILE")]
+    config_profile: Option<String>,
+
     /// Check for outdated Nixpkgs inputs.
     #[arg(long, env = "NIX_FLAKE_CHECKER_CHECK_OUTDATED", default_value_t = true)]
     check_outdated: bool,
 
-    /// Check that Nixpkgs inputs have "NixOS" as the GitHub owner.
+    /// Check that Nixpkgs inputs have "NixOS" (or --upstream-owner) as the GitHub owner.
     #[arg(long, env = "NIX_FLAKE_CHECKER_CHECK_OWNER", default_value_t = true)]
     check_owner: bool,
 
+    /// Check the owner of Nixpkgs inputs strictly: it has to match "NixOS" (or --upstream-owner)
+    /// exactly, including its case, and owners a single typo away from it (like `nixso`) are
+    /// flagged as possible typosquats.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_STRICT_NIXPKGS_OWNER",
+        default_value_t = false
+    )]
+    strict_nixpkgs_owner: bool,
+
+    /// The GitHub owner considered upstream by the owner check.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_UPSTREAM_OWNER",
+        default_value = flake::UPSTREAM_OWNER
+    )]
+    upstream_owner: String,
+
+    /// The GitHub repository considered upstream by the owner check, which then also checks that
+    /// inputs come from --upstream-owner/--upstream-repo rather than from any repository of
+    /// --upstream-owner.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_UPSTREAM_REPO")]
+    upstream_repo: Option<String>,
+
+    /// Treat a renamed repository as its canonical repository in the owner and approved revision
+    /// checks, as `<from>=<to>` where each side is `<repo>` or `<owner>/<repo>` (e.g.
+    /// `our-nixpkgs=nixpkgs` or `my-org/our-nixpkgs=NixOS/nixpkgs`). Renaming only the repository
+    /// keeps the owner, so for the owner check it only matters with --upstream-repo. Repeat this to
+    /// rename several repositories.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_INPUT_RENAME", value_delimiter = ',')]
+    input_rename: Vec<RepoRename>,
+
     /// Check that Git refs for Nixpkgs inputs are supported.
     #[arg(
         long,
@@ -48,6 +166,260 @@ struct Cli {
     )]
     check_supported: bool,
 
+    /// Require Nixpkgs inputs to have a Git ref that's supported, so that inputs pinned to a
+    /// revision without a ref fail the supported check too. Implies --check-supported.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_STRICT_SUPPORTED",
+        default_value_t = false
+    )]
+    strict_supported: bool,
+
+    /// Apply stricter checks when running in CI (as detected from environment variables like
+    /// CI): Nixpkgs inputs without a ref, which follow the default branch, are flagged as with
+    /// --strict-supported.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_CI_STRICT", default_value_t = false)]
+    ci_strict: bool,
+
+    /// Treat `<channel>-small` refs as supported whenever `<channel>` is supported.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_ACCEPT_SMALL_CHANNELS",
+        default_value_t = false
+    )]
+    accept_small_channels: bool,
+
+    /// Flag Nixpkgs inputs whose Git ref matches this regular expression, which must match the
+    /// entire ref (e.g. `.*-beta` or `wip/.*`), even if the ref is supported. Repeat this to deny
+    /// several patterns.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_DENY_REF_PATTERN",
+        value_parser = ref_pattern_regex
+    )]
+    deny_ref_pattern: Vec<Regex>,
+
+    /// Check that the NAR hashes of all inputs use the sha256 algorithm.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_HASH_ALGO",
+        default_value_t = false
+    )]
+    check_hash_algo: bool,
+
+    /// Flag inputs whose locked attributes lack a NAR hash, so Nix can't verify their contents.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_REQUIRE_HASHES",
+        default_value_t = false
+    )]
+    require_hashes: bool,
+
+    /// Flag Nixpkgs inputs whose locked attributes have no Git revision, like indirect inputs
+    /// locked by old tools, since such inputs aren't reproducible. Tarball and path inputs, which
+    /// are locked by their NAR hash, are exempt.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_LOCKED_REVS",
+        default_value_t = false
+    )]
+    check_locked_revs: bool,
+
+    /// Flag nodes that are locked to the same revision of the same repository but record
+    /// different NAR hashes for it, which points to a corrupted or tampered-with flake.lock.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_NAR_HASH_CONSISTENCY",
+        default_value_t = false
+    )]
+    check_nar_hash_consistency: bool,
+
+    /// Warn about Nixpkgs inputs that follow a stable channel (like `nixos-24.11`) that reaches
+    /// its end of life within --deprecation-window, before it stops being supported.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_WARN_EOL", default_value_t = false)]
+    warn_eol: bool,
+
+    /// How many days (or how long, like `4w` or `P1M`) before a channel's end of life --warn-eol
+    /// starts warning about it.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_DEPRECATION_WINDOW",
+        default_value_t = flake::DEPRECATION_WINDOW,
+        value_parser = duration::parse_day_count::<u32>
+    )]
+    deprecation_window: u32,
+
+    /// Check that every input declared in a flake.nix next to the flake.lock is in the flake.lock.
+    /// The flake.nix is scanned on a best-effort basis rather than evaluated.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_UNLOCKED_INPUTS",
+        default_value_t = false
+    )]
+    check_unlocked_inputs: bool,
+
+    /// Check that the URL that a flake.nix next to the flake.lock declares for each input matches
+    /// the original reference that the flake.lock was locked from. Only URLs given as plain strings
+    /// are compared.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_ORIGINAL_URLS",
+        default_value_t = false
+    )]
+    check_original_urls: bool,
+
+    /// Flag a flake.lock that isn't formatted exactly the way Nix writes it, which means that it
+    /// was edited by hand or reformatted by other tooling.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_ENFORCE_LOCK_FORMAT",
+        default_value_t = false
+    )]
+    enforce_lock_format: bool,
+
+    /// Flag Nixpkgs inputs that are resolved using the flake registry.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_DENY_INDIRECT", default_value_t = false)]
+    deny_indirect: bool,
+
+    /// Flag inputs whose dependencies follow an input that's resolved using the flake registry,
+    /// tracing chains of follows to the node that they end at.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_TRANSITIVE_INDIRECT",
+        default_value_t = false
+    )]
+    check_transitive_indirect: bool,
+
+    /// Flag transitive Nixpkgs inputs that follow a different channel than the flake's own Nixpkgs
+    /// inputs, like a home-manager/nixpkgs on nixos-unstable when nixpkgs is on nixos-24.11.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_REQUIRE_CONSISTENT_TRANSITIVE_NIXPKGS",
+        default_value_t = false
+    )]
+    require_consistent_transitive_nixpkgs: bool,
+
+    /// Flag inputs that are fetched over a transport without TLS, like http:// or git://.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_REQUIRE_HTTPS", default_value_t = false)]
+    require_https: bool,
+
+    /// Flag data-only inputs, like single files and patches, that are locked as flakes rather than
+    /// with `flake = false`.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_DATA_INPUTS",
+        default_value_t = false
+    )]
+    check_data_inputs: bool,
+
+    /// Check that each Nixpkgs input is locked from the Git ref (like `nixos-24.11`) that a
+    /// flake.nix next to the flake.lock declares in its URL, which catches a lock that wasn't
+    /// updated after the flake.nix switched branches. The flake.nix is scanned on a best-effort
+    /// basis rather than evaluated.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_DECLARED_REFS",
+        default_value_t = false
+    )]
+    check_declared_refs: bool,
+
+    /// Flag Nixpkgs inputs that follow a pull request (like `refs/pull/123/head`) or a branch
+    /// named as work in progress (like `wip/openssl` or `fix-openssl`), which are meant to be
+    /// temporary but are easily forgotten.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CHECK_TEMPORARY_REFS",
+        default_value_t = false
+    )]
+    check_temporary_refs: bool,
+
+    /// Apply --check-temporary-refs to every input rather than only to Nixpkgs inputs. This has no
+    /// effect without --check-temporary-refs.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_TEMPORARY_REFS_ALL_INPUTS",
+        default_value_t = false
+    )]
+    temporary_refs_all_inputs: bool,
+
+    /// The maximum number of unique inputs, including transitive inputs, in the flake.lock.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_MAX_INPUTS")]
+    max_inputs: Option<usize>,
+
+    /// How many levels of inputs deep the flake.lock's input graph may go, where the flake's own
+    /// inputs are at depth 1. Deeper graphs are flagged, and --transitive stops walking there.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_MAX_TRANSITIVE_DEPTH")]
+    max_transitive_depth: Option<usize>,
+
+    /// Flag inputs locked to a revision that isn't in this file of approved `owner/repo rev`
+    /// lines.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_APPROVED_REVS_FILE")]
+    approved_revs_file: Option<PathBuf>,
+
+    /// A JSON or TOML file that maps Nixpkgs channels to statuses (rolling, beta, stable,
+    /// deprecated or unmaintained), overriding the built-in statuses for an organization's own
+    /// channel policy.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_CHANNELS_FILE")]
+    channels_file: Option<PathBuf>,
+
+    /// A JSON or TOML file that maps NixOS releases (like 24.11) to their end-of-life dates
+    /// (YYYY-MM-DD) for --warn-eol, overriding or adding to the built-in dates.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_EOL_DATES_FILE")]
+    eol_dates_file: Option<PathBuf>,
+
+    /// A TOML file of input tiers (like `critical` or `experimental`), each with the inputs in it
+    /// and its own maximum age, owner, and allowed refs for them.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_TIERS_FILE")]
+    tiers_file: Option<PathBuf>,
+
+    /// The number of days after which Nixpkgs inputs are considered outdated. This can also be a
+    /// duration like `2w` or an ISO 8601 duration like `P2W`.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_MAX_DAYS",
+        default_value_t = flake::MAX_DAYS,
+        value_parser = duration::parse_day_count::<i64>
+    )]
+    max_days: i64,
+
+    /// The age after which Nixpkgs inputs are considered outdated as a duration like `45d`, `2w`,
+    /// `3mo` (months are 30 days), or `P2W`. This is an alternative to --max-days; either one
+    /// given as a flag takes precedence over the other's environment variable.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_MAX_AGE", value_parser = duration::parse_days)]
+    max_age: Option<i64>,
+
+    /// Don't flag inputs as outdated if they follow a NixOS release (like `nixos-24.11`) that came
+    /// out within this many days (or within a duration like `2w` or `P2W`).
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_GRACE_DAYS",
+        default_value_t = 0,
+        value_parser = duration::parse_day_count::<u32>
+    )]
+    grace_days: u32,
+
+    /// Also check the Nixpkgs inputs of inputs, not just the flake's own inputs.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_TRANSITIVE", default_value_t = false)]
+    transitive: bool,
+
+    /// With --transitive, only fail because of issues with the flake's own inputs and report
+    /// issues with transitive inputs as info.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_FIRST_PARTY_ONLY",
+        default_value_t = true
+    )]
+    first_party_only: bool,
+
+    /// Show issues with transitive inputs in the human-readable summary. Either way, they're part
+    /// of machine-readable reports and count toward the exit code.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_INCLUDE_TRANSITIVE_IN_SUMMARY",
+        default_value_t = false
+    )]
+    include_transitive_in_summary: bool,
+
     /// Ignore a missing flake.lock file.
     #[arg(
         long,
@@ -56,14 +428,105 @@ struct Cli {
     )]
     ignore_missing_flake_lock: bool,
 
-    /// The path to the flake.lock file to check.
+    /// Fail if the flake.lock is missing but there's a flake.nix next to it (an unlocked flake),
+    /// even with --ignore-missing-flake-lock. A missing flake.lock without a flake.nix is skipped.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_TREAT_MISSING_LOCK_AS_ERROR",
+        default_value_t = false
+    )]
+    treat_missing_lock_as_error: bool,
+
+    /// The path to the flake.lock file to check, or a file:// or https:// URL (which requires
+    /// --online).
     #[arg(
         env = "NIX_FLAKE_CHECKER_FLAKE_LOCK_PATH",
         default_value = "flake.lock"
     )]
     flake_lock_path: PathBuf,
 
-    /// Fail with an exit code of 1 if any issues are encountered.
+    /// Check every flake.lock matching a glob pattern relative to the current directory, like
+    /// `*/flake.lock`, instead of a single flake.lock. `*` and `?` match within a path component.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_FLAKE_LOCK_GLOB",
+        conflicts_with_all = ["flake_lock_path", "from_archive_json", "output", "cache_report"]
+    )]
+    flake_lock_glob: Option<String>,
+
+    /// The most lockfiles to read (and fetch, for URLs) at once when checking several, like with
+    /// --flake-lock-glob, which bounds the load on small CI runners and on the servers that
+    /// lockfiles are fetched from. Defaults to the number of CPUs.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_MAX_PARALLELISM")]
+    max_parallelism: Option<NonZeroUsize>,
+
+    /// Instead of checking the flake.lock (or each flake.lock matching --flake-lock-glob), report
+    /// how many of their Nixpkgs inputs follow each channel, as `text` (the default) or `json`.
+    #[arg(
+        long,
+        value_enum,
+        env = "NIX_FLAKE_CHECKER_COMPARE_CHANNELS",
+        num_args = 0..=1,
+        default_missing_value = "text"
+    )]
+    compare_channels: Option<ChannelsFormat>,
+
+    /// Instead of checking the flake.lock, print its resolved input graph (every node with its
+    /// type, owner, repo, and revision, and the edges to its inputs, with `follows` resolved) as
+    /// `json`.
+    #[arg(
+        long,
+        value_enum,
+        env = "NIX_FLAKE_CHECKER_DUMP_GRAPH",
+        num_args = 0..=1,
+        default_missing_value = "json",
+        conflicts_with_all = ["flake_lock_glob", "compare_channels"]
+    )]
+    dump_graph: Option<GraphFormat>,
+
+    /// Check the flake.lock embedded in `nix flake archive --json` output rather than a
+    /// flake.lock file.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_FROM_ARCHIVE_JSON")]
+    from_archive_json: Option<PathBuf>,
+
+    /// Allow fetching the flake.lock from an https:// URL.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_ONLINE", default_value_t = false)]
+    online: bool,
+
+    /// Instead of checking, print which lockfiles would be checked (with their Nixpkgs inputs),
+    /// which checks would run, and which network requests would be made. Remote lockfiles aren't
+    /// fetched.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_DRY_RUN", default_value_t = false)]
+    dry_run: bool,
+
+    /// Render the flake.lock's path relative to the working directory (or --path-base) in
+    /// summaries, annotations, and reports, so that output is the same on every machine. On by
+    /// default in CI (as detected from environment variables like CI).
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_RELATIVE_PATHS",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    relative_paths: Option<bool>,
+
+    /// The directory that paths are rendered relative to with --relative-paths, which defaults to
+    /// the working directory.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_PATH_BASE")]
+    path_base: Option<PathBuf>,
+
+    /// Decompress the flake.lock before parsing it. Files ending in .zst are decompressed as zstd
+    /// even without this.
+    #[arg(long, value_enum, env = "NIX_FLAKE_CHECKER_DECOMPRESS")]
+    decompress: Option<Decompression>,
+
+    /// The encoding of the flake.lock, like `utf-16le`, if it isn't UTF-8. A byte order mark at the
+    /// start of the file determines the encoding even without this.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_LOCKFILE_ENCODING", value_parser = encoding::parse_encoding)]
+    lockfile_encoding: Option<&'static Encoding>,
+
+    /// Fail with an exit code of 1 if any issues are encountered (unless overridden by
+    /// --severity-map).
     #[arg(
         long,
         short,
@@ -72,6 +535,50 @@ struct Cli {
     )]
     fail_mode: bool,
 
+    /// Fail with an exit code of 1 if any issues are warnings, like -Werror. Issue kinds that
+    /// --severity-map classifies as info still don't fail.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_FAIL_ON_WARNINGS",
+        default_value_t = false
+    )]
+    fail_on_warnings: bool,
+
+    /// Never fail because of issues, even with --fail-mode, --fail-on-warnings, or --severity-map,
+    /// while still reporting all of them. Errors are reported as warnings instead.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_WARN_ONLY", default_value_t = false)]
+    warn_only: bool,
+
+    /// Stop checking a lockfile at the first issue that's an error instead of collecting all of
+    /// its issues.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_FAIL_FAST", default_value_t = false)]
+    fail_fast: bool,
+
+    /// Only fail if a flake.lock has more than this many issues that are errors (after
+    /// --severity-map and --fail-on-warnings are applied), which allows for a budget of issues.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_FAIL_THRESHOLD", default_value_t = 0)]
+    fail_threshold: usize,
+
+    /// Only fail because of issues with inputs that are new or locked differently than in the
+    /// flake.lock at this Git revision (e.g. `origin/main`). Issues with other inputs are reported
+    /// as info.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_SINCE_COMMIT",
+        conflicts_with = "from_archive_json"
+    )]
+    since_commit: Option<String>,
+
+    /// Print a Markdown table of the inputs that were added, relocked, or removed since this Git
+    /// ref (e.g. `origin/main`), with their old and new revisions, age changes, and new or cleared
+    /// issues, for use as a pull request comment.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_DIFF_BASE_REF",
+        conflicts_with = "from_archive_json"
+    )]
+    diff_base_ref: Option<String>,
+
     /// Nixpkgs input keys as a comma-separated list.
     #[arg(
         long,
@@ -83,6 +590,37 @@ struct Cli {
     )]
     nixpkgs_keys: Vec<String>,
 
+    /// A file containing Nixpkgs input keys (newline- or comma-separated). These are added to
+    /// any keys supplied via --nixpkgs-keys.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_NIXPKGS_KEYS_FILE")]
+    nixpkgs_keys_file: Option<PathBuf>,
+
+    /// Select the inputs to check using a regular expression that must match the entire input
+    /// name (e.g. `nixpkgs_.*`). Only inputs that look like Nixpkgs are selected. This replaces
+    /// --nixpkgs-keys.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_INPUT_NAME_REGEX",
+        value_parser = input_name_regex,
+        conflicts_with_all = ["KEY_LIST", "nixpkgs_keys_file"]
+    )]
+    input_name_regex: Option<Regex>,
+
+    /// Check only the named input, and only report issues with it and its own inputs. This
+    /// replaces --nixpkgs-keys.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_INPUT",
+        value_name = "NAME",
+        conflicts_with_all = ["KEY_LIST", "nixpkgs_keys_file", "input_name_regex"]
+    )]
+    input: Option<String>,
+
+    /// Reclassify issue kinds as a comma-separated list of <kind>=<severity> pairs, e.g.
+    /// `non-upstream=error,outdated=info`. Only errors cause a nonzero exit code.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_SEVERITY_MAP")]
+    severity_map: Option<SeverityMap>,
+
     /// Display Markdown summary (in GitHub Actions).
     #[arg(
         long,
@@ -92,166 +630,1565 @@ struct Cli {
     )]
     markdown_summary: bool,
 
-    /// The Common Expression Language (CEL) policy to apply to each Nixpkgs input.
+    /// Collapse each issue section of the Markdown summary.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_MARKDOWN_COLLAPSIBLE",
+        default_value_t = false
+    )]
+    markdown_collapsible: bool,
+
+    /// The heading of the summary, instead of the default one, which tells summaries apart when
+    /// several flakes are checked in the same workflow (e.g. `Flake Checker: backend/flake.lock`).
+    #[arg(long, env = "NIX_FLAKE_CHECKER_SUMMARY_TITLE")]
+    summary_title: Option<String>,
+
+    /// List outdated inputs in the summary once per channel that they follow, rather than one by
+    /// one.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_GROUP_OUTDATED",
+        default_value_t = false
+    )]
+    group_outdated: bool,
+
+    /// The language of the summary.
+    #[arg(long, value_enum, env = "NIX_FLAKE_CHECKER_LANG", default_value_t = Lang::En)]
+    lang: Lang,
+
+    /// When to color the console output. With `auto`, output is colored if it's a terminal, unless
+    /// the NO_COLOR, CLICOLOR_FORCE, or CLICOLOR environment variables say otherwise.
+    #[arg(long, value_enum, env = "NIX_FLAKE_CHECKER_COLOR", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// The Common Expression Language (CEL) policy to apply to each Nixpkgs input (or, with
+    /// --condition-scope all, to each input).
     #[arg(long, short, env = "NIX_FLAKE_CHECKER_CONDITION")]
     condition: Option<String>,
+
+    /// The format of --condition: a CEL condition, or the path of a JSON or YAML policy file with
+    /// `max_days`, `allowed_refs`, and `required_owner` fields, which is compiled to CEL.
+    #[arg(
+        long,
+        value_enum,
+        env = "NIX_FLAKE_CHECKER_CONDITION_FORMAT",
+        default_value_t = ConditionFormat::Cel
+    )]
+    condition_format: ConditionFormat,
+
+    /// A directory of `.cel` files, each of which is a CEL policy rule named after its file (e.g.
+    /// `recent.cel` is the `recent` rule). Each rule is applied like --condition, and inputs that
+    /// violate a rule are reported along with the rule's name.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_CONDITION_DIR",
+        conflicts_with = "condition"
+    )]
+    condition_dir: Option<PathBuf>,
+
+    /// The inputs that --condition applies to: the selected Nixpkgs inputs or every input.
+    #[arg(
+        long,
+        value_enum,
+        env = "NIX_FLAKE_CHECKER_CONDITION_SCOPE",
+        default_value_t = ConditionScope::Nixpkgs
+    )]
+    condition_scope: ConditionScope,
+
+    /// The format in which to report results. Machine-readable formats are written to --output
+    /// (or to stdout in place of the human-readable summary). Repeat this (or supply a
+    /// comma-separated list) to combine the text format with a machine-readable one.
+    #[arg(
+        long,
+        alias = "format",
+        value_enum,
+        env = "NIX_FLAKE_CHECKER_OUTPUT_FORMAT",
+        value_delimiter = ',',
+        default_values_t = [OutputFormat::Text]
+    )]
+    output_format: Vec<OutputFormat>,
+
+    /// Pretty-print JSON output (the default).
+    #[arg(long, conflicts_with = "compact")]
+    pretty: bool,
+
+    /// Write JSON output on a single line without whitespace.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_COMPACT")]
+    compact: bool,
+
+    /// The file to write machine-readable output to.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_OUTPUT")]
+    output: Option<PathBuf>,
+
+    /// A directory to write one machine-readable report per flake.lock to, at the flake.lock's
+    /// path with the format's extension appended (e.g. `a/flake.lock.json`). This is useful with
+    /// --flake-lock-glob.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_OUTPUT_DIR", conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
+
+    /// A file in which to cache the check results. If neither the flake.lock's contents nor the
+    /// settings have changed since a cached run earlier the same day, the cached results are
+    /// reported instead of re-running the checks.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_CACHE_REPORT")]
+    cache_report: Option<PathBuf>,
+
+    /// A directory in which to keep the age of each flake.lock's oldest input between runs, so
+    /// that the summary tells whether it improved or regressed since the last run.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_BASELINE_AGE")]
+    baseline_age: Option<PathBuf>,
+
+    /// Write a pins file that accepts every current issue to this path, to be trimmed and placed
+    /// next to the flake.lock as flake-checker.pins.toml.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_GENERATE_SUPPRESSIONS",
+        conflicts_with = "flake_lock_glob"
+    )]
+    generate_suppressions: Option<PathBuf>,
+
+    /// Write issue counts as job outputs to the file named by the GITHUB_OUTPUT environment variable.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_GITHUB_OUTPUT", default_value_t = false)]
+    github_output: bool,
+
+    /// Replace owners, registry IDs, and URLs in reported issues with stable hashes so that the
+    /// report can be shared without revealing private names.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_REDACT", default_value_t = false)]
+    redact: bool,
+
+    /// Don't print the one-line verdict (like `flake-checker: 2 issues (2 outdated) in
+    /// flake.lock`) to stderr after checking each flake.lock.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_QUIET", default_value_t = false)]
+    quiet: bool,
+
+    /// Print nothing at all (not even the summary or the verdict) for a flake.lock without issues,
+    /// so that output only appears when there's something to look into. Files like the --output
+    /// file and the GitHub Actions step summary are still written.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_QUIET_CLEAN", default_value_t = false)]
+    quiet_clean: bool,
+
+    /// Print one line per issue to stderr and leave the issues out of the text summary, which
+    /// stays on stdout along with the verdict, so that `2>failures.log` collects just the
+    /// failures. This doesn't apply in GitHub Actions.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_ONLY_FAILURES_TO_STDERR",
+        default_value_t = false
+    )]
+    only_failures_to_stderr: bool,
+
+    /// List the checked inputs that had no issues, along with the checks that they passed, in the
+    /// report and the summary.
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_REPORT_CLEAN_INPUTS",
+        default_value_t = false
+    )]
+    report_clean_inputs: bool,
+
+    /// Explain the result for each checked input: whether it passed each of the checks that ran on
+    /// it and, if it didn't, why.
+    #[arg(long, env = "NIX_FLAKE_CHECKER_EXPLAIN", default_value_t = false)]
+    explain: bool,
+
+    /// The format in which --explain prints its breakdown. As JSON, the breakdown is printed in
+    /// place of the summary (pretty-printed unless --compact is set).
+    #[arg(
+        long,
+        env = "NIX_FLAKE_CHECKER_EXPLAIN_FORMAT",
+        value_enum,
+        default_value_t = ExplainFormat::Text
+    )]
+    explain_format: ExplainFormat,
+
+    /// The proxy to use for fetching ref statuses and sending telemetry. Without it, the
+    /// HTTPS_PROXY, HTTP_PROXY, and NO_PROXY environment variables are honored.
+    #[arg(long, global = true, env = "NIX_FLAKE_CHECKER_PROXY")]
+    proxy: Option<String>,
+
+    /// The number of seconds to wait for the ref statuses to be fetched.
+    #[arg(
+        long,
+        global = true,
+        env = "NIX_FLAKE_CHECKER_REF_TIMEOUT",
+        default_value_t = 10
+    )]
+    ref_timeout: u64,
+
+    /// The URL of the Prometheus-compatible server to fetch ref statuses from.
+    #[arg(
+        long,
+        global = true,
+        env = "NIX_FLAKE_CHECKER_REF_STATUS_URL",
+        default_value = ref_statuses::REF_STATUS_URL
+    )]
+    ref_status_url: String,
+
+    /// The PromQL query that yields the ref statuses, as results with `channel` and `status`
+    /// labels.
+    #[arg(
+        long,
+        global = true,
+        env = "NIX_FLAKE_CHECKER_REF_STATUS_QUERY",
+        default_value = ref_statuses::REF_STATUS_QUERY
+    )]
+    ref_status_query: String,
+
+    /// The `Authorization` header to send when fetching ref statuses, e.g. `Bearer <token>`, for
+    /// servers that require authentication.
+    #[arg(
+        long,
+        global = true,
+        env = "NIX_FLAKE_CHECKER_REF_STATUS_AUTH",
+        hide_env_values = true
+    )]
+    ref_status_auth: Option<String>,
 }
 
-#[cfg(not(feature = "ref-statuses"))]
-pub(crate) fn supported_refs(ref_statuses: HashMap<String, String>) -> Vec<String> {
-    let mut return_value: Vec<String> = ref_statuses
+#[derive(Subcommand)]
+enum Command {
+    /// Manage the Nixpkgs ref statuses that determine which Git refs are supported.
+    Refs {
+        #[command(subcommand)]
+        command: RefsCommand,
+    },
+    /// Explain an issue kind: what's checked, why it matters, and how to fix it.
+    Explain {
+        /// The issue kind, e.g. `disallowed`.
+        #[arg(value_parser = PossibleValuesParser::new(ISSUE_KINDS.iter().copied()))]
+        kind: String,
+    },
+    /// Check a directory of flake.lock snapshots and report their issue counts over time.
+    Trend {
+        /// The directory of snapshots. Every `.lock` file in it is checked, in order of file name
+        /// (e.g. `2024-06-01.lock`).
+        dir: PathBuf,
+        /// The format of the trend report.
+        #[arg(long, value_enum, default_value_t = TrendFormat::Csv)]
+        format: TrendFormat,
+    },
+    /// Write a starter flake-checker.toml config file to the current directory and print a CI
+    /// snippet that runs Flake Checker with it.
+    Init {
+        /// Overwrite an existing flake-checker.toml.
+        #[arg(long, default_value_t = false)]
+        force: bool,
+        /// The CI system to print a snippet for.
+        #[arg(long, value_enum, default_value_t = CiProvider::Github)]
+        ci: CiProvider,
+    },
+    /// Print a shell completion script, e.g. `flake-checker completions zsh > _flake-checker`.
+    Completions {
+        /// The shell to generate completions for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Validate the settings and every config file that they name, like channels, tiers, pins, and
+    /// policy files, without checking any flake.lock. Exits with 2 if there are problems.
+    ValidateConfig,
+    /// Print the version of Flake Checker.
+    Version {
+        /// Print the name, version, and enabled compile-time features as JSON, for wrapper
+        /// scripts that check compatibility.
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+/// The optional Cargo features, and whether each was enabled at compile time.
+const FEATURES: &[(&str, bool)] = &[
+    ("protobuf", cfg!(feature = "protobuf")),
+    ("zstd", cfg!(feature = "zstd")),
+];
+
+// The name, version, and enabled features of this build.
+fn version_info() -> serde_json::Value {
+    let features: Vec<&str> = FEATURES
         .iter()
-        .filter_map(|(channel, status)| {
-            if *status != "unmaintained" && *status != "beta" {
-                Some(channel.clone())
-            } else {
-                None
-            }
-        })
+        .filter(|(_, enabled)| *enabled)
+        .map(|(feature, _)| *feature)
         .collect();
-    return_value.sort();
-    return_value
+    serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "features": features,
+    })
+}
+
+// Write the completion script for `shell` to `out`.
+fn write_completions(shell: Shell, out: &mut dyn std::io::Write) {
+    clap_complete::generate(shell, &mut Cli::command(), "flake-checker", out);
 }
 
-#[cfg(not(feature = "ref-statuses"))]
-fn main() -> Result<ExitCode, FlakeCheckerError> {
-    let ref_statuses: HashMap<String, String> =
-        serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+// Whether a missing flake.lock fails the run rather than being skipped. With
+// --treat-missing-lock-as-error, that depends on whether there's a flake.nix next to it: a flake.nix
+// without a flake.lock is an unlocked flake, while a directory without either has no flake at all.
+fn missing_lock_fails(
+    flake_lock_path: &Path,
+    ignore_missing_flake_lock: bool,
+    treat_missing_lock_as_error: bool,
+) -> bool {
+    if treat_missing_lock_as_error {
+        flake_lock_path.with_file_name("flake.nix").exists()
+    } else {
+        !ignore_missing_flake_lock
+    }
+}
 
+// The contents of the flake.lock at `flake_lock_path`, decompressed and decoded if need be. With
+// `from_archive_json`, the file is `nix flake archive --json` output and the lock is extracted.
+fn read_lock(
+    flake_lock_path: &Path,
+    client: Option<&Client>,
+    online: bool,
+    decompress: Option<Decompression>,
+    encoding: Option<&'static Encoding>,
+    from_archive_json: bool,
+) -> Result<String, FlakeCheckerError> {
+    let decompression = decompress.or_else(|| Decompression::detect(flake_lock_path));
+    let lock_contents =
+        LockSource::parse(flake_lock_path)?.read(client, online, decompression, encoding)?;
+    if from_archive_json {
+        lock_from_archive_json(&lock_contents)
+    } else {
+        Ok(lock_contents)
+    }
+}
+
+// The settings from NIX_FLAKE_CHECKER_SETTINGS (or, without it, from a flake-checker.toml in the
+// current directory, using the `profile`) along with where they're from, if there are any.
+fn load_settings(profile: Option<&str>) -> Result<Option<(String, String)>, FlakeCheckerError> {
+    match std::env::var(settings::SETTINGS_ENV_VAR) {
+        Ok(_) if profile.is_some() => Err(FlakeCheckerError::Settings(format!(
+            "--config-profile selects a profile in {}, which isn't read when {} is set",
+            settings::CONFIG_FILE_NAME,
+            settings::SETTINGS_ENV_VAR
+        ))),
+        Ok(settings) => Ok(Some((settings, settings_origin(profile)))),
+        Err(_) => Ok(settings::config_file_settings(
+            Path::new(settings::CONFIG_FILE_NAME),
+            profile,
+        )?
+        .map(|settings| (settings, settings_origin(profile)))),
+    }
+}
+
+// Where the settings that load_settings loads are from.
+fn settings_origin(profile: Option<&str>) -> String {
+    match (std::env::var_os(settings::SETTINGS_ENV_VAR), profile) {
+        (Some(_), _) => String::from(settings::SETTINGS_ENV_VAR),
+        (None, Some(profile)) => format!("{} (profile {profile:?})", settings::CONFIG_FILE_NAME),
+        (None, None) => String::from(settings::CONFIG_FILE_NAME),
+    }
+}
+
+// Parse the command line, with any settings from NIX_FLAKE_CHECKER_SETTINGS (or, without it, from
+// a flake-checker.toml in the current directory, using the --config-profile) as defaults.
+fn parse_cli() -> Result<Cli, FlakeCheckerError> {
+    // --help, --version, and `init` are handled before the settings are loaded, so that they work
+    // even with invalid settings (which `init --force` replaces)
+    let validate_config = match Cli::command().try_get_matches_from(std::env::args_os()) {
+        Ok(matches) if matches.subcommand_name() == Some("init") => {
+            return Ok(Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()));
+        }
+        Err(e) if matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => {
+            e.exit();
+        }
+        Ok(matches) => matches.subcommand_name() == Some("validate-config"),
+        Err(_) => false,
+    };
+
+    let profile = settings::config_profile(std::env::args_os());
+    if validate_config {
+        // `validate-config` reports the problems with the settings in its own output, so settings
+        // that can't be loaded or applied are left out here
+        let matches = load_settings(profile.as_deref())
+            .ok()
+            .flatten()
+            .and_then(|(settings, _)| settings::command(Some(&settings)).ok())
+            .and_then(|command| command.try_get_matches().ok())
+            .unwrap_or_else(|| Cli::command().get_matches());
+        let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        return Ok(resolve_max_age(cli, &matches).unwrap_or_else(|e| e.exit()));
+    }
+    let settings = load_settings(profile.as_deref())?.map(|(settings, _)| settings);
+    let matches = settings::command(settings.as_deref())?.get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    Ok(resolve_max_age(cli, &matches).unwrap_or_else(|e| e.exit()))
+}
+
+// --max-days and --max-age are alternatives, so supplying both as flags (or both as environment
+// variables) is an error. A flag takes precedence over the other one's environment variable,
+// though, so that a flag can override an environment variable that's set for CI, say. Settings
+// are defaults and thus never conflict with either.
+fn resolve_max_age(cli: Cli, matches: &ArgMatches) -> Result<Cli, clap::Error> {
+    let source = |id| match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => Some(ValueSource::CommandLine),
+        Some(ValueSource::EnvVariable) => Some(ValueSource::EnvVariable),
+        _ => None,
+    };
+
+    match (source("max_days"), source("max_age")) {
+        (Some(max_days), Some(max_age)) if max_days == max_age => Err(Cli::command().error(
+            ErrorKind::ArgumentConflict,
+            "--max-days and --max-age can't both be supplied",
+        )),
+        (Some(ValueSource::CommandLine), Some(ValueSource::EnvVariable)) => Ok(Cli {
+            max_age: None,
+            ..cli
+        }),
+        _ => Ok(cli),
+    }
+}
+
+fn main() -> ExitCode {
+    // Diagnostics like failures to send telemetry are only logged, to stderr, when enabled using
+    // NIX_FLAKE_CHECKER_LOG, e.g. NIX_FLAKE_CHECKER_LOG=debug
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_env("NIX_FLAKE_CHECKER_LOG"))
+        .with_writer(std::io::stderr)
+        .init();
+
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<ExitCode, FlakeCheckerError> {
     let Cli {
+        command,
         no_telemetry,
+        strict_telemetry_off,
+        list_checks,
+        config_profile,
         check_outdated,
         check_owner,
+        strict_nixpkgs_owner,
+        upstream_owner,
+        upstream_repo,
+        input_rename,
         check_supported,
+        strict_supported,
+        ci_strict,
+        accept_small_channels,
+        deny_ref_pattern,
+        check_hash_algo,
+        require_hashes,
+        check_locked_revs,
+        check_nar_hash_consistency,
+        warn_eol,
+        deprecation_window,
+        check_unlocked_inputs,
+        check_original_urls,
+        enforce_lock_format,
+        deny_indirect,
+        check_transitive_indirect,
+        require_consistent_transitive_nixpkgs,
+        require_https,
+        check_data_inputs,
+        check_declared_refs,
+        check_temporary_refs,
+        temporary_refs_all_inputs,
+        max_inputs,
+        max_transitive_depth,
+        approved_revs_file,
+        channels_file,
+        eol_dates_file,
+        tiers_file,
+        max_days,
+        max_age,
+        grace_days,
+        transitive,
+        first_party_only,
+        include_transitive_in_summary,
         ignore_missing_flake_lock,
+        treat_missing_lock_as_error,
         flake_lock_path,
+        flake_lock_glob,
+        max_parallelism,
+        compare_channels,
+        dump_graph,
+        from_archive_json,
+        online,
+        dry_run,
+        relative_paths,
+        path_base,
+        decompress,
+        lockfile_encoding,
         fail_mode,
-        nixpkgs_keys,
+        fail_on_warnings,
+        warn_only,
+        fail_fast,
+        fail_threshold,
+        since_commit,
+        diff_base_ref,
+        mut nixpkgs_keys,
+        nixpkgs_keys_file,
+        input_name_regex,
+        input,
+        severity_map,
         markdown_summary,
+        markdown_collapsible,
+        summary_title,
+        group_outdated,
+        lang,
+        color,
         condition,
-    } = Cli::parse();
+        condition_format,
+        condition_dir,
+        condition_scope,
+        output_format,
+        pretty: _,
+        compact,
+        output,
+        output_dir,
+        cache_report,
+        baseline_age,
+        generate_suppressions,
+        github_output,
+        redact,
+        quiet,
+        quiet_clean,
+        only_failures_to_stderr,
+        report_clean_inputs,
+        explain,
+        explain_format,
+        proxy,
+        ref_timeout,
+        ref_status_url,
+        ref_status_query,
+        ref_status_auth,
+    } = parse_cli()?;
+
+    if list_checks {
+        print!("{}", checks::list_checks());
+        return Ok(ExitCode::SUCCESS);
+    }
 
-    if !flake_lock_path.exists() {
-        if ignore_missing_flake_lock {
-            println!("no flake lockfile found at {:?}; ignoring", flake_lock_path);
-            return Ok(ExitCode::SUCCESS);
+    if let Some(Command::Init { force, ci }) = &command {
+        let snippet = init::init(Path::new("."), *force, *ci)?;
+        println!(
+            "Wrote {}. Add this to your CI:\n",
+            settings::CONFIG_FILE_NAME
+        );
+        print!("{snippet}");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Completions { shell }) = &command {
+        write_completions(*shell, &mut std::io::stdout());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Version { json }) = &command {
+        if *json {
+            println!("{}", version_info());
         } else {
-            println!("no flake lockfile found at {:?}", flake_lock_path);
-            return Ok(ExitCode::FAILURE);
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::ValidateConfig) = &command {
+        // Settings that can't be loaded are reported like any other problem
+        let (settings, settings_problem) = match load_settings(config_profile.as_deref()) {
+            Ok(settings) => (settings, None),
+            Err(e) => (None, Some(e)),
+        };
+        let mut validations: Vec<validate::Validation> = settings_problem
+            .map(|problem| validate::Validation {
+                source: format!(
+                    "settings from {}",
+                    settings_origin(config_profile.as_deref())
+                ),
+                problem: Some(problem),
+            })
+            .into_iter()
+            .collect();
+        validations.extend(validate::validate(&validate::Sources {
+            settings: settings
+                .as_ref()
+                .map(|(settings, origin)| (settings.as_str(), origin.as_str())),
+            channels_file: channels_file.as_deref(),
+            eol_dates_file: eol_dates_file.as_deref(),
+            tiers_file: tiers_file.as_deref(),
+            approved_revs_file: approved_revs_file.as_deref(),
+            nixpkgs_keys_file: nixpkgs_keys_file.as_deref(),
+            condition: condition
+                .as_deref()
+                .map(|condition| (condition, condition_format)),
+            condition_dir: condition_dir.as_deref(),
+            // With --flake-lock-glob, there's no single flake.lock whose pins file to validate
+            flake_lock_path: flake_lock_glob
+                .is_none()
+                .then_some(flake_lock_path.as_path()),
+        }));
+        print!("{}", validate::render(&validations));
+        return Ok(
+            if validations
+                .iter()
+                .any(|validation| validation.problem.is_some())
+            {
+                ExitCode::from(2)
+            } else {
+                ExitCode::SUCCESS
+            },
+        );
+    }
+
+    if let Some(Command::Explain { kind }) = &command {
+        if let Some(explanation) = explain::explanation(kind, max_age.unwrap_or(max_days)) {
+            println!("{explanation}");
+        }
+        for check in checks::CHECKS
+            .iter()
+            .filter(|check| check.issue_kinds.contains(&kind.as_str()))
+        {
+            println!(
+                "\nReported by the {} check, controlled by {} (or {}).",
+                check.name, check.flag, check.env
+            );
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Trend { dir, format }) = &command {
+        let entries = trend::trend(dir, &resolve_allowed_refs(RefStatusSource::Embedded)?)?;
+        print!("{}", trend::render(&entries, *format)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let client = http::optional_client(proxy.as_deref(), strict_telemetry_off)?;
+
+    if let Some(Command::Refs { command }) = command {
+        let Some(client) = client else {
+            return Err(FlakeCheckerError::StrictTelemetryOff(String::from(
+                "fetching ref statuses",
+            )));
+        };
+        let eol_fetcher = EndOfLifeFetcher::new(
+            client.clone(),
+            EOL_DATES_URL,
+            Duration::from_secs(ref_timeout),
+        );
+        let mut fetcher = PrometheusFetcher::new(
+            client,
+            &ref_status_url,
+            &ref_status_query,
+            Duration::from_secs(ref_timeout),
+        );
+        if let Some(auth) = &ref_status_auth {
+            fetcher = fetcher.with_auth(auth)?;
+        }
+        let ref_statuses = RefStatusSource::Embedded.ref_statuses()?;
+        return ref_statuses::run(command, ref_statuses, &fetcher, &eol_fetcher);
+    }
+
+    // An organization's channel policy takes precedence over the built-in ref statuses
+    let ref_status_source = match &channels_file {
+        Some(channels_file) => {
+            RefStatusSource::WithChannelsFile(&RefStatusSource::Embedded, channels_file)
         }
+        None => RefStatusSource::Embedded,
+    };
+    let ref_statuses = ref_status_source.ref_statuses()?;
+
+    // A report in --output-dir is written to a file just like --output
+    let output_plan = output::plan(&output_format, output.as_deref().or(output_dir.as_deref()))?;
+    if output_dir.is_some() && output_plan.machine.is_none() {
+        return Err(FlakeCheckerError::OutputFormat(String::from(
+            "--output-dir requires a machine-readable format",
+        )));
+    }
+    // NDJSON reports are appended as lockfiles are checked, so the run starts with an empty file
+    if let (Some(OutputFormat::NdjsonReport), Some(output)) = (output_plan.machine, &output) {
+        std::fs::write(output, "")?;
     }
 
-    let flake_lock = FlakeLock::new(&flake_lock_path)?;
+    // Keys from the file are only added once, even if the file or --nixpkgs-keys already lists them
+    if let Some(nixpkgs_keys_file) = nixpkgs_keys_file {
+        for key in read_nixpkgs_keys_file(&nixpkgs_keys_file)? {
+            if !nixpkgs_keys.contains(&key) {
+                nixpkgs_keys.push(key);
+            }
+        }
+    }
+    if let Some(input) = &input {
+        nixpkgs_keys = vec![input.clone()];
+    }
+
+    let flake_lock_paths = if let Some(pattern) = &flake_lock_glob {
+        let paths = glob::expand(pattern)?;
+        if paths.is_empty() {
+            if ignore_missing_flake_lock {
+                println!("no flake lockfiles match {:?}; ignoring", pattern);
+                return Ok(ExitCode::SUCCESS);
+            } else {
+                return Err(FlakeCheckerError::NoMatchingFlakeLocks(pattern.clone()));
+            }
+        }
+        paths
+    } else {
+        // A file:// URL is checked, and reported, as the path that it points to
+        let (flake_lock_path, remote) = match &from_archive_json {
+            Some(path) => (path.clone(), false),
+            None => match LockSource::parse(&flake_lock_path)? {
+                LockSource::File(path) => (path, false),
+                LockSource::Https(_) => (flake_lock_path, true),
+            },
+        };
+
+        if from_archive_json.is_none() && !remote && !flake_lock_path.exists() {
+            if missing_lock_fails(
+                &flake_lock_path,
+                ignore_missing_flake_lock,
+                treat_missing_lock_as_error,
+            ) {
+                return Err(FlakeCheckerError::MissingFlakeLock(flake_lock_path));
+            } else {
+                println!("no flake lockfile found at {:?}; ignoring", flake_lock_path);
+                return Ok(ExitCode::SUCCESS);
+            }
+        }
+
+        vec![flake_lock_path]
+    };
 
     let flake_check_config = FlakeCheckConfig {
         check_supported,
+        strict_supported,
         check_outdated,
         check_owner,
+        strict_nixpkgs_owner,
+        check_hash_algo,
+        require_hashes,
+        check_locked_revs,
+        check_nar_hash_consistency,
+        warn_eol,
+        deprecation_window,
+        check_unlocked_inputs,
+        check_original_urls,
+        enforce_lock_format,
+        deny_indirect,
+        check_transitive_indirect,
+        require_consistent_transitive_nixpkgs,
+        require_https,
+        check_data_inputs,
+        check_declared_refs,
+        check_temporary_refs,
+        temporary_refs_all_inputs,
+        accept_small_channels,
+        max_inputs,
+        max_transitive_depth,
+        max_days: max_age.unwrap_or(max_days),
+        grace_days,
+        transitive,
+        first_party_only,
+        include_transitive_in_summary,
         nixpkgs_keys: nixpkgs_keys.clone(),
+        upstream_owner,
+        upstream_repo,
+        input_name_regex,
+        deny_ref_patterns: deny_ref_pattern,
+        approved_revs: approved_revs_file
+            .as_deref()
+            .map(read_approved_revs_file)
+            .transpose()?,
         fail_mode,
+        fail_on_warnings,
+        warn_only,
+        fail_fast,
+        fail_threshold,
+        severity_map: severity_map.unwrap_or_default(),
+        pins: Pins::default(),
+        changed_inputs: None,
+        ref_statuses: ref_statuses.clone(),
+        eol_dates: ref_statuses::eol_dates(eol_dates_file.as_deref())?,
+        repo_renames: input_rename,
+        condition_scope,
+        tiers: tiers_file
+            .as_deref()
+            .map(Tiers::read)
+            .transpose()?
+            .unwrap_or_default(),
+    };
+    let flake_check_config = if ci_strict {
+        flake_check_config.with_ci_strict(is_ci::cached())
+    } else {
+        flake_check_config
     };
 
-    let allowed_refs = supported_refs(ref_statuses.clone());
+    if dry_run {
+        let plan = dry_run::Plan::new(
+            &flake_lock_paths,
+            &flake_check_config,
+            |flake_lock_path| {
+                read_lock(
+                    flake_lock_path,
+                    None,
+                    false,
+                    decompress,
+                    lockfile_encoding,
+                    from_archive_json.is_some(),
+                )
+            },
+            online,
+            client.is_some() && !no_telemetry,
+        )?;
+        print!("{}", plan.render());
+        return Ok(ExitCode::SUCCESS);
+    }
 
-    let issues = if let Some(condition) = &condition {
-        evaluate_condition(
-            &flake_lock,
-            &nixpkgs_keys,
-            condition,
-            ref_statuses,
-            allowed_refs.clone(),
-        )?
+    if let Some(format) = compare_channels {
+        let mut distribution = ChannelDistribution::default();
+        for flake_lock_path in &flake_lock_paths {
+            let lock_contents = read_lock(
+                flake_lock_path,
+                client.as_ref(),
+                online,
+                decompress,
+                lockfile_encoding,
+                from_archive_json.is_some(),
+            )?;
+            distribution.add(&lock_contents.parse()?, &flake_check_config)?;
+        }
+        print!("{}", distribution.render(format)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(format) = dump_graph {
+        for flake_lock_path in &flake_lock_paths {
+            let lock_contents = read_lock(
+                flake_lock_path,
+                client.as_ref(),
+                online,
+                decompress,
+                lockfile_encoding,
+                from_archive_json.is_some(),
+            )?;
+            print!("{}", Graph::new(&lock_contents.parse()?)?.render(format)?);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let allowed_refs = resolve_allowed_refs(ref_status_source)?;
+    let color = color::use_color(
+        color,
+        |name| std::env::var(name).ok(),
+        std::io::stdout().is_terminal(),
+    );
+
+    // A structured policy is checked as the CEL condition that it compiles to
+    let condition = match condition_format {
+        ConditionFormat::Cel => condition,
+        format => condition
+            .map(|path| condition::read_policy(Path::new(&path), format))
+            .transpose()?,
+    };
+
+    // A directory of rules is checked rule by rule but reported as their conjunction
+    let condition_rules = condition_dir
+        .as_deref()
+        .map(condition::read_condition_dir)
+        .transpose()?;
+    let condition = match &condition_rules {
+        Some(rules) => Some(
+            rules
+                .iter()
+                .map(|(_, rule)| format!("({})", rule.trim()))
+                .collect::<Vec<_>>()
+                .join(" && "),
+        ),
+        None => condition,
+    };
+
+    let path_base = if relative_paths.unwrap_or_else(is_ci::cached) {
+        let cwd = std::env::current_dir()?;
+        Some((path_base.unwrap_or_else(|| cwd.clone()), cwd))
     } else {
-        check_flake_lock(&flake_lock, &flake_check_config, allowed_refs.clone())?
+        None
     };
 
-    if !no_telemetry {
-        telemetry::TelemetryReport::make_and_send(&issues);
-    }
+    let mut has_errors = false;
+    // With --flake-lock-glob, telemetry and GitHub job outputs cover every flake.lock at once
+    let mut all_issues = vec![];
+    let mut all_num_inputs = 0;
+    // With --flake-lock-glob, the summaries are merged into one once every flake.lock is checked
+    let mut markdown_summaries = vec![];
+    let mut text_summaries = vec![];
 
-    let summary = Summary::new(
-        &issues,
-        flake_lock_path,
-        flake_check_config,
-        allowed_refs,
-        condition,
+    // The lockfiles are read in the background, in parallel, while earlier ones are checked, but
+    // their errors only surface in turn
+    let lock_contents = pool::map_bounded(
+        flake_lock_paths.clone(),
+        max_parallelism.unwrap_or_else(pool::default_parallelism),
+        {
+            let client = client.clone();
+            let from_archive_json = from_archive_json.is_some();
+            move |flake_lock_path| {
+                read_lock(
+                    flake_lock_path,
+                    client.as_ref(),
+                    online,
+                    decompress,
+                    lockfile_encoding,
+                    from_archive_json,
+                )
+            }
+        },
     );
 
-    if std::env::var("GITHUB_ACTIONS").is_ok() {
-        if markdown_summary {
-            summary.generate_markdown()?;
+    for (flake_lock_path, lock_contents) in flake_lock_paths.into_iter().zip(lock_contents) {
+        // The path as shown in output; URLs are shown as given
+        let display_path = match (&path_base, LockSource::parse(&flake_lock_path)) {
+            (Some((base, cwd)), Ok(LockSource::File(path))) => {
+                output::relative_path(&path, base, cwd)
+            }
+            _ => flake_lock_path.clone(),
+        };
+
+        // With --quiet-clean, this waits until it's known whether the flake.lock has issues
+        if flake_lock_glob.is_some() && !quiet_clean {
+            println!("Checking {}", display_path.display());
+        }
+
+        let lock_contents = lock_contents?;
+
+        let flake_check_config = if let Some(rev) = &since_commit {
+            match since_commit::changed_inputs(&flake_lock_path, &lock_contents, rev) {
+                Ok(changed_inputs) => FlakeCheckConfig {
+                    changed_inputs,
+                    ..flake_check_config.clone()
+                },
+                Err(FlakeCheckerError::NotGitRepo(dir)) => {
+                    eprintln!("warning: {dir} isn't in a Git repository, so --since-commit is ignored and all inputs are checked");
+                    flake_check_config.clone()
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            flake_check_config.clone()
+        };
+        // Pins live next to each flake.lock
+        let flake_check_config = FlakeCheckConfig {
+            pins: Pins::for_flake_lock(&flake_lock_path)?,
+            ..flake_check_config
+        };
+
+        let check = || -> Result<CheckReport, FlakeCheckerError> {
+            let (flake_lock, unparsable_nodes) = FlakeLock::from_str_lenient(&lock_contents)?;
+            let num_inputs = nixpkgs_deps(
+                &flake_lock,
+                &nixpkgs_keys,
+                flake_check_config.input_name_regex.as_ref(),
+                flake_check_config.transitive,
+                flake_check_config.max_transitive_depth,
+            )?
+            .len();
+
+            // Conditions are evaluated against every input, so only the checks record what ran
+            let (mut issues, checks_run) = if let Some(rules) = &condition_rules {
+                let issues = evaluate_rules(
+                    &flake_lock,
+                    &flake_check_config,
+                    rules,
+                    ref_statuses.clone(),
+                    allowed_refs.clone(),
+                )?;
+                (issues, ChecksRun::default())
+            } else if let Some(condition) = &condition {
+                let issues = evaluate_condition(
+                    &flake_lock,
+                    &flake_check_config,
+                    condition,
+                    ref_statuses.clone(),
+                    allowed_refs.clone(),
+                )?;
+                (issues, ChecksRun::default())
+            } else {
+                check_flake_lock_with_checks_run(
+                    &flake_lock,
+                    &flake_check_config,
+                    allowed_refs.clone(),
+                )?
+            };
+
+            // Nodes that can't be parsed are left out of the check rather than failing it
+            issues.extend(unparsable_nodes.into_iter().map(|(key, e)| Issue {
+                input: key,
+                kind: IssueKind::UnparsableNode(UnparsableNode {
+                    error: e.to_string(),
+                }),
+                location: None,
+            }));
+
+            // A lock extracted from archive JSON has no formatting of its own to check
+            if flake_check_config.enforce_lock_format && from_archive_json.is_none() {
+                issues.extend(lock_format_drift(&lock_contents)?);
+            }
+
+            Ok(CheckReport {
+                matched_refs: matched_refs(&flake_lock, &flake_check_config, &allowed_refs)?,
+                input_ages: input_ages(&flake_lock, &flake_check_config)?,
+                checks_run,
+                ..CheckReport::new(
+                    lock_contents.as_bytes(),
+                    issues,
+                    num_inputs,
+                    total_inputs(&flake_lock),
+                )
+            })
+        };
+
+        let mut report = if let Some(cache_report) = &cache_report {
+            // Everything besides the flake.lock that the results depend on
+            let settings = format!(
+                "{:?}",
+                (
+                    flake_check_config.fingerprint(),
+                    &condition,
+                    &condition_rules,
+                    &allowed_refs,
+                    from_archive_json.is_some(),
+                )
+            );
+            report::cached_or_check(cache_report, lock_contents.as_bytes(), &settings, check)?
+        } else {
+            check()?
+        };
+
+        // The flake.nix isn't part of the cached report because it can change without the lock
+        if (flake_check_config.check_unlocked_inputs
+            || flake_check_config.check_original_urls
+            || flake_check_config.check_declared_refs)
+            && condition.is_none()
+            && from_archive_json.is_none()
+        {
+            if let Ok(flake_nix) =
+                std::fs::read_to_string(flake_lock_path.with_file_name("flake.nix"))
+            {
+                let flake_lock = FlakeLock::from_str_lenient(&lock_contents)?.0;
+                if flake_check_config.check_unlocked_inputs {
+                    report
+                        .issues
+                        .extend(flake_nix::unlocked_inputs(&flake_lock, &flake_nix));
+                }
+                let declared_ref_mismatches = if flake_check_config.check_declared_refs {
+                    flake_nix::declared_ref_mismatches(
+                        &flake_lock,
+                        &flake_nix,
+                        &flake_check_config.nixpkgs_keys,
+                        &mut report.checks_run,
+                    )
+                } else {
+                    vec![]
+                };
+                if flake_check_config.check_original_urls {
+                    // A URL that only differs in its ref is already reported as a ref mismatch
+                    report.issues.extend(
+                        flake_nix::original_mismatches(
+                            &flake_lock,
+                            &flake_nix,
+                            &mut report.checks_run,
+                        )
+                        .into_iter()
+                        .filter(|issue| {
+                            !declared_ref_mismatches
+                                .iter()
+                                .any(|mismatch| mismatch.input == issue.input)
+                        }),
+                    );
+                }
+                report.issues.extend(declared_ref_mismatches);
+            }
+        }
+        // Outdated inputs are exempted while checking, while other pinned kinds are dropped here so
+        // that pins apply to cached reports and flake.nix issues too
+        let today = Utc::now().date_naive();
+        report.issues.retain(|issue| {
+            !flake_check_config
+                .pins
+                .exempts(&issue.input, issue.kind.name(), today)
+        });
+        if let Some(path) = &generate_suppressions {
+            std::fs::write(path, flake_check_config.pins.generate(&report.issues))?;
+        }
+        if let Some(input) = &input {
+            let scope = input_scope(&FlakeLock::from_str_lenient(&lock_contents)?.0, input)?;
+            report.issues.retain(|issue| scope.contains(&issue.input));
+        }
+        if report_clean_inputs {
+            let flake_lock = FlakeLock::from_str_lenient(&lock_contents)?.0;
+            report.clean_inputs = Some(clean_inputs(
+                &flake_lock,
+                &flake_check_config,
+                &report.issues,
+                &report.checks_run,
+                condition.is_some(),
+            )?);
+        }
+        // The baseline is compared after caching, since it changes from run to run
+        if let Some(dir) = &baseline_age {
+            report.age_change =
+                baseline::compare_and_store(dir, &flake_lock_path, report.input_ages)?;
+        }
+        // Locations refer to the flake.lock's text, which archive JSON doesn't preserve
+        if from_archive_json.is_none() {
+            location::locate_issues(&mut report.issues, &lock_contents)?;
+        }
+        let report = if redact { report.redacted() } else { report };
+        let issues = &report.issues;
+        let silent = quiet_clean && issues.is_empty();
+
+        if flake_lock_glob.is_some() && quiet_clean && !silent {
+            println!("Checking {}", display_path.display());
+        }
+
+        let verdict = output::verdict(&report, &display_path);
+        // The breakdown as JSON replaces the text summary on stdout, so that it can be parsed
+        let explain_json = explain && explain_format == ExplainFormat::Json;
+
+        let summary = Summary::new(
+            &report,
+            display_path.clone(),
+            flake_check_config.clone(),
+            allowed_refs.clone(),
+            condition.clone(),
+            markdown_collapsible,
+            lang,
+        );
+        let summary = match &summary_title {
+            Some(title) => summary.with_title(title),
+            None => summary,
+        };
+        let summary = if group_outdated {
+            summary.with_outdated_by_channel()
+        } else {
+            summary
+        };
+        let summary = summary.with_color(color);
+        // Only the text summary's issues are moved to stderr
+        let issues_on_stderr = only_failures_to_stderr && std::env::var("GITHUB_ACTIONS").is_err();
+        let summary = if issues_on_stderr {
+            summary.with_issues_on_stderr()
+        } else {
+            summary
+        };
+
+        let output = match (&output_dir, output_plan.machine) {
+            (Some(output_dir), Some(format)) => {
+                let path = output::report_path(output_dir, &flake_lock_path, format);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                // Each lockfile has a report of its own, which isn't appended to an earlier run's
+                if format == OutputFormat::NdjsonReport && path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+                Some(path)
+            }
+            _ => output.clone(),
+        };
+
+        if let Some(format) = output_plan.machine.filter(|_| !silent || output.is_some()) {
+            if format == OutputFormat::Html {
+                output::write_html(&summary, output.as_deref())?;
+            } else {
+                output::write_report(
+                    &report,
+                    &display_path,
+                    &lock_contents,
+                    &flake_check_config,
+                    format,
+                    !compact,
+                    output.as_deref(),
+                )?;
+            }
+        }
+
+        all_issues.extend(issues.iter().cloned());
+        all_num_inputs += report.num_inputs;
+        has_errors |= severity::has_errors(issues, &flake_check_config);
+
+        if output_plan.summary {
+            if std::env::var("GITHUB_ACTIONS").is_ok() {
+                if !silent {
+                    summary.console_log_errors()?;
+                }
+                if markdown_summary {
+                    if flake_lock_glob.is_some() {
+                        markdown_summaries.push(summary);
+                    } else {
+                        summary.generate_markdown()?;
+                    }
+                }
+            } else if !silent {
+                if issues_on_stderr {
+                    for line in summary.issue_lines() {
+                        eprintln!("{line}");
+                    }
+                }
+                if flake_lock_glob.is_some() && !explain_json {
+                    summary.generate_text_section()?;
+                    text_summaries.push(summary);
+                } else if !explain_json {
+                    summary.generate_text()?;
+                }
+            }
+        }
+
+        if let Some(base_ref) = diff_base_ref
+            .as_ref()
+            .filter(|_| output_plan.summary && !silent && !explain_json)
+        {
+            match diff_table::changed_inputs_table(
+                &flake_lock_path,
+                &lock_contents,
+                base_ref,
+                &flake_check_config,
+                allowed_refs.clone(),
+            ) {
+                Ok(table) => print!("{table}"),
+                Err(FlakeCheckerError::NotGitRepo(dir)) => {
+                    eprintln!(
+                        "warning: {dir} isn't in a Git repository, so --diff-base-ref is ignored"
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if explain && output_plan.summary && !silent {
+            let flake_lock = FlakeLock::from_str_lenient(&lock_contents)?.0;
+            output::write_explain(
+                &input_checks(
+                    &flake_lock,
+                    &flake_check_config,
+                    issues,
+                    &report.checks_run,
+                    condition.is_some(),
+                )?,
+                explain_format,
+                !compact,
+            )?;
+        }
+
+        if !quiet && !silent {
+            let flake_lock = FlakeLock::from_str_lenient(&lock_contents)?.0;
+            let mut lines: Vec<String> = non_flake_nixpkgs(&flake_lock, &flake_check_config)?
+                .into_iter()
+                .map(|input| {
+                    format!(
+                        "note: skipped the {input} input, which isn't a flake (flake = false) and is used as a source tree"
+                    )
+                })
+                .collect();
+            lines.push(verdict);
+            // With --only-failures-to-stderr, stderr is kept for the issues
+            for line in lines {
+                if issues_on_stderr {
+                    println!("{line}");
+                } else {
+                    eprintln!("{line}");
+                }
+            }
         }
-        summary.console_log_errors()?;
-    } else {
-        summary.generate_text()?;
     }
 
-    if fail_mode && !issues.is_empty() {
+    if let Some(client) = client.as_ref().filter(|_| !no_telemetry) {
+        telemetry::TelemetryReport::make_and_send(&all_issues, client);
+    }
+    if github_output {
+        output::write_github_output(&all_issues, all_num_inputs)?;
+    }
+
+    if !markdown_summaries.is_empty() {
+        Summary::merge(markdown_summaries).generate_markdown()?;
+    }
+    if !text_summaries.is_empty() {
+        Summary::merge(text_summaries).generate_text()?;
+    }
+
+    if has_errors {
         return Ok(ExitCode::FAILURE);
     }
 
     Ok(ExitCode::SUCCESS)
 }
 
-#[cfg(feature = "ref-statuses")]
-#[derive(Parser)]
-struct Cli {
-    // Check to make sure that Flake Checker is aware of the current supported branches.
-    #[arg(long, hide = true)]
-    check_ref_statuses: bool,
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use std::path::PathBuf;
 
-    // Check to make sure that Flake Checker is aware of the current supported branches.
-    #[arg(long, hide = true)]
-    get_ref_statuses: bool,
-}
+    use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+    use clap_complete::Shell;
 
-#[cfg(feature = "ref-statuses")]
-fn main() -> Result<ExitCode, FlakeCheckerError> {
-    let Cli {
-        check_ref_statuses,
-        get_ref_statuses,
-    } = Cli::parse();
+    use crate::output::OutputFormat;
+    use crate::ref_statuses::RefsCommand;
+    use crate::summary::Lang;
+    use crate::{
+        missing_lock_fails, resolve_max_age, version_info, write_completions, Cli, Command,
+    };
 
-    if !get_ref_statuses && !check_ref_statuses {
-        panic!("You must select either --get-ref-statuses or --check-ref-statuses");
+    fn refs_command(args: &[&str]) -> Option<RefsCommand> {
+        match Cli::try_parse_from(args).unwrap().command {
+            Some(Command::Refs { command }) => Some(command),
+            _ => None,
+        }
     }
 
-    if get_ref_statuses {
-        match ref_statuses::fetch_ref_statuses() {
-            Ok(refs) => {
-                let json_refs = serde_json::to_string(&refs)?;
-                println!("{json_refs}");
-                return Ok(ExitCode::SUCCESS);
-            }
-            Err(e) => {
-                println!("Error fetching ref statuses: {}", e);
-                return Ok(ExitCode::FAILURE);
+    #[test]
+    fn refs_subcommands() {
+        assert_eq!(
+            refs_command(&["flake-checker", "refs", "check"]),
+            Some(RefsCommand::Check)
+        );
+        assert_eq!(
+            refs_command(&["flake-checker", "refs", "fetch"]),
+            Some(RefsCommand::Fetch)
+        );
+        assert_eq!(
+            refs_command(&["flake-checker", "refs", "update", "--out", "refs.json"]),
+            Some(RefsCommand::Update {
+                out: PathBuf::from("refs.json")
+            })
+        );
+
+        // Checking a flake.lock doesn't involve a subcommand
+        assert_eq!(refs_command(&["flake-checker", "flake.lock"]), None);
+        assert_eq!(refs_command(&["flake-checker"]), None);
+
+        // Check flags can't be mixed with the refs subcommands
+        assert!(Cli::try_parse_from(["flake-checker", "--fail-mode", "refs", "check"]).is_err());
+        assert!(Cli::try_parse_from(["flake-checker", "refs"]).is_err());
+    }
+
+    #[test]
+    fn explain_subcommand() {
+        let kind = |args: &[&str]| match Cli::try_parse_from(args).unwrap().command {
+            Some(Command::Explain { kind }) => kind,
+            _ => panic!("not the explain subcommand"),
+        };
+
+        assert_eq!(
+            kind(&["flake-checker", "explain", "disallowed"]),
+            "disallowed"
+        );
+        assert_eq!(
+            kind(&["flake-checker", "explain", "non-upstream"]),
+            "non-upstream"
+        );
+        assert!(Cli::try_parse_from(["flake-checker", "explain", "unknown"]).is_err());
+    }
+
+    #[test]
+    fn completions() {
+        for shell in Shell::value_variants() {
+            let name = shell.to_possible_value().unwrap().get_name().to_string();
+            match Cli::try_parse_from(["flake-checker", "completions", &name])
+                .unwrap()
+                .command
+            {
+                Some(Command::Completions { shell: parsed }) => assert_eq!(parsed, *shell),
+                _ => panic!("not the completions subcommand"),
             }
+
+            let mut script = vec![];
+            write_completions(*shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("fail-mode"), "{name}: {script}");
         }
+        assert!(Cli::try_parse_from(["flake-checker", "completions", "tcsh"]).is_err());
     }
 
-    if check_ref_statuses {
-        let mut ref_statuses: HashMap<String, String> =
-            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+    #[test]
+    fn version() {
+        match Cli::try_parse_from(["flake-checker", "version", "--json"])
+            .unwrap()
+            .command
+        {
+            Some(Command::Version { json }) => assert!(json),
+            _ => panic!("not the version subcommand"),
+        }
 
-        match ref_statuses::check_ref_statuses(ref_statuses) {
-            Ok(equals) => {
-                if equals {
-                    println!("The reference statuses sets are up to date.");
-                    return Ok(ExitCode::SUCCESS);
-                } else {
-                    println!(
-                        "The reference statuses sets are NOT up to date. Make sure to update."
-                    );
-                    return Ok(ExitCode::FAILURE);
-                }
-            }
-            Err(e) => {
-                println!("Error checking ref statuses: {}", e);
-                return Ok(ExitCode::FAILURE);
-            }
+        let info = version_info();
+        assert_eq!(info["name"], "flake-checker");
+        assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            info["features"]
+                .as_array()
+                .unwrap()
+                .contains(&"zstd".into()),
+            cfg!(feature = "zstd")
+        );
+    }
+
+    #[test]
+    fn lang() {
+        let lang = |args: &[&str]| Cli::try_parse_from(args).unwrap().lang;
+
+        assert_eq!(lang(&["flake-checker"]), Lang::En);
+        assert_eq!(lang(&["flake-checker", "--lang", "en"]), Lang::En);
+        assert!(Cli::try_parse_from(["flake-checker", "--lang", "xx"]).is_err());
+    }
+
+    #[test]
+    fn max_age() {
+        let cli = |args: &[&str]| Cli::try_parse_from(args).unwrap();
+
+        let defaults = cli(&["flake-checker"]);
+        assert_eq!((defaults.max_days, defaults.max_age), (30, None));
+        assert_eq!(cli(&["flake-checker", "--max-days", "45"]).max_days, 45);
+        assert_eq!(cli(&["flake-checker", "--max-age", "2w"]).max_age, Some(14));
+        assert_eq!(
+            cli(&["flake-checker", "--max-age", "3mo"]).max_age,
+            Some(90)
+        );
+
+        let error = Cli::try_parse_from(["flake-checker", "--max-age", "soon"])
+            .err()
+            .unwrap()
+            .source()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("invalid duration \"soon\""), "{error}");
+
+        // ISO 8601 durations are accepted wherever a number of days is
+        assert_eq!(cli(&["flake-checker", "--max-days", "P2W"]).max_days, 14);
+        assert_eq!(
+            cli(&["flake-checker", "--max-age", "P1M2W"]).max_age,
+            Some(44)
+        );
+        assert_eq!(
+            cli(&["flake-checker", "--grace-days", "P3W"]).grace_days,
+            21
+        );
+        assert_eq!(cli(&["flake-checker", "--grace-days", "10"]).grace_days, 10);
+        for flag in ["--max-days", "--max-age", "--grace-days"] {
+            let error = Cli::try_parse_from(["flake-checker", flag, "P2X"])
+                .err()
+                .unwrap()
+                .source()
+                .unwrap()
+                .to_string();
+            assert!(
+                error.contains("invalid duration \"P2X\""),
+                "{flag}: {error}"
+            );
+        }
+
+        // Only one form of the maximum age can be supplied
+        let resolved = |args: &[&str]| {
+            let matches = Cli::command().try_get_matches_from(args).unwrap();
+            resolve_max_age(Cli::from_arg_matches(&matches).unwrap(), &matches)
+        };
+        assert!(resolved(&["flake-checker", "--max-days", "45", "--max-age", "2w"]).is_err());
+        assert_eq!(
+            resolved(&["flake-checker", "--max-days", "45"])
+                .unwrap()
+                .max_days,
+            45
+        );
+
+        // Neither can be negative
+        for flag in ["--max-days=-5", "--max-age=-5d"] {
+            assert!(
+                Cli::try_parse_from(["flake-checker", flag]).is_err(),
+                "{flag}"
+            );
         }
     }
 
-    Ok(ExitCode::SUCCESS)
+    #[test]
+    fn repeated_output_format() {
+        let formats = |args: &[&str]| Cli::try_parse_from(args).unwrap().output_format;
+
+        assert_eq!(formats(&["flake-checker"]), vec![OutputFormat::Text]);
+        assert_eq!(
+            formats(&[
+                "flake-checker",
+                "--output-format",
+                "text",
+                "--output-format",
+                "json",
+                "--output",
+                "report.json"
+            ]),
+            vec![OutputFormat::Text, OutputFormat::Json]
+        );
+        assert_eq!(
+            formats(&["flake-checker", "--output-format", "text,json"]),
+            vec![OutputFormat::Text, OutputFormat::Json]
+        );
+    }
+
+    #[test]
+    fn flake_lock_glob_conflicts() {
+        let cli =
+            Cli::try_parse_from(["flake-checker", "--flake-lock-glob", "*/flake.lock"]).unwrap();
+        assert_eq!(cli.flake_lock_glob.as_deref(), Some("*/flake.lock"));
+
+        assert!(Cli::try_parse_from([
+            "flake-checker",
+            "--flake-lock-glob",
+            "*/flake.lock",
+            "flake.lock"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn strict_telemetry_off_flag() {
+        let cli = Cli::try_parse_from(["flake-checker", "--strict-telemetry-off"]).unwrap();
+        assert!(cli.strict_telemetry_off);
+
+        // Fetching a remote flake.lock would need network access
+        assert!(
+            Cli::try_parse_from(["flake-checker", "--strict-telemetry-off", "--online"]).is_err()
+        );
+    }
+
+    #[test]
+    fn proxy_flag() {
+        let proxy = |args: &[&str]| Cli::try_parse_from(args).unwrap().proxy;
+
+        assert_eq!(
+            proxy(&["flake-checker", "--proxy", "http://proxy:3128"]),
+            Some(String::from("http://proxy:3128"))
+        );
+        // The proxy also applies to the refs subcommands
+        assert_eq!(
+            proxy(&[
+                "flake-checker",
+                "refs",
+                "fetch",
+                "--proxy",
+                "http://proxy:3128"
+            ]),
+            Some(String::from("http://proxy:3128"))
+        );
+    }
+
+    #[test]
+    fn missing_lock() {
+        // A directory with a flake.nix but no flake.lock
+        let unlocked = PathBuf::from("tests/missing-lock/flake.lock");
+        // A directory without a flake at all
+        let no_flake = PathBuf::from("tests/flake.lock");
+        assert!(!unlocked.exists() && !no_flake.exists());
+
+        for path in [&unlocked, &no_flake] {
+            assert!(!missing_lock_fails(path, true, false));
+            assert!(missing_lock_fails(path, false, false));
+        }
+
+        assert!(missing_lock_fails(&unlocked, true, true));
+        assert!(missing_lock_fails(&unlocked, false, true));
+        assert!(!missing_lock_fails(&no_flake, true, true));
+        assert!(!missing_lock_fails(&no_flake, false, true));
+    }
 }
@@ -0,0 +1,1066 @@
+use crate::error::FlakeCheckerError;
+use crate::flake::TRANSITIVE_INPUT_SEPARATOR;
+use crate::issue::{Issue, ISSUE_KINDS};
+use crate::report::{CheckReport, InputChecks};
+use crate::severity::Severity;
+use crate::summary::Summary;
+use crate::FlakeCheckConfig;
+
+use std::collections::HashMap;
+use std::fs::{write, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+use parse_flake_lock::FlakeLock;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use terminal_size::{terminal_size, Width};
+
+/// The format in which to report check results.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// A human-readable summary (Markdown in GitHub Actions).
+    Text,
+    /// The check report as JSON.
+    Json,
+    /// One line of JSON per flake.lock: its check report with the lockfile's path as `flake_lock`.
+    NdjsonReport,
+    /// One `file,input,kind,detail,severity` row per issue.
+    Csv,
+    /// The issues as a table with aligned `input`, `kind`, `detail`, and `severity` columns.
+    Table,
+    /// The flake's input tree as a Mermaid graph, with inputs colored by their issues.
+    Mermaid,
+    /// The summary as a self-contained HTML page, for sharing as a CI artifact.
+    Html,
+    /// The check report encoded using Protocol Buffers (see `proto/check_report.proto`).
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+}
+
+impl OutputFormat {
+    /// The file extension for reports in this format, as used by `--output-dir`.
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Json => "json",
+            Self::NdjsonReport => "ndjson",
+            Self::Csv => "csv",
+            Self::Table => "txt",
+            Self::Mermaid => "mmd",
+            Self::Html => "html",
+            #[cfg(feature = "protobuf")]
+            Self::Protobuf => "pb",
+        }
+    }
+}
+
+/// The format in which `--explain` prints the outcome of each check of each checked input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum ExplainFormat {
+    /// A line per check, indented under its input.
+    #[default]
+    Text,
+    /// A JSON array of `{ "input", "checks": [{ "name", "passed", "detail" }] }` objects.
+    Json,
+}
+
+/// How the requested output formats are reported.
+#[derive(Debug, PartialEq)]
+pub(crate) struct OutputPlan {
+    /// The machine-readable format to write (if any).
+    pub(crate) machine: Option<OutputFormat>,
+    /// Whether to show the human-readable summary.
+    pub(crate) summary: bool,
+}
+
+/// Decide how to report the requested formats. The human-readable summary can be combined with one
+/// machine-readable format as long as that format is written to an `output` file. Without the
+/// `text` format, machine-readable output written to stdout replaces the summary.
+pub(crate) fn plan(
+    formats: &[OutputFormat],
+    output: Option<&Path>,
+) -> Result<OutputPlan, FlakeCheckerError> {
+    let mut machine: Vec<OutputFormat> = vec![];
+    for format in formats {
+        if *format != OutputFormat::Text && !machine.contains(format) {
+            machine.push(*format);
+        }
+    }
+
+    if machine.len() > 1 {
+        return Err(FlakeCheckerError::OutputFormat(String::from(
+            "only one machine-readable format can be written per run",
+        )));
+    }
+
+    let machine = machine.first().copied();
+    let text = formats.contains(&OutputFormat::Text);
+
+    if machine.is_some() && text && output.is_none() {
+        return Err(FlakeCheckerError::OutputFormat(String::from(
+            "combining the text format with a machine-readable format requires --output",
+        )));
+    }
+
+    Ok(OutputPlan {
+        machine,
+        summary: machine.is_none() || text || output.is_some(),
+    })
+}
+
+/// Write the check report for the `flake.lock` at `flake_lock_path` (whose contents are
+/// `lock_contents`) in a machine-readable format to the `output` file or, if no file is supplied,
+/// to stdout. `pretty` only applies to JSON. NDJSON reports are appended to the `output` file so
+/// that it gets a line for every lockfile checked in the run. HTML pages are rendered from the
+/// summary instead (see [write_html]).
+pub(crate) fn write_report(
+    report: &CheckReport,
+    flake_lock_path: &Path,
+    lock_contents: &str,
+    config: &FlakeCheckConfig,
+    format: OutputFormat,
+    pretty: bool,
+    output: Option<&Path>,
+) -> Result<(), FlakeCheckerError> {
+    let bytes: Vec<u8> = match format {
+        // The human-readable summary and the HTML page are rendered separately
+        OutputFormat::Text | OutputFormat::Html => return Ok(()),
+        OutputFormat::Json => json(report, pretty)?.into_bytes(),
+        OutputFormat::NdjsonReport => ndjson_report(report, flake_lock_path)?.into_bytes(),
+        OutputFormat::Csv => csv(report, flake_lock_path, config).into_bytes(),
+        // Only tables printed to a terminal need to fit its width
+        OutputFormat::Table => table(
+            report,
+            config,
+            output
+                .is_none()
+                .then(terminal_size)
+                .flatten()
+                .map(|(Width(width), _)| usize::from(width)),
+        )
+        .into_bytes(),
+        OutputFormat::Mermaid => mermaid(
+            report,
+            &FlakeLock::from_str_lenient(lock_contents)?.0,
+            config,
+        )?
+        .into_bytes(),
+        #[cfg(feature = "protobuf")]
+        OutputFormat::Protobuf => crate::protobuf::encode(report),
+    };
+
+    write_output(&bytes, format, output)
+}
+
+/// Write the `summary` as a self-contained HTML page to the `output` file or, if no file is
+/// supplied, to stdout.
+pub(crate) fn write_html(
+    summary: &Summary,
+    output: Option<&Path>,
+) -> Result<(), FlakeCheckerError> {
+    write_output(
+        summary.render_html()?.as_bytes(),
+        OutputFormat::Html,
+        output,
+    )
+}
+
+/// Print each of the `input_checks` with the outcome of each of its checks in `format`, for
+/// `--explain`. `pretty` is as for JSON reports.
+pub(crate) fn write_explain(
+    input_checks: &[InputChecks],
+    format: ExplainFormat,
+    pretty: bool,
+) -> Result<(), FlakeCheckerError> {
+    let text = match format {
+        ExplainFormat::Text => explain(input_checks),
+        ExplainFormat::Json => explain_json(input_checks, pretty)? + "\n",
+    };
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// Write the `bytes` of a report in `format` to the `output` file or, if none is supplied, stdout.
+fn write_output(
+    bytes: &[u8],
+    format: OutputFormat,
+    output: Option<&Path>,
+) -> Result<(), FlakeCheckerError> {
+    match output {
+        Some(path) if format == OutputFormat::NdjsonReport => OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+            .write_all(bytes)?,
+        Some(path) => write(path, bytes)?,
+        // Lines of streaming formats like NDJSON show up as soon as each lockfile is checked
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(bytes)?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The path of the report for the `flake.lock` at `flake_lock_path` in `output_dir`, which mirrors
+/// the `flake.lock`'s path with the format's extension appended, e.g. `a/flake.lock.json`. Leading
+/// `/`, `.`, and `..` components are dropped so that reports stay inside `output_dir`, and as that
+/// could make the paths of different `flake.lock`s (like `a/flake.lock` and `../a/flake.lock`) the
+/// same, a hash of the path as given is added to the names of such reports, e.g.
+/// `a/flake.lock.0123456789abcdef.json`.
+pub(crate) fn report_path(
+    output_dir: &Path,
+    flake_lock_path: &Path,
+    format: OutputFormat,
+) -> PathBuf {
+    let mut path = output_dir.to_path_buf();
+    let mut outside = false;
+    for component in flake_lock_path.components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => outside = true,
+        }
+    }
+
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    if outside {
+        let hash = format!(
+            "{:x}",
+            Sha256::digest(flake_lock_path.as_os_str().as_encoded_bytes())
+        );
+        file_name.push(format!(".{}", &hash[..16]));
+    }
+    file_name.push(format!(".{}", format.extension()));
+    path.set_file_name(file_name);
+    path
+}
+
+/// The `flake.lock`'s path as displayed in summaries and reports, relative to `base`, e.g.
+/// `../other/flake.lock`. Both paths are made absolute against `cwd` and compared component by
+/// component, without resolving symlinks, since the `flake.lock` may not exist on this machine.
+pub(crate) fn relative_path(flake_lock_path: &Path, base: &Path, cwd: &Path) -> PathBuf {
+    let path = normalize(&cwd.join(flake_lock_path));
+    let base = normalize(&cwd.join(base));
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+
+    let common = path
+        .iter()
+        .zip(base.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+// The components of the absolute `path` with `.` and `..` components resolved lexically.
+fn normalize(path: &Path) -> PathBuf {
+    let mut components: Vec<Component> = vec![];
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(components.last(), Some(Component::Normal(_))) {
+                    components.pop();
+                }
+            }
+            _ => components.push(component),
+        }
+    }
+    components.iter().collect()
+}
+
+fn json(report: &CheckReport, pretty: bool) -> Result<String, FlakeCheckerError> {
+    Ok(if pretty {
+        serde_json::to_string_pretty(report)?
+    } else {
+        serde_json::to_string(report)?
+    })
+}
+
+/// The `input_checks` as JSON, for `--explain-format json`.
+fn explain_json(input_checks: &[InputChecks], pretty: bool) -> Result<String, FlakeCheckerError> {
+    Ok(if pretty {
+        serde_json::to_string_pretty(input_checks)?
+    } else {
+        serde_json::to_string(input_checks)?
+    })
+}
+
+// Each of the `input_checks` followed by the outcome of each of its checks, for `--explain`.
+fn explain(input_checks: &[InputChecks]) -> String {
+    let mut text = String::new();
+    for input in input_checks {
+        text.push_str(&format!("{}:\n", input.input));
+        if input.checks.is_empty() {
+            text.push_str("  no checks ran\n");
+        }
+        for check in &input.checks {
+            match check.detail.as_deref().filter(|_| !check.passed) {
+                Some(detail) => text.push_str(&format!("  {}: failed ({detail})\n", check.name)),
+                None => text.push_str(&format!("  {}: passed\n", check.name)),
+            }
+        }
+    }
+    text
+}
+
+/// The check report for the `flake.lock` at `flake_lock_path` as a line of JSON.
+fn ndjson_report(
+    report: &CheckReport,
+    flake_lock_path: &Path,
+) -> Result<String, FlakeCheckerError> {
+    #[derive(Serialize)]
+    struct NdjsonReport<'a> {
+        flake_lock: String,
+        #[serde(flatten)]
+        report: &'a CheckReport,
+    }
+
+    let line = serde_json::to_string(&NdjsonReport {
+        flake_lock: flake_lock_path.display().to_string(),
+        report,
+    })?;
+    Ok(format!("{line}\n"))
+}
+
+/// The check report as CSV with a header row and one row per issue.
+fn csv(report: &CheckReport, flake_lock_path: &Path, config: &FlakeCheckConfig) -> String {
+    let file = flake_lock_path.display().to_string();
+    let mut rows = vec![String::from("file,input,kind,detail,severity")];
+    for issue in &report.issues {
+        let detail = issue.kind.detail();
+        let severity = issue.severity(config).to_string();
+        let fields = [
+            file.as_str(),
+            issue.input.as_str(),
+            issue.kind.name(),
+            detail.as_str(),
+            severity.as_str(),
+        ];
+        rows.push(
+            fields
+                .into_iter()
+                .map(csv_field)
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+    }
+
+    rows.iter().map(|row| format!("{row}\n")).collect()
+}
+
+/// The narrowest that the input and detail columns of a table are truncated to.
+const MIN_TABLE_COLUMN_WIDTH: usize = 12;
+
+/// The check report as a table with a header row and one row per issue, with columns aligned by
+/// padding. If the rows are wider than `width`, the detail column and then the input column are
+/// truncated (with an ellipsis) to fit, down to [MIN_TABLE_COLUMN_WIDTH].
+fn table(report: &CheckReport, config: &FlakeCheckConfig, width: Option<usize>) -> String {
+    let mut rows = vec![[
+        String::from("Input"),
+        String::from("Kind"),
+        String::from("Detail"),
+        String::from("Severity"),
+    ]];
+    for issue in &report.issues {
+        rows.push([
+            issue.input.clone(),
+            issue.kind.name().to_string(),
+            issue.kind.detail(),
+            issue.severity(config).to_string(),
+        ]);
+    }
+
+    let mut widths = [0; 4];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // Columns are separated by two spaces
+    if let Some(max_width) = width {
+        for column in [2, 0] {
+            let total = widths.iter().sum::<usize>() + 2 * (widths.len() - 1);
+            let excess = total.saturating_sub(max_width);
+            let shrinkable = widths[column].saturating_sub(MIN_TABLE_COLUMN_WIDTH);
+            widths[column] -= excess.min(shrinkable);
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", truncate(cell, width)))
+                .collect();
+            format!("{}\n", cells.join("  ").trim_end())
+        })
+        .collect()
+}
+
+// The string cut to at most `width` characters, ending in an ellipsis if it was cut.
+fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        s.to_string()
+    } else {
+        let mut truncated: String = s.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+/// The Mermaid classes for inputs with errors, with warnings (or info), and without issues.
+const MERMAID_CLASSES: [(&str, &str); 3] = [
+    ("error", "fill:#ffebe9,stroke:#cf222e"),
+    ("warning", "fill:#fff8c5,stroke:#bf8700"),
+    ("ok", "fill:#dafbe1,stroke:#1a7f37"),
+];
+
+/// The flake's input tree (as walked by [FlakeLock::walk]) as a Mermaid graph. Each input is
+/// colored by the most severe of its issues: red for errors, yellow for warnings and info, and
+/// green for none. Issues with the flake as a whole color the root.
+fn mermaid(
+    report: &CheckReport,
+    flake_lock: &FlakeLock,
+    config: &FlakeCheckConfig,
+) -> Result<String, FlakeCheckerError> {
+    let walked = flake_lock.walk()?;
+
+    // Issues are reported by input name (or path of input names for transitive inputs) and, for
+    // checks of every node, by node key
+    let status = |names: &[&str]| {
+        report
+            .issues
+            .iter()
+            .filter(|issue| names.contains(&issue.input.as_str()))
+            .map(|issue| issue.severity(config))
+            .max()
+            .map_or("ok", |severity| match severity {
+                Severity::Error => "error",
+                Severity::Warning | Severity::Info => "warning",
+            })
+    };
+
+    let mut lines = vec![String::from("graph LR"), String::from("    n0[\"root\"]")];
+    let mut statuses = vec![status(&["root", flake_lock.root_key.as_str()])];
+
+    // The root is n0 and inputs are numbered in the order that they're walked, which is breadth
+    // first, so every input's parent already has a number
+    let mut ids: HashMap<&[String], usize> = HashMap::new();
+    for input in &walked {
+        let Some((name, parent_path)) = input.path.split_last() else {
+            continue;
+        };
+        let id = statuses.len();
+        let parent = ids.get(parent_path).copied().unwrap_or(0);
+
+        lines.push(format!(
+            "    n{parent} --> n{id}[\"{}\"]",
+            name.replace('"', "#quot;")
+        ));
+        ids.insert(input.path.as_slice(), id);
+        statuses.push(status(&[
+            &input.path.join(TRANSITIVE_INPUT_SEPARATOR),
+            input.key.as_str(),
+        ]));
+    }
+
+    for (class, style) in MERMAID_CLASSES {
+        lines.push(format!("    classDef {class} {style}"));
+    }
+    for (class, _) in MERMAID_CLASSES {
+        let ids: Vec<String> = statuses
+            .iter()
+            .enumerate()
+            .filter(|(_, status)| **status == class)
+            .map(|(id, _)| format!("n{id}"))
+            .collect();
+        if !ids.is_empty() {
+            lines.push(format!("    class {} {class}", ids.join(",")));
+        }
+    }
+
+    Ok(lines.iter().map(|line| format!("{line}\n")).collect())
+}
+
+/// Quote a CSV field that contains a comma, quote, or line break, doubling any quotes (as in
+/// RFC 4180).
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A one-line verdict for stderr, e.g. `flake-checker: 3 issues (1 disallowed, 2 outdated) in
+/// flake.lock`, which is printed regardless of the output format so that people watching CI logs
+/// see the result even if stdout is redirected.
+pub(crate) fn verdict(report: &CheckReport, flake_lock_path: &Path) -> String {
+    let num_issues = report.issues.len();
+    let issue_word = if num_issues == 1 { "issue" } else { "issues" };
+    let file = flake_lock_path.display();
+
+    let counts: Vec<String> = ISSUE_KINDS
+        .iter()
+        .filter_map(|kind| {
+            let count = report
+                .issues
+                .iter()
+                .filter(|i| i.kind.name() == *kind)
+                .count();
+            (count > 0).then(|| format!("{count} {kind}"))
+        })
+        .collect();
+
+    if counts.is_empty() {
+        format!("flake-checker: {num_issues} {issue_word} in {file}")
+    } else {
+        format!(
+            "flake-checker: {num_issues} {issue_word} ({}) in {file}",
+            counts.join(", ")
+        )
+    }
+}
+
+/// Append the counts of `issues` and of the `num_inputs` checked to the file named by
+/// `GITHUB_OUTPUT` so that subsequent steps in a GitHub Actions job can use them. With
+/// `--flake-lock-glob`, these are the totals across every lockfile. If `GITHUB_OUTPUT` isn't set,
+/// nothing is written.
+pub(crate) fn write_github_output(
+    issues: &[Issue],
+    num_inputs: usize,
+) -> Result<(), FlakeCheckerError> {
+    match std::env::var_os("GITHUB_OUTPUT") {
+        Some(path) => append_github_output(Path::new(&path), issues, num_inputs),
+        None => {
+            eprintln!("GITHUB_OUTPUT isn't set; not writing GitHub job outputs");
+            Ok(())
+        }
+    }
+}
+
+fn append_github_output(
+    path: &Path,
+    issues: &[Issue],
+    num_inputs: usize,
+) -> Result<(), FlakeCheckerError> {
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    file.write_all(github_output(issues, num_inputs).as_bytes())?;
+    Ok(())
+}
+
+/// The `key=value` lines for GitHub job outputs, e.g. `issue_count=2` and `disallowed_count=1`.
+fn github_output(issues: &[Issue], num_inputs: usize) -> String {
+    let mut lines = vec![
+        format!("issue_count={}", issues.len()),
+        format!("input_count={num_inputs}"),
+    ];
+    for kind in ISSUE_KINDS {
+        let count = issues.iter().filter(|i| i.kind.name() == *kind).count();
+        lines.push(format!("{}_count={count}", kind.replace('-', "_")));
+    }
+
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs::{read_to_string, remove_file};
+    use std::path::Path;
+
+    use parse_flake_lock::FlakeLock;
+
+    use crate::flake::{check_flake_lock_with_checks_run, input_checks};
+    use crate::issue::{
+        Disallowed, FutureTimestamp, InsecureTransport, Issue, IssueKind, Outdated,
+    };
+    use crate::ref_statuses::supported_refs;
+    use crate::report::CheckReport;
+    use crate::FlakeCheckConfig;
+
+    use super::{
+        append_github_output, csv, explain_json, json, mermaid, plan, relative_path, report_path,
+        table, verdict, write_report, OutputFormat, OutputPlan,
+    };
+
+    fn report() -> CheckReport {
+        CheckReport::new(
+            b"{}",
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            }],
+            1,
+            2,
+        )
+    }
+
+    #[test]
+    fn pretty_json() {
+        let pretty = json(&report(), true).unwrap();
+        assert!(pretty.starts_with("{\n  \"lock_hash\": "));
+        assert_eq!(
+            serde_json::from_str::<CheckReport>(&pretty).unwrap(),
+            report()
+        );
+    }
+
+    #[test]
+    fn compact_json() {
+        let compact = json(&report(), false).unwrap();
+        assert!(!compact.contains('\n') && !compact.contains(": "));
+        assert!(compact.starts_with("{\"lock_hash\":"));
+        assert_eq!(
+            serde_json::from_str::<CheckReport>(&compact).unwrap(),
+            report()
+        );
+    }
+
+    #[test]
+    fn explained_json() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let flake_lock = FlakeLock::new(Path::new("tests/flake.explicit-keys.0.lock")).unwrap();
+        let config = FlakeCheckConfig {
+            check_outdated: false,
+            nixpkgs_keys: vec![String::from("nixpkgs"), String::from("nixpkgs-alt")],
+            ..Default::default()
+        };
+        let (issues, checks_run) =
+            check_flake_lock_with_checks_run(&flake_lock, &config, supported_refs(ref_statuses))
+                .unwrap();
+        let input_checks = input_checks(&flake_lock, &config, &issues, &checks_run, false).unwrap();
+
+        // nixpkgs passes every check while nixpkgs-alt has an owner that isn't upstream
+        assert_eq!(
+            explain_json(&input_checks, true).unwrap(),
+            r#"[
+  {
+    "input": "nixpkgs",
+    "checks": [
+      {
+        "name": "Supported branches",
+        "passed": true,
+        "detail": null
+      },
+      {
+        "name": "Upstream owner",
+        "passed": true,
+        "detail": null
+      }
+    ]
+  },
+  {
+    "input": "nixpkgs-alt",
+    "checks": [
+      {
+        "name": "Supported branches",
+        "passed": true,
+        "detail": null
+      },
+      {
+        "name": "Upstream owner",
+        "passed": false,
+        "detail": "seems-pretty-shady"
+      }
+    ]
+  }
+]"#
+        );
+    }
+
+    #[test]
+    fn mermaid_graph() {
+        let flake_lock = FlakeLock::new(Path::new("tests/flake.transitive.0.lock")).unwrap();
+        let report = CheckReport::new(
+            b"{}",
+            vec![
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("this-should-fail"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+                // Future timestamps are informational by default
+                Issue {
+                    input: String::from("tools/nixpkgs"),
+                    kind: IssueKind::FutureTimestamp(FutureTimestamp::new(1689078114, 3)),
+                    location: None,
+                },
+            ],
+            1,
+            5,
+        );
+        let config = FlakeCheckConfig {
+            fail_mode: true,
+            ..Default::default()
+        };
+
+        // utils/nixpkgs follows tools/nixpkgs and so only appears once
+        assert_eq!(
+            mermaid(&report, &flake_lock, &config).unwrap(),
+            r#"graph LR
+    n0["root"]
+    n0 --> n1["nixpkgs"]
+    n0 --> n2["tools"]
+    n2 --> n3["nixpkgs"]
+    n2 --> n4["utils"]
+    classDef error fill:#ffebe9,stroke:#cf222e
+    classDef warning fill:#fff8c5,stroke:#bf8700
+    classDef ok fill:#dafbe1,stroke:#1a7f37
+    class n1 error
+    class n3 warning
+    class n0,n2,n4 ok
+"#
+        );
+    }
+
+    #[test]
+    fn csv_rows() {
+        let report = CheckReport::new(
+            b"{}",
+            vec![
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("this-should-fail"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("tools"),
+                    kind: IssueKind::InsecureTransport(InsecureTransport {
+                        url: String::from("http://example.com/tools.tar.gz?a=1,b=\"2\""),
+                    }),
+                    location: None,
+                },
+            ],
+            2,
+            2,
+        );
+        let config = FlakeCheckConfig {
+            fail_mode: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            csv(&report, Path::new("dir,with,commas/flake.lock"), &config),
+            "file,input,kind,detail,severity\n\
+             \"dir,with,commas/flake.lock\",nixpkgs,disallowed,this-should-fail,error\n\
+             \"dir,with,commas/flake.lock\",tools,insecure-transport,\"http://example.com/tools.tar.gz?a=1,b=\"\"2\"\"\",error\n"
+        );
+    }
+
+    #[test]
+    fn table_columns() {
+        let report = CheckReport::new(
+            b"{}",
+            vec![
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("this-should-fail"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("home-manager/nixpkgs"),
+                    kind: IssueKind::InsecureTransport(InsecureTransport {
+                        url: String::from("http://example.com/nixpkgs.tar.gz"),
+                    }),
+                    location: None,
+                },
+            ],
+            2,
+            2,
+        );
+        let config = FlakeCheckConfig {
+            transitive: true,
+            first_party_only: false,
+            fail_mode: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            table(&report, &config, None),
+            "\
+Input                 Kind                Detail                             Severity
+nixpkgs               disallowed          this-should-fail                   error
+home-manager/nixpkgs  insecure-transport  http://example.com/nixpkgs.tar.gz  error
+"
+        );
+
+        // Details and then inputs are truncated to fit the width
+        assert_eq!(
+            table(&report, &config, Some(60)),
+            "\
+Input             Kind                Detail        Severity
+nixpkgs           disallowed          this-should…  error
+home-manager/ni…  insecure-transport  http://exam…  error
+"
+        );
+    }
+
+    #[test]
+    fn verdict_line() {
+        let path = Path::new("flake.lock");
+        assert_eq!(
+            verdict(&CheckReport::new(b"{}", vec![], 1, 1), path),
+            "flake-checker: 0 issues in flake.lock"
+        );
+        assert_eq!(
+            verdict(&report(), path),
+            "flake-checker: 1 issue (1 disallowed) in flake.lock"
+        );
+
+        let outdated = |input: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::Outdated(Outdated::new(1, 40)),
+            location: None,
+        };
+        let mut report = report();
+        report
+            .issues
+            .extend([outdated("nixpkgs"), outdated("nixpkgs-stable")]);
+        assert_eq!(
+            verdict(&report, path),
+            "flake-checker: 3 issues (1 disallowed, 2 outdated) in flake.lock"
+        );
+    }
+
+    #[test]
+    fn multiple_formats() {
+        let output = std::env::temp_dir().join(format!(
+            "flake-checker-multiple-formats-{}.json",
+            std::process::id()
+        ));
+
+        // The summary goes to stdout and the JSON report to the output file
+        let formats = [OutputFormat::Text, OutputFormat::Json];
+        let output_plan = plan(&formats, Some(&output)).unwrap();
+        assert_eq!(
+            output_plan,
+            OutputPlan {
+                machine: Some(OutputFormat::Json),
+                summary: true,
+            }
+        );
+        write_report(
+            &report(),
+            Path::new("flake.lock"),
+            "{}",
+            &FlakeCheckConfig::default(),
+            OutputFormat::Json,
+            true,
+            Some(&output),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<CheckReport>(&read_to_string(&output).unwrap()).unwrap(),
+            report()
+        );
+        remove_file(&output).unwrap();
+
+        // Both formats can't share stdout
+        assert!(plan(&formats, None).is_err());
+
+        // Repeating a format is harmless
+        let formats = [OutputFormat::Json, OutputFormat::Json];
+        assert_eq!(
+            plan(&formats, None).unwrap(),
+            OutputPlan {
+                machine: Some(OutputFormat::Json),
+                summary: false,
+            }
+        );
+
+        assert_eq!(
+            plan(&[OutputFormat::Text], None).unwrap(),
+            OutputPlan {
+                machine: None,
+                summary: true,
+            }
+        );
+        assert_eq!(
+            plan(&[OutputFormat::Json], Some(Path::new("report.json"))).unwrap(),
+            OutputPlan {
+                machine: Some(OutputFormat::Json),
+                summary: true,
+            }
+        );
+    }
+
+    #[test]
+    fn ndjson_reports() {
+        let output = std::env::temp_dir().join(format!(
+            "flake-checker-ndjson-reports-{}.ndjson",
+            std::process::id()
+        ));
+        let _ = remove_file(&output);
+
+        let clean = CheckReport::new(b"{\"nodes\":{}}", vec![], 0, 1);
+        for (flake_lock, report) in [("a/flake.lock", report()), ("b/flake.lock", clean.clone())] {
+            write_report(
+                &report,
+                Path::new(flake_lock),
+                "{}",
+                &FlakeCheckConfig::default(),
+                OutputFormat::NdjsonReport,
+                true,
+                Some(&output),
+            )
+            .unwrap();
+        }
+
+        // One line per lockfile, each a complete report
+        let contents = read_to_string(&output).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, (flake_lock, report)) in lines
+            .iter()
+            .zip([("a/flake.lock", report()), ("b/flake.lock", clean)])
+        {
+            assert_eq!(serde_json::from_str::<CheckReport>(line).unwrap(), report);
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["flake_lock"], flake_lock);
+        }
+
+        remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn github_output() {
+        let output_path = std::env::temp_dir().join(format!(
+            "flake-checker-github-output-{}",
+            std::process::id()
+        ));
+        let _ = remove_file(&output_path);
+
+        let issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ];
+        append_github_output(&output_path, &issues, 1).unwrap();
+
+        let contents = read_to_string(&output_path).unwrap();
+        for expected in [
+            "issue_count=2",
+            "input_count=1",
+            "disallowed_count=1",
+            "outdated_count=1",
+            "non_upstream_count=0",
+        ] {
+            assert!(
+                contents.lines().any(|line| line == expected),
+                "expected {expected:?} in:\n{contents}"
+            );
+        }
+
+        // Outputs are appended to any written by earlier steps
+        append_github_output(&output_path, &issues, 1).unwrap();
+        let appended = read_to_string(&output_path).unwrap();
+        assert_eq!(appended, format!("{contents}{contents}"));
+
+        remove_file(&output_path).unwrap();
+    }
+
+    #[test]
+    fn report_paths() {
+        let out = Path::new("out");
+        assert_eq!(
+            report_path(out, Path::new("a/b/flake.lock"), OutputFormat::Json),
+            Path::new("out/a/b/flake.lock.json")
+        );
+        assert_eq!(
+            report_path(out, Path::new("flake.lock"), OutputFormat::Csv),
+            Path::new("out/flake.lock.csv")
+        );
+
+        assert_eq!(
+            report_path(out, Path::new("./a/flake.lock"), OutputFormat::Json),
+            Path::new("out/a/flake.lock.json")
+        );
+
+        // Reports never end up outside of the output directory, and the reports of flake.locks
+        // outside of it don't collide with those of the flake.locks inside of it
+        let outside = [
+            report_path(out, Path::new("/src/./flake.lock"), OutputFormat::Mermaid),
+            report_path(out, Path::new("../flake.lock"), OutputFormat::Json),
+            report_path(out, Path::new("../a/flake.lock"), OutputFormat::Json),
+            report_path(out, Path::new("../../a/flake.lock"), OutputFormat::Json),
+        ];
+        assert!(outside.iter().all(|path| path.starts_with(out)));
+        assert_eq!(outside[0].parent(), Some(Path::new("out/src")));
+        assert_eq!(outside[2].parent(), Some(Path::new("out/a")));
+        assert_ne!(outside[1], Path::new("out/flake.lock.json"));
+        assert_ne!(outside[2], Path::new("out/a/flake.lock.json"));
+        assert_ne!(outside[2], outside[3]);
+        assert!(outside[2]
+            .to_string_lossy()
+            .starts_with("out/a/flake.lock."));
+        assert!(outside[2].to_string_lossy().ends_with(".json"));
+    }
+
+    #[test]
+    fn relative_paths() {
+        let cwd = Path::new("/work/repo");
+        let base = Path::new("/work/repo");
+        assert_eq!(
+            relative_path(Path::new("/work/repo/a/flake.lock"), base, cwd),
+            Path::new("a/flake.lock")
+        );
+        assert_eq!(
+            relative_path(Path::new("./a/../b/flake.lock"), base, cwd),
+            Path::new("b/flake.lock")
+        );
+
+        // Paths outside of the base directory climb out of it
+        assert_eq!(
+            relative_path(Path::new("/work/other/flake.lock"), base, cwd),
+            Path::new("../other/flake.lock")
+        );
+
+        // A relative base is relative to the working directory
+        assert_eq!(
+            relative_path(Path::new("flake.lock"), Path::new(".."), cwd),
+            Path::new("repo/flake.lock")
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Deliberate pins, which exempt inputs from the outdated check. Pins are listed in a
+//! `flake-checker.pins.toml` file next to the `flake.lock`:
+//!
+//! ```toml
+//! [pins.nixpkgs-legacy]
+//! reason = "The last revision that builds our kernel"
+//! expires = "2025-06-30"
+//! ```
+//!
+//! A pin without an `expires` date never expires. A pin can also accept other kinds of issues with
+//! the input by listing them, in which case `outdated` has to be listed to keep the exemption:
+//!
+//! ```toml
+//! [pins.nixpkgs-legacy]
+//! kinds = ["outdated", "non-upstream"]
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::error::FlakeCheckerError;
+use crate::issue::{Issue, ISSUE_KINDS};
+
+/// The name of the pins file, which is looked for next to the `flake.lock`.
+pub(crate) const PINS_FILE_NAME: &str = "flake-checker.pins.toml";
+
+/// The inputs that are deliberately pinned, keyed by input name (e.g. `home-manager/nixpkgs` for a
+/// transitive input).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Pins(BTreeMap<String, Pin>);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Pin {
+    expires: Option<NaiveDate>,
+    /// The kinds of issues that the pin accepts.
+    kinds: Vec<String>,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinsFile {
+    #[serde(default)]
+    pins: HashMap<String, PinEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinEntry {
+    /// The last day on which the pin applies (`YYYY-MM-DD`).
+    expires: Option<String>,
+    /// The kinds of issues that the pin accepts, only `outdated` by default.
+    kinds: Option<Vec<String>>,
+    /// Why the input is pinned, which is only for the people reading the file (and kept by
+    /// `--generate-suppressions`).
+    reason: Option<String>,
+}
+
+impl Pins {
+    /// The pins in the pins file next to the `flake.lock` at `flake_lock_path`. Without a pins
+    /// file, no inputs are pinned.
+    pub(crate) fn for_flake_lock(flake_lock_path: &Path) -> Result<Self, FlakeCheckerError> {
+        let path = flake_lock_path.with_file_name(PINS_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        read_to_string(&path)?
+            .parse()
+            .map_err(|e| FlakeCheckerError::PinsFile(format!("{}: {e}", path.display())))
+    }
+
+    /// Whether issues of the given `kind` with `input` are accepted on `today`, i.e. whether the
+    /// input has a pin for that kind that hasn't expired.
+    pub(crate) fn exempts(&self, input: &str, kind: &str, today: NaiveDate) -> bool {
+        self.0.get(input).is_some_and(|pin| {
+            pin.kinds.iter().any(|k| k == kind)
+                && pin.expires.is_none_or(|expires| today <= expires)
+        })
+    }
+
+    /// A pins file that accepts all of the given `issues` on top of these pins, for
+    /// `--generate-suppressions`. There's one pin per input, listing the kinds of its issues along
+    /// with the kinds that its existing pin accepts. Existing pins keep their expiry dates and
+    /// reasons, while new pins get a reason to be filled in.
+    pub(crate) fn generate(&self, issues: &[Issue]) -> String {
+        let mut kinds: BTreeMap<&str, Vec<&str>> = self
+            .0
+            .iter()
+            .map(|(input, pin)| {
+                (
+                    input.as_str(),
+                    pin.kinds.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        for issue in issues {
+            let input_kinds = kinds.entry(&issue.input).or_default();
+            if !input_kinds.contains(&issue.kind.name()) {
+                input_kinds.push(issue.kind.name());
+            }
+        }
+
+        // JSON strings are valid TOML basic strings
+        let quote = |s: &str| serde_json::Value::from(s).to_string();
+        let mut file = format!(
+            "# Accepts the {} current issues in this flake.lock. Fill in the reasons, and remove pins\n\
+             # as their issues are fixed.\n",
+            issues.len()
+        );
+        for (input, input_kinds) in kinds {
+            let pin = self.0.get(input);
+            file.push_str(&format!("\n[pins.{}]\n", quote(input)));
+            if let Some(expires) = pin.and_then(|pin| pin.expires) {
+                file.push_str(&format!("expires = \"{expires}\"\n"));
+            }
+            let input_kinds: Vec<String> = input_kinds.into_iter().map(quote).collect();
+            let reason = pin.and_then(|pin| pin.reason.as_deref()).unwrap_or("TODO");
+            file.push_str(&format!(
+                "kinds = [{}]\nreason = {}\n",
+                input_kinds.join(", "),
+                quote(reason)
+            ));
+        }
+        file
+    }
+}
+
+impl FromStr for Pins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: PinsFile = toml::from_str(s).map_err(|e| e.to_string())?;
+
+        let mut pins = BTreeMap::new();
+        for (input, entry) in file.pins {
+            let expires = entry
+                .expires
+                .map(|date| {
+                    NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+                        format!("the pin for {input:?} expires on {date:?}, which isn't a YYYY-MM-DD date")
+                    })
+                })
+                .transpose()?;
+            let kinds = entry
+                .kinds
+                .unwrap_or_else(|| vec![String::from("outdated")]);
+            if let Some(kind) = kinds
+                .iter()
+                .find(|kind| !ISSUE_KINDS.contains(&kind.as_str()))
+            {
+                return Err(format!(
+                    "the pin for {input:?} accepts {kind:?}, which isn't a kind of issue"
+                ));
+            }
+            pins.insert(
+                input,
+                Pin {
+                    expires,
+                    kinds,
+                    reason: entry.reason,
+                },
+            );
+        }
+
+        Ok(Self(pins))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use crate::issue::{Issue, IssueKind, NonUpstream, Outdated};
+
+    use super::Pins;
+
+    #[test]
+    fn active_and_expired_pins() {
+        let pins: Pins = r#"
+            [pins.nixpkgs]
+            reason = "Known-good build"
+            expires = "2025-06-30"
+
+            [pins."home-manager/nixpkgs"]
+        "#
+        .parse()
+        .unwrap();
+
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        // The pin applies through its expiry date
+        assert!(pins.exempts("nixpkgs", "outdated", date("2025-01-01")));
+        assert!(pins.exempts("nixpkgs", "outdated", date("2025-06-30")));
+        assert!(!pins.exempts("nixpkgs", "outdated", date("2025-07-01")));
+
+        // Pins without an expiry date never expire
+        assert!(pins.exempts("home-manager/nixpkgs", "outdated", date("2099-01-01")));
+
+        assert!(!pins.exempts("nixpkgs-unstable", "outdated", date("2025-01-01")));
+
+        // Pins only accept the outdated check's issues unless they list other kinds
+        assert!(!pins.exempts("nixpkgs", "non-upstream", date("2025-01-01")));
+    }
+
+    #[test]
+    fn generated_pins() {
+        let issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("someone"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("home-manager/nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ];
+        let generated = Pins::default().generate(&issues);
+        assert!(generated.contains(
+            "[pins.\"nixpkgs\"]\nkinds = [\"outdated\", \"non-upstream\"]\nreason = \"TODO\"\n"
+        ));
+
+        // The generated file accepts every issue, and nothing else
+        let pins: Pins = generated.parse().unwrap();
+        let today = NaiveDate::parse_from_str("2025-01-01", "%Y-%m-%d").unwrap();
+        for issue in &issues {
+            assert!(pins.exempts(&issue.input, issue.kind.name(), today));
+        }
+        assert!(!pins.exempts("home-manager/nixpkgs", "non-upstream", today));
+        assert!(!pins.exempts("nixpkgs-unstable", "outdated", today));
+
+        // Regenerating keeps the existing pins, with their expiry dates and reasons, even though
+        // their issues are no longer reported
+        let existing: Pins = r#"
+            [pins.nixpkgs]
+            reason = "Known-good build"
+            expires = "2025-06-30"
+
+            [pins.nixpkgs-legacy]
+            reason = "The last revision that builds our kernel"
+        "#
+        .parse()
+        .unwrap();
+        let generated = existing.generate(&issues);
+        assert!(generated.contains(
+            "[pins.\"nixpkgs\"]\nexpires = \"2025-06-30\"\n\
+             kinds = [\"outdated\", \"non-upstream\"]\nreason = \"Known-good build\"\n"
+        ));
+        assert!(generated.contains(
+            "[pins.\"nixpkgs-legacy\"]\nkinds = [\"outdated\"]\n\
+             reason = \"The last revision that builds our kernel\"\n"
+        ));
+        let pins: Pins = generated.parse().unwrap();
+        assert!(pins.exempts("nixpkgs-legacy", "outdated", today));
+        assert!(pins.exempts("nixpkgs", "non-upstream", today));
+        let expired = NaiveDate::parse_from_str("2025-07-01", "%Y-%m-%d").unwrap();
+        assert!(!pins.exempts("nixpkgs", "non-upstream", expired));
+    }
+
+    #[test]
+    fn invalid_pins() {
+        assert!(r#"[pins.nixpkgs]
+expires = "next year""#
+            .parse::<Pins>()
+            .is_err());
+        assert!(r#"[pins.nixpkgs]
+until = "2025-06-30""#
+            .parse::<Pins>()
+            .is_err());
+        assert!(r#"[pins.nixpkgs]
+kinds = ["old"]"#
+            .parse::<Pins>()
+            .is_err());
+        assert_eq!("".parse::<Pins>(), Ok(Pins::default()));
+    }
+}
@@ -0,0 +1,153 @@
+//! A bounded pool of threads for independent tasks, like reading the lockfiles that match
+//! `--flake-lock-glob`, so that `--max-parallelism` caps how much runs at once.
+
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+/// The parallelism without `--max-parallelism`: the number of CPUs, or 1 if that's unknown.
+pub(crate) fn default_parallelism() -> NonZeroUsize {
+    std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Run `task` on each of `items` in the background, with at most `max_parallelism` tasks running
+/// at once. The results come in the order of `items`, each as soon as it (and every result before
+/// it) is ready, so that they can be reported while later tasks are still running.
+pub(crate) fn map_bounded<T, R, F>(
+    items: Vec<T>,
+    max_parallelism: NonZeroUsize,
+    task: F,
+) -> Results<R>
+where
+    T: Send + Sync + 'static,
+    R: Send + 'static,
+    F: Fn(&T) -> R + Send + Sync + 'static,
+{
+    let len = items.len();
+    let (sender, receiver) = mpsc::channel();
+
+    // Each worker takes the next item that no other worker has taken yet
+    let shared = Arc::new((items, task, AtomicUsize::new(0)));
+    for _ in 0..max_parallelism.get().min(len) {
+        let shared = Arc::clone(&shared);
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            let (items, task, next) = &*shared;
+            loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                // Once the results are dropped, nobody is waiting for the rest
+                if sender.send((index, task(item))).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    // Only the workers can send, so that a panicking task doesn't leave the results waiting
+    drop(sender);
+
+    Results {
+        receiver,
+        pending: BTreeMap::new(),
+        next: 0,
+        len,
+    }
+}
+
+/// The results of [map_bounded], in the order of the items they're for.
+pub(crate) struct Results<R> {
+    receiver: Receiver<(usize, R)>,
+    /// Results that are ready before the ones for earlier items.
+    pending: BTreeMap<usize, R>,
+    next: usize,
+    len: usize,
+}
+
+impl<R> Iterator for Results<R> {
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        if self.next == self.len {
+            return None;
+        }
+
+        while !self.pending.contains_key(&self.next) {
+            let (index, result) = self.receiver.recv().expect("a task panicked");
+            self.pending.insert(index, result);
+        }
+        let result = self.pending.remove(&self.next);
+        self.next += 1;
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::map_bounded;
+
+    #[test]
+    fn at_most_max_parallelism_tasks_at_once() {
+        let items: Vec<usize> = (0..12).collect();
+
+        for max_parallelism in [1, 3, 20] {
+            let running = Arc::new(AtomicUsize::new(0));
+            let most_running = Arc::new(AtomicUsize::new(0));
+            let results: Vec<usize> = map_bounded(
+                items.clone(),
+                NonZeroUsize::new(max_parallelism).unwrap(),
+                {
+                    let running = Arc::clone(&running);
+                    let most_running = Arc::clone(&most_running);
+                    move |item| {
+                        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        most_running.fetch_max(now_running, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(20));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        item * 2
+                    }
+                },
+            )
+            .collect();
+
+            // The results are in order, however the tasks were scheduled
+            assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+            let most_running = most_running.load(Ordering::SeqCst);
+            assert!(
+                most_running <= max_parallelism.min(items.len()),
+                "{most_running} tasks ran at once with a maximum of {max_parallelism}"
+            );
+        }
+        assert_eq!(
+            map_bounded(vec![], NonZeroUsize::MIN, |i: &usize| *i).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn results_come_before_slow_tasks_finish() {
+        // The second task is a slow one that only finishes once it's told to
+        let (finish, finished) = mpsc::channel::<()>();
+        let finished = Arc::new(Mutex::new(finished));
+        let mut results = map_bounded(vec![0, 1], NonZeroUsize::new(2).unwrap(), move |item| {
+            if *item == 1 {
+                finished.lock().unwrap().recv().unwrap();
+            }
+            *item
+        });
+
+        assert_eq!(results.next(), Some(0));
+        finish.send(()).unwrap();
+        assert_eq!(results.next(), Some(1));
+        assert_eq!(results.next(), None);
+    }
+}
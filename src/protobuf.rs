@@ -0,0 +1,97 @@
+//! A Protocol Buffers representation of [CheckReport]s that mirrors `proto/check_report.proto`.
+
+use crate::report::CheckReport;
+
+use prost::Message;
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct ProtoCheckReport {
+    #[prost(string, tag = "1")]
+    pub lock_hash: String,
+    #[prost(message, repeated, tag = "2")]
+    pub issues: Vec<ProtoIssue>,
+    #[prost(uint64, tag = "3")]
+    pub num_inputs: u64,
+    #[prost(uint64, tag = "4")]
+    pub total_inputs: u64,
+    #[prost(string, tag = "5")]
+    pub lock_blob: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct ProtoIssue {
+    #[prost(string, tag = "1")]
+    pub input: String,
+    #[prost(string, tag = "2")]
+    pub kind: String,
+    #[prost(string, tag = "3")]
+    pub detail: String,
+}
+
+impl From<&CheckReport> for ProtoCheckReport {
+    fn from(report: &CheckReport) -> Self {
+        Self {
+            lock_hash: report.lock_hash.clone(),
+            issues: report
+                .issues
+                .iter()
+                .map(|issue| ProtoIssue {
+                    input: issue.input.clone(),
+                    kind: issue.kind.name().to_string(),
+                    detail: issue.kind.detail(),
+                })
+                .collect(),
+            num_inputs: report.num_inputs as u64,
+            total_inputs: report.total_inputs as u64,
+            lock_blob: report.lock_blob.clone(),
+        }
+    }
+}
+
+pub(crate) fn encode(report: &CheckReport) -> Vec<u8> {
+    ProtoCheckReport::from(report).encode_to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use prost::Message;
+
+    use crate::issue::{Disallowed, Issue, IssueKind};
+    use crate::report::CheckReport;
+
+    use super::{encode, ProtoCheckReport, ProtoIssue};
+
+    #[test]
+    fn round_trip() {
+        let report = CheckReport::new(
+            b"{}",
+            vec![Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            }],
+            1,
+            3,
+        );
+
+        let decoded = ProtoCheckReport::decode(encode(&report).as_slice()).unwrap();
+
+        assert_eq!(
+            decoded,
+            ProtoCheckReport {
+                lock_hash: report.lock_hash.clone(),
+                issues: vec![ProtoIssue {
+                    input: String::from("nixpkgs"),
+                    kind: String::from("disallowed"),
+                    detail: String::from("this-should-fail"),
+                }],
+                num_inputs: 1,
+                total_inputs: 3,
+                lock_blob: report.lock_blob.clone(),
+            }
+        );
+    }
+}
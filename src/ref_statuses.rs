@@ -1,10 +1,58 @@
 use crate::error::FlakeCheckerError;
 
+use chrono::NaiveDate;
+use clap::Subcommand;
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
 use serde::Deserialize;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::LazyLock;
+use std::time::Duration;
 
-const ALLOWED_REFS_URL: &str = "https://prometheus.nixos.org/api/v1/query?query=channel_revision";
+/// The Prometheus instance that tracks the statuses of Nixpkgs channels.
+pub const REF_STATUS_URL: &str = "https://prometheus.nixos.org";
+
+/// The PromQL query for the channel statuses, whose results have `channel` and `status` labels.
+pub const REF_STATUS_QUERY: &str = "channel_revision";
+
+/// The endoflife.date API endpoint for the NixOS releases and their end-of-life dates.
+pub const EOL_DATES_URL: &str = "https://endoflife.date/api/nixos.json";
+
+/// The end-of-life dates of NixOS releases built into Flake Checker (`eol-dates.json`), keyed by
+/// release (e.g. `24.11`). They're parsed once, the first time that they're needed.
+pub static EOL_DATES: LazyLock<HashMap<String, NaiveDate>> = LazyLock::new(|| {
+    parse_eol_dates(
+        serde_json::from_str(include_str!("../eol-dates.json"))
+            .expect("eol-dates.json should be a map of releases to dates"),
+    )
+    .expect("eol-dates.json should have YYYY-MM-DD dates")
+});
+
+/// Subcommands for the Nixpkgs ref statuses that determine which Git refs are supported.
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+pub enum RefsCommand {
+    /// Check that the ref statuses built into Flake Checker are up to date.
+    Check,
+    /// Fetch the current ref statuses and print them in the format of `ref-statuses.json`.
+    Fetch,
+    /// Fetch the current ref statuses and write them to a `ref-statuses.json` file.
+    Update {
+        /// The file to write the ref statuses to.
+        #[arg(long, default_value = "ref-statuses.json")]
+        out: PathBuf,
+    },
+    /// Fetch the current end-of-life dates of NixOS releases and write them to an
+    /// `eol-dates.json` file.
+    UpdateEolDates {
+        /// The file to write the end-of-life dates to.
+        #[arg(long, default_value = "eol-dates.json")]
+        out: PathBuf,
+    },
+}
 
 #[derive(Deserialize)]
 struct Response {
@@ -27,21 +75,805 @@ struct Metric {
     status: String,
 }
 
-pub(crate) fn check_ref_statuses(
-    ref_statuses: HashMap<String, String>,
-) -> Result<bool, FlakeCheckerError> {
-    Ok(fetch_ref_statuses()? == ref_statuses)
+// A release in the endoflife.date API, whose `eol` is a date or, if it has none, `false`.
+#[derive(Deserialize)]
+struct Release {
+    cycle: String,
+    eol: serde_json::Value,
+}
+
+/// Where to get the statuses of Nixpkgs refs from.
+#[derive(Clone, Copy)]
+pub enum RefStatusSource<'a> {
+    /// The `ref-statuses.json` built into Flake Checker.
+    Embedded,
+    /// A file in the format of `ref-statuses.json`.
+    File(&'a Path),
+    /// The current statuses, fetched when resolving.
+    Live(&'a dyn RefStatusFetcher),
+    /// The statuses from another source, overridden by an organization's channel policy (see
+    /// [read_channels_file]).
+    WithChannelsFile(&'a RefStatusSource<'a>, &'a Path),
+}
+
+impl RefStatusSource<'_> {
+    /// The statuses of Nixpkgs refs, keyed by ref (e.g. `nixos-24.11`).
+    pub fn ref_statuses(&self) -> Result<HashMap<String, String>, FlakeCheckerError> {
+        match self {
+            Self::Embedded => Ok(serde_json::from_str(include_str!("../ref-statuses.json"))?),
+            Self::File(path) => Ok(serde_json::from_str(&read_to_string(path)?)?),
+            Self::Live(fetcher) => fetcher.fetch(),
+            Self::WithChannelsFile(source, channels_file) => {
+                let mut ref_statuses = source.ref_statuses()?;
+                ref_statuses.extend(read_channels_file(channels_file)?);
+                Ok(ref_statuses)
+            }
+        }
+    }
+}
+
+/// The refs that inputs are allowed to use according to the ref statuses from `source`, sorted.
+pub fn resolve_allowed_refs(source: RefStatusSource) -> Result<Vec<String>, FlakeCheckerError> {
+    Ok(supported_refs(source.ref_statuses()?))
+}
+
+/// The refs whose status makes them supported, i.e. all but unmaintained and beta refs, sorted.
+pub fn supported_refs(ref_statuses: HashMap<String, String>) -> Vec<String> {
+    let mut return_value: Vec<String> = ref_statuses
+        .iter()
+        .filter_map(|(channel, status)| {
+            if *status != "unmaintained" && *status != "beta" {
+                Some(channel.clone())
+            } else {
+                None
+            }
+        })
+        .collect();
+    return_value.sort();
+    return_value
+}
+
+/// The statuses that a Nixpkgs ref can have.
+const STATUSES: [&str; 5] = ["rolling", "beta", "stable", "deprecated", "unmaintained"];
+
+/// Read an organization's channel policy from a `--channels-file`, which maps refs to statuses
+/// like `ref-statuses.json` does. Files with a `.toml` extension are read as TOML, others as JSON.
+/// The policy's statuses override the embedded ones, e.g. `"nixos-24.11" = "unmaintained"` makes a
+/// stable channel end-of-life ahead of upstream.
+pub fn read_channels_file(path: &Path) -> Result<HashMap<String, String>, FlakeCheckerError> {
+    let invalid = |e: String| FlakeCheckerError::ChannelsFile(format!("{}: {e}", path.display()));
+    let contents = read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    let policy: HashMap<String, String> = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+    };
+
+    let mut unknown: Vec<_> = policy
+        .iter()
+        .filter(|(_, status)| !STATUSES.contains(&status.as_str()))
+        .collect();
+    unknown.sort();
+    if let Some((channel, status)) = unknown.first() {
+        return Err(invalid(format!(
+            "{channel} has status {status:?}, which isn't one of {}",
+            STATUSES.join(", ")
+        )));
+    }
+
+    Ok(policy)
+}
+
+/// The end-of-life dates to use for `--warn-eol`: the built-in ones (see [EOL_DATES]), overridden
+/// by those in the `eol_dates_file`, if there is one (see [read_eol_dates_file]).
+pub fn eol_dates(
+    eol_dates_file: Option<&Path>,
+) -> Result<HashMap<String, NaiveDate>, FlakeCheckerError> {
+    let mut eol_dates = EOL_DATES.clone();
+    if let Some(path) = eol_dates_file {
+        eol_dates.extend(read_eol_dates_file(path)?);
+    }
+    Ok(eol_dates)
 }
 
-pub(crate) fn fetch_ref_statuses() -> Result<HashMap<String, String>, FlakeCheckerError> {
-    let mut officially_supported: HashMap<String, String> =
-        reqwest::blocking::get(ALLOWED_REFS_URL)?
-            .json::<Response>()?
+/// Read end-of-life dates from an `--eol-dates-file`, which maps NixOS releases to `YYYY-MM-DD`
+/// dates like `eol-dates.json` does. Files with a `.toml` extension are read as TOML, others as
+/// JSON. The file's dates override the built-in ones, e.g. `"24.11" = "2025-03-31"` for an
+/// organization that moves off 24.11 ahead of upstream, or add releases that are newer than
+/// Flake Checker.
+pub fn read_eol_dates_file(path: &Path) -> Result<HashMap<String, NaiveDate>, FlakeCheckerError> {
+    let invalid = |e: String| FlakeCheckerError::EolDatesFile(format!("{}: {e}", path.display()));
+    let contents = read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    let dates: HashMap<String, String> = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+    };
+    parse_eol_dates(dates).map_err(invalid)
+}
+
+// Parse the `YYYY-MM-DD` dates of `dates`, failing for the first release (in order) with an
+// invalid date.
+fn parse_eol_dates(dates: HashMap<String, String>) -> Result<HashMap<String, NaiveDate>, String> {
+    let mut dates: Vec<(String, String)> = dates.into_iter().collect();
+    dates.sort();
+    dates
+        .into_iter()
+        .map(
+            |(release, date)| match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                Ok(parsed) => Ok((release, parsed)),
+                Err(_) => Err(format!(
+                    "{release} has the date {date:?}, which isn't YYYY-MM-DD"
+                )),
+            },
+        )
+        .collect()
+}
+
+/// A source of the current statuses of Nixpkgs refs.
+pub trait RefStatusFetcher {
+    fn fetch(&self) -> Result<HashMap<String, String>, FlakeCheckerError>;
+}
+
+/// Fetches ref statuses from a Prometheus-compatible server, like the Prometheus instance that
+/// tracks Nixpkgs channels.
+pub struct PrometheusFetcher {
+    /// The URL of the server's instant query endpoint.
+    url: String,
+    /// The PromQL query whose results are the ref statuses.
+    query: String,
+    client: Client,
+    timeout: Duration,
+    /// The `Authorization` header for servers that require it, which is marked as sensitive so
+    /// that it's never logged.
+    auth: Option<HeaderValue>,
+}
+
+impl PrometheusFetcher {
+    /// A fetcher that sends `query` to the server at `url` (e.g. `https://prometheus.nixos.org`).
+    pub fn new(client: Client, url: &str, query: &str, timeout: Duration) -> Self {
+        Self {
+            url: format!("{}/api/v1/query", url.trim_end_matches('/')),
+            query: query.to_string(),
+            client,
+            timeout,
+            auth: None,
+        }
+    }
+
+    /// Send `auth` as the `Authorization` header, e.g. `Bearer <token>` or `Basic <credentials>`,
+    /// for mirrors whose metrics endpoint is protected.
+    pub fn with_auth(mut self, auth: &str) -> Result<Self, FlakeCheckerError> {
+        // The error leaves out the value, which is a secret
+        let mut auth = HeaderValue::from_str(auth).map_err(|_| {
+            FlakeCheckerError::Settings(String::from(
+                "--ref-status-auth isn't a valid Authorization header value",
+            ))
+        })?;
+        auth.set_sensitive(true);
+        self.auth = Some(auth);
+        Ok(self)
+    }
+}
+
+impl RefStatusFetcher for PrometheusFetcher {
+    fn fetch(&self) -> Result<HashMap<String, String>, FlakeCheckerError> {
+        let mut request = self
+            .client
+            .get(&self.url)
+            .query(&[("query", &self.query)])
+            .timeout(self.timeout);
+        if let Some(auth) = &self.auth {
+            request = request.header(AUTHORIZATION, auth.clone());
+        }
+
+        let officially_supported: HashMap<String, String> = request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json::<Response>())
+            .map_err(|e| {
+                if e.is_timeout() {
+                    FlakeCheckerError::RefStatusTimeout(self.timeout)
+                } else {
+                    FlakeCheckerError::Http(e)
+                }
+            })?
             .data
             .result
             .iter()
             .map(|res| (res.metric.channel.clone(), res.metric.status.clone()))
             .collect();
 
-    Ok(officially_supported)
+        Ok(officially_supported)
+    }
+}
+
+/// A source of the current end-of-life dates of NixOS releases, as `YYYY-MM-DD` dates keyed by
+/// release.
+pub trait EolDateFetcher {
+    fn fetch_eol_dates(&self) -> Result<HashMap<String, String>, FlakeCheckerError>;
+}
+
+/// Fetches end-of-life dates from the endoflife.date API (see [EOL_DATES_URL]).
+pub struct EndOfLifeFetcher {
+    url: String,
+    client: Client,
+    timeout: Duration,
+}
+
+impl EndOfLifeFetcher {
+    /// A fetcher that gets the NixOS releases from `url`.
+    pub fn new(client: Client, url: &str, timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            client,
+            timeout,
+        }
+    }
+}
+
+impl EolDateFetcher for EndOfLifeFetcher {
+    fn fetch_eol_dates(&self) -> Result<HashMap<String, String>, FlakeCheckerError> {
+        let releases: Vec<Release> = self
+            .client
+            .get(&self.url)
+            .timeout(self.timeout)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())?;
+
+        // Releases without an end of life, like unstable, have no date
+        Ok(releases
+            .into_iter()
+            .filter_map(|release| match release.eol {
+                serde_json::Value::String(date) => Some((release.cycle, date)),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+pub fn run(
+    command: RefsCommand,
+    ref_statuses: HashMap<String, String>,
+    fetcher: &dyn RefStatusFetcher,
+    eol_fetcher: &dyn EolDateFetcher,
+) -> Result<ExitCode, FlakeCheckerError> {
+    match command {
+        RefsCommand::Check => match check_ref_statuses(fetcher, &ref_statuses) {
+            Ok(true) => {
+                println!("The reference statuses sets are up to date.");
+                Ok(ExitCode::SUCCESS)
+            }
+            Ok(false) => {
+                println!("The reference statuses sets are NOT up to date. Make sure to update.");
+                Ok(ExitCode::FAILURE)
+            }
+            Err(e) => {
+                println!("Error checking ref statuses: {}", e);
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        RefsCommand::Fetch => match fetcher.fetch() {
+            Ok(fetched) => {
+                println!("{}", ref_statuses_json(&fetched)?);
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                eprintln!("Error fetching ref statuses: {}", e);
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        RefsCommand::Update { out } => match update_ref_statuses(fetcher, &out) {
+            Ok(()) => {
+                println!("Wrote the current ref statuses to {}", out.display());
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                eprintln!(
+                    "Error updating ref statuses (left {} unchanged): {}",
+                    out.display(),
+                    e
+                );
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        RefsCommand::UpdateEolDates { out } => match update_eol_dates(eol_fetcher, &out) {
+            Ok(()) => {
+                println!("Wrote the current end-of-life dates to {}", out.display());
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                eprintln!(
+                    "Error updating end-of-life dates (left {} unchanged): {}",
+                    out.display(),
+                    e
+                );
+                Ok(ExitCode::FAILURE)
+            }
+        },
+    }
+}
+
+/// Whether the given ref statuses match the current ones.
+fn check_ref_statuses(
+    fetcher: &dyn RefStatusFetcher,
+    ref_statuses: &HashMap<String, String>,
+) -> Result<bool, FlakeCheckerError> {
+    Ok(&fetcher.fetch()? == ref_statuses)
+}
+
+/// Fetch the current ref statuses and write them to `out`. Nothing is written if fetching fails.
+fn update_ref_statuses(
+    fetcher: &dyn RefStatusFetcher,
+    out: &Path,
+) -> Result<(), FlakeCheckerError> {
+    let fetched = fetcher.fetch()?;
+    write(out, format!("{}\n", ref_statuses_json(&fetched)?))?;
+    Ok(())
+}
+
+/// Fetch the current end-of-life dates and write them to `out` in the format of `eol-dates.json`.
+/// Nothing is written if fetching fails or any of the dates is invalid.
+fn update_eol_dates(fetcher: &dyn EolDateFetcher, out: &Path) -> Result<(), FlakeCheckerError> {
+    let fetched = fetcher.fetch_eol_dates()?;
+    parse_eol_dates(fetched.clone()).map_err(FlakeCheckerError::Invalid)?;
+    write(out, format!("{}\n", ref_statuses_json(&fetched)?))?;
+    Ok(())
+}
+
+/// Ref statuses as pretty-printed JSON with sorted keys, which is the format of the
+/// `ref-statuses.json` file built into Flake Checker.
+fn ref_statuses_json(ref_statuses: &HashMap<String, String>) -> Result<String, FlakeCheckerError> {
+    let sorted: BTreeMap<&String, &String> = ref_statuses.iter().collect();
+    Ok(serde_json::to_string_pretty(&sorted)?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs::{create_dir_all, read_to_string, remove_dir_all, remove_file, write};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    use reqwest::blocking::Client;
+
+    use crate::error::FlakeCheckerError;
+
+    use super::{
+        check_ref_statuses, eol_dates, read_channels_file, read_eol_dates_file, ref_statuses_json,
+        resolve_allowed_refs, update_eol_dates, update_ref_statuses, EndOfLifeFetcher,
+        EolDateFetcher, PrometheusFetcher, RefStatusFetcher, RefStatusSource, EOL_DATES,
+        REF_STATUS_QUERY,
+    };
+
+    fn fetcher(url: String) -> PrometheusFetcher {
+        PrometheusFetcher::new(
+            Client::new(),
+            &url,
+            REF_STATUS_QUERY,
+            Duration::from_secs(10),
+        )
+    }
+
+    fn embedded() -> HashMap<String, String> {
+        serde_json::from_str(include_str!("../ref-statuses.json")).unwrap()
+    }
+
+    // A fetcher that returns canned ref statuses (or fails if there are none)
+    struct FakeFetcher(Option<HashMap<String, String>>);
+
+    impl RefStatusFetcher for FakeFetcher {
+        fn fetch(&self) -> Result<HashMap<String, String>, FlakeCheckerError> {
+            self.0
+                .clone()
+                .ok_or_else(|| FlakeCheckerError::Invalid(String::from("fetch failed")))
+        }
+    }
+
+    // Serve a single HTTP response from a local port in place of a Prometheus server and return
+    // the server's URL along with a handle that yields the request line and headers once it's
+    // served.
+    fn serve_once(status: &'static str, body: &'static str) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            // Read the request up to the blank line that ends its headers
+            let mut reader = BufReader::new(&stream);
+            let mut head = vec![];
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                head.push(line.trim_end().to_string());
+                line.clear();
+            }
+
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            head
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn update_writes_ref_statuses_file() {
+        let out = std::env::temp_dir().join(format!(
+            "flake-checker-ref-statuses-{}.json",
+            std::process::id()
+        ));
+
+        let (url, request) = serve_once(
+            "200 OK",
+            r#"{"status":"success","data":{"resultType":"vector","result":[
+                {"metric":{"channel":"nixos-unstable","status":"rolling"},"value":[1,"1"]},
+                {"metric":{"channel":"nixos-24.11","status":"stable"},"value":[1,"1"]}
+            ]}}"#,
+        );
+        update_ref_statuses(&fetcher(url), &out).unwrap();
+        assert_eq!(
+            request.join().unwrap()[0],
+            "GET /api/v1/query?query=channel_revision HTTP/1.1"
+        );
+        assert_eq!(
+            read_to_string(&out).unwrap(),
+            "{\n  \"nixos-24.11\": \"stable\",\n  \"nixos-unstable\": \"rolling\"\n}\n"
+        );
+
+        // A failed fetch leaves the existing file alone
+        write(&out, "{}\n").unwrap();
+        let (url, _) = serve_once("503 Service Unavailable", "unavailable");
+        assert!(update_ref_statuses(&fetcher(url), &out).is_err());
+        assert_eq!(read_to_string(&out).unwrap(), "{}\n");
+
+        remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn fetch_with_custom_query() {
+        let (url, request) = serve_once(
+            "200 OK",
+            r#"{"status":"success","data":{"resultType":"vector","result":[
+                {"metric":{"channel":"nixos-24.11","status":"stable"},"value":[1,"1"]}
+            ]}}"#,
+        );
+
+        // A mirror with its own metric names, whose URL has a trailing slash
+        let fetcher = PrometheusFetcher::new(
+            Client::new(),
+            &format!("{url}/"),
+            r#"mirror_channel_status{job="nixpkgs"}"#,
+            Duration::from_secs(10),
+        );
+        assert_eq!(
+            fetcher.fetch().unwrap(),
+            HashMap::from([(String::from("nixos-24.11"), String::from("stable"))])
+        );
+        assert_eq!(
+            request.join().unwrap()[0],
+            "GET /api/v1/query?query=mirror_channel_status%7Bjob%3D%22nixpkgs%22%7D HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn fetch_with_auth() {
+        let body = r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#;
+
+        let (url, request) = serve_once("200 OK", body);
+        fetcher(url)
+            .with_auth("Bearer s3cr3t")
+            .unwrap()
+            .fetch()
+            .unwrap();
+        assert!(request
+            .join()
+            .unwrap()
+            .iter()
+            .any(|header| header.eq_ignore_ascii_case("authorization: Bearer s3cr3t")));
+
+        // Without it, no header is sent
+        let (url, request) = serve_once("200 OK", body);
+        fetcher(url).fetch().unwrap();
+        assert!(!request
+            .join()
+            .unwrap()
+            .iter()
+            .any(|header| header.to_lowercase().starts_with("authorization:")));
+
+        // An invalid value is rejected without echoing it
+        let err = fetcher(String::from("http://127.0.0.1"))
+            .with_auth("Bearer s3cr3t\n")
+            .err()
+            .unwrap();
+        assert!(!err.to_string().contains("s3cr3t"), "{err}");
+    }
+
+    #[test]
+    fn fetch_times_out() {
+        // A server that accepts the connection but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_secs(5));
+        });
+
+        let fetcher = PrometheusFetcher {
+            timeout: Duration::from_millis(200),
+            ..fetcher(url)
+        };
+        let err = fetcher.fetch().unwrap_err();
+        assert!(matches!(err, FlakeCheckerError::RefStatusTimeout(_)));
+        assert_eq!(
+            err.to_string(),
+            "timed out after 200ms fetching ref statuses"
+        );
+    }
+
+    #[test]
+    fn check_compares_with_embedded() {
+        let ref_statuses = embedded();
+        assert!(check_ref_statuses(&FakeFetcher(Some(embedded())), &ref_statuses).unwrap());
+
+        let mut fetched = embedded();
+        fetched.insert(String::from("nixos-25.05"), String::from("beta"));
+        assert!(!check_ref_statuses(&FakeFetcher(Some(fetched)), &ref_statuses).unwrap());
+
+        let mut fetched = embedded();
+        fetched.insert(String::from("nixos-24.11"), String::from("deprecated"));
+        assert!(!check_ref_statuses(&FakeFetcher(Some(fetched)), &ref_statuses).unwrap());
+
+        assert!(check_ref_statuses(&FakeFetcher(None), &ref_statuses).is_err());
+    }
+
+    #[test]
+    fn update_with_fake_fetcher() {
+        let out = std::env::temp_dir().join(format!(
+            "flake-checker-fake-ref-statuses-{}.json",
+            std::process::id()
+        ));
+
+        update_ref_statuses(&FakeFetcher(Some(embedded())), &out).unwrap();
+        assert_eq!(
+            read_to_string(&out).unwrap(),
+            include_str!("../ref-statuses.json")
+        );
+
+        // A failed fetch leaves the existing file alone
+        assert!(update_ref_statuses(&FakeFetcher(None), &out).is_err());
+        assert_eq!(
+            read_to_string(&out).unwrap(),
+            include_str!("../ref-statuses.json")
+        );
+
+        remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn fetch_output_matches_embedded_format() {
+        // Printing the output of `refs fetch` (with a trailing newline) reproduces the file
+        let json = ref_statuses_json(&embedded()).unwrap();
+        assert_eq!(format!("{json}\n"), include_str!("../ref-statuses.json"));
+    }
+
+    #[test]
+    fn update_in_directory_with_spaces() {
+        let dir =
+            std::env::temp_dir().join(format!("flake checker refs {}/my repo", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let out = dir.join("ref statuses.json");
+
+        let (url, _) = serve_once(
+            "200 OK",
+            r#"{"status":"success","data":{"resultType":"vector","result":[
+                {"metric":{"channel":"nixos-unstable","status":"rolling"},"value":[1,"1"]}
+            ]}}"#,
+        );
+        update_ref_statuses(&fetcher(url), &out).unwrap();
+        assert_eq!(
+            read_to_string(&out).unwrap(),
+            "{\n  \"nixos-unstable\": \"rolling\"\n}\n"
+        );
+
+        remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn allowed_refs_from_each_source() {
+        let embedded_refs = resolve_allowed_refs(RefStatusSource::Embedded).unwrap();
+        assert!(embedded_refs.contains(&String::from("nixos-unstable")));
+        assert!(embedded_refs.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let ref_statuses = HashMap::from([
+            (String::from("nixos-unstable"), String::from("rolling")),
+            (String::from("nixos-24.11"), String::from("stable")),
+            (String::from("nixos-24.05"), String::from("deprecated")),
+            (String::from("nixos-23.11"), String::from("unmaintained")),
+            (String::from("nixos-25.05"), String::from("beta")),
+        ]);
+        let expected = vec![
+            String::from("nixos-24.05"),
+            String::from("nixos-24.11"),
+            String::from("nixos-unstable"),
+        ];
+
+        let out = std::env::temp_dir().join(format!(
+            "flake-checker-allowed-refs-{}.json",
+            std::process::id()
+        ));
+        write(&out, ref_statuses_json(&ref_statuses).unwrap()).unwrap();
+        assert_eq!(
+            resolve_allowed_refs(RefStatusSource::File(&out)).unwrap(),
+            expected
+        );
+        remove_file(&out).unwrap();
+        assert!(resolve_allowed_refs(RefStatusSource::File(&out)).is_err());
+
+        let fetcher = FakeFetcher(Some(ref_statuses));
+        assert_eq!(
+            resolve_allowed_refs(RefStatusSource::Live(&fetcher)).unwrap(),
+            expected
+        );
+        assert!(resolve_allowed_refs(RefStatusSource::Live(&FakeFetcher(None))).is_err());
+
+        // A channel policy overrides the statuses from any other source
+        write(&out, r#"{"nixos-24.05": "unmaintained"}"#).unwrap();
+        let live = RefStatusSource::Live(&fetcher);
+        assert_eq!(
+            resolve_allowed_refs(RefStatusSource::WithChannelsFile(&live, &out)).unwrap(),
+            expected[1..]
+        );
+        write(&out, r#"{"nixos-24.05": "banned"}"#).unwrap();
+        assert!(resolve_allowed_refs(RefStatusSource::WithChannelsFile(&live, &out)).is_err());
+        remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn stricter_channel_policy() {
+        let dir =
+            std::env::temp_dir().join(format!("flake-checker-channels-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let json = dir.join("channels.json");
+        let toml = dir.join("channels.toml");
+        write(
+            &json,
+            r#"{"nixos-24.11": "unmaintained", "nixos-unstable": "beta"}"#,
+        )
+        .unwrap();
+        write(
+            &toml,
+            "\"nixos-24.11\" = \"unmaintained\"\n\"nixos-unstable\" = \"beta\"\n",
+        )
+        .unwrap();
+
+        for path in [&json, &toml] {
+            let source = RefStatusSource::WithChannelsFile(&RefStatusSource::Embedded, path);
+            let ref_statuses = source.ref_statuses().unwrap();
+            let allowed_refs = resolve_allowed_refs(source).unwrap();
+
+            // Channels that upstream supports are disallowed by the policy
+            assert!(!allowed_refs.contains(&String::from("nixos-24.11")));
+            assert!(!allowed_refs.contains(&String::from("nixos-unstable")));
+            assert_eq!(ref_statuses["nixos-24.11"], "unmaintained");
+            assert_eq!(ref_statuses["nixos-unstable"], "beta");
+            assert_eq!(ref_statuses.len(), embedded().len());
+
+            // Channels the policy doesn't mention keep their upstream status
+            assert!(allowed_refs.contains(&String::from("nixos-24.11-small")));
+            assert!(allowed_refs.contains(&String::from("nixpkgs-unstable")));
+        }
+
+        write(&json, r#"{"nixos-24.11": "banned"}"#).unwrap();
+        let err = read_channels_file(&json).unwrap_err();
+        assert!(matches!(err, FlakeCheckerError::ChannelsFile(_)));
+        assert!(err
+            .to_string()
+            .contains(r#"nixos-24.11 has status "banned""#));
+        write(&toml, "nixos-24.11 = [\"stable\"]\n").unwrap();
+        assert!(matches!(
+            read_channels_file(&toml),
+            Err(FlakeCheckerError::ChannelsFile(_))
+        ));
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn update_writes_eol_dates_file() {
+        let out = std::env::temp_dir().join(format!(
+            "flake-checker-eol-dates-{}.json",
+            std::process::id()
+        ));
+        let fetcher =
+            |url: String| EndOfLifeFetcher::new(Client::new(), &url, Duration::from_secs(10));
+
+        // Releases without an end of life are left out
+        let (url, request) = serve_once(
+            "200 OK",
+            r#"[
+                {"cycle":"25.11","releaseDate":"2025-11-30","eol":"2026-06-30"},
+                {"cycle":"25.05","releaseDate":"2025-05-23","eol":"2025-12-31"},
+                {"cycle":"unstable","eol":false}
+            ]"#,
+        );
+        update_eol_dates(&fetcher(url), &out).unwrap();
+        assert!(request.join().unwrap()[0].starts_with("GET / HTTP/1.1"));
+        assert_eq!(
+            read_to_string(&out).unwrap(),
+            "{\n  \"25.05\": \"2025-12-31\",\n  \"25.11\": \"2026-06-30\"\n}\n"
+        );
+
+        // A failed fetch, or one with invalid dates, leaves the existing file alone
+        write(&out, "{}\n").unwrap();
+        let (url, _) = serve_once("503 Service Unavailable", "unavailable");
+        assert!(update_eol_dates(&fetcher(url), &out).is_err());
+        let (url, _) = serve_once("200 OK", r#"[{"cycle":"25.11","eol":"soon"}]"#);
+        assert!(update_eol_dates(&fetcher(url), &out).is_err());
+        assert_eq!(read_to_string(&out).unwrap(), "{}\n");
+
+        remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn embedded_eol_dates_match_update_format() {
+        struct Embedded;
+        impl EolDateFetcher for Embedded {
+            fn fetch_eol_dates(&self) -> Result<HashMap<String, String>, FlakeCheckerError> {
+                Ok(serde_json::from_str(include_str!("../eol-dates.json")).unwrap())
+            }
+        }
+
+        let out = std::env::temp_dir().join(format!(
+            "flake-checker-embedded-eol-dates-{}.json",
+            std::process::id()
+        ));
+        update_eol_dates(&Embedded, &out).unwrap();
+        assert_eq!(
+            read_to_string(&out).unwrap(),
+            include_str!("../eol-dates.json")
+        );
+        remove_file(&out).unwrap();
+
+        assert!(EOL_DATES.contains_key("25.11"));
+    }
+
+    #[test]
+    fn eol_dates_file_overrides_embedded() {
+        let dir =
+            std::env::temp_dir().join(format!("flake-checker-eol-dates-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let json = dir.join("eol-dates.json");
+        let toml = dir.join("eol-dates.toml");
+        write(&json, r#"{"24.11": "2025-03-31", "99.05": "2099-12-31"}"#).unwrap();
+        write(
+            &toml,
+            "\"24.11\" = \"2025-03-31\"\n\"99.05\" = \"2099-12-31\"\n",
+        )
+        .unwrap();
+
+        for path in [&json, &toml] {
+            let dates = eol_dates(Some(path)).unwrap();
+            assert_eq!(dates["24.11"].to_string(), "2025-03-31");
+            assert_eq!(dates["99.05"].to_string(), "2099-12-31");
+            // Releases that the file doesn't mention keep their built-in dates
+            assert_eq!(dates["25.05"], EOL_DATES["25.05"]);
+        }
+        assert_eq!(eol_dates(None).unwrap(), *EOL_DATES);
+
+        write(&json, r#"{"24.11": "end of March"}"#).unwrap();
+        let err = read_eol_dates_file(&json).unwrap_err();
+        assert!(matches!(err, FlakeCheckerError::EolDatesFile(_)));
+        assert!(err
+            .to_string()
+            .contains("24.11 has the date \"end of March\""));
+
+        remove_dir_all(&dir).unwrap();
+    }
 }
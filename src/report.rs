@@ -0,0 +1,511 @@
+use crate::baseline::AgeChange;
+use crate::error::FlakeCheckerError;
+use crate::issue::{Issue, IssueKind};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// The results of checking a single `flake.lock`, keyed by a hash of the lockfile's contents.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct CheckReport {
+    pub lock_hash: String,
+    /// The Git blob ID of the `flake.lock` (see [lock_blob]), which stores and looks up results by
+    /// lockfile across branches and commits.
+    #[serde(default)]
+    pub lock_blob: String,
+    pub issues: Vec<Issue>,
+    /// The number of inputs that were selected for checking.
+    #[serde(default)]
+    pub num_inputs: usize,
+    /// The total number of unique inputs in the `flake.lock`, including transitive inputs.
+    #[serde(default)]
+    pub total_inputs: usize,
+    /// The `flake.lock`'s format version (if it could be read).
+    #[serde(default)]
+    pub lock_version: Option<usize>,
+    /// The supported refs that the checked inputs use, sorted (see [crate::flake::matched_refs]).
+    #[serde(default)]
+    pub matched_refs: Vec<String>,
+    /// The ages of the oldest and newest checked inputs (see [crate::flake::input_ages]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_ages: Option<InputAges>,
+    /// With `--baseline-age`, how the age of the oldest input changed since the last run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_change: Option<AgeChange>,
+    /// The checked inputs without any issues, with `--report-clean-inputs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clean_inputs: Option<Vec<CleanInput>>,
+    /// The checks that ran on each input, which clean inputs are derived from. They're cached
+    /// along with the report but aren't part of it.
+    #[serde(skip)]
+    pub checks_run: ChecksRun,
+}
+
+/// The checks that ran on each input (or node), keyed by the name that the check reports issues
+/// with, e.g. `nixpkgs` for the owner check but the node's key for the NAR hash check. A check
+/// doesn't run on inputs that it doesn't apply to, like the owner check on a tarball input, nor on
+/// the inputs that it didn't get to with `fail_fast`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct ChecksRun(BTreeMap<String, BTreeSet<String>>);
+
+impl ChecksRun {
+    /// Record that `check` (a name from [crate::checks::CHECKS]) ran on `input`.
+    pub(crate) fn record(&mut self, input: &str, check: &str) {
+        debug_assert!(
+            crate::checks::CHECKS.iter().any(|c| c.name == check),
+            "{check:?} isn't a check"
+        );
+        self.0
+            .entry(input.to_string())
+            .or_default()
+            .insert(check.to_string());
+    }
+
+    /// Whether `check` ran on `input`.
+    pub(crate) fn ran(&self, input: &str, check: &str) -> bool {
+        self.0
+            .get(input)
+            .is_some_and(|checks| checks.contains(check))
+    }
+}
+
+/// The span of ages of the checked inputs, in whole days, which shows at a glance how fresh a
+/// lockfile is.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct InputAges {
+    pub oldest_days: i64,
+    pub newest_days: i64,
+}
+
+/// An input that was checked and had no issues, along with the checks that it passed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct CleanInput {
+    pub input: String,
+    /// The names of the checks, as in the summary's list of checks performed.
+    pub checks: Vec<String>,
+}
+
+/// A checked input and the outcome of each check that applies to it, for `--explain`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct InputChecks {
+    pub input: String,
+    pub checks: Vec<CheckOutcome>,
+}
+
+/// Whether an input passed a check and, if it didn't, the details of the issues that the check
+/// reported.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct CheckOutcome {
+    /// The name of the check, as in [CleanInput::checks].
+    pub name: String,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+impl CheckReport {
+    pub(crate) fn new(
+        lock_contents: &[u8],
+        issues: Vec<Issue>,
+        num_inputs: usize,
+        total_inputs: usize,
+    ) -> Self {
+        Self {
+            lock_hash: lock_hash(lock_contents),
+            lock_blob: lock_blob(lock_contents),
+            issues,
+            num_inputs,
+            total_inputs,
+            lock_version: lock_version(lock_contents),
+            matched_refs: vec![],
+            input_ages: None,
+            age_change: None,
+            clean_inputs: None,
+            checks_run: ChecksRun::default(),
+        }
+    }
+
+    /// A copy of the report in which identifying issue details (owners, flake registry IDs, and
+    /// URLs) are replaced with stable hashes. The same name always redacts to the same hash, so
+    /// the structure of the issues is preserved.
+    pub(crate) fn redacted(&self) -> Self {
+        let issues = self
+            .issues
+            .iter()
+            .cloned()
+            .map(|mut issue| {
+                match &mut issue.kind {
+                    IssueKind::NonUpstream(non_upstream) => {
+                        non_upstream.owner = redact(&non_upstream.owner);
+                        non_upstream.repo = non_upstream.repo.as_deref().map(redact);
+                    }
+                    IssueKind::Violation(violation) => {
+                        violation.snapshot.owner = redact(&violation.snapshot.owner);
+                    }
+                    IssueKind::IndirectInput(indirect_input) => {
+                        indirect_input.id = redact(&indirect_input.id);
+                    }
+                    IssueKind::InsecureTransport(insecure_transport) => {
+                        insecure_transport.url = redact_url(&insecure_transport.url);
+                    }
+                    IssueKind::UnapprovedRev(unapproved_rev) => {
+                        unapproved_rev.repo = redact(&unapproved_rev.repo);
+                    }
+                    IssueKind::HashConflict(hash_conflict) => {
+                        hash_conflict.locked_repo = redact(&hash_conflict.locked_repo);
+                    }
+                    IssueKind::PossibleTyposquat(possible_typosquat) => {
+                        possible_typosquat.suspicious_owner =
+                            redact(&possible_typosquat.suspicious_owner);
+                    }
+                    IssueKind::OriginalMismatch(original_mismatch) => {
+                        original_mismatch.declared_url =
+                            redact_url(&original_mismatch.declared_url);
+                        original_mismatch.original_url =
+                            redact_url(&original_mismatch.original_url);
+                    }
+                    IssueKind::UnexpectedFlake(unexpected_flake) => {
+                        unexpected_flake.data_url = redact_url(&unexpected_flake.data_url);
+                    }
+                    IssueKind::TransitivelyIndirect(transitively_indirect) => {
+                        transitively_indirect.registry_id =
+                            redact(&transitively_indirect.registry_id);
+                    }
+                    IssueKind::UnparsableNode(unparsable_node) => {
+                        // Parse errors can quote the node's contents, like its URL
+                        unparsable_node.error = redact(&unparsable_node.error);
+                    }
+                    // Nothing to redact. These are listed rather than matched with `_` so that
+                    // each new kind of issue has to be considered here.
+                    IssueKind::Disallowed(_)
+                    | IssueKind::Outdated(_)
+                    | IssueKind::UnexpectedHashAlgo(_)
+                    | IssueKind::TooManyInputs(_)
+                    | IssueKind::FutureTimestamp(_)
+                    | IssueKind::MissingHash(_)
+                    | IssueKind::UnlockedInput(_)
+                    | IssueKind::ExcessiveDepth(_)
+                    | IssueKind::LockFormatDrift(_)
+                    | IssueKind::DeclaredRefMismatch(_)
+                    | IssueKind::TemporaryRef(_)
+                    | IssueKind::ApproachingEol(_)
+                    | IssueKind::UnlockedRev(_)
+                    | IssueKind::MixedNixpkgsChannels(_) => {}
+                }
+                issue
+            })
+            .collect();
+
+        Self {
+            issues,
+            ..self.clone()
+        }
+    }
+}
+
+/// A report cached by [cached_or_check], along with the key that it was cached under.
+#[derive(Deserialize, Serialize)]
+struct CachedReport {
+    key: String,
+    report: CheckReport,
+    #[serde(default)]
+    checks_run: ChecksRun,
+}
+
+// The key of a cached report: a hash of the `flake.lock`'s contents, the `settings` that the
+// results depend on, and the date of the check, since the ages of inputs change from day to day.
+fn cache_key(lock_contents: &[u8], settings: &str, today: NaiveDate) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(lock_hash(lock_contents));
+    hasher.update(settings);
+    hasher.update(today.to_string());
+    format!("{:x}", hasher.finalize())
+}
+
+// Load a cached report, returning `None` if the cache is missing, unreadable, or was cached under a
+// different key.
+fn load_cached(path: &Path, key: &str) -> Option<CheckReport> {
+    let contents = read_to_string(path).ok()?;
+    let cached: CachedReport = serde_json::from_str(&contents).ok()?;
+    (cached.key == key).then_some(CheckReport {
+        checks_run: cached.checks_run,
+        ..cached.report
+    })
+}
+
+pub(crate) fn lock_hash(lock_contents: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(lock_contents);
+    format!("{:x}", hasher.finalize())
+}
+
+/// The Git blob ID of the lockfile's contents, as computed by `git hash-object flake.lock` (or
+/// shown by `git rev-parse HEAD:flake.lock`), i.e. the SHA-1 hash of a `blob <length>` header and
+/// the contents. For compressed or UTF-16 lockfiles, it's the ID of the decoded contents.
+pub(crate) fn lock_blob(lock_contents: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", lock_contents.len()));
+    hasher.update(lock_contents);
+    format!("{:x}", hasher.finalize())
+}
+
+// The `version` field of the `flake.lock`, read without parsing the rest of the lockfile.
+fn lock_version(lock_contents: &[u8]) -> Option<usize> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: usize,
+    }
+
+    serde_json::from_slice::<Version>(lock_contents)
+        .ok()
+        .map(|v| v.version)
+}
+
+// A stable stand-in for an identifying name, e.g. `redacted-5f6d2a1c9b3e`.
+fn redact(name: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+    format!("redacted-{}", &hash[..12])
+}
+
+// Redact a URL while keeping its scheme (e.g. `http://`), which insecure transport issues are about.
+fn redact_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => format!("{scheme}://{}", redact(rest)),
+        None => redact(url),
+    }
+}
+
+/// Reuse the report cached at `cache_path` if it was produced today for the same `flake.lock`
+/// contents and the same `settings` (everything else that the results depend on, like the config
+/// and `--condition`). Otherwise, run `check` and cache the report that it produces.
+pub(crate) fn cached_or_check<F>(
+    cache_path: &Path,
+    lock_contents: &[u8],
+    settings: &str,
+    check: F,
+) -> Result<CheckReport, FlakeCheckerError>
+where
+    F: FnOnce() -> Result<CheckReport, FlakeCheckerError>,
+{
+    let key = cache_key(lock_contents, settings, Utc::now().date_naive());
+    if let Some(report) = load_cached(cache_path, &key) {
+        return Ok(report);
+    }
+
+    let report = check()?;
+    let cached = CachedReport {
+        key,
+        checks_run: report.checks_run.clone(),
+        report,
+    };
+    write(cache_path, serde_json::to_string_pretty(&cached)?)?;
+    Ok(CheckReport {
+        checks_run: cached.checks_run,
+        ..cached.report
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{read, remove_file};
+    use std::path::PathBuf;
+
+    use crate::issue::{
+        Disallowed, InsecureTransport, Issue, IssueKind, NonUpstream, OriginalMismatch,
+        TransitivelyIndirect, UnexpectedFlake,
+    };
+
+    use chrono::NaiveDate;
+
+    use super::{cache_key, cached_or_check, lock_blob, CheckReport, ChecksRun};
+
+    #[test]
+    fn cache_hit_skips_check() {
+        let cache_path = std::env::temp_dir().join(format!(
+            "flake-checker-cache-report-{}.json",
+            std::process::id()
+        ));
+        let _ = remove_file(&cache_path);
+
+        let lock_contents = read(PathBuf::from("tests/flake.dirty.0.lock")).unwrap();
+        let issues = vec![Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::NonUpstream(NonUpstream {
+                owner: String::from("bitcoin-miner-org"),
+                ..Default::default()
+            }),
+            location: None,
+        }];
+
+        // Cache miss: the check runs and its results are stored
+        let mut checks_run = ChecksRun::default();
+        checks_run.record("nixpkgs", "Upstream owner");
+        let report = cached_or_check(&cache_path, &lock_contents, "settings", || {
+            Ok(CheckReport {
+                checks_run: checks_run.clone(),
+                ..CheckReport::new(&lock_contents, issues.clone(), 1, 2)
+            })
+        })
+        .unwrap();
+        assert_eq!(report.issues, issues);
+
+        // Cache hit: the check (and any ref fetching it would do) never runs, and the checks that
+        // it ran are cached along with the report
+        let report = cached_or_check(&cache_path, &lock_contents, "settings", || {
+            panic!("the check shouldn't run when the cached report matches")
+        })
+        .unwrap();
+        assert_eq!(report.issues, issues);
+        assert_eq!(report.checks_run, checks_run);
+
+        // A changed flake.lock invalidates the cache
+        let other_contents = read(PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+        let report = cached_or_check(&cache_path, &other_contents, "settings", || {
+            Ok(CheckReport::new(&other_contents, vec![], 1, 2))
+        })
+        .unwrap();
+        assert!(report.issues.is_empty());
+
+        // So do changed settings
+        let report = cached_or_check(&cache_path, &other_contents, "other settings", || {
+            Ok(CheckReport::new(&other_contents, issues.clone(), 1, 2))
+        })
+        .unwrap();
+        assert_eq!(report.issues, issues);
+
+        remove_file(&cache_path).unwrap();
+    }
+
+    #[test]
+    fn cache_expires_daily() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let key = cache_key(b"{}", "settings", date("2025-01-01"));
+        assert_eq!(key, cache_key(b"{}", "settings", date("2025-01-01")));
+        assert_ne!(key, cache_key(b"{}", "settings", date("2025-01-02")));
+    }
+
+    #[test]
+    fn lock_blob_ids() {
+        let lock_contents = read(PathBuf::from("tests/flake.clean.0.lock")).unwrap();
+
+        // The same ID as `git hash-object tests/flake.clean.0.lock`
+        let report = CheckReport::new(&lock_contents, vec![], 1, 5);
+        assert_eq!(report.lock_blob, "c2d0d5f855f67472242578ac80a1cd89510c5087");
+        assert_eq!(lock_blob(b"{}"), "9e26dfeeb6e641a33dae4961196235bdb965b21b");
+
+        // Identical locks have the same ID, wherever they're from and whatever their issues
+        let copy = lock_contents.clone();
+        let other_report = CheckReport::new(&copy, vec![], 2, 5);
+        assert_eq!(other_report.lock_blob, report.lock_blob);
+        assert_ne!(
+            CheckReport::new(&read("tests/flake.clean.1.lock").unwrap(), vec![], 1, 5).lock_blob,
+            report.lock_blob
+        );
+
+        // It's part of the JSON report
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(
+            json["lock_blob"],
+            "c2d0d5f855f67472242578ac80a1cd89510c5087"
+        );
+    }
+
+    #[test]
+    fn redaction() {
+        let non_upstream = |input: &str, owner: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::NonUpstream(NonUpstream {
+                owner: String::from(owner),
+                ..Default::default()
+            }),
+            location: None,
+        };
+        let report = CheckReport::new(
+            b"{}",
+            vec![
+                non_upstream("nixpkgs", "acme-corp"),
+                non_upstream("nixpkgs-alt", "acme-corp"),
+                non_upstream("nixpkgs-old", "other-corp"),
+                Issue {
+                    input: String::from("tools"),
+                    kind: IssueKind::InsecureTransport(InsecureTransport {
+                        url: String::from("http://git.acme-corp.internal/tools.tar.gz"),
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("nixos-22.05"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("tools"),
+                    kind: IssueKind::OriginalMismatch(OriginalMismatch {
+                        declared_url: String::from("github:acme-corp/tools/main"),
+                        original_url: String::from("https://git.acme-corp.internal/tools"),
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("hosts"),
+                    kind: IssueKind::UnexpectedFlake(UnexpectedFlake {
+                        data_url: String::from("github:acme-corp/hosts"),
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("tools/nixpkgs"),
+                    kind: IssueKind::TransitivelyIndirect(TransitivelyIndirect {
+                        via: String::from("tools"),
+                        follows: String::from("nixpkgs"),
+                        registry_id: String::from("acme-corp-nixpkgs"),
+                    }),
+                    location: None,
+                },
+            ],
+            4,
+            4,
+        );
+
+        let redacted = report.redacted();
+        let details: Vec<String> = redacted.issues.iter().map(|i| i.kind.detail()).collect();
+
+        // The same owner is always replaced with the same hash and different owners differ
+        assert!(details[0].starts_with("redacted-"));
+        assert_eq!(details[0], details[1]);
+        assert_ne!(details[0], details[2]);
+        assert_eq!(report.redacted(), redacted);
+
+        // URLs keep their scheme
+        assert!(details[3].starts_with("http://redacted-"));
+        assert!(details[5].contains(" in flake.nix, https://redacted-"));
+
+        // Nothing identifying remains, while the rest of the report is untouched
+        assert!(details.iter().all(|detail| !detail.contains("acme-corp")));
+        assert_eq!(details[4], "nixos-22.05");
+        let inputs: Vec<&str> = redacted.issues.iter().map(|i| i.input.as_str()).collect();
+        assert_eq!(
+            inputs,
+            vec![
+                "nixpkgs",
+                "nixpkgs-alt",
+                "nixpkgs-old",
+                "tools",
+                "nixpkgs",
+                "tools",
+                "hosts",
+                "tools/nixpkgs"
+            ]
+        );
+        assert_eq!(redacted.lock_hash, report.lock_hash);
+    }
+}
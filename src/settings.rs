@@ -0,0 +1,364 @@
+use crate::error::FlakeCheckerError;
+use crate::Cli;
+
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use clap::{error::ErrorKind, Arg, ArgAction, Command, CommandFactory};
+use serde_json::Value;
+
+/// The environment variable holding a JSON object of settings, like
+/// `{"fail-mode": true, "nixpkgs-keys": ["nixpkgs", "nixpkgs-alt"]}`.
+pub(crate) const SETTINGS_ENV_VAR: &str = "NIX_FLAKE_CHECKER_SETTINGS";
+
+/// The name of the config file, which is looked for in the current directory when
+/// `NIX_FLAKE_CHECKER_SETTINGS` isn't set.
+pub(crate) const CONFIG_FILE_NAME: &str = "flake-checker.toml";
+
+/// The config file table holding the profiles, e.g. `[profile.strict]`.
+const PROFILE_TABLE: &str = "profile";
+
+/// The settings in the config file at `path` as a JSON object of settings (see [`command`]), or
+/// `None` if there's no config file. The file's top-level keys and values are the settings, and
+/// those of the `profile` (if any) override them.
+pub(crate) fn config_file_settings(
+    path: &Path,
+    profile: Option<&str>,
+) -> Result<Option<String>, FlakeCheckerError> {
+    if !path.exists() {
+        return match profile {
+            Some(profile) => Err(FlakeCheckerError::ConfigFile(format!(
+                "{}: the file doesn't exist, so it has no profile {profile:?}",
+                path.display()
+            ))),
+            None => Ok(None),
+        };
+    }
+
+    settings_from_toml(&read_to_string(path)?, profile)
+        .map(Some)
+        .map_err(|e| FlakeCheckerError::ConfigFile(format!("{}: {e}", path.display())))
+}
+
+/// The settings in the TOML contents of a config file as a JSON object, with the settings of the
+/// `profile` (if any) merged over the top-level ones.
+pub(crate) fn settings_from_toml(contents: &str, profile: Option<&str>) -> Result<String, String> {
+    let mut table: toml::Table = contents.parse().map_err(|e| format!("{e}"))?;
+
+    let profiles = match table.remove(PROFILE_TABLE) {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => return Err(format!("{PROFILE_TABLE:?} must be a table of profiles")),
+        None => toml::Table::new(),
+    };
+    if let Some(profile) = profile {
+        match profiles.get(profile) {
+            Some(toml::Value::Table(settings)) => table.extend(settings.clone()),
+            Some(_) => return Err(format!("the profile {profile:?} must be a table")),
+            None => return Err(format!("there's no profile {profile:?}")),
+        }
+    }
+
+    serde_json::to_string(&table).map_err(|e| e.to_string())
+}
+
+/// The `--config-profile` (or `NIX_FLAKE_CHECKER_CONFIG_PROFILE`) given on the command line `args`,
+/// which has to be known before the config file's settings are applied. If the arguments don't
+/// parse, there's no profile, and the full parse reports the error.
+pub(crate) fn config_profile<I, T>(args: I) -> Option<String>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+        .ok()?
+        .get_one::<String>("config_profile")
+        .cloned()
+}
+
+/// The command-line interface with `settings` applied as defaults. Each key is the name of a flag
+/// without the leading `--` (or `flake-lock-path`) and each value is a string, number, Boolean, or
+/// (for flags that take several values) an array of them. Because the settings are defaults,
+/// explicit flags and their environment variables take precedence over them.
+pub(crate) fn command(settings: Option<&str>) -> Result<Command, FlakeCheckerError> {
+    let mut command = Cli::command();
+    let Some(settings) = settings else {
+        return Ok(command);
+    };
+
+    let settings: serde_json::Map<String, Value> = serde_json::from_str(settings)
+        .map_err(|e| FlakeCheckerError::Settings(format!("expected a JSON object ({e})")))?;
+
+    for (key, value) in settings {
+        let id = setting_arg(&command, &key)
+            .map(|arg| arg.get_id().clone())
+            .ok_or_else(|| FlakeCheckerError::Settings(format!("unknown setting {key:?}")))?;
+
+        let values = match value {
+            Value::Array(values) => values
+                .into_iter()
+                .map(|value| setting_value(&key, value))
+                .collect::<Result<Vec<_>, _>>()?,
+            value => vec![setting_value(&key, value)?],
+        };
+
+        command = command.mut_arg(id, |arg| arg.default_values(values));
+    }
+
+    Ok(command)
+}
+
+/// Check that the `settings` (a JSON object of settings, see [`command`]) can be applied, as a run
+/// applies them, and that none of them conflict with each other.
+pub(crate) fn validate(settings: &str) -> Result<(), FlakeCheckerError> {
+    let settings_map: serde_json::Map<String, Value> = serde_json::from_str(settings)
+        .map_err(|e| FlakeCheckerError::Settings(format!("expected a JSON object ({e})")))?;
+
+    // The values are parsed when a run parses the command line, and clap doesn't say which
+    // default is invalid, so each setting is applied on its own
+    for (key, value) in settings_map {
+        let setting = serde_json::Map::from_iter([(key.clone(), value)]);
+        command(Some(&Value::Object(setting).to_string()))?
+            .try_get_matches_from([env!("CARGO_PKG_NAME")])
+            .map_err(|e| {
+                FlakeCheckerError::Settings(match e.kind() {
+                    ErrorKind::InvalidValue | ErrorKind::ValueValidation => {
+                        format!("invalid value for {key:?}")
+                    }
+                    kind => format!("{key:?}: {kind}"),
+                })
+            })?;
+    }
+
+    check_conflicts(settings)
+}
+
+/// Check that none of the `settings` (a JSON object of settings, see [`command`]) conflict with
+/// each other, like `output` and `output-dir`. Since settings are applied as defaults, conflicting
+/// settings are otherwise only caught when they're given as flags.
+pub(crate) fn check_conflicts(settings: &str) -> Result<(), FlakeCheckerError> {
+    let settings: serde_json::Map<String, Value> = serde_json::from_str(settings)
+        .map_err(|e| FlakeCheckerError::Settings(format!("expected a JSON object ({e})")))?;
+    // Building the command resolves the conflicts of global arguments
+    let mut command = Cli::command();
+    command.build();
+
+    // Unknown settings are reported when the settings are applied
+    let args: Vec<(&String, &Arg)> = settings
+        .keys()
+        .filter_map(|key| Some((key, setting_arg(&command, key)?)))
+        .collect();
+    let conflicts_with = |arg: &Arg, other: &Arg| {
+        command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|conflict| conflict.get_id() == other.get_id())
+    };
+
+    let mut conflicts = vec![];
+    for (i, (key, arg)) in args.iter().enumerate() {
+        // Either of the arguments can declare the conflict, and each pair is only reported once
+        for (other_key, other) in &args[i + 1..] {
+            if conflicts_with(arg, other) || conflicts_with(other, arg) {
+                conflicts.push(format!("{key:?} conflicts with {other_key:?}"));
+            }
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(FlakeCheckerError::Settings(conflicts.join(", ")))
+    }
+}
+
+/// The argument that the setting `key` is for: the flag named `key`, or the positional argument
+/// with that name.
+fn setting_arg<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    command.get_arguments().find(|arg| {
+        !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version)
+            && (arg.get_long() == Some(key)
+                || (arg.is_positional() && arg.get_id() == key.replace('-', "_").as_str()))
+    })
+}
+
+fn setting_value(key: &str, value: Value) -> Result<String, FlakeCheckerError> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Bool(value) => Ok(value.to_string()),
+        Value::Number(value) => Ok(value.to_string()),
+        value => Err(FlakeCheckerError::Settings(format!(
+            "{key:?} must be a string, number, Boolean, or array of them but is {value}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use clap::FromArgMatches;
+
+    use crate::Cli;
+
+    use super::command;
+
+    fn parse(settings: &str, args: &[&str]) -> Cli {
+        let matches = command(Some(settings))
+            .unwrap()
+            .try_get_matches_from(args)
+            .unwrap();
+        Cli::from_arg_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn settings() {
+        let cli = parse(
+            r#"{
+                "fail-mode": true,
+                "check-outdated": false,
+                "max-inputs": 20,
+                "nixpkgs-keys": ["nixpkgs", "nixpkgs-alt"],
+                "condition": "owner == 'NixOS'",
+                "flake-lock-path": "sub/flake.lock"
+            }"#,
+            &["flake-checker"],
+        );
+        assert!(cli.fail_mode);
+        assert!(!cli.check_outdated);
+        assert_eq!(cli.max_inputs, Some(20));
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs", "nixpkgs-alt"]);
+        assert_eq!(cli.condition.as_deref(), Some("owner == 'NixOS'"));
+        assert_eq!(cli.flake_lock_path, PathBuf::from("sub/flake.lock"));
+
+        // Settings that aren't supplied keep their usual defaults
+        assert!(cli.check_owner);
+        assert_eq!(cli.max_days, crate::flake::MAX_DAYS);
+    }
+
+    #[test]
+    fn flags_take_precedence() {
+        let settings = r#"{"max-inputs": 20, "nixpkgs-keys": "nixpkgs-alt", "flake-lock-path": "sub/flake.lock"}"#;
+
+        let cli = parse(
+            settings,
+            &[
+                "flake-checker",
+                "--max-inputs",
+                "30",
+                "--nixpkgs-keys",
+                "nixpkgs",
+                "other/flake.lock",
+            ],
+        );
+        assert_eq!(cli.max_inputs, Some(30));
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs"]);
+        assert_eq!(cli.flake_lock_path, PathBuf::from("other/flake.lock"));
+
+        let cli = parse(settings, &["flake-checker"]);
+        assert_eq!(cli.max_inputs, Some(20));
+    }
+
+    #[test]
+    fn config_file() {
+        let settings = super::settings_from_toml(
+            r#"
+                # Comments are allowed
+                fail-mode = true
+                max-inputs = 20
+                nixpkgs-keys = ["nixpkgs", "nixpkgs-alt"]
+            "#,
+            None,
+        )
+        .unwrap();
+        let cli = parse(&settings, &["flake-checker"]);
+        assert!(cli.fail_mode);
+        assert_eq!(cli.max_inputs, Some(20));
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs", "nixpkgs-alt"]);
+
+        assert!(super::settings_from_toml("fail-mode = ", None).is_err());
+        // Tables aren't settings
+        let settings = super::settings_from_toml("[fail-mode]\nenabled = true", None).unwrap();
+        assert!(command(Some(&settings)).is_err());
+    }
+
+    #[test]
+    fn config_profiles() {
+        let contents = r#"
+            max-inputs = 20
+            nixpkgs-keys = ["nixpkgs"]
+
+            [profile.strict]
+            fail-mode = true
+            max-inputs = 10
+
+            [profile.ci]
+            nixpkgs-keys = ["nixpkgs", "nixpkgs-ci"]
+        "#;
+        let profile = |profile| {
+            let settings = super::settings_from_toml(contents, profile).unwrap();
+            parse(&settings, &["flake-checker"])
+        };
+
+        // Without a profile, only the top-level settings apply
+        let cli = profile(None);
+        assert!(!cli.fail_mode);
+        assert_eq!(cli.max_inputs, Some(20));
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs"]);
+
+        // A profile's settings are merged over the top-level ones
+        let cli = profile(Some("strict"));
+        assert!(cli.fail_mode);
+        assert_eq!(cli.max_inputs, Some(10));
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs"]);
+
+        let cli = profile(Some("ci"));
+        assert!(!cli.fail_mode);
+        assert_eq!(cli.max_inputs, Some(20));
+        assert_eq!(cli.nixpkgs_keys, vec!["nixpkgs", "nixpkgs-ci"]);
+
+        assert!(super::settings_from_toml(contents, Some("nightly")).is_err());
+        assert!(super::settings_from_toml("profile = \"strict\"", None).is_err());
+
+        // The profile is picked out of the arguments before the settings are applied
+        assert_eq!(
+            super::config_profile(["flake-checker", "--config-profile", "ci", "flake.lock"]),
+            Some(String::from("ci"))
+        );
+        assert_eq!(
+            super::config_profile(["flake-checker", "--config-profile=ci"]),
+            Some(String::from("ci"))
+        );
+        assert_eq!(super::config_profile(["flake-checker"]), None);
+    }
+
+    #[test]
+    fn invalid_settings() {
+        for settings in [
+            "not json",
+            r#"["fail-mode"]"#,
+            r#"{"no-such-flag": true}"#,
+            r#"{"help": true}"#,
+            r#"{"max-inputs": {"value": 20}}"#,
+        ] {
+            assert!(command(Some(settings)).is_err(), "{settings}");
+        }
+
+        // Values are validated like flags
+        assert!(command(Some(r#"{"max-inputs": "many"}"#))
+            .unwrap()
+            .try_get_matches_from(["flake-checker"])
+            .is_err());
+
+        // Validating the settings names the setting with the invalid value
+        assert_eq!(
+            super::validate(r#"{"fail-mode": true, "max-inputs": "many"}"#)
+                .unwrap_err()
+                .to_string(),
+            "invalid settings: invalid value for \"max-inputs\""
+        );
+        assert!(super::validate(r#"{"fail-mode": true, "max-inputs": 20}"#).is_ok());
+    }
+}
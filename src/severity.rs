@@ -0,0 +1,254 @@
+use crate::issue::{Issue, IssueKind};
+use crate::FlakeCheckConfig;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// How seriously an issue should be taken. Only errors cause a nonzero exit code.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Self::Info),
+            "warning" | "warn" => Ok(Self::Warning),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "unknown severity {s:?} (expected one of: info, warning, error)"
+            )),
+        }
+    }
+}
+
+/// A mapping of issue kinds to severities, supplied as a comma-separated list of
+/// `<kind>=<severity>` pairs (e.g. `non-upstream=error,outdated=info`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SeverityMap(BTreeMap<String, Severity>);
+
+impl SeverityMap {
+    pub(crate) fn get(&self, kind: &IssueKind) -> Option<Severity> {
+        self.0.get(kind.name()).copied()
+    }
+}
+
+impl FromStr for SeverityMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = BTreeMap::new();
+
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((kind, severity)) = entry.split_once('=') else {
+                return Err(format!("expected <kind>=<severity> but got {entry:?}"));
+            };
+            let kind: IssueKind = kind.trim().parse()?;
+            map.insert(kind.to_string(), severity.trim().parse()?);
+        }
+
+        Ok(Self(map))
+    }
+}
+
+/// Whether the issues are severe enough to warrant a nonzero exit code, which takes more errors
+/// than the config's `fail_threshold`.
+pub(crate) fn has_errors(issues: &[Issue], config: &FlakeCheckConfig) -> bool {
+    issues
+        .iter()
+        .filter(|issue| issue.severity(config) == Severity::Error)
+        .count()
+        > config.fail_threshold
+}
+
+#[cfg(test)]
+mod test {
+    use crate::flake::FlakeCheckConfig;
+    use crate::issue::{
+        Disallowed, IndirectInput, Issue, IssueKind, NonUpstream, Outdated, TooManyInputs,
+        Violation,
+    };
+
+    use super::{has_errors, Severity, SeverityMap};
+
+    fn issues() -> Vec<Issue> {
+        vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs-alt"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("seems-pretty-shady"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn parse_severity_map() {
+        let map: SeverityMap = "non-upstream=error, outdated=info,disallowed=warn"
+            .parse()
+            .unwrap();
+        let disallowed = IssueKind::Disallowed(Disallowed {
+            reference: String::from("this-should-fail"),
+            ..Default::default()
+        });
+        assert_eq!(map.get(&disallowed), Some(Severity::Warning));
+        assert_eq!(map.get(&IssueKind::Violation(Violation::default())), None);
+
+        assert!("outdated".parse::<SeverityMap>().is_err());
+        assert!("stale=error".parse::<SeverityMap>().is_err());
+        assert!("outdated=fatal".parse::<SeverityMap>().is_err());
+    }
+
+    #[test]
+    fn exit_behavior() {
+        // (fail_mode, severity map, expected failure)
+        let cases: Vec<(bool, &str, bool)> = vec![
+            (false, "", false),
+            (true, "", true),
+            (false, "non-upstream=error", true),
+            (false, "outdated=error", true),
+            (true, "outdated=info,non-upstream=warning", false),
+            (true, "outdated=info", true),
+        ];
+
+        for (fail_mode, severity_map, expected) in cases {
+            let config = FlakeCheckConfig {
+                fail_mode,
+                severity_map: severity_map.parse().unwrap(),
+                ..Default::default()
+            };
+            assert_eq!(
+                has_errors(&issues(), &config),
+                expected,
+                "unexpected exit behavior with fail_mode={fail_mode} and severity map {severity_map:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn fail_on_warnings() {
+        let issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::IndirectInput(IndirectInput {
+                    id: String::from("nixpkgs"),
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("root"),
+                kind: IssueKind::TooManyInputs(TooManyInputs { count: 30, max: 20 }),
+                location: None,
+            },
+        ];
+
+        // (severity map, expected failure)
+        let cases: Vec<(&str, bool)> = vec![
+            ("", true),
+            ("indirect-input=warning", true),
+            // Issues explicitly mapped to info stay non-fatal
+            ("indirect-input=info,too-many-inputs=info", false),
+            ("indirect-input=info", true),
+        ];
+
+        for (severity_map, expected) in cases {
+            let config = FlakeCheckConfig {
+                fail_on_warnings: true,
+                severity_map: severity_map.parse().unwrap(),
+                ..Default::default()
+            };
+            assert_eq!(
+                has_errors(&issues, &config),
+                expected,
+                "unexpected exit behavior with severity map {severity_map:?}"
+            );
+        }
+
+        assert!(!has_errors(&issues, &FlakeCheckConfig::default()));
+    }
+
+    #[test]
+    fn warn_only() {
+        // Nothing fails, however strict the rest of the config is
+        let config = FlakeCheckConfig {
+            fail_mode: true,
+            fail_on_warnings: true,
+            warn_only: true,
+            severity_map: "non-upstream=error,outdated=info".parse().unwrap(),
+            ..Default::default()
+        };
+        assert!(!has_errors(&issues(), &config));
+
+        // Errors are reported as warnings, while other severities stay as they are
+        let severities: Vec<Severity> = issues().iter().map(|i| i.severity(&config)).collect();
+        assert_eq!(severities, vec![Severity::Info, Severity::Warning]);
+
+        // Warnings aren't made errors by fail_on_warnings either, whether they're warnings by
+        // default or by the severity map
+        for (fail_mode, severity_map) in [(false, ""), (true, "non-upstream=warning")] {
+            let config = FlakeCheckConfig {
+                fail_mode,
+                fail_on_warnings: true,
+                warn_only: true,
+                severity_map: severity_map.parse().unwrap(),
+                ..Default::default()
+            };
+            assert!(!has_errors(&issues(), &config));
+            assert_eq!(issues()[1].severity(&config), Severity::Warning);
+        }
+    }
+
+    #[test]
+    fn fail_threshold() {
+        // (fail threshold, expected failure) with both issues being errors
+        let cases: Vec<(usize, bool)> = vec![(0, true), (1, true), (2, false), (3, false)];
+
+        for (fail_threshold, expected) in cases {
+            let config = FlakeCheckConfig {
+                fail_mode: true,
+                fail_threshold,
+                ..Default::default()
+            };
+            assert_eq!(
+                has_errors(&issues(), &config),
+                expected,
+                "unexpected exit behavior with a fail threshold of {fail_threshold}"
+            );
+        }
+
+        // Only errors count toward the threshold
+        let config = FlakeCheckConfig {
+            fail_on_warnings: true,
+            fail_threshold: 1,
+            severity_map: "outdated=info".parse().unwrap(),
+            ..Default::default()
+        };
+        assert!(!has_errors(&issues(), &config));
+    }
+}
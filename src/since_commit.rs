@@ -0,0 +1,132 @@
+//! Support for `--since-commit`, which scopes failures to the inputs that changed since a Git
+//! revision by comparing the `flake.lock` against its version at that revision.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::Command;
+
+use parse_flake_lock::{FlakeLock, Node};
+
+use crate::error::FlakeCheckerError;
+use crate::flake::TRANSITIVE_INPUT_SEPARATOR;
+
+/// The inputs of the `flake.lock` (whose contents are `lock_contents`) that are new or locked
+/// differently than at Git revision `rev`, named as in issues. Whole-lock issues are reported for
+/// the `root` input, which counts as changed whenever any input changed. If the `flake.lock` didn't
+/// exist at `rev`, everything is new, so `None` is returned.
+pub(crate) fn changed_inputs(
+    flake_lock_path: &Path,
+    lock_contents: &str,
+    rev: &str,
+) -> Result<Option<HashSet<String>>, FlakeCheckerError> {
+    let Some(base_contents) = lock_at_revision(flake_lock_path, rev)? else {
+        return Ok(None);
+    };
+
+    let flake_lock = FlakeLock::from_str_lenient(lock_contents)?.0;
+    let base = FlakeLock::from_str_lenient(&base_contents)?.0;
+    let mut changed = diff(&flake_lock, &base)?;
+    if !changed.is_empty() {
+        changed.insert(String::from("root"));
+    }
+
+    Ok(Some(changed))
+}
+
+// The inputs of `flake_lock`, named by their paths of input names, that `base` doesn't have at the
+// same path or that are locked differently there.
+pub(crate) fn diff(
+    flake_lock: &FlakeLock,
+    base: &FlakeLock,
+) -> Result<HashSet<String>, FlakeCheckerError> {
+    let base_inputs: HashMap<String, &Node> = base
+        .walk()?
+        .into_iter()
+        .map(|input| (input.path.join(TRANSITIVE_INPUT_SEPARATOR), input.node))
+        .collect();
+
+    Ok(flake_lock
+        .walk()?
+        .into_iter()
+        .filter_map(|input| {
+            let name = input.path.join(TRANSITIVE_INPUT_SEPARATOR);
+            match base_inputs.get(&name) {
+                Some(base_node) if same_lock(input.node, base_node) => None,
+                _ => Some(name),
+            }
+        })
+        .collect())
+}
+
+// Whether two nodes are locked to the same contents from the same source.
+fn same_lock(node: &Node, other: &Node) -> bool {
+    node.nar_hash() == other.nar_hash()
+        && node.locked_rev() == other.locked_rev()
+        && node.original_url() == other.original_url()
+}
+
+// The contents of the flake.lock at `flake_lock_path` as of Git revision `rev`, or `None` if it
+// didn't exist at that revision.
+pub(crate) fn lock_at_revision(
+    flake_lock_path: &Path,
+    rev: &str,
+) -> Result<Option<String>, FlakeCheckerError> {
+    let dir = match flake_lock_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = flake_lock_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("flake.lock"));
+
+    // The paths are passed as separate arguments so that spaces in them don't matter
+    if !git(dir, &["rev-parse", "--is-inside-work-tree"])?
+        .status
+        .success()
+    {
+        return Err(FlakeCheckerError::NotGitRepo(dir.display().to_string()));
+    }
+
+    let commit = format!("{rev}^{{commit}}");
+    if !git(dir, &["rev-parse", "--verify", "--quiet", &commit])?
+        .status
+        .success()
+    {
+        // CI checkouts are often shallow, so the revision may just not have been fetched
+        if is_shallow(dir)? {
+            return Err(FlakeCheckerError::Git(format!(
+                "unknown revision `{rev}` in a shallow clone, which may be missing its history; \
+                 deepen the clone with `git fetch --unshallow` (or `fetch-depth: 0` for \
+                 actions/checkout), or fetch just the commit with `git fetch --depth=1 origin \
+                 <commit>`"
+            )));
+        }
+        return Err(FlakeCheckerError::Git(format!("unknown revision `{rev}`")));
+    }
+
+    // `./` makes the path relative to `dir` rather than to the top of the repository
+    let output = git(dir, &["show", &format!("{rev}:./{file_name}")])?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    String::from_utf8(output.stdout)
+        .map(Some)
+        .map_err(|e| FlakeCheckerError::Git(format!("the flake.lock at `{rev}` isn't UTF-8: {e}")))
+}
+
+// Whether the repository at `dir` is a shallow clone, i.e. only has part of its history.
+fn is_shallow(dir: &Path) -> Result<bool, FlakeCheckerError> {
+    let output = git(dir, &["rev-parse", "--is-shallow-repository"])?;
+    Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<std::process::Output, FlakeCheckerError> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| FlakeCheckerError::Git(format!("couldn't run git: {e}")))
+}
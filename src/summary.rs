@@ -1,70 +1,262 @@
+use crate::checks::CHECKS;
+use crate::color::severity_label;
 use crate::error::FlakeCheckerError;
-use crate::flake::MAX_DAYS;
-use crate::issue::{Issue, IssueKind};
+use crate::explain::explanation;
+use crate::flake::{is_transitive_input, EXPECTED_HASH_ALGO};
+use crate::issue::{DisallowedReason, Issue, IssueKind, ISSUE_KINDS};
+use crate::report::CheckReport;
+use crate::severity::has_errors;
 use crate::FlakeCheckConfig;
 
+use std::collections::BTreeMap;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
 
-use handlebars::Handlebars;
+use clap::ValueEnum;
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
 use serde_json::json;
 
-static CEL_MARKDOWN_TEMPLATE: &str = include_str!(concat!(
-    env!("CARGO_MANIFEST_DIR"),
-    "/src/templates/summary.cel.md.hbs"
-));
+// Markdown code spans as HTML, for text that isn't rendered as Markdown, like the title of a
+// collapsed section
+handlebars_helper!(code_spans: |text: str| {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{part}</code>")
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<String>()
+});
+
+/// A set of summary templates in a single language.
+struct Templates {
+    cel_markdown: &'static str,
+    cel_text: &'static str,
+    standard_markdown: &'static str,
+    /// The section of the standard Markdown summary for each kind of issue, either under a heading
+    /// or collapsed (with `--markdown-collapsible`).
+    markdown_section: &'static str,
+    collapsible_markdown_section: &'static str,
+    standard_text: &'static str,
+    merged_markdown: &'static str,
+    merged_text: &'static str,
+    merged_text_section: &'static str,
+    standard_html: &'static str,
+}
 
-static CEL_TEXT_TEMPLATE: &str = include_str!(concat!(
-    env!("CARGO_MANIFEST_DIR"),
-    "/src/templates/summary.cel.txt.hbs"
-));
+static EN_TEMPLATES: Templates = Templates {
+    cel_markdown: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.cel.md.hbs"
+    )),
+    cel_text: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.cel.txt.hbs"
+    )),
+    standard_markdown: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.standard.md.hbs"
+    )),
+    markdown_section: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.section.md.hbs"
+    )),
+    collapsible_markdown_section: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.collapsible-section.md.hbs"
+    )),
+    standard_text: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.standard.txt.hbs"
+    )),
+    merged_markdown: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.merged.md.hbs"
+    )),
+    merged_text: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.merged.txt.hbs"
+    )),
+    merged_text_section: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.merged-section.txt.hbs"
+    )),
+    standard_html: include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/templates/summary.standard.html.hbs"
+    )),
+};
 
-static STANDARD_MARKDOWN_TEMPLATE: &str = include_str!(concat!(
-    env!("CARGO_MANIFEST_DIR"),
-    "/src/templates/summary.standard.md.hbs"
-));
+/// The languages that the summary can be rendered in. The English templates live in
+/// `src/templates` and translations in `src/templates/<code>`, using the same file names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum Lang {
+    /// English.
+    #[default]
+    En,
+}
 
-static STANDARD_TEXT_TEMPLATE: &str = include_str!(concat!(
-    env!("CARGO_MANIFEST_DIR"),
-    "/src/templates/summary.standard.txt.hbs"
-));
+impl Lang {
+    fn templates(self) -> &'static Templates {
+        match self {
+            Self::En => &EN_TEMPLATES,
+        }
+    }
+}
+
+/// The Nix versions that can read each `flake.lock` format version. Versions 5 and 6 were only
+/// written by pre-releases of Nix 2.4, while every release since writes version 7.
+const LOCK_VERSION_REQUIREMENTS: &[(usize, &str)] = &[
+    (5, "Nix 2.4 or later"),
+    (6, "Nix 2.4 or later"),
+    (7, "Nix 2.4 or later"),
+];
+
+/// The issues of each kind, which the standard templates list in sections of their own. These
+/// are merged into the rest of the template data, as a single `json!` call can't hold them all.
+#[derive(Serialize)]
+struct IssuesByKind<'a> {
+    /// Disallowed refs
+    has_disallowed: bool,
+    disallowed: Vec<&'a Issue>,
+    /// Outdated refs
+    has_outdated: bool,
+    outdated: Vec<&'a Issue>,
+    /// Non-upstream refs
+    has_non_upstream: bool,
+    non_upstream: Vec<&'a Issue>,
+    /// Owners a single typo away from the upstream owner
+    has_possible_typosquat: bool,
+    possible_typosquat: Vec<&'a Issue>,
+    /// Unexpected hash algorithms
+    has_unexpected_hash_algo: bool,
+    unexpected_hash_algo: Vec<&'a Issue>,
+    /// Indirect inputs
+    has_indirect_input: bool,
+    indirect_input: Vec<&'a Issue>,
+    /// Inputs that follow indirect inputs
+    has_transitively_indirect: bool,
+    transitively_indirect: Vec<&'a Issue>,
+    /// Insecure transports
+    has_insecure_transport: bool,
+    insecure_transport: Vec<&'a Issue>,
+    /// Too many inputs
+    has_too_many_inputs: bool,
+    too_many_inputs: Vec<&'a Issue>,
+    /// Future timestamps
+    has_future_timestamp: bool,
+    future_timestamp: Vec<&'a Issue>,
+    /// Unapproved revisions
+    has_unapproved_rev: bool,
+    unapproved_rev: Vec<&'a Issue>,
+    /// Missing NAR hashes
+    has_missing_hash: bool,
+    missing_hash: Vec<&'a Issue>,
+    /// Nixpkgs inputs without a locked revision
+    has_unlocked_rev: bool,
+    unlocked_rev: Vec<&'a Issue>,
+    /// Nodes that record different NAR hashes for the same revision
+    has_hash_conflict: bool,
+    hash_conflict: Vec<&'a Issue>,
+    /// Stable channels that reach their end of life soon or are already past it
+    has_approaching_eol: bool,
+    approaching_eol: Vec<&'a Issue>,
+    /// Inputs missing from the lock
+    has_unlocked_input: bool,
+    unlocked_input: Vec<&'a Issue>,
+    /// Inputs nested too deeply
+    has_excessive_depth: bool,
+    excessive_depth: Vec<&'a Issue>,
+    /// Transitive Nixpkgs inputs on other channels
+    has_mixed_nixpkgs_channels: bool,
+    mixed_nixpkgs_channels: Vec<&'a Issue>,
+    /// Nodes that couldn't be parsed
+    has_unparsable_node: bool,
+    unparsable_node: Vec<&'a Issue>,
+    /// Reformatted lockfiles
+    has_lock_format_drift: bool,
+    lock_format_drift: Vec<&'a Issue>,
+    /// Inputs locked from URLs other than the declared ones
+    has_original_mismatch: bool,
+    original_mismatch: Vec<&'a Issue>,
+    /// Data inputs locked as flakes
+    has_unexpected_flake: bool,
+    unexpected_flake: Vec<&'a Issue>,
+    /// Nixpkgs inputs locked from another branch than flake.nix declares
+    has_declared_ref_mismatch: bool,
+    declared_ref_mismatch: Vec<&'a Issue>,
+    /// Inputs that follow pull requests or work-in-progress branches
+    has_temporary_ref: bool,
+    temporary_ref: Vec<&'a Issue>,
+}
 
 pub(crate) struct Summary {
     pub issues: Vec<Issue>,
+    /// The number of issues with transitive inputs that are left out of `issues`.
+    num_hidden_transitive: usize,
+    num_inputs: usize,
     data: serde_json::Value,
     flake_lock_path: PathBuf,
     flake_check_config: FlakeCheckConfig,
     condition: Option<String>,
+    markdown_collapsible: bool,
+    lang: Lang,
+    /// Whether to color the console output.
+    color: bool,
 }
 
 impl Summary {
     pub(crate) fn new(
-        issues: &Vec<Issue>,
+        report: &CheckReport,
         flake_lock_path: PathBuf,
         flake_check_config: FlakeCheckConfig,
         allowed_refs: Vec<String>,
         condition: Option<String>,
+        markdown_collapsible: bool,
+        lang: Lang,
     ) -> Self {
+        // Issues with transitive inputs are left out of the summary (but not the report) unless
+        // requested, as they can easily outnumber the flake's own issues
+        let (issues, hidden): (Vec<Issue>, Vec<Issue>) =
+            report.issues.iter().cloned().partition(|issue| {
+                flake_check_config.include_transitive_in_summary
+                    || !is_transitive_input(&issue.input)
+            });
+        let issues = &issues;
+        let num_hidden_transitive = hidden.len();
+        let num_inputs = report.num_inputs;
+        let total_inputs = report.total_inputs;
         let num_issues = issues.len();
-        let clean = issues.is_empty();
+        // Checking zero inputs shouldn't be reported as a clean result
+        let no_inputs = num_inputs == 0;
+        let clean = report.issues.is_empty() && !no_inputs;
         let issue_word = if issues.len() == 1 { "issue" } else { "issues" };
+        // Machine-readable counts embedded in the Markdown summary as an HTML comment
+        let metadata = issue_counts(&report.issues).to_string();
 
         let data = if let Some(condition) = &condition {
-            let inputs_with_violations: Vec<String> = issues
-                .iter()
-                .filter(|i| i.kind.is_violation())
-                .map(|i| i.input.to_owned())
-                .collect();
+            let violations: Vec<&Issue> = issues.iter().filter(|i| i.kind.is_violation()).collect();
 
             json!({
                 "issues": issues,
                 "num_issues": num_issues,
+                "num_inputs": num_inputs,
+                "total_inputs": total_inputs,
+                "no_inputs": no_inputs,
                 "clean": clean,
-                "dirty": !clean,
+                "dirty": !issues.is_empty(),
                 "issue_word": issue_word,
+                "metadata": metadata,
+                "num_hidden_transitive": num_hidden_transitive,
                 "condition": condition,
-                "inputs_with_violations": inputs_with_violations,
+                // Each violation carries a snapshot of the input's CEL variables
+                "violations": violations,
+                "clean_inputs": report.clean_inputs,
             })
         } else {
             let disallowed: Vec<&Issue> =
@@ -72,41 +264,288 @@ impl Summary {
             let outdated: Vec<&Issue> = issues.iter().filter(|i| i.kind.is_outdated()).collect();
             let non_upstream: Vec<&Issue> =
                 issues.iter().filter(|i| i.kind.is_non_upstream()).collect();
+            let possible_typosquat: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_possible_typosquat())
+                .collect();
+            let unlocked_rev: Vec<&Issue> =
+                issues.iter().filter(|i| i.kind.is_unlocked_rev()).collect();
+            let unexpected_hash_algo: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_unexpected_hash_algo())
+                .collect();
+            let indirect_input: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_indirect_input())
+                .collect();
+            let transitively_indirect: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_transitively_indirect())
+                .collect();
+            let insecure_transport: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_insecure_transport())
+                .collect();
+            let too_many_inputs: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_too_many_inputs())
+                .collect();
+            let future_timestamp: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_future_timestamp())
+                .collect();
+            let unapproved_rev: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_unapproved_rev())
+                .collect();
+            let missing_hash: Vec<&Issue> =
+                issues.iter().filter(|i| i.kind.is_missing_hash()).collect();
+            let unlocked_input: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_unlocked_input())
+                .collect();
+            let excessive_depth: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_excessive_depth())
+                .collect();
+            let mixed_nixpkgs_channels: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_mixed_nixpkgs_channels())
+                .collect();
+            let unparsable_node: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_unparsable_node())
+                .collect();
+            let hash_conflict: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_hash_conflict())
+                .collect();
+            let approaching_eol: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_approaching_eol())
+                .collect();
+            let lock_format_drift: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_lock_format_drift())
+                .collect();
+            let original_mismatch: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_original_mismatch())
+                .collect();
+            let unexpected_flake: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_unexpected_flake())
+                .collect();
+            let declared_ref_mismatch: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_declared_ref_mismatch())
+                .collect();
+            let temporary_ref: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.kind.is_temporary_ref())
+                .collect();
 
-            json!({
+            let by_kind = IssuesByKind {
+                has_disallowed: !disallowed.is_empty(),
+                disallowed,
+                has_outdated: !outdated.is_empty(),
+                outdated,
+                has_non_upstream: !non_upstream.is_empty(),
+                non_upstream,
+                has_possible_typosquat: !possible_typosquat.is_empty(),
+                possible_typosquat,
+                has_unexpected_hash_algo: !unexpected_hash_algo.is_empty(),
+                unexpected_hash_algo,
+                has_indirect_input: !indirect_input.is_empty(),
+                indirect_input,
+                has_transitively_indirect: !transitively_indirect.is_empty(),
+                transitively_indirect,
+                has_insecure_transport: !insecure_transport.is_empty(),
+                insecure_transport,
+                has_too_many_inputs: !too_many_inputs.is_empty(),
+                too_many_inputs,
+                has_future_timestamp: !future_timestamp.is_empty(),
+                future_timestamp,
+                has_unapproved_rev: !unapproved_rev.is_empty(),
+                unapproved_rev,
+                has_missing_hash: !missing_hash.is_empty(),
+                missing_hash,
+                has_unlocked_rev: !unlocked_rev.is_empty(),
+                unlocked_rev,
+                has_hash_conflict: !hash_conflict.is_empty(),
+                hash_conflict,
+                has_approaching_eol: !approaching_eol.is_empty(),
+                approaching_eol,
+                has_unlocked_input: !unlocked_input.is_empty(),
+                unlocked_input,
+                has_excessive_depth: !excessive_depth.is_empty(),
+                excessive_depth,
+                has_mixed_nixpkgs_channels: !mixed_nixpkgs_channels.is_empty(),
+                mixed_nixpkgs_channels,
+                has_unparsable_node: !unparsable_node.is_empty(),
+                unparsable_node,
+                has_lock_format_drift: !lock_format_drift.is_empty(),
+                lock_format_drift,
+                has_original_mismatch: !original_mismatch.is_empty(),
+                original_mismatch,
+                has_unexpected_flake: !unexpected_flake.is_empty(),
+                unexpected_flake,
+                has_declared_ref_mismatch: !declared_ref_mismatch.is_empty(),
+                declared_ref_mismatch,
+                has_temporary_ref: !temporary_ref.is_empty(),
+                temporary_ref,
+            };
+
+            let mut data = json!({
                 "issues": issues,
                 "num_issues": num_issues,
+                "num_inputs": num_inputs,
+                "total_inputs": total_inputs,
+                "no_inputs": no_inputs,
                 "clean": clean,
-                "dirty": !clean,
+                "dirty": !issues.is_empty(),
                 "issue_word": issue_word,
-                // Disallowed refs
-                "has_disallowed": !disallowed.is_empty(),
-                "disallowed": disallowed,
-                // Outdated refs
-                "has_outdated": !outdated.is_empty(),
-                "outdated": outdated,
-                // Non-upstream refs
-                "has_non_upstream": !non_upstream.is_empty(),
-                "non_upstream": non_upstream,
+                "metadata": metadata,
+                "num_hidden_transitive": num_hidden_transitive,
+                // The checks that ran
+                "checks": checks_performed(&flake_check_config),
+                // The inputs without issues (with --report-clean-inputs)
+                "clean_inputs": report.clean_inputs,
+                // Compatibility
+                "lock_version": report.lock_version,
+                "lock_version_requirement": report.lock_version.and_then(lock_version_requirement),
+                // The supported refs in use
+                "matched_refs": report.matched_refs,
+                // The span of input ages
+                "input_ages": report.input_ages,
+                // How the age of the oldest input changed since the last run
+                "age_change": report.age_change,
                 // Constants
-                "max_days": MAX_DAYS,
+                "max_days": flake_check_config.max_days,
+                "upstream_owner": flake_check_config.upstream_owner,
+                "upstream_repo": flake_check_config.upstream_repo.as_deref().unwrap_or("nixpkgs"),
+                "expected_hash_algo": EXPECTED_HASH_ALGO,
                 "supported_ref_names": allowed_refs,
-            })
+            });
+            if let (serde_json::Value::Object(data), serde_json::Value::Object(by_kind)) =
+                (&mut data, json!(by_kind))
+            {
+                data.extend(by_kind);
+            }
+            data
         };
 
         Self {
             issues: issues.to_vec(),
+            num_hidden_transitive,
+            num_inputs,
             data,
             flake_lock_path,
             flake_check_config,
             condition,
+            markdown_collapsible,
+            lang,
+            color: false,
         }
     }
 
+    /// The summary with a custom heading in place of the default one.
+    pub(crate) fn with_title(mut self, title: &str) -> Self {
+        self.data["title"] = json!(title);
+        self
+    }
+
+    /// The summary with its issues left out of the text, since they're printed to stderr as
+    /// [Summary::issue_lines] instead.
+    pub(crate) fn with_issues_on_stderr(mut self) -> Self {
+        self.data["issues_on_stderr"] = json!(true);
+        self
+    }
+
+    /// One line per issue in the summary, like `flake.lock:5:5: warning: nixpkgs: outdated (45 days
+    /// old)`, with the issue's location in the `flake.lock` if it has one.
+    pub(crate) fn issue_lines(&self) -> Vec<String> {
+        let file = self.flake_lock_path.display();
+        self.issues
+            .iter()
+            .map(|issue| {
+                let location = match issue.location {
+                    Some((line, column)) => format!("{file}:{line}:{column}"),
+                    None => file.to_string(),
+                };
+                format!(
+                    "{location}: {}: {}: {} ({})",
+                    issue.severity(&self.flake_check_config),
+                    issue.input,
+                    issue.kind,
+                    issue.kind.detail()
+                )
+            })
+            .collect()
+    }
+
+    /// The summary with its console output colored (see [crate::color::use_color]).
+    pub(crate) fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The summary with outdated inputs listed once per channel that they follow rather than one
+    /// by one, which condenses flakes whose inputs all went stale together.
+    pub(crate) fn with_outdated_by_channel(mut self) -> Self {
+        let mut channels: BTreeMap<Option<&str>, (Vec<&str>, i64)> = BTreeMap::new();
+        for issue in &self.issues {
+            if let IssueKind::Outdated(outdated) = &issue.kind {
+                let (inputs, max_days_old) =
+                    channels.entry(outdated.channel.as_deref()).or_default();
+                inputs.push(&issue.input);
+                *max_days_old = (*max_days_old).max(outdated.num_days_old);
+            }
+        }
+
+        // Inputs without a channel come last
+        let (no_channel, channels): (Vec<_>, Vec<_>) = channels
+            .into_iter()
+            .partition(|(channel, _)| channel.is_none());
+        let outdated_by_channel: Vec<serde_json::Value> = channels
+            .into_iter()
+            .chain(no_channel)
+            .map(|(channel, (inputs, max_days_old))| {
+                json!({
+                    "channel": channel,
+                    "inputs": inputs,
+                    "num_inputs": inputs.len(),
+                    "max_days_old": max_days_old,
+                })
+            })
+            .collect();
+
+        if !outdated_by_channel.is_empty() {
+            self.data["outdated_by_channel"] = json!(outdated_by_channel);
+        }
+        self
+    }
+
+    /// Merge the summaries of several `flake.lock` files into one report, with a section for each
+    /// file (rendered as on its own) and totals across them.
+    pub(crate) fn merge(summaries: Vec<Summary>) -> MergedSummary {
+        MergedSummary { summaries }
+    }
+
     pub fn console_log_errors(&self) -> Result<(), FlakeCheckerError> {
         let file = self.flake_lock_path.to_string_lossy();
 
-        if self.issues.is_empty() {
+        if self.num_inputs == 0 {
+            println!(
+                "The Determinate Nix Flake Checker scanned {file} but no inputs matched the selection"
+            );
+            // Issues with the lock as a whole, like too many inputs, are still reported
+            if self.issues.is_empty() {
+                return Ok(());
+            }
+        }
+
+        if self.issues.is_empty() && self.num_hidden_transitive == 0 {
             println!("The Determinate Nix Flake Checker scanned {file} and found no issues");
             return Ok(());
         }
@@ -118,25 +557,38 @@ impl Summary {
             );
             println!("The following inputs violate that condition:\n");
             for issue in self.issues.iter() {
-                println!("* {}", issue.input);
+                println!("* {} ({})", issue.input, issue.kind.detail());
             }
         } else {
-            let level = if self.flake_check_config.fail_mode {
-                "error"
-            } else {
-                "warning"
-            };
-
             for issue in self.issues.iter() {
                 let input = &issue.input;
+                let level = issue.severity(&self.flake_check_config);
 
                 let message: Option<String> = match &issue.kind {
                     IssueKind::Disallowed(disallowed) => {
-                        if self.flake_check_config.check_supported {
-                            let reference = &disallowed.reference;
+                        if disallowed.reference.is_empty() {
                             Some(format!(
-                                "the `{input}` input uses the non-supported Git branch `{reference}` for Nixpkgs"
-                             ))
+                                "the `{input}` input is pinned without a Git branch for Nixpkgs"
+                            ))
+                        } else if self.flake_check_config.check_supported
+                            || self.flake_check_config.strict_supported
+                            || !self.flake_check_config.deny_ref_patterns.is_empty()
+                        {
+                            let reference = &disallowed.reference;
+                            Some(match (disallowed.reason, &disallowed.upgrade_to) {
+                                (DisallowedReason::EndOfLife, Some(upgrade_to)) => format!(
+                                    "the `{input}` input uses `{reference}`, which is end-of-life; upgrade to `{upgrade_to}`"
+                                ),
+                                (DisallowedReason::EndOfLife, None) => format!(
+                                    "the `{input}` input uses `{reference}`, which is end-of-life"
+                                ),
+                                (DisallowedReason::Unknown, _) => format!(
+                                    "the `{input}` input uses `{reference}`, which isn't a recognized Nixpkgs channel"
+                                ),
+                                (DisallowedReason::Unsupported, _) => format!(
+                                    "the `{input}` input uses the non-supported Git branch `{reference}` for Nixpkgs"
+                                ),
+                            })
                         } else {
                             None
                         }
@@ -144,47 +596,254 @@ impl Summary {
                     IssueKind::Outdated(outdated) => {
                         if self.flake_check_config.check_outdated {
                             let num_days_old = outdated.num_days_old;
+                            let last_modified = &outdated.last_modified;
+                            let max_days = self.flake_check_config.max_days;
                             Some(format!(
-                                "the `{input}` input is {num_days_old} days old (the max allowed is {MAX_DAYS})"
+                                "the `{input}` input was last updated {last_modified}, {num_days_old} days ago (the max allowed is {max_days} days)"
                             ))
                         } else {
                             None
                         }
                     }
                     IssueKind::NonUpstream(non_upstream) => {
-                        if self.flake_check_config.check_owner {
+                        if self.flake_check_config.check_owner
+                            || self.flake_check_config.strict_nixpkgs_owner
+                        {
                             let owner = &non_upstream.owner;
+                            let upstream_owner = &self.flake_check_config.upstream_owner;
+                            Some(
+                                match (&non_upstream.repo, &self.flake_check_config.upstream_repo) {
+                                    (Some(repo), Some(upstream_repo)) => format!(
+                                        "the `{input}` input comes from the non-upstream repository `{owner}/{repo}` rather than `{upstream_owner}/{upstream_repo}` (upstream)"
+                                    ),
+                                    _ => format!(
+                                        "the `{input}` input has the non-upstream owner `{owner}` rather than `{upstream_owner}` (upstream)"
+                                    ),
+                                },
+                            )
+                        } else {
+                            None
+                        }
+                    }
+                    IssueKind::Violation(violation) => Some(match &violation.rule {
+                        Some(rule) => format!("policy violation of the `{rule}` rule"),
+                        None => String::from("policy violation"),
+                    }),
+                    IssueKind::UnexpectedHashAlgo(unexpected_hash_algo) => {
+                        if self.flake_check_config.check_hash_algo {
+                            let algo = &unexpected_hash_algo.algo;
                             Some(format!(
-                                "the `{input}` input has the non-upstream owner `{owner}` rather than `NixOS` (upstream)"
+                                "the `{input}` input's NAR hash uses the `{algo}` algorithm rather than `{EXPECTED_HASH_ALGO}`"
                             ))
                         } else {
                             None
                         }
                     }
-                    IssueKind::Violation => Some(String::from("policy violation")),
+                    IssueKind::IndirectInput(indirect_input) => {
+                        if self.flake_check_config.deny_indirect {
+                            let id = &indirect_input.id;
+                            Some(format!(
+                                "the `{input}` input is resolved from the `{id}` flake registry entry rather than an explicit flake reference"
+                            ))
+                        } else {
+                            None
+                        }
+                    }
+                    IssueKind::TransitivelyIndirect(transitively_indirect) => {
+                        let via = &transitively_indirect.via;
+                        let follows = &transitively_indirect.follows;
+                        let id = &transitively_indirect.registry_id;
+                        Some(format!(
+                            "the `{input}` input's `{via}` follows `{follows}`, which is resolved from the `{id}` flake registry entry"
+                        ))
+                    }
+                    IssueKind::InsecureTransport(insecure_transport) => {
+                        if self.flake_check_config.require_https {
+                            let url = &insecure_transport.url;
+                            Some(format!(
+                                "the `{input}` input is fetched from `{url}` without TLS"
+                            ))
+                        } else {
+                            None
+                        }
+                    }
+                    IssueKind::TooManyInputs(too_many_inputs) => {
+                        let count = too_many_inputs.count;
+                        let max = too_many_inputs.max;
+                        Some(format!(
+                            "the flake.lock has {count} inputs, more than the maximum of {max}"
+                        ))
+                    }
+                    IssueKind::FutureTimestamp(future_timestamp) => {
+                        let last_modified = &future_timestamp.last_modified;
+                        let num_days_ahead = future_timestamp.num_days_ahead;
+                        Some(format!(
+                            "the `{input}` input was supposedly last updated {last_modified}, {num_days_ahead} days in the future"
+                        ))
+                    }
+                    IssueKind::UnapprovedRev(unapproved_rev) => {
+                        let repo = &unapproved_rev.repo;
+                        let rev = &unapproved_rev.rev;
+                        Some(format!(
+                            "the `{input}` input is locked to `{repo}` at the unapproved revision `{rev}`"
+                        ))
+                    }
+                    IssueKind::MissingHash(missing_hash) => {
+                        let locked_type = &missing_hash.locked_type;
+                        Some(format!(
+                            "the `{input}` input (of type `{locked_type}`) has no NAR hash in the flake.lock"
+                        ))
+                    }
+                    IssueKind::UnlockedRev(unlocked_rev) => {
+                        let unlocked_type = &unlocked_rev.unlocked_type;
+                        Some(format!(
+                            "the `{input}` input (of type `{unlocked_type}`) isn't locked to a revision in the flake.lock"
+                        ))
+                    }
+                    IssueKind::HashConflict(hash_conflict) => {
+                        let repo = &hash_conflict.locked_repo;
+                        let rev = &hash_conflict.locked_rev;
+                        let nodes = hash_conflict.conflicting_nodes.join("`, `");
+                        Some(format!(
+                            "the `{nodes}` nodes are all locked to `{repo}` at `{rev}` but record different NAR hashes"
+                        ))
+                    }
+                    IssueKind::PossibleTyposquat(possible_typosquat) => {
+                        let owner = &possible_typosquat.suspicious_owner;
+                        let resembles = &possible_typosquat.resembles;
+                        Some(format!(
+                            "the `{input}` input has the owner `{owner}`, which is a single typo away from `{resembles}` and may be a typosquat"
+                        ))
+                    }
+                    IssueKind::ApproachingEol(approaching_eol) => {
+                        let channel = &approaching_eol.channel;
+                        let days_left = approaching_eol.days_left;
+                        if days_left < 0 {
+                            let days_ago = -days_left;
+                            Some(format!(
+                                "the `{input}` input follows `{channel}`, which reached its end of life {days_ago} days ago"
+                            ))
+                        } else {
+                            Some(format!(
+                                "the `{input}` input follows `{channel}`, which reaches its end of life in {days_left} days"
+                            ))
+                        }
+                    }
+                    IssueKind::UnlockedInput(_) => Some(format!(
+                        "the `{input}` input is declared in flake.nix but missing from the flake.lock"
+                    )),
+                    IssueKind::ExcessiveDepth(excessive_depth) => {
+                        let path = &excessive_depth.path;
+                        let depth = excessive_depth.depth;
+                        let max_depth = excessive_depth.max_depth;
+                        Some(format!(
+                            "the flake.lock's input graph reaches depth {depth} at `{path}`, deeper than the maximum of {max_depth}"
+                        ))
+                    }
+                    IssueKind::MixedNixpkgsChannels(mixed_nixpkgs_channels) => {
+                        let inputs = mixed_nixpkgs_channels.inputs.join("`, `");
+                        let channels = mixed_nixpkgs_channels.channels.join("`, `");
+                        Some(format!(
+                            "the transitive Nixpkgs inputs `{inputs}` follow a different channel than the flake's own (channels in use: `{channels}`)"
+                        ))
+                    }
+                    IssueKind::UnparsableNode(unparsable_node) => {
+                        let error = &unparsable_node.error;
+                        Some(format!(
+                            "the flake.lock node `{input}` couldn't be parsed ({error}), so it wasn't checked"
+                        ))
+                    }
+                    IssueKind::LockFormatDrift(lock_format_drift) => {
+                        let line = lock_format_drift.first_differing_line;
+                        Some(format!(
+                            "the flake.lock isn't formatted the way Nix writes it, starting at line {line}"
+                        ))
+                    }
+                    IssueKind::OriginalMismatch(original_mismatch) => {
+                        let declared_url = &original_mismatch.declared_url;
+                        let original_url = &original_mismatch.original_url;
+                        Some(format!(
+                            "the `{input}` input is declared as `{declared_url}` in flake.nix but was locked from `{original_url}`"
+                        ))
+                    }
+                    IssueKind::UnexpectedFlake(unexpected_flake) => {
+                        let data_url = &unexpected_flake.data_url;
+                        Some(format!(
+                            "the `{input}` input from `{data_url}` looks like data but is locked as a flake"
+                        ))
+                    }
+                    IssueKind::DeclaredRefMismatch(declared_ref_mismatch) => {
+                        let declared_ref = &declared_ref_mismatch.declared_ref;
+                        Some(match &declared_ref_mismatch.locked_ref {
+                            Some(locked_ref) => format!(
+                                "the `{input}` input follows `{declared_ref}` in flake.nix but is locked from `{locked_ref}`"
+                            ),
+                            None => format!(
+                                "the `{input}` input follows `{declared_ref}` in flake.nix but is locked without a ref"
+                            ),
+                        })
+                    }
+                    IssueKind::TemporaryRef(temporary_ref) => {
+                        let reference = &temporary_ref.temporary_ref;
+                        Some(match temporary_ref.pull_request {
+                            Some(number) => format!(
+                                "the `{input}` input follows pull request #{number} (`{reference}`), which is meant to be temporary"
+                            ),
+                            None => format!(
+                                "the `{input}` input follows the work-in-progress branch `{reference}`, which is meant to be temporary"
+                            ),
+                        })
+                    }
                 };
 
                 if let Some(message) = message {
-                    println!("{}: {}", level.to_uppercase(), message);
+                    let label = severity_label(level, self.color);
+                    match issue.location {
+                        Some((line, column)) => {
+                            println!("{label}: {message} (at {file}:{line}:{column})")
+                        }
+                        None => println!("{label}: {message}"),
+                    }
                 }
             }
         }
+
+        if self.num_hidden_transitive > 0 {
+            println!(
+                "{} issue(s) with transitive inputs in {file} aren't shown; set --include-transitive-in-summary to show them",
+                self.num_hidden_transitive
+            );
+        }
         Ok(())
     }
 
-    pub fn generate_markdown(&self) -> Result<(), FlakeCheckerError> {
+    pub fn render_markdown(&self) -> Result<String, FlakeCheckerError> {
+        let templates = self.lang.templates();
         let template = if self.condition.is_some() {
-            CEL_MARKDOWN_TEMPLATE
+            templates.cel_markdown
         } else {
-            STANDARD_MARKDOWN_TEMPLATE
+            templates.standard_markdown
+        };
+        let section = if self.markdown_collapsible {
+            templates.collapsible_markdown_section
+        } else {
+            templates.markdown_section
         };
 
         let mut handlebars = Handlebars::new();
+        handlebars.register_helper("code_spans", Box::new(code_spans));
 
         handlebars
             .register_template_string("summary.md", template)
             .map_err(Box::new)?;
-        let summary_md = handlebars.render("summary.md", &self.data)?;
+        handlebars
+            .register_partial("section", section)
+            .map_err(Box::new)?;
+        Ok(handlebars.render("summary.md", &self.data)?)
+    }
+
+    pub fn generate_markdown(&self) -> Result<(), FlakeCheckerError> {
+        let summary_md = self.render_markdown()?;
 
         let summary_md_filepath = std::env::var("GITHUB_STEP_SUMMARY")?;
         let mut summary_md_file = OpenOptions::new()
@@ -196,11 +855,12 @@ impl Summary {
         Ok(())
     }
 
-    pub fn generate_text(&self) -> Result<(), FlakeCheckerError> {
+    pub fn render_text(&self) -> Result<String, FlakeCheckerError> {
+        let templates = self.lang.templates();
         let template = if self.condition.is_some() {
-            CEL_TEXT_TEMPLATE
+            templates.cel_text
         } else {
-            STANDARD_TEXT_TEMPLATE
+            templates.standard_text
         };
 
         let mut handlebars = Handlebars::new();
@@ -208,10 +868,1149 @@ impl Summary {
             .register_template_string("summary.txt", template)
             .map_err(Box::new)?;
 
-        let summary_txt = handlebars.render("summary.txt", &self.data)?;
+        Ok(handlebars.render("summary.txt", &self.data)?)
+    }
+
+    pub fn generate_text(&self) -> Result<(), FlakeCheckerError> {
+        let summary_txt = self.render_text()?;
+
+        print!("{}", summary_txt);
+
+        Ok(())
+    }
+
+    /// The summary as the section for its `flake.lock` in the text summary of several lockfiles,
+    /// headed by the lockfile's path and number of issues.
+    pub fn render_text_section(&self) -> Result<String, FlakeCheckerError> {
+        let mut handlebars = Handlebars::new();
+        handlebars
+            .register_template_string("section.txt", self.lang.templates().merged_text_section)
+            .map_err(Box::new)?;
+
+        Ok(handlebars.render("section.txt", &file_data(self, self.render_text()?))?)
+    }
+
+    /// Print the summary's section of the text summary of several lockfiles, flushing it so that
+    /// it shows up while later lockfiles are still being checked. The totals across the lockfiles
+    /// follow once they're all checked (see [MergedSummary::generate_text]).
+    pub fn generate_text_section(&self) -> Result<(), FlakeCheckerError> {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(self.render_text_section()?.as_bytes())?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+
+    /// The summary as a self-contained HTML page, with a section for each kind of issue that's
+    /// colored by the issues' highest severity, followed by an overview of the flake's inputs.
+    pub fn render_html(&self) -> Result<String, FlakeCheckerError> {
+        let config = &self.flake_check_config;
+        let sections: Vec<serde_json::Value> = ISSUE_KINDS
+            .iter()
+            .filter_map(|kind| {
+                let issues: Vec<&Issue> = self
+                    .issues
+                    .iter()
+                    .filter(|issue| issue.kind.name() == *kind)
+                    .collect();
+                let severity = issues.iter().map(|issue| issue.severity(config)).max()?;
+                let name = CHECKS
+                    .iter()
+                    .find(|check| check.issue_kinds.contains(kind))
+                    .map_or(*kind, |check| check.name);
+                let issues: Vec<serde_json::Value> = issues
+                    .iter()
+                    .map(|issue| json!({ "input": issue.input, "detail": issue.kind.detail() }))
+                    .collect();
+
+                Some(json!({
+                    "kind": kind,
+                    "name": name,
+                    "severity": severity,
+                    "explanation": explanation(kind, config.max_days),
+                    "issues": issues,
+                }))
+            })
+            .collect();
+
+        let mut data = self.data.clone();
+        data["flake_lock"] = json!(self.flake_lock_path.display().to_string());
+        data["has_errors"] = json!(has_errors(&self.issues, config));
+        data["sections"] = json!(sections);
+
+        let mut handlebars = Handlebars::new();
+        handlebars
+            .register_template_string("summary.html", self.lang.templates().standard_html)
+            .map_err(Box::new)?;
+
+        Ok(handlebars.render("summary.html", &data)?)
+    }
+}
+
+/// The summaries of several `flake.lock` files, as merged by [Summary::merge].
+pub(crate) struct MergedSummary {
+    summaries: Vec<Summary>,
+}
+
+impl MergedSummary {
+    /// The template data, where `sections` are the summaries rendered in the same format.
+    fn data(&self, sections: Vec<String>) -> serde_json::Value {
+        let issues: Vec<Issue> = self
+            .summaries
+            .iter()
+            .flat_map(|summary| summary.issues.iter().cloned())
+            .collect();
+        let num_issues = issues.len();
+
+        let files: Vec<serde_json::Value> = self
+            .summaries
+            .iter()
+            .zip(sections)
+            .map(|(summary, section)| file_data(summary, section))
+            .collect();
+
+        json!({
+            // The first summary's custom heading (they're all made with the same settings)
+            "title": self.summaries.first().map(|summary| &summary.data["title"]),
+            "files": files,
+            "num_files": self.summaries.len(),
+            "num_dirty": self.summaries.iter().filter(|summary| !summary.issues.is_empty()).count(),
+            "num_inputs": self.summaries.iter().map(|summary| summary.num_inputs).sum::<usize>(),
+            "num_issues": num_issues,
+            "issue_word": if num_issues == 1 { "issue" } else { "issues" },
+            "metadata": issue_counts(&issues).to_string(),
+        })
+    }
+
+    fn lang(&self) -> Lang {
+        self.summaries
+            .first()
+            .map(|summary| summary.lang)
+            .unwrap_or_default()
+    }
+
+    pub fn render_markdown(&self) -> Result<String, FlakeCheckerError> {
+        let sections = self
+            .summaries
+            .iter()
+            .map(Summary::render_markdown)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut handlebars = Handlebars::new();
+        handlebars
+            .register_template_string("summary.md", self.lang().templates().merged_markdown)
+            .map_err(Box::new)?;
+        Ok(handlebars.render("summary.md", &self.data(sections))?)
+    }
+
+    pub fn generate_markdown(&self) -> Result<(), FlakeCheckerError> {
+        let summary_md = self.render_markdown()?;
+
+        let summary_md_filepath = std::env::var("GITHUB_STEP_SUMMARY")?;
+        let mut summary_md_file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(summary_md_filepath)?;
+        summary_md_file.write_all(summary_md.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// The totals across the lockfiles, which end the text summary after each lockfile's section
+    /// (see [Summary::render_text_section]). Unlike in Markdown, the sections are printed as soon
+    /// as each lockfile is checked, so they aren't part of the merged text summary.
+    pub fn render_text(&self) -> Result<String, FlakeCheckerError> {
+        let mut handlebars = Handlebars::new();
+        handlebars
+            .register_template_string("summary.txt", self.lang().templates().merged_text)
+            .map_err(Box::new)?;
+        Ok(handlebars.render("summary.txt", &self.data(vec![]))?)
+    }
+
+    pub fn generate_text(&self) -> Result<(), FlakeCheckerError> {
+        let summary_txt = self.render_text()?;
 
         print!("{}", summary_txt);
 
         Ok(())
     }
 }
+
+/// The template data for a lockfile's section of a merged summary, where `section` is its summary
+/// rendered in the same format.
+fn file_data(summary: &Summary, section: String) -> serde_json::Value {
+    let num_issues = summary.issues.len();
+    json!({
+        "path": summary.flake_lock_path.display().to_string(),
+        "num_issues": num_issues,
+        "issue_word": if num_issues == 1 { "issue" } else { "issues" },
+        "dirty": num_issues > 0,
+        "section": section,
+    })
+}
+
+/// Each check and whether it's enabled in the config (and thus ran).
+fn checks_performed(config: &FlakeCheckConfig) -> serde_json::Value {
+    CHECKS
+        .iter()
+        .map(|check| json!({ "name": check.name, "enabled": (check.enabled)(config) }))
+        .collect()
+}
+
+/// The Nix versions that can read a `flake.lock` with the given format version (if it's known).
+fn lock_version_requirement(version: usize) -> Option<&'static str> {
+    LOCK_VERSION_REQUIREMENTS
+        .iter()
+        .find(|(v, _)| *v == version)
+        .map(|(_, requirement)| *requirement)
+}
+
+fn issue_counts(issues: &[Issue]) -> serde_json::Value {
+    let mut counts = serde_json::Map::new();
+    for kind in ISSUE_KINDS {
+        let count = issues.iter().filter(|i| i.kind.name() == *kind).count();
+        counts.insert(kind.to_string(), json!(count));
+    }
+    counts.insert(String::from("total"), json!(issues.len()));
+    serde_json::Value::Object(counts)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::flake::FlakeCheckConfig;
+    use crate::issue::{
+        ApproachingEol, Disallowed, DisallowedReason, Issue, IssueKind, NonUpstream, Outdated,
+        Violation, ViolationSnapshot, ISSUE_KINDS,
+    };
+    use crate::report::{CheckReport, CleanInput, InputAges};
+    use crate::severity::SeverityMap;
+
+    use super::{Lang, Summary};
+
+    fn summary(issues: Vec<Issue>) -> Summary {
+        Summary::new(
+            &CheckReport::new(b"{}", issues, 1, 1),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        )
+    }
+
+    #[test]
+    fn default_lang() {
+        assert_eq!(Lang::default(), Lang::En);
+
+        // The English templates are used
+        let text = summary(vec![]).render_text().unwrap();
+        assert!(text.contains("Checks performed:"), "{text}");
+    }
+
+    #[test]
+    fn custom_title() {
+        let title = "Flake Checker — backend/flake.lock";
+
+        let text = summary(vec![]).with_title(title).render_text().unwrap();
+        assert_eq!(text.lines().next(), Some(title));
+        let markdown = summary(vec![]).with_title(title).render_markdown().unwrap();
+        assert!(markdown.contains(&format!(
+            "\n# ![](https://avatars.githubusercontent.com/u/80991770?s=30) {title}\n"
+        )));
+
+        // Without a custom title, the default heading is used
+        let text = summary(vec![]).render_text().unwrap();
+        assert_eq!(text.lines().next(), Some("Flake checker results:"));
+        let markdown = summary(vec![]).render_markdown().unwrap();
+        assert!(markdown.contains(
+            "# ![](https://avatars.githubusercontent.com/u/80991770?s=30) Flake checkup\n"
+        ));
+    }
+
+    #[test]
+    fn merged_summaries() {
+        let disallowed = |input: &str| Issue {
+            input: String::from(input),
+            kind: IssueKind::Disallowed(Disallowed {
+                reference: String::from("this-should-fail"),
+                ..Default::default()
+            }),
+            location: None,
+        };
+        let summary = |path: &str, issues: Vec<Issue>| {
+            Summary::new(
+                &CheckReport::new(b"{}", issues, 2, 2),
+                PathBuf::from(path),
+                FlakeCheckConfig::default(),
+                vec![],
+                None,
+                false,
+                Lang::default(),
+            )
+        };
+        let summaries = || {
+            vec![
+                summary("a/flake.lock", vec![]),
+                summary(
+                    "b/flake.lock",
+                    vec![disallowed("nixpkgs"), disallowed("nixpkgs-stable")],
+                ),
+            ]
+        };
+
+        // Each file's section is its own summary, and the totals come once they're all printed
+        let mut text: String = summaries()
+            .iter()
+            .map(|summary| summary.render_text_section().unwrap())
+            .collect();
+        text.push_str(&Summary::merge(summaries()).render_text().unwrap());
+        let a = text.find("== a/flake.lock: 0 issues ==").unwrap();
+        let b = text.find("== b/flake.lock: 2 issues ==").unwrap();
+        let totals = text.rfind("Flake checker results:").unwrap();
+        assert!(a < b && b < totals, "{text}");
+        assert!(text[a..b].contains("didn't identify any issues"), "{text}");
+        assert!(text[b..totals].contains("this-should-fail"), "{text}");
+        assert!(
+            text[totals..].ends_with(
+                "scanned 2 flake.lock files with 4 inputs\nand discovered 2 issues in 1 of them.\n"
+            ),
+            "{text}"
+        );
+
+        let markdown = Summary::merge(summaries()).render_markdown().unwrap();
+        assert!(markdown.starts_with(&format!(
+            "<!-- flake-checker: {} -->",
+            super::issue_counts(&[disallowed("nixpkgs"), disallowed("nixpkgs-stable")])
+        )));
+        assert!(
+            markdown.contains("<summary>✅ <code>a/flake.lock</code>: 0 issues</summary>"),
+            "{markdown}"
+        );
+        assert!(
+            markdown.contains(
+                "<details open>\n<summary>⚠️ <code>b/flake.lock</code>: 2 issues</summary>"
+            ),
+            "{markdown}"
+        );
+    }
+
+    #[test]
+    fn outdated_by_channel() {
+        let outdated = |input: &str, channel: Option<&str>, num_days_old| Issue {
+            input: String::from(input),
+            kind: IssueKind::Outdated(Outdated {
+                channel: channel.map(String::from),
+                ..Outdated::new(1689078114, num_days_old)
+            }),
+            location: None,
+        };
+        let issues = vec![
+            outdated("nixpkgs", Some("nixos-unstable"), 45),
+            outdated("home-manager/nixpkgs", Some("nixos-unstable"), 60),
+            outdated("nixpkgs-stable", Some("nixos-24.11"), 40),
+            outdated("nixpkgs-tarball", None, 50),
+            outdated("crane/nixpkgs", Some("nixos-unstable"), 45),
+        ];
+        let config = FlakeCheckConfig {
+            include_transitive_in_summary: true,
+            ..Default::default()
+        };
+        let summary = Summary::new(
+            &CheckReport::new(b"{}", issues, 5, 5),
+            PathBuf::from("flake.lock"),
+            config,
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        )
+        .with_outdated_by_channel();
+
+        let markdown = summary.render_markdown().unwrap();
+        let lines: Vec<&str> = markdown
+            .lines()
+            .filter(|line| line.starts_with("* "))
+            .filter(|line| line.contains("days old"))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "* The `nixos-24.11` channel: `nixpkgs-stable` (**40** days old)",
+                "* The `nixos-unstable` channel: `nixpkgs`, `home-manager/nixpkgs`, `crane/nixpkgs` (up to **60** days old)",
+                "* No channel: `nixpkgs-tarball` (**50** days old)",
+            ]
+        );
+        assert!(!markdown.contains("was last updated"));
+
+        let text = summary.render_text().unwrap();
+        let lines: Vec<&str> = text
+            .lines()
+            .filter(|line| line.starts_with("> ") && line.contains("days old"))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "> The nixos-24.11 channel: nixpkgs-stable (40 days old)",
+                "> The nixos-unstable channel: nixpkgs, home-manager/nixpkgs, crane/nixpkgs (up to 60 days old)",
+                "> No channel: nixpkgs-tarball (50 days old)",
+            ]
+        );
+    }
+
+    #[test]
+    fn outdated_date() {
+        let summary = summary(vec![Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+            location: None,
+        }]);
+
+        let text = summary.render_text().unwrap();
+        assert!(text.contains("The nixpkgs input was last updated 2023-07-11, 45 days ago"));
+
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains("The `nixpkgs` input was last updated 2023-07-11, **45** days ago")
+        );
+    }
+
+    #[test]
+    fn overdue_eol() {
+        let eol = |days_left| Issue {
+            input: String::from("nixpkgs"),
+            kind: IssueKind::ApproachingEol(ApproachingEol {
+                channel: String::from("nixos-24.11"),
+                days_left,
+            }),
+            location: None,
+        };
+        let summary = summary(vec![eol(20), eol(-5)]);
+
+        let text = summary.render_text().unwrap();
+        assert!(text.contains("nixos-24.11, which reaches its end of\n  life in 20 days"));
+        assert!(text.contains("nixos-24.11, which is past its end of\n  life"));
+
+        let markdown = summary.render_markdown().unwrap();
+        assert!(markdown.contains("`nixos-24.11`, which reaches its end of life in 20 days"));
+        assert!(markdown.contains("`nixos-24.11`, which is past its end of life\n"));
+    }
+
+    #[test]
+    fn lock_version_note() {
+        let report = CheckReport::new(include_bytes!("../tests/flake.clean.0.lock"), vec![], 1, 1);
+        assert_eq!(report.lock_version, Some(7));
+
+        let summary = Summary::new(
+            &report,
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        let text = summary.render_text().unwrap();
+        assert!(text.contains("Its lock file format (version 7) requires Nix 2.4 or later."));
+        let markdown = summary.render_markdown().unwrap();
+        assert!(markdown.contains("Its lock file format (version 7) requires Nix 2.4 or later."));
+
+        // Unknown versions get no note
+        let lock = r#"{"nodes": {}, "root": "root", "version": 99}"#;
+        let summary = Summary::new(
+            &CheckReport::new(lock.as_bytes(), vec![], 1, 1),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        );
+        assert!(!summary.render_text().unwrap().contains("lock file format"));
+    }
+
+    #[test]
+    fn matched_refs() {
+        // Without matched refs, there's no note
+        let text = summary(vec![]).render_text().unwrap();
+        assert!(!text.contains("Supported branches in use"), "{text}");
+
+        let report = CheckReport {
+            matched_refs: vec![String::from("nixos-24.05"), String::from("nixos-unstable")],
+            ..CheckReport::new(b"{}", vec![], 2, 2)
+        };
+        let summary = Summary::new(
+            &report,
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![String::from("nixos-24.05"), String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains("Supported branches in use: nixos-24.05, nixos-unstable\n"),
+            "{text}"
+        );
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains("Supported branches in use: `nixos-24.05`, `nixos-unstable`\n"),
+            "{markdown}"
+        );
+    }
+
+    #[test]
+    fn input_ages() {
+        // Without ages, there's no note
+        let text = summary(vec![]).render_text().unwrap();
+        assert!(!text.contains("Input ages"), "{text}");
+
+        let report = CheckReport {
+            input_ages: Some(InputAges {
+                oldest_days: 120,
+                newest_days: 2,
+            }),
+            ..CheckReport::new(b"{}", vec![], 2, 2)
+        };
+        let summary = Summary::new(
+            &report,
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        assert_eq!(summary.data["input_ages"]["oldest_days"], 120);
+        assert_eq!(summary.data["input_ages"]["newest_days"], 2);
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains("Input ages: oldest 120 days, newest 2 days.\n"),
+            "{text}"
+        );
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains("Input ages: oldest 120 days, newest 2 days.\n"),
+            "{markdown}"
+        );
+    }
+
+    #[test]
+    fn clean_inputs() {
+        // Without --report-clean-inputs, there's no list
+        let text = summary(vec![]).render_text().unwrap();
+        assert!(!text.contains("Inputs without issues"), "{text}");
+
+        let report = CheckReport {
+            clean_inputs: Some(vec![CleanInput {
+                input: String::from("nixpkgs"),
+                checks: vec![
+                    String::from("Supported branches"),
+                    String::from("Upstream owner"),
+                ],
+            }]),
+            ..CheckReport::new(b"{}", vec![], 1, 1)
+        };
+        let summary = Summary::new(
+            &report,
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains(
+                "Inputs without issues:\n\n* nixpkgs: passed Supported branches, Upstream owner\n"
+            ),
+            "{text}"
+        );
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains("* ✅ `nixpkgs` passed Supported branches, Upstream owner\n"),
+            "{markdown}"
+        );
+    }
+
+    #[test]
+    fn disallowed_reasons() {
+        let summary = summary(vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("nixos-22.11"),
+                    reason: DisallowedReason::EndOfLife,
+                    upgrade_to: Some(String::from("nixos-24.11")),
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs-alt"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("nixos-99.99"),
+                    reason: DisallowedReason::Unknown,
+                    upgrade_to: None,
+                }),
+                location: None,
+            },
+        ]);
+
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains("* The `nixpkgs` input uses the `nixos-22.11` branch, which is end-of-life; upgrade to `nixos-24.11`\n"),
+            "{markdown}"
+        );
+        assert!(
+            markdown.contains("* The `nixpkgs-alt` input uses the `nixos-99.99` branch, which isn't a recognized Nixpkgs channel\n"),
+            "{markdown}"
+        );
+
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains("> The nixpkgs input uses the nixos-22.11 branch, which is end-of-life; upgrade to nixos-24.11\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("> The nixpkgs-alt input uses the nixos-99.99 branch, which isn't a recognized Nixpkgs channel\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn markdown_metadata() {
+        let summary = summary(vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("bitcoin-miner-org"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs-alt"),
+                kind: IssueKind::NonUpstream(NonUpstream {
+                    owner: String::from("pretty-shady"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+        ]);
+
+        let markdown = summary.render_markdown().unwrap();
+        let metadata = markdown
+            .lines()
+            .find_map(|line| {
+                line.strip_prefix("<!-- flake-checker: ")
+                    .and_then(|line| line.strip_suffix(" -->"))
+            })
+            .expect("no metadata comment in the Markdown summary");
+        let counts: serde_json::Value = serde_json::from_str(metadata).unwrap();
+
+        // Every kind is counted, even if there are no issues of that kind
+        let mut expected = serde_json::Map::new();
+        for kind in ISSUE_KINDS {
+            expected.insert(kind.to_string(), serde_json::json!(0));
+        }
+        expected.insert(String::from("disallowed"), serde_json::json!(1));
+        expected.insert(String::from("non-upstream"), serde_json::json!(2));
+        expected.insert(String::from("total"), serde_json::json!(3));
+        assert_eq!(counts, serde_json::Value::Object(expected));
+    }
+
+    #[test]
+    fn no_inputs_matched() {
+        let cases: Vec<Option<String>> = vec![None, Some(String::from("owner == 'NixOS'"))];
+
+        for condition in cases {
+            let summary = Summary::new(
+                &CheckReport::new(b"{}", vec![], 0, 0),
+                PathBuf::from("flake.lock"),
+                FlakeCheckConfig::default(),
+                vec![String::from("nixos-unstable")],
+                condition,
+                false,
+                Lang::default(),
+            );
+            assert_eq!(summary.data["clean"], false);
+            assert_eq!(summary.data["no_inputs"], true);
+
+            let text = summary.render_text().unwrap();
+            assert!(text.contains("no inputs matched the selection"));
+            assert!(!text.contains("didn't identify any issues"));
+
+            let markdown = summary.render_markdown().unwrap();
+            assert!(markdown.contains("no inputs matched the selection"));
+            assert!(!markdown.contains("didn't identify any issues"));
+        }
+    }
+
+    #[test]
+    fn markdown_status_icons() {
+        let summary = summary(vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ]);
+        let markdown = summary.render_markdown().unwrap();
+
+        for expected in [
+            "❌ Use supported branches",
+            "⚠️ Are less than 30 days old",
+            "✅ Use upstream Nixpkgs",
+            "## ❌ Non-supported Git branches for Nixpkgs",
+            "## ⚠️ Outdated Nixpkgs dependencies",
+        ] {
+            assert!(
+                markdown.lines().any(|line| line == expected),
+                "expected the line {expected:?} in:\n{markdown}"
+            );
+        }
+        assert!(!markdown.contains("## ❌ Non-upstream Nixpkgs dependencies"));
+
+        // The text summary stays plain
+        let text = summary.render_text().unwrap();
+        assert!(!text.contains('❌') && !text.contains('✅'));
+
+        let summary = Summary::new(
+            &CheckReport::new(
+                b"{}",
+                vec![Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Violation(Violation {
+                        snapshot: ViolationSnapshot {
+                            git_ref: String::from("nixos-unstable"),
+                            owner: String::from("bitcoin-miner-org"),
+                            num_days_old: 12,
+                            rev: String::from("04af42f3b31dba0ef742d254456dc4c14eedac86"),
+                        },
+                        rule: None,
+                    }),
+                    location: None,
+                }],
+                1,
+                1,
+            ),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![],
+            Some(String::from("owner == 'NixOS'")),
+            false,
+            Lang::default(),
+        );
+        let markdown = summary.render_markdown().unwrap();
+        assert!(markdown.contains("❌ The following inputs violate that condition:"));
+        assert!(markdown.contains(
+            "* `nixpkgs` (`gitRef`: `nixos-unstable`, `owner`: `bitcoin-miner-org`, `numDaysOld`: 12, `rev`: `04af42f3b31dba0ef742d254456dc4c14eedac86`)"
+        ));
+    }
+
+    #[test]
+    fn custom_upstream_owner() {
+        let summary = Summary::new(
+            &CheckReport::new(
+                b"{}",
+                vec![Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner: String::from("NixOS"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                }],
+                1,
+                1,
+            ),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig {
+                upstream_owner: String::from("my-org"),
+                ..Default::default()
+            },
+            vec![],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        let markdown = summary.render_markdown().unwrap();
+        assert!(markdown.contains(
+            "* The `nixpkgs` input has `NixOS` as an owner rather than the `my-org` org"
+        ));
+
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains("The nixpkgs input has NixOS as an owner rather\n  than the my-org org")
+        );
+    }
+
+    #[test]
+    fn custom_upstream_repo() {
+        let summary = Summary::new(
+            &CheckReport::new(
+                b"{}",
+                vec![Issue {
+                    input: String::from("base"),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner: String::from("my-org"),
+                        repo: Some(String::from("base-fork")),
+                    }),
+                    location: None,
+                }],
+                1,
+                1,
+            ),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig {
+                upstream_owner: String::from("my-org"),
+                upstream_repo: Some(String::from("base-flake")),
+                ..Default::default()
+            },
+            vec![],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains(
+                "* The `base` input comes from `my-org/base-fork` rather than `my-org/base-flake`"
+            ),
+            "{markdown}"
+        );
+        assert!(
+            markdown.contains("github:my-org/base-flake\""),
+            "{markdown}"
+        );
+
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains(
+                "The base input comes from my-org/base-fork rather\n  than my-org/base-flake"
+            ),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn checks_performed() {
+        let summary = Summary::new(
+            &CheckReport::new(b"{}", vec![], 1, 1),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig {
+                check_outdated: false,
+                check_owner: false,
+                require_https: true,
+                ..Default::default()
+            },
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        );
+
+        let text = summary.render_text().unwrap();
+        assert!(
+            text.contains(
+                "Checks performed:
+
+* Supported branches: ran
+* Denied branches: skipped
+* Temporary refs: skipped
+* Outdated inputs: skipped
+* End of life: skipped
+* Upstream owner: skipped
+* Hash algorithms: skipped
+* NAR hashes: skipped
+* Locked revisions: skipped
+* Hash consistency: skipped
+* Unlocked inputs: skipped
+* Original URLs: skipped
+* Declared refs: skipped
+* Lock format: skipped
+* Indirect inputs: skipped
+* Transitively indirect inputs: skipped
+* TLS transport: ran
+* Data inputs: skipped
+* Maximum inputs: skipped
+* Maximum depth: skipped
+* Transitive Nixpkgs channels: skipped
+* Approved revisions: skipped
+"
+            ),
+            "{text}"
+        );
+
+        let markdown = summary.render_markdown().unwrap();
+        assert!(
+            markdown.contains(
+                "### Checks performed
+
+* ✅ Supported branches
+* ➖ Denied branches (disabled)
+* ➖ Temporary refs (disabled)
+* ➖ Outdated inputs (disabled)
+* ➖ End of life (disabled)
+* ➖ Upstream owner (disabled)
+* ➖ Hash algorithms (disabled)
+* ➖ NAR hashes (disabled)
+* ➖ Locked revisions (disabled)
+* ➖ Hash consistency (disabled)
+* ➖ Unlocked inputs (disabled)
+* ➖ Original URLs (disabled)
+* ➖ Declared refs (disabled)
+* ➖ Lock format (disabled)
+* ➖ Indirect inputs (disabled)
+* ➖ Transitively indirect inputs (disabled)
+* ✅ TLS transport
+* ➖ Data inputs (disabled)
+* ➖ Maximum inputs (disabled)
+* ➖ Maximum depth (disabled)
+* ➖ Transitive Nixpkgs channels (disabled)
+* ➖ Approved revisions (disabled)
+"
+            ),
+            "{markdown}"
+        );
+
+        // With a condition, the condition is shown instead
+        let summary = Summary::new(
+            &CheckReport::new(b"{}", vec![], 1, 1),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![],
+            Some(String::from("owner == 'NixOS'")),
+            false,
+            Lang::default(),
+        );
+        let text = summary.render_text().unwrap();
+        assert!(text.contains("Checks performed:\n\nThe CEL condition owner == 'NixOS'"));
+        assert!(!text.contains("Supported branches"));
+        let markdown = summary.render_markdown().unwrap();
+        assert!(markdown.contains("### Checks performed\n\nThe CEL condition `owner == 'NixOS'`"));
+    }
+
+    #[test]
+    fn transitive_issues() {
+        let report = CheckReport::new(
+            b"{}",
+            vec![
+                Issue {
+                    input: String::from("nixpkgs"),
+                    kind: IssueKind::Disallowed(Disallowed {
+                        reference: String::from("this-should-fail"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+                Issue {
+                    input: String::from("home-manager/nixpkgs"),
+                    kind: IssueKind::NonUpstream(NonUpstream {
+                        owner: String::from("bitcoin-miner-org"),
+                        ..Default::default()
+                    }),
+                    location: None,
+                },
+            ],
+            2,
+            3,
+        );
+        let summary = |include_transitive_in_summary| {
+            Summary::new(
+                &report,
+                PathBuf::from("flake.lock"),
+                FlakeCheckConfig {
+                    transitive: true,
+                    include_transitive_in_summary,
+                    ..Default::default()
+                },
+                vec![String::from("nixos-unstable")],
+                None,
+                false,
+                Lang::default(),
+            )
+        };
+
+        // By default, only the root input's issue is shown
+        let excluded = summary(false);
+        assert_eq!(excluded.issues, report.issues[..1]);
+        assert_eq!(excluded.data["num_issues"], 1);
+        let text = excluded.render_text().unwrap();
+        assert!(text.contains("The nixpkgs input uses the this-should-fail branch"));
+        assert!(!text.contains("home-manager/nixpkgs"));
+        assert!(text.contains("1 issue(s) with transitive inputs aren't shown here"));
+        let markdown = excluded.render_markdown().unwrap();
+        assert!(!markdown.contains("home-manager/nixpkgs"));
+        assert!(markdown.contains("1 issue(s) with transitive inputs aren't shown here"));
+
+        // The metadata still counts every issue
+        assert!(markdown.contains("\"total\":2"));
+
+        let included = summary(true);
+        assert_eq!(included.issues, report.issues);
+        let text = included.render_text().unwrap();
+        assert!(text.contains("The home-manager/nixpkgs input has bitcoin-miner-org as an owner"));
+        assert!(!text.contains("aren't shown here"));
+    }
+
+    #[test]
+    fn markdown_collapsible() {
+        let issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ];
+        let collapsible = Summary::new(
+            &CheckReport::new(b"{}", issues.clone(), 1, 1),
+            PathBuf::from("flake.lock"),
+            FlakeCheckConfig::default(),
+            vec![String::from("nixos-unstable")],
+            None,
+            true,
+            Lang::default(),
+        );
+        let markdown = collapsible.render_markdown().unwrap();
+
+        // Each issue section is collapsed, with its own nested "What to do" and "Why" sections
+        let structure: Vec<&str> = markdown
+            .lines()
+            .filter(|line| {
+                line.starts_with("<details>")
+                    || line.starts_with("</details>")
+                    || line.starts_with("<summary>")
+            })
+            .collect();
+        assert_eq!(
+            structure,
+            vec![
+                "<details>",
+                "<summary><strong>❌ Non-supported Git branches for Nixpkgs</strong></summary>",
+                "<details>",
+                "<summary>What to do 🧰</summary>",
+                "</details>",
+                "<details>",
+                "<summary>Why it's important to use supported branches 📚</summary>",
+                "</details>",
+                "</details>",
+                "<details>",
+                "<summary><strong>⚠️ Outdated Nixpkgs dependencies</strong></summary>",
+                "<details>",
+                "<summary>What to do 🧰</summary>",
+                "</details>",
+                "<details>",
+                "<summary>Why it's important to keep Nix dependencies up to date 📚</summary>",
+                "</details>",
+                "</details>",
+            ]
+        );
+        assert!(!markdown.contains("\n## "));
+
+        // The issues themselves are the same as in the standard template
+        let standard = summary(issues).render_markdown().unwrap();
+        for line in standard.lines().filter(|line| line.starts_with("* ")) {
+            assert!(markdown.lines().any(|l| l == line), "missing {line:?}");
+        }
+    }
+
+    #[test]
+    fn html_page() {
+        let issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("this-should-fail"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ];
+        let config = FlakeCheckConfig {
+            severity_map: "disallowed=error".parse::<SeverityMap>().unwrap(),
+            ..Default::default()
+        };
+        let page = Summary::new(
+            &CheckReport::new(b"{}", issues, 1, 1),
+            PathBuf::from("a/flake.lock"),
+            config,
+            vec![String::from("nixos-unstable")],
+            None,
+            false,
+            Lang::default(),
+        );
+        let html = page.render_html().unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"), "{html}");
+        assert!(html.contains("<style>"), "{html}");
+        assert!(html.contains("<p>Results for <code>a/flake.lock</code></p>"));
+        assert!(html.contains(
+            "<p class=\"status error\">⚠️ Flake Checker discovered 2 issues that we recommend looking into.</p>"
+        ));
+
+        // Each kind of issue has a section colored by its severity, in the order of the kinds
+        let sections: Vec<&str> = html
+            .lines()
+            .filter(|line| line.starts_with("<section"))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                "<section class=\"error\" id=\"disallowed\">",
+                "<section class=\"warning\" id=\"outdated\">",
+            ]
+        );
+        assert!(html.contains("<h2>Supported branches <small>disallowed · error</small></h2>"));
+        assert!(html.contains("<li><code>nixpkgs</code>: this-should-fail</li>"));
+        assert!(html.contains("<li><code>nixpkgs</code>: 45 days old</li>"));
+
+        // The dependency overview follows the issues
+        assert!(html.contains("<tr><th>Inputs checked</th><td>1</td></tr>"));
+        assert!(html.contains("<h2>Checks performed</h2>"));
+
+        // A clean flake has no sections
+        let html = summary(vec![]).render_html().unwrap();
+        assert!(html.contains("<p class=\"status clean\">"));
+        assert!(!html.contains("<section"));
+    }
+}
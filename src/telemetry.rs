@@ -2,11 +2,14 @@ use crate::issue::Issue;
 
 use std::env;
 
+use reqwest::blocking::Client;
 use sha2::{Digest, Sha256};
 
 const TELEMETRY_ENDPOINT: &str = "https://install.determinate.systems/flake-checker/telemetry";
 
-/// A telemetry report to identify trends in outdated locks against nixpkgs
+/// A telemetry report to identify trends in outdated locks against nixpkgs. The report contains
+/// exactly these fields, serialized in this order, and nothing random or time-dependent, so
+/// identical inputs always yield a byte-identical payload.
 #[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
 pub(crate) struct TelemetryReport {
     pub distinct_id: String,
@@ -21,11 +24,20 @@ pub(crate) struct TelemetryReport {
 
 impl TelemetryReport {
     pub(crate) fn new(issues: &[Issue]) -> Result<TelemetryReport, env::VarError> {
+        Self::from_env(issues, |name| env::var(name), is_ci::cached())
+    }
+
+    // Build the report using `var` to look up environment variables.
+    fn from_env(
+        issues: &[Issue],
+        var: impl Fn(&str) -> Result<String, env::VarError>,
+        is_ci: bool,
+    ) -> Result<TelemetryReport, env::VarError> {
         Ok(TelemetryReport {
-            distinct_id: env::var("DETSYS_CORRELATION").or_else(|_| calculate_opaque_id())?,
+            distinct_id: var("DETSYS_CORRELATION").or_else(|_| calculate_opaque_id(&var))?,
 
             version: env!("CARGO_PKG_VERSION").to_string(),
-            is_ci: is_ci::cached(),
+            is_ci,
 
             disallowed: issues
                 .iter()
@@ -42,27 +54,228 @@ impl TelemetryReport {
         })
     }
 
-    pub fn make_and_send(issues: &[Issue]) {
+    pub fn make_and_send(issues: &[Issue], client: &Client) {
         if let Ok(report) = TelemetryReport::new(issues) {
-            if let Ok(serialized) = serde_json::to_string_pretty(&report) {
-                let _ = reqwest::blocking::Client::new()
-                    .post(TELEMETRY_ENDPOINT)
-                    .body(serialized)
-                    .header("Content-Type", "application/json")
-                    .timeout(std::time::Duration::from_millis(3000))
-                    .send();
+            report.send(client, TELEMETRY_ENDPOINT);
+        }
+    }
+
+    /// Send the report. Telemetry never fails the run, but failures are logged for debugging.
+    fn send(&self, client: &Client, endpoint: &str) {
+        if let Ok(serialized) = serde_json::to_string_pretty(self) {
+            let sent = client
+                .post(endpoint)
+                .body(serialized)
+                .header("Content-Type", "application/json")
+                .timeout(std::time::Duration::from_millis(3000))
+                .send()
+                .and_then(|response| response.error_for_status());
+
+            if let Err(e) = sent {
+                tracing::debug!("failed to send telemetry to {endpoint}: {e}");
             }
         }
     }
 }
 
-fn calculate_opaque_id() -> Result<String, env::VarError> {
+// A SHA-256 hash of the repository's details, which is the same for every run in a repository but
+// doesn't reveal the repository.
+fn calculate_opaque_id(
+    var: impl Fn(&str) -> Result<String, env::VarError>,
+) -> Result<String, env::VarError> {
     let mut hasher = Sha256::new();
-    hasher.update(env::var("GITHUB_REPOSITORY")?);
-    hasher.update(env::var("GITHUB_REPOSITORY_ID")?);
-    hasher.update(env::var("GITHUB_REPOSITORY_OWNER")?);
-    hasher.update(env::var("GITHUB_REPOSITORY_OWNER_ID")?);
+    hasher.update(var("GITHUB_REPOSITORY")?);
+    hasher.update(var("GITHUB_REPOSITORY_ID")?);
+    hasher.update(var("GITHUB_REPOSITORY_OWNER")?);
+    hasher.update(var("GITHUB_REPOSITORY_OWNER_ID")?);
 
     let result = hasher.finalize();
     Ok(format!("{:x}", result))
 }
+
+#[cfg(test)]
+mod test {
+    use std::env::VarError;
+    use std::fmt;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc, Mutex};
+
+    use reqwest::blocking::Client;
+    use tracing::field::Field;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    use crate::issue::{Disallowed, Issue, IssueKind, Outdated};
+
+    use super::TelemetryReport;
+
+    // A subscriber that collects the messages of debug events
+    struct DebugMessages(Arc<Mutex<Vec<String>>>);
+
+    impl Subscriber for DebugMessages {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            if *event.metadata().level() == Level::DEBUG {
+                let mut message = String::new();
+                event.record(&mut |field: &Field, value: &dyn fmt::Debug| {
+                    if field.name() == "message" {
+                        message = format!("{value:?}");
+                    }
+                });
+                self.0.lock().unwrap().push(message);
+            }
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn failed_send_logs_debug_event() {
+        // Nothing listens on this port once the listener is dropped
+        let endpoint = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/telemetry", listener.local_addr().unwrap())
+        };
+
+        let report = TelemetryReport {
+            distinct_id: String::from("test"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            is_ci: false,
+            disallowed: 0,
+            outdated: 0,
+            non_upstream: 0,
+        };
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(DebugMessages(messages.clone()), || {
+            report.send(&Client::new(), &endpoint);
+        });
+
+        let messages = messages.lock().unwrap();
+        assert!(
+            messages
+                .iter()
+                .any(|message| message
+                    .starts_with(&format!("failed to send telemetry to {endpoint}")))
+        );
+    }
+
+    // The environment of a GitHub Actions run
+    fn github_env(name: &str) -> Result<String, VarError> {
+        match name {
+            "GITHUB_REPOSITORY" => Ok(String::from("acme-corp/website")),
+            "GITHUB_REPOSITORY_ID" => Ok(String::from("123456")),
+            "GITHUB_REPOSITORY_OWNER" => Ok(String::from("acme-corp")),
+            "GITHUB_REPOSITORY_OWNER_ID" => Ok(String::from("654321")),
+            _ => Err(VarError::NotPresent),
+        }
+    }
+
+    // Send the report to a local endpoint and return the request body that it receives
+    fn sent_payload(report: &TelemetryReport) -> Vec<u8> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/telemetry", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut reader = BufReader::new(&stream);
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            tx.send(body).unwrap();
+
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+        });
+
+        report.send(&Client::new(), &endpoint);
+        rx.recv().unwrap()
+    }
+
+    #[test]
+    fn deterministic_payload() {
+        let issues = vec![
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Disallowed(Disallowed {
+                    reference: String::from("nixos-22.05"),
+                    ..Default::default()
+                }),
+                location: None,
+            },
+            Issue {
+                input: String::from("nixpkgs"),
+                kind: IssueKind::Outdated(Outdated::new(1689078114, 45)),
+                location: None,
+            },
+        ];
+
+        let first = sent_payload(&TelemetryReport::from_env(&issues, github_env, true).unwrap());
+        let second = sent_payload(&TelemetryReport::from_env(&issues, github_env, true).unwrap());
+        assert_eq!(first, second);
+
+        // These are the only facts sent, and the repository's details are hashed
+        let payload: serde_json::Value = serde_json::from_slice(&first).unwrap();
+        let mut fields: Vec<&str> = payload
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                "disallowed",
+                "distinct_id",
+                "is_ci",
+                "non_upstream",
+                "outdated",
+                "version"
+            ]
+        );
+        assert_eq!(payload["disallowed"], 1);
+        assert_eq!(payload["outdated"], 1);
+        assert_eq!(payload["non_upstream"], 0);
+        let payload = String::from_utf8(first).unwrap();
+        assert!(!payload.contains("acme-corp"), "{payload}");
+        assert!(!payload.contains("nixos-22.05"), "{payload}");
+
+        // Outside of GitHub Actions, there's no ID to send unless DETSYS_CORRELATION is set
+        assert!(TelemetryReport::from_env(&issues, |_| Err(VarError::NotPresent), false).is_err());
+        let correlated = TelemetryReport::from_env(
+            &issues,
+            |name| match name {
+                "DETSYS_CORRELATION" => Ok(String::from("correlation-id")),
+                name => github_env(name),
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(correlated.distinct_id, "correlation-id");
+    }
+}
@@ -0,0 +1,182 @@
+//! Input tiers, which apply their own policy to the inputs assigned to them, since not all inputs
+//! are equally critical. Tiers are listed in a file passed using `--tiers-file`:
+//!
+//! ```toml
+//! [tiers.critical]
+//! inputs = ["nixpkgs"]
+//! max-days = 14
+//! allowed-refs = ["nixos-24.11"]
+//!
+//! [tiers.experimental]
+//! inputs = ["nixpkgs-unstable", "home-manager/nixpkgs"]
+//! max-days = 90
+//! check-owner = false
+//! ```
+//!
+//! A tier's settings override the ones that the inputs would otherwise be checked with, and inputs
+//! without a tier are checked with those.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::error::FlakeCheckerError;
+use crate::flake::FlakeCheckConfig;
+
+/// The tiers that inputs are assigned to, keyed by input name (e.g. `home-manager/nixpkgs` for a
+/// transitive input).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Tiers(BTreeMap<String, Tier>);
+
+/// The policy of a tier.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Tier {
+    pub(crate) name: String,
+    max_days: Option<i64>,
+    check_owner: Option<bool>,
+    upstream_owner: Option<String>,
+    /// The Git refs that the tier's inputs are allowed to use, in place of the supported ones.
+    pub(crate) allowed_refs: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TiersFile {
+    #[serde(default)]
+    tiers: HashMap<String, TierEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct TierEntry {
+    /// The inputs in the tier.
+    inputs: Vec<String>,
+    max_days: Option<i64>,
+    check_owner: Option<bool>,
+    upstream_owner: Option<String>,
+    allowed_refs: Option<Vec<String>>,
+}
+
+impl Tiers {
+    /// The tiers in the tiers file at `path`.
+    pub(crate) fn read(path: &Path) -> Result<Self, FlakeCheckerError> {
+        let invalid = |e: String| FlakeCheckerError::TiersFile(format!("{}: {e}", path.display()));
+        read_to_string(path)
+            .map_err(|e| invalid(e.to_string()))?
+            .parse()
+            .map_err(invalid)
+    }
+
+    /// The tier that `input` is assigned to, if any.
+    pub(crate) fn tier(&self, input: &str) -> Option<&Tier> {
+        self.0.get(input)
+    }
+}
+
+impl Tier {
+    /// `config` with the tier's policy applied.
+    pub(crate) fn apply(&self, config: &FlakeCheckConfig) -> FlakeCheckConfig {
+        FlakeCheckConfig {
+            max_days: self.max_days.unwrap_or(config.max_days),
+            check_owner: self.check_owner.unwrap_or(config.check_owner),
+            upstream_owner: self
+                .upstream_owner
+                .clone()
+                .unwrap_or_else(|| config.upstream_owner.clone()),
+            ..config.clone()
+        }
+    }
+}
+
+impl FromStr for Tiers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: TiersFile = toml::from_str(s).map_err(|e| e.to_string())?;
+
+        // Sorted so that errors about inputs in several tiers don't depend on the order
+        let mut entries: Vec<(String, TierEntry)> = file.tiers.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut tiers: BTreeMap<String, Tier> = BTreeMap::new();
+        for (name, entry) in entries {
+            let tier = Tier {
+                name: name.clone(),
+                max_days: entry.max_days,
+                check_owner: entry.check_owner,
+                upstream_owner: entry.upstream_owner,
+                allowed_refs: entry.allowed_refs,
+            };
+            for input in entry.inputs {
+                if let Some(other) = tiers.get(&input) {
+                    return Err(format!(
+                        "{input:?} is in both the {:?} and {name:?} tiers",
+                        other.name
+                    ));
+                }
+                tiers.insert(input, tier.clone());
+            }
+        }
+
+        Ok(Self(tiers))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::flake::FlakeCheckConfig;
+
+    use super::Tiers;
+
+    #[test]
+    fn tier_policies() {
+        let tiers: Tiers = r#"
+            [tiers.critical]
+            inputs = ["nixpkgs"]
+            max-days = 14
+            allowed-refs = ["nixos-24.11"]
+
+            [tiers.experimental]
+            inputs = ["nixpkgs-unstable"]
+            check-owner = false
+        "#
+        .parse()
+        .unwrap();
+
+        let config = FlakeCheckConfig::default();
+        let critical = tiers.tier("nixpkgs").unwrap();
+        assert_eq!(critical.name, "critical");
+        assert_eq!(critical.apply(&config).max_days, 14);
+        assert!(critical.apply(&config).check_owner);
+        assert_eq!(
+            critical.allowed_refs,
+            Some(vec![String::from("nixos-24.11")])
+        );
+
+        // Settings that a tier doesn't have are left alone
+        let experimental = tiers.tier("nixpkgs-unstable").unwrap();
+        assert_eq!(experimental.apply(&config).max_days, config.max_days);
+        assert!(!experimental.apply(&config).check_owner);
+        assert_eq!(experimental.allowed_refs, None);
+
+        assert_eq!(tiers.tier("home-manager/nixpkgs"), None);
+    }
+
+    #[test]
+    fn invalid_tiers() {
+        assert!("[tiers.critical]\nmax-days = 14".parse::<Tiers>().is_err());
+        assert!("[tiers.critical]\ninputs = []\nmax-age = 14"
+            .parse::<Tiers>()
+            .is_err());
+        assert_eq!(
+            "[tiers.a]\ninputs = [\"nixpkgs\"]\n[tiers.b]\ninputs = [\"nixpkgs\"]".parse::<Tiers>(),
+            Err(String::from(
+                "\"nixpkgs\" is in both the \"a\" and \"b\" tiers"
+            ))
+        );
+        assert_eq!("".parse::<Tiers>(), Ok(Tiers::default()));
+    }
+}
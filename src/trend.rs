@@ -0,0 +1,200 @@
+use crate::error::FlakeCheckerError;
+use crate::flake::{check_flake_lock, nixpkgs_deps, total_inputs, FlakeCheckConfig};
+use crate::issue::ISSUE_KINDS;
+use crate::output::csv_field;
+use crate::report::CheckReport;
+
+use std::collections::BTreeMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use parse_flake_lock::FlakeLock;
+use serde::Serialize;
+
+/// The format in which to report a trend.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum TrendFormat {
+    /// A header row and one row of issue counts per lockfile.
+    Csv,
+    /// An array with one object of issue counts per lockfile.
+    Json,
+}
+
+/// The issue counts for a single lockfile snapshot.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct TrendEntry {
+    /// The snapshot's file name.
+    pub(crate) file: String,
+    pub(crate) issue_count: usize,
+    pub(crate) input_count: usize,
+    /// The number of issues of each kind, keyed by issue kind (e.g. `disallowed`).
+    pub(crate) counts: BTreeMap<String, usize>,
+}
+
+impl TrendEntry {
+    pub(crate) fn new(file: String, report: &CheckReport) -> Self {
+        let counts = ISSUE_KINDS
+            .iter()
+            .map(|kind| {
+                let count = report
+                    .issues
+                    .iter()
+                    .filter(|i| i.kind.name() == *kind)
+                    .count();
+                (kind.to_string(), count)
+            })
+            .collect();
+
+        Self {
+            file,
+            issue_count: report.issues.len(),
+            input_count: report.num_inputs,
+            counts,
+        }
+    }
+}
+
+/// The lockfile snapshots in `dir`, i.e. its `.lock` files, sorted by file name. Snapshots named
+/// after their dates (e.g. `2024-06-01.lock`) are thus in chronological order.
+pub(crate) fn snapshots(dir: &Path) -> Result<Vec<PathBuf>, FlakeCheckerError> {
+    let mut paths = vec![];
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "lock") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// Check each lockfile snapshot in `dir` and count its issues. The outdated check is skipped
+/// because an input's age is measured against today, which says nothing about how outdated it was
+/// when the snapshot was taken.
+pub(crate) fn trend(
+    dir: &Path,
+    allowed_refs: &[String],
+) -> Result<Vec<TrendEntry>, FlakeCheckerError> {
+    let config = FlakeCheckConfig {
+        check_outdated: false,
+        ..Default::default()
+    };
+
+    let mut entries = vec![];
+    for path in snapshots(dir)? {
+        let lock_contents = read_to_string(&path)?;
+        let flake_lock: FlakeLock = lock_contents.parse()?;
+        let num_inputs = nixpkgs_deps(
+            &flake_lock,
+            &config.nixpkgs_keys,
+            config.input_name_regex.as_ref(),
+            config.transitive,
+            config.max_transitive_depth,
+        )?
+        .len();
+        let issues = check_flake_lock(&flake_lock, &config, allowed_refs.to_vec())?;
+        let report = CheckReport::new(
+            lock_contents.as_bytes(),
+            issues,
+            num_inputs,
+            total_inputs(&flake_lock),
+        );
+
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        entries.push(TrendEntry::new(file, &report));
+    }
+
+    Ok(entries)
+}
+
+/// Render a trend in the given format.
+pub(crate) fn render(
+    entries: &[TrendEntry],
+    format: TrendFormat,
+) -> Result<String, FlakeCheckerError> {
+    Ok(match format {
+        TrendFormat::Csv => csv(entries),
+        TrendFormat::Json => serde_json::to_string_pretty(entries)?,
+    })
+}
+
+fn csv(entries: &[TrendEntry]) -> String {
+    let mut header = vec![String::from("file,issue_count,input_count")];
+    header.extend(
+        ISSUE_KINDS
+            .iter()
+            .map(|kind| format!("{}_count", kind.replace('-', "_"))),
+    );
+
+    let mut rows = vec![header.join(",")];
+    for entry in entries {
+        let mut row = vec![
+            csv_field(&entry.file),
+            entry.issue_count.to_string(),
+            entry.input_count.to_string(),
+        ];
+        row.extend(
+            ISSUE_KINDS
+                .iter()
+                .map(|kind| entry.counts.get(*kind).copied().unwrap_or(0).to_string()),
+        );
+        rows.push(row.join(","));
+    }
+
+    rows.iter().map(|row| format!("{row}\n")).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use crate::ref_statuses::supported_refs;
+
+    use super::{render, trend, TrendFormat};
+
+    #[test]
+    fn trend_over_snapshots() {
+        let ref_statuses: HashMap<String, String> =
+            serde_json::from_str(include_str!("../ref-statuses.json")).unwrap();
+        let allowed_refs = supported_refs(ref_statuses);
+
+        let entries = trend(Path::new("tests/trend"), &allowed_refs).unwrap();
+
+        // Snapshots are sorted by file name and the flake gets healthier over time
+        let summary: Vec<(&str, usize)> = entries
+            .iter()
+            .map(|entry| (entry.file.as_str(), entry.issue_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2024-01-01.lock", 2),
+                ("2024-02-01.lock", 2),
+                ("2024-03-01.lock", 0),
+            ]
+        );
+        assert_eq!(entries[0].counts["disallowed"], 1);
+        assert_eq!(entries[0].counts["non-upstream"], 1);
+        assert_eq!(entries[0].counts["outdated"], 0);
+
+        let csv = render(&entries, TrendFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("file,issue_count,input_count,disallowed_count,"));
+        assert!(lines.next().unwrap().starts_with("2024-01-01.lock,2,1,1,"));
+        assert_eq!(lines.count(), 2);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&entries, TrendFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[2]["file"], "2024-03-01.lock");
+        assert_eq!(json[2]["issue_count"], 0);
+    }
+}
@@ -0,0 +1,286 @@
+//! Support for `flake-checker validate-config`, which loads every source of config for a run, like
+//! the settings and the files that they name, and reports the problems with them without checking
+//! any `flake.lock`.
+
+use std::path::Path;
+
+use crate::condition::{compile_condition, read_condition_dir, read_policy, ConditionFormat};
+use crate::error::FlakeCheckerError;
+use crate::flake::{read_approved_revs_file, read_nixpkgs_keys_file};
+use crate::pins::{Pins, PINS_FILE_NAME};
+use crate::ref_statuses::{read_channels_file, read_eol_dates_file};
+use crate::settings;
+use crate::tiers::Tiers;
+
+/// The sources of config for a run.
+#[derive(Default)]
+pub(crate) struct Sources<'a> {
+    /// The settings as a JSON object (see [settings::command]), and where they're from.
+    pub(crate) settings: Option<(&'a str, &'a str)>,
+    pub(crate) channels_file: Option<&'a Path>,
+    pub(crate) eol_dates_file: Option<&'a Path>,
+    pub(crate) tiers_file: Option<&'a Path>,
+    pub(crate) approved_revs_file: Option<&'a Path>,
+    pub(crate) nixpkgs_keys_file: Option<&'a Path>,
+    /// The `--condition`, which is the path of a policy file unless its format is CEL.
+    pub(crate) condition: Option<(&'a str, ConditionFormat)>,
+    pub(crate) condition_dir: Option<&'a Path>,
+    /// The `flake.lock` whose pins file, if it has one, is validated.
+    pub(crate) flake_lock_path: Option<&'a Path>,
+}
+
+/// A source of config and the problem with it, if there is one.
+#[derive(Debug)]
+pub(crate) struct Validation {
+    pub(crate) source: String,
+    pub(crate) problem: Option<FlakeCheckerError>,
+}
+
+impl Validation {
+    fn new<T>(source: String, loaded: Result<T, FlakeCheckerError>) -> Self {
+        Self {
+            source,
+            problem: loaded.err(),
+        }
+    }
+}
+
+/// Load each of the `sources` of config, in the order that a run loads them.
+pub(crate) fn validate(sources: &Sources) -> Vec<Validation> {
+    let mut validations = vec![];
+
+    if let Some((settings, origin)) = sources.settings {
+        validations.push(Validation::new(
+            format!("settings from {origin}"),
+            settings::validate(settings),
+        ));
+    }
+    if let Some(path) = sources.channels_file {
+        validations.push(Validation::new(
+            format!("channels file {}", path.display()),
+            read_channels_file(path),
+        ));
+    }
+    if let Some(path) = sources.eol_dates_file {
+        validations.push(Validation::new(
+            format!("end-of-life dates file {}", path.display()),
+            read_eol_dates_file(path),
+        ));
+    }
+    if let Some(path) = sources.tiers_file {
+        validations.push(Validation::new(
+            format!("tiers file {}", path.display()),
+            Tiers::read(path),
+        ));
+    }
+    if let Some(path) = sources.approved_revs_file {
+        validations.push(Validation::new(
+            format!("approved revisions file {}", path.display()),
+            read_approved_revs_file(path),
+        ));
+    }
+    if let Some(path) = sources.nixpkgs_keys_file {
+        validations.push(Validation::new(
+            format!("Nixpkgs keys file {}", path.display()),
+            read_nixpkgs_keys_file(path),
+        ));
+    }
+    match sources.condition {
+        Some((condition, ConditionFormat::Cel)) => validations.push(Validation::new(
+            String::from("CEL condition"),
+            compile_condition(condition),
+        )),
+        Some((path, format)) => validations.push(Validation::new(
+            format!("policy file {path}"),
+            read_policy(Path::new(path), format)
+                .and_then(|condition| compile_condition(&condition)),
+        )),
+        None => {}
+    }
+    if let Some(path) = sources.condition_dir {
+        validations.push(Validation::new(
+            format!("condition directory {}", path.display()),
+            read_condition_dir(path),
+        ));
+    }
+    if let Some(flake_lock_path) = sources.flake_lock_path {
+        let path = flake_lock_path.with_file_name(PINS_FILE_NAME);
+        if path.exists() {
+            validations.push(Validation::new(
+                format!("pins file {}", path.display()),
+                Pins::for_flake_lock(flake_lock_path),
+            ));
+        }
+    }
+
+    validations
+}
+
+/// The validations as text, with a line per source followed by the total number of problems.
+pub(crate) fn render(validations: &[Validation]) -> String {
+    let mut text = String::new();
+    for validation in validations {
+        match &validation.problem {
+            None => text.push_str(&format!("ok: {}\n", validation.source)),
+            Some(problem) => text.push_str(&format!("error: {}: {problem}\n", validation.source)),
+        }
+    }
+
+    let problems = validations
+        .iter()
+        .filter(|validation| validation.problem.is_some())
+        .count();
+    text.push_str(&match (validations.len(), problems) {
+        (0, _) => String::from("There's no config to validate.\n"),
+        (_, 0) => String::from("\nThe config is valid.\n"),
+        (_, 1) => String::from("\nFound 1 problem in the config.\n"),
+        (_, problems) => format!("\nFound {problems} problems in the config.\n"),
+    });
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::path::{Path, PathBuf};
+
+    use crate::condition::ConditionFormat;
+
+    use super::{render, validate, Sources};
+
+    // A scratch directory with the given files in it.
+    fn dir_with(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "flake-checker-validate-{name}-{}",
+            std::process::id()
+        ));
+        create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn problems(sources: &Sources) -> Vec<String> {
+        validate(sources)
+            .into_iter()
+            .filter_map(|validation| validation.problem.map(|problem| problem.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_config() {
+        let dir = dir_with(
+            "valid",
+            &[
+                ("channels.toml", "\"nixos-24.11\" = \"unmaintained\"\n"),
+                (
+                    "tiers.toml",
+                    "[tiers.critical]\ninputs = [\"nixpkgs\"]\nmax-days = 14\n",
+                ),
+                (
+                    "flake-checker.pins.toml",
+                    "[pins.nixpkgs]\nexpires = \"2999-01-01\"\n",
+                ),
+            ],
+        );
+        let (channels_file, tiers_file, flake_lock_path) = (
+            dir.join("channels.toml"),
+            dir.join("tiers.toml"),
+            dir.join("flake.lock"),
+        );
+        let sources = Sources {
+            settings: Some((
+                r#"{"fail-mode": true, "output": "report.json"}"#,
+                "flake-checker.toml",
+            )),
+            channels_file: Some(&channels_file),
+            tiers_file: Some(&tiers_file),
+            nixpkgs_keys_file: Some(Path::new("tests/nixpkgs-keys.txt")),
+            condition: Some(("owner == 'NixOS'", ConditionFormat::Cel)),
+            flake_lock_path: Some(&flake_lock_path),
+            ..Default::default()
+        };
+
+        let validations = validate(&sources);
+        assert_eq!(validations.len(), 6);
+        assert!(validations
+            .iter()
+            .all(|validation| validation.problem.is_none()));
+        let text = render(&validations);
+        assert!(
+            text.starts_with("ok: settings from flake-checker.toml\n"),
+            "{text}"
+        );
+        assert!(text.ends_with("\nThe config is valid.\n"), "{text}");
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_config() {
+        let dir = dir_with(
+            "malformed",
+            &[
+                ("channels.json", r#"{"nixos-24.11": "retired"}"#),
+                (
+                    "tiers.toml",
+                    "[tiers.a]\ninputs = [\"nixpkgs\"]\n[tiers.b]\ninputs = [\"nixpkgs\"]\n",
+                ),
+                ("policy.json", r#"{"max_age": 30}"#),
+                (
+                    "flake-checker.pins.toml",
+                    "[pins.nixpkgs]\nkinds = [\"old\"]\n",
+                ),
+            ],
+        );
+        let (channels_file, tiers_file, approved_revs_file, policy, flake_lock_path) = (
+            dir.join("channels.json"),
+            dir.join("tiers.toml"),
+            dir.join("missing.txt"),
+            dir.join("policy.json"),
+            dir.join("flake.lock"),
+        );
+        let sources = Sources {
+            settings: Some((
+                r#"{"output": "report.json", "output-dir": "reports"}"#,
+                "NIX_FLAKE_CHECKER_SETTINGS",
+            )),
+            channels_file: Some(&channels_file),
+            tiers_file: Some(&tiers_file),
+            approved_revs_file: Some(&approved_revs_file),
+            condition: Some((policy.to_str().unwrap(), ConditionFormat::Json)),
+            flake_lock_path: Some(&flake_lock_path),
+            ..Default::default()
+        };
+
+        let problems = problems(&sources);
+        assert_eq!(problems.len(), 6, "{problems:?}");
+        assert_eq!(
+            problems[0],
+            "invalid settings: \"output\" conflicts with \"output-dir\""
+        );
+        assert!(problems[1].contains("\"retired\""), "{}", problems[1]);
+        assert!(
+            problems[2].contains("is in both the \"a\" and \"b\" tiers"),
+            "{}",
+            problems[2]
+        );
+        assert!(
+            problems[4].starts_with("invalid policy file"),
+            "{}",
+            problems[4]
+        );
+        assert!(problems[5].contains("\"old\""), "{}", problems[5]);
+
+        // A broken CEL condition is caught without checking anything
+        let sources = Sources {
+            condition: Some(("owner ==", ConditionFormat::Cel)),
+            ..Default::default()
+        };
+        assert_eq!(validate(&sources).len(), 1);
+        assert!(render(&validate(&sources)).ends_with("\nFound 1 problem in the config.\n"));
+
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use std::fs;
+
+fn issue_count(cache_report: &std::path::Path, check_outdated: &str) -> usize {
+    let output = common::flake_checker()
+        .args([
+            "--no-telemetry",
+            "--output-format",
+            "json",
+            "--cache-report",
+        ])
+        .arg(cache_report)
+        .arg("tests/flake.dirty.0.lock")
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", check_outdated)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    report["issues"].as_array().unwrap().len()
+}
+
+#[test]
+fn changed_flag_invalidates_cache() {
+    let cache_report = std::env::temp_dir().join(format!(
+        "flake-checker-cache-report-flag-{}.json",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&cache_report);
+
+    let with_outdated = issue_count(&cache_report, "true");
+    assert_eq!(issue_count(&cache_report, "true"), with_outdated);
+
+    // Disabling the outdated check drops its issues rather than replaying the cached ones
+    let without_outdated = issue_count(&cache_report, "false");
+    assert!(without_outdated < with_outdated, "{without_outdated}");
+
+    fs::remove_file(&cache_report).unwrap();
+}
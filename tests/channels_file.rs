@@ -0,0 +1,65 @@
+mod common;
+
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::Path;
+use std::process::Output;
+
+fn flake_checker(flake_lock: &str, channels_file: Option<&Path>) -> Output {
+    let mut command = common::flake_checker();
+    command
+        .args(["--no-telemetry", "--fail-mode"])
+        .arg(flake_lock)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false");
+    if let Some(channels_file) = channels_file {
+        command.arg("--channels-file").arg(channels_file);
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn policy_stricter_than_upstream() {
+    let dir = std::env::temp_dir().join(format!(
+        "flake-checker-channels-file-{}",
+        std::process::id()
+    ));
+    create_dir_all(&dir).unwrap();
+
+    // nixos-24.11 is a supported stable channel upstream
+    assert!(flake_checker("tests/flake.clean.2.lock", None)
+        .status
+        .success());
+
+    let json = dir.join("channels.json");
+    write(&json, r#"{"nixos-24.11": "unmaintained"}"#).unwrap();
+    let toml = dir.join("channels.toml");
+    write(&toml, "\"nixos-24.11\" = \"unmaintained\"\n").unwrap();
+
+    for channels_file in [&json, &toml] {
+        let output = flake_checker("tests/flake.clean.2.lock", Some(channels_file));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(1), "{stdout}");
+        assert!(
+            stdout.contains("uses the nixos-24.11 branch, which is end-of-life"),
+            "{stdout}"
+        );
+    }
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unknown_status_is_a_config_error() {
+    let dir = std::env::temp_dir().join(format!(
+        "flake-checker-channels-file-invalid-{}",
+        std::process::id()
+    ));
+    create_dir_all(&dir).unwrap();
+    let json = dir.join("channels.json");
+    write(&json, r#"{"nixos-24.11": "banned"}"#).unwrap();
+
+    let output = flake_checker("tests/flake.clean.2.lock", Some(&json));
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid channels file"));
+
+    remove_dir_all(&dir).unwrap();
+}
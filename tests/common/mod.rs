@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests, each of which uses only some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A `flake-checker` command that's isolated from the environment the tests run in, so that it
+/// neither picks up settings from `NIX_FLAKE_CHECKER_SETTINGS` nor behaves as it does in GitHub
+/// Actions.
+pub fn flake_checker() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_flake-checker"));
+    command
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("NIX_FLAKE_CHECKER_SETTINGS");
+    command
+}
+
+/// Run `git` with `args` in `dir`, as a test author and without the system's Git config.
+pub fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", "test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+/// A fresh directory whose path contains a space.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("flake-checker-{}", std::process::id()))
+        .join(format!("{name} flake"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
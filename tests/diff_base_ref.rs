@@ -0,0 +1,75 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::{git, temp_dir};
+
+fn flake_checker(flake_lock: &Path) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--diff-base-ref", "HEAD"])
+        .arg(flake_lock)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn changed_inputs_table() {
+    let dir = temp_dir("repo");
+    let flake_lock = dir.join("flake.lock");
+    git(&dir, &["init", "--quiet"]);
+    fs::copy("tests/flake.dirty.0.lock", &flake_lock).unwrap();
+    git(&dir, &["add", "flake.lock"]);
+    git(&dir, &["commit", "--quiet", "-m", "Lock"]);
+
+    let output = flake_checker(&flake_lock);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("No inputs changed since `HEAD`.\n"),
+        "{stdout}"
+    );
+
+    // Relock Nixpkgs to a supported upstream branch, about 11.5 days later
+    let relocked = fs::read_to_string("tests/flake.dirty.0.lock")
+        .unwrap()
+        .replace("bitcoin-miner-org", "NixOS")
+        .replace("this-should-fail", "nixos-unstable")
+        .replace(
+            "b969a89c3e84a121c9b3af2e4ef277cd822b988a",
+            "0123456789abcdef0123456789abcdef01234567",
+        )
+        .replacen("1681358109", "1682358109", 1);
+    fs::write(&flake_lock, relocked).unwrap();
+
+    let output = flake_checker(&flake_lock);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "### Inputs changed since `HEAD`\n\n\
+             | Input | Old rev | New rev | Age | Issues |\n\
+             | :---- | :------ | :------ | :-- | :----- |\n"
+        ),
+        "{stdout}"
+    );
+
+    // Only Nixpkgs changed, and its issues were cleared
+    let rows: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("| `"))
+        .collect();
+    assert_eq!(rows.len(), 1, "{stdout}");
+    assert!(
+        rows[0].starts_with("| `nixpkgs` | `b969a89` | `0123456` | "),
+        "{}",
+        rows[0]
+    );
+    assert!(
+        rows[0].ends_with(" days (11 days newer) | ✅ cleared `disallowed`, `non-upstream` |"),
+        "{}",
+        rows[0]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(args: &[&str]) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--fail-mode", "--dry-run"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn plan_without_checking() {
+    // The lockfile has issues, but nothing is checked, so nothing fails
+    let output = flake_checker(&["--check-hash-algo", "tests/flake.dirty.0.lock"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.starts_with("Dry run: nothing is checked and no network requests are made.\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(
+            "Lockfiles:\n\n* tests/flake.dirty.0.lock, with 1 Nixpkgs input to check: nixpkgs\n"
+        ),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("* Supported branches: would run\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("* Hash algorithms: would run\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("* Indirect inputs: skipped\n"), "{stdout}");
+    assert!(
+        stdout.ends_with("Network requests:\n\n* None\n"),
+        "{stdout}"
+    );
+
+    // No issues were found, since no checks ran
+    assert!(!stdout.contains("this-should-fail"), "{stdout}");
+    assert!(!stdout.contains("Flake Checker discovered"), "{stdout}");
+}
+
+#[test]
+fn remote_lockfile_isnt_fetched() {
+    // Nothing listens on the discard port, so fetching the lockfile would fail
+    let output = flake_checker(&["--online", "https://127.0.0.1:9/flake.lock"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("* https://127.0.0.1:9/flake.lock, which would be fetched\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("* Fetching 1 lockfile\n"), "{stdout}");
+}
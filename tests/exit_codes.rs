@@ -0,0 +1,102 @@
+mod common;
+
+use std::fs;
+
+#[test]
+fn missing_flake_lock() {
+    let dir = common::temp_dir("missing lock");
+
+    // Skipped by default
+    let output = common::flake_checker()
+        .arg("--no-telemetry")
+        .arg(dir.join("flake.lock"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+
+    // A missing flake.lock is a filesystem failure, not an issue in a flake.lock
+    let output = common::flake_checker()
+        .arg("--no-telemetry")
+        .env("NIX_FLAKE_CHECKER_IGNORE_MISSING_FLAKE_LOCK", "false")
+        .arg(dir.join("flake.lock"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no flake lockfile found at"), "{stderr}");
+
+    let output = common::flake_checker()
+        .arg("--no-telemetry")
+        .env("NIX_FLAKE_CHECKER_IGNORE_MISSING_FLAKE_LOCK", "false")
+        .arg("--flake-lock-glob")
+        .arg(format!("{}/*/flake.lock", dir.display()))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no flake lockfiles match"), "{stderr}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn existing_config_file() {
+    let dir = common::temp_dir("existing config");
+    fs::write(dir.join("flake-checker.toml"), "").unwrap();
+
+    // Overwriting the config file takes --force, so this is an invalid invocation
+    let output = common::flake_checker()
+        .arg("init")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pass --force to overwrite it"), "{stderr}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_settings() {
+    let dir = common::temp_dir("invalid settings");
+    fs::write(
+        dir.join("flake-checker.toml"),
+        "max-days = \"not a number\"\n",
+    )
+    .unwrap();
+
+    let output = common::flake_checker()
+        .arg("--no-telemetry")
+        .arg(
+            std::env::current_dir()
+                .unwrap()
+                .join("tests/flake.clean.0.lock"),
+        )
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unparsable_flake_lock() {
+    let dir = common::temp_dir("unparsable lock");
+    fs::write(dir.join("flake.lock"), "{").unwrap();
+
+    let output = common::flake_checker()
+        .arg("--no-telemetry")
+        .arg(dir.join("flake.lock"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
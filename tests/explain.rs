@@ -0,0 +1,137 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(flake_lock: &str, args: &[&str]) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--explain"])
+        .args(args)
+        .arg(flake_lock)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn explains_every_checked_input() {
+    let output = flake_checker(
+        "tests/flake.explicit-keys.0.lock",
+        &["--nixpkgs-keys", "nixpkgs,nixpkgs-alt"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains(
+        "nixpkgs:\n  Supported branches: passed\n  Upstream owner: passed\n\
+         nixpkgs-alt:\n  Supported branches: passed\n  Upstream owner: failed (seems-pretty-shady)\n"
+    ));
+}
+
+#[test]
+fn explains_only_the_input_named() {
+    // The other input's issue is neither explained nor reported
+    let output = flake_checker("tests/flake.explicit-keys.0.lock", &["--input", "nixpkgs"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("didn't identify any issues"), "{stdout}");
+    assert!(stdout.contains("nixpkgs:\n  Supported branches: passed\n  Upstream owner: passed\n"));
+    assert!(!stdout.contains("nixpkgs-alt"), "{stdout}");
+
+    let output = flake_checker(
+        "tests/flake.explicit-keys.0.lock",
+        &["--input", "nixpkgs-alt"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("discovered 1 issue"), "{stdout}");
+    assert!(stdout.contains(
+        "nixpkgs-alt:\n  Supported branches: passed\n  Upstream owner: failed (seems-pretty-shady)\n"
+    ));
+    assert!(!stdout.contains("\nnixpkgs:\n"), "{stdout}");
+}
+
+#[test]
+fn scopes_checks_of_every_node() {
+    // Issues from checks of every node in the flake.lock are limited to the input's nodes too
+    let output = flake_checker(
+        "tests/flake.insecure.0.lock",
+        &["--input", "nixpkgs", "--require-https"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("discovered 1 issue"), "{stdout}");
+    assert!(stdout.contains("nixpkgs:\n  TLS transport: failed (http://"));
+    assert!(!stdout.contains("tools"), "{stdout}");
+}
+
+#[test]
+fn explains_as_json() {
+    // The JSON breakdown replaces the summary on stdout
+    let output = flake_checker(
+        "tests/flake.mixed-channels.0.lock",
+        &["--transitive", "--explain-format", "json"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        stdout,
+        r#"[
+  {
+    "input": "devshell/nixpkgs",
+    "checks": [
+      {
+        "name": "Supported branches",
+        "passed": false,
+        "detail": "nixos-24.05"
+      },
+      {
+        "name": "Upstream owner",
+        "passed": true,
+        "detail": null
+      }
+    ]
+  },
+  {
+    "input": "home-manager/nixpkgs",
+    "checks": [
+      {
+        "name": "Supported branches",
+        "passed": true,
+        "detail": null
+      },
+      {
+        "name": "Upstream owner",
+        "passed": true,
+        "detail": null
+      }
+    ]
+  },
+  {
+    "input": "nixpkgs",
+    "checks": [
+      {
+        "name": "Supported branches",
+        "passed": false,
+        "detail": "nixos-24.05"
+      },
+      {
+        "name": "Upstream owner",
+        "passed": true,
+        "detail": null
+      }
+    ]
+  }
+]
+"#
+    );
+
+    let output = flake_checker(
+        "tests/flake.mixed-channels.0.lock",
+        &["--transitive", "--explain-format", "json", "--compact"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("[{\"input\":\"devshell/nixpkgs\",\"checks\":[{"),
+        "{stdout}"
+    );
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+#[test]
+fn lock_wide_issues_without_selected_inputs() {
+    let summary = std::env::temp_dir().join(format!(
+        "flake-checker-github-actions-log-{}.md",
+        std::process::id()
+    ));
+    let output = common::flake_checker()
+        .args([
+            "--no-telemetry",
+            "--input-name-regex",
+            "^nothing-matches$",
+            "--max-inputs",
+            "1",
+            "tests/flake.dirty.0.lock",
+        ])
+        .env("GITHUB_ACTIONS", "true")
+        .env("GITHUB_STEP_SUMMARY", &summary)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+
+    // No inputs are selected, but the too-many-inputs issue is about the lock as a whole
+    assert!(
+        stdout.contains("no inputs matched the selection"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("more than the maximum of 1"), "{stdout}");
+
+    let _ = std::fs::remove_file(&summary);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use std::fs;
+
+#[test]
+fn totals_across_flake_locks() {
+    let root = std::env::temp_dir().join(format!(
+        "flake-checker-github-output-glob-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["a", "b"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::copy(
+            "tests/flake.dirty.0.lock",
+            root.join(dir).join("flake.lock"),
+        )
+        .unwrap();
+    }
+    let github_output = root.join("github-output");
+
+    let output = common::flake_checker()
+        .args([
+            "--no-telemetry",
+            "--github-output",
+            "--flake-lock-glob",
+            "*/flake.lock",
+        ])
+        .current_dir(&root)
+        .env("GITHUB_OUTPUT", &github_output)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The outputs are written once, counting the issues and inputs of both lockfiles
+    let contents = fs::read_to_string(&github_output).unwrap();
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| line.starts_with("issue_count=") || line.starts_with("input_count="))
+        .collect();
+    assert_eq!(lines, vec!["issue_count=4", "input_count=2"], "{contents}");
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use std::fs;
+
+#[test]
+fn force_replaces_malformed_config() {
+    let dir = common::temp_dir("init");
+    fs::write(
+        dir.join("flake-checker.toml"),
+        "max-days = \"not a number\n",
+    )
+    .unwrap();
+
+    let output = common::flake_checker()
+        .args(["init", "--force"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Wrote flake-checker.toml"));
+
+    // The starter config replaced the malformed one, so checking works again
+    let config = fs::read_to_string(dir.join("flake-checker.toml")).unwrap();
+    assert!(
+        config.contains("flake-lock-path = \"flake.lock\""),
+        "{config}"
+    );
+    let output = common::flake_checker()
+        .arg("validate-config")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
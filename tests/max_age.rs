@@ -0,0 +1,55 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(args: &[&str], env: &[(&str, &str)]) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--fail-mode"])
+        .args(args)
+        .arg("tests/flake.clean.0.lock")
+        .env_remove("NIX_FLAKE_CHECKER_MAX_DAYS")
+        .env_remove("NIX_FLAKE_CHECKER_MAX_AGE")
+        .envs(env.iter().copied())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn flag_overrides_other_env_var() {
+    // The inputs are years old, so a day is too old but 100000 days isn't
+    let output = flake_checker(&[], &[("NIX_FLAKE_CHECKER_MAX_AGE", "1d")]);
+    assert!(!output.status.success());
+
+    let output = flake_checker(
+        &["--max-days", "100000"],
+        &[("NIX_FLAKE_CHECKER_MAX_AGE", "1d")],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = flake_checker(
+        &["--max-age", "100000d"],
+        &[("NIX_FLAKE_CHECKER_MAX_DAYS", "1")],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn both_env_vars_conflict() {
+    let output = flake_checker(
+        &[],
+        &[
+            ("NIX_FLAKE_CHECKER_MAX_DAYS", "1"),
+            ("NIX_FLAKE_CHECKER_MAX_AGE", "1d"),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("can't both be supplied"));
+}
@@ -0,0 +1,7 @@
+{
+  description = "A flake that was never locked";
+
+  inputs.nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+
+  outputs = { self, nixpkgs }: { };
+}
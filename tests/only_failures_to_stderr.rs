@@ -0,0 +1,59 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(flake_lock: &str) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--only-failures-to-stderr"])
+        .arg(flake_lock)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn issues_only_on_stderr() {
+    let output = flake_checker("tests/flake.dirty.0.lock");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    // stderr has a line per issue and nothing else
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2, "{stderr}");
+    assert!(
+        lines[0].starts_with("tests/flake.dirty.0.lock:")
+            && lines[0].ends_with(": warning: nixpkgs: disallowed (this-should-fail)"),
+        "{stderr}"
+    );
+    assert!(
+        lines[1].contains(": warning: nixpkgs: non-upstream ("),
+        "{stderr}"
+    );
+
+    // stdout has the summary without the issues, and the verdict
+    assert!(
+        stdout.contains("that we recommend looking into, which are listed on stderr."),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("this-should-fail"), "{stdout}");
+    assert!(!stdout.contains("bitcoin-miner-org"), "{stdout}");
+    assert!(
+        stdout.contains(
+            "flake-checker: 2 issues (1 disallowed, 1 non-upstream) in tests/flake.dirty.0.lock"
+        ),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn clean_lock_leaves_stderr_empty() {
+    let output = flake_checker("tests/flake.clean.0.lock");
+
+    assert!(output.status.success());
+    assert!(!output.stdout.is_empty());
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
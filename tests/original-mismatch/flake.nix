@@ -0,0 +1,13 @@
+{
+  description = "A flake whose lock was locked from another Nixpkgs branch";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+    fenix = {
+      url = "https://flakehub.com/f/nix-community/fenix/0.1.*";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+  };
+
+  outputs = { self, nixpkgs, fenix }: { };
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::fs;
+
+#[test]
+fn one_report_per_flake_lock() {
+    let root =
+        std::env::temp_dir().join(format!("flake-checker-output-dir-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (dir, fixture) in [("a", "flake.clean.0.lock"), ("b", "flake.dirty.0.lock")] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::copy(
+            format!("tests/{fixture}"),
+            root.join(dir).join("flake.lock"),
+        )
+        .unwrap();
+    }
+
+    let output = common::flake_checker()
+        .args([
+            "--no-telemetry",
+            "--flake-lock-glob",
+            "*/flake.lock",
+            "--output-format",
+            "json",
+            "--output-dir",
+            "reports",
+        ])
+        .current_dir(&root)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The reports mirror the flake.locks' paths
+    let report = |path: &str| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(root.join("reports").join(path)).unwrap()).unwrap()
+    };
+    assert_eq!(report("a/flake.lock.json")["issues"], serde_json::json!([]));
+    assert!(!report("b/flake.lock.json")["issues"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+
+#[test]
+fn proxy_from_environment() {
+    // A proxy that records the request line it receives and responds itself
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        tx.send(request_line).unwrap();
+
+        let body =
+            r#"{"data":{"result":[{"metric":{"channel":"nixos-unstable","status":"rolling"}}]}}"#;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+    });
+
+    // Without --proxy, HTTP_PROXY is honored
+    let output = common::flake_checker()
+        .args([
+            "refs",
+            "fetch",
+            "--ref-status-url",
+            "http://prometheus.invalid",
+        ])
+        .env("HTTP_PROXY", &proxy)
+        .env_remove("http_proxy")
+        .env_remove("ALL_PROXY")
+        .env_remove("all_proxy")
+        .env_remove("NO_PROXY")
+        .env_remove("no_proxy")
+        .env_remove("NIX_FLAKE_CHECKER_PROXY")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stdout}{stderr}");
+    assert!(
+        rx.recv()
+            .unwrap()
+            .starts_with("GET http://prometheus.invalid/api/v1/query?"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(r#""nixos-unstable": "rolling""#),
+        "{stdout}"
+    );
+}
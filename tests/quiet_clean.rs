@@ -0,0 +1,37 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(flake_lock: &str) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--quiet-clean"])
+        .arg(flake_lock)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn silent_on_clean_lock() {
+    let output = flake_checker("tests/flake.clean.0.lock");
+
+    assert!(output.status.success());
+    assert!(
+        output.stdout.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn output_on_dirty_lock() {
+    let output = flake_checker("tests/flake.dirty.0.lock");
+
+    assert!(!output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
+}
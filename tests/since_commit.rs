@@ -0,0 +1,111 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::{git, temp_dir};
+
+fn flake_checker(flake_lock: &Path) -> Output {
+    flake_checker_since(flake_lock, "HEAD")
+}
+
+fn flake_checker_since(flake_lock: &Path, rev: &str) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--fail-mode", "--since-commit", rev])
+        .arg(flake_lock)
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn only_changed_inputs_fail() {
+    let dir = temp_dir("repo");
+    let flake_lock = dir.join("flake.lock");
+    git(&dir, &["init", "--quiet"]);
+    fs::copy("tests/flake.dirty.0.lock", &flake_lock).unwrap();
+    git(&dir, &["add", "flake.lock"]);
+    git(&dir, &["commit", "--quiet", "-m", "Lock"]);
+
+    // The issues were there at HEAD already
+    let output = flake_checker(&flake_lock);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Relocking Nixpkgs to another disallowed branch is a change that fails
+    fs::copy("tests/flake.dirty.1.lock", &flake_lock).unwrap();
+    let output = flake_checker(&flake_lock);
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn outside_a_repo() {
+    let dir = temp_dir("plain");
+    let flake_lock = dir.join("flake.lock");
+    fs::copy("tests/flake.dirty.0.lock", &flake_lock).unwrap();
+
+    // Everything is checked, with a warning
+    let output = flake_checker(&flake_lock);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't in a Git repository"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn shallow_clone() {
+    let dir = temp_dir("origin");
+    git(&dir, &["init", "--quiet"]);
+    fs::copy("tests/flake.dirty.0.lock", dir.join("flake.lock")).unwrap();
+    git(&dir, &["add", "flake.lock"]);
+    git(&dir, &["commit", "--quiet", "-m", "Lock"]);
+    fs::copy("tests/flake.dirty.1.lock", dir.join("flake.lock")).unwrap();
+    git(&dir, &["commit", "--quiet", "-am", "Relock"]);
+
+    // Like a CI checkout, the clone only has the latest commit
+    let clone = temp_dir("clone");
+    git(
+        &dir,
+        &[
+            "clone",
+            "--quiet",
+            "--depth=1",
+            "--no-local",
+            ".",
+            clone.to_str().unwrap(),
+        ],
+    );
+    let flake_lock = clone.join("flake.lock");
+
+    // The base revision exists in the full history but not in the clone
+    assert_eq!(
+        flake_checker_since(&dir.join("flake.lock"), "HEAD~1")
+            .status
+            .code(),
+        Some(1)
+    );
+    let output = flake_checker_since(&flake_lock, "HEAD~1");
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown revision `HEAD~1` in a shallow clone"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("git fetch --unshallow"), "{stderr}");
+
+    // Once the clone is deepened, the revision is found, and relocking Nixpkgs fails as usual
+    git(&clone, &["fetch", "--quiet", "--unshallow"]);
+    assert_eq!(
+        flake_checker_since(&flake_lock, "HEAD~1").status.code(),
+        Some(1)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&clone).unwrap();
+}
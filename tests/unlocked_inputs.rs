@@ -0,0 +1,33 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(args: &[&str]) -> Output {
+    common::flake_checker()
+        .arg("--no-telemetry")
+        .args(args)
+        .arg("tests/unlocked/flake.lock")
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn unchecked_by_default() {
+    let output = flake_checker(&[]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("home-manager"), "{stdout}");
+}
+
+#[test]
+fn checked_when_enabled() {
+    let output = flake_checker(&["--check-unlocked-inputs"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("The home-manager input is declared in flake.nix but isn't in flake.lock"),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,38 @@
+mod common;
+
+#[test]
+fn unparsable_node_is_reported() {
+    // The crane node's inputs are malformed, which leaves out rust-overlay's inputs that follow
+    // crane's, but the rest of the flake.lock is still checked
+    let output = common::flake_checker()
+        .args([
+            "--no-telemetry",
+            "--fail-mode",
+            "--transitive",
+            "--output-format",
+            "json",
+            "tests/flake.unparsable-node.0.lock",
+        ])
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{stdout}");
+
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let issues = report["issues"].as_array().unwrap();
+    assert_eq!(issues.len(), 1, "{stdout}");
+    assert_eq!(issues[0]["input"], "crane");
+    assert!(
+        issues[0]["kind"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("untagged enum Input"),
+        "{stdout}"
+    );
+    assert_eq!(report["total_inputs"], 6, "{stdout}");
+    assert_eq!(
+        report["matched_refs"],
+        serde_json::json!(["nixos-unstable"])
+    );
+}
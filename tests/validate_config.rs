@@ -0,0 +1,148 @@
+mod common;
+
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+// Run `flake-checker validate-config` with the given extra args in `dir`.
+fn validate_config(dir: &Path, args: &[&str]) -> Output {
+    common::flake_checker()
+        .arg("validate-config")
+        .args(args)
+        .current_dir(dir)
+        .env_remove("NIX_FLAKE_CHECKER_CONFIG_PROFILE")
+        .output()
+        .unwrap()
+}
+
+// A scratch directory with a flake-checker.toml and the other given files in it.
+fn dir_with(name: &str, config: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "flake-checker-validate-config-{name}-{}",
+        std::process::id()
+    ));
+    create_dir_all(&dir).unwrap();
+    write(dir.join("flake-checker.toml"), config).unwrap();
+    for (file, contents) in files {
+        write(dir.join(file), contents).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn valid_config() {
+    let dir = dir_with(
+        "valid",
+        "fail-mode = true\ntiers-file = \"tiers.toml\"\n\n[profile.strict]\nmax-days = 7\n",
+        &[("tiers.toml", "[tiers.critical]\ninputs = [\"nixpkgs\"]\n")],
+    );
+
+    for args in [&[][..], &["--config-profile", "strict"]] {
+        let output = validate_config(&dir, args);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{stdout}");
+        assert!(stdout.contains("ok: tiers file tiers.toml\n"), "{stdout}");
+        assert!(stdout.ends_with("\nThe config is valid.\n"), "{stdout}");
+    }
+    let output = validate_config(&dir, &["--config-profile", "strict"]);
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .starts_with("ok: settings from flake-checker.toml (profile \"strict\")\n"));
+
+    remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn malformed_configs() {
+    type Files = &'static [(&'static str, &'static str)];
+    let cases: &[(&str, &str, Files, &str)] = &[
+        // An unknown setting
+        (
+            "unknown-key",
+            "fail-moed = true\n",
+            &[],
+            "unknown setting \"fail-moed\"",
+        ),
+        // A setting of the wrong type
+        (
+            "wrong-type",
+            "max-inputs = [\"many\"]\n",
+            &[],
+            "invalid value",
+        ),
+        // A profile that doesn't exist
+        (
+            "missing-profile",
+            "[profile.lax]\n",
+            &[],
+            "there's no profile",
+        ),
+        // Settings that can't be used together
+        (
+            "conflict",
+            "output = \"report.json\"\noutput-dir = \"reports\"\n",
+            &[],
+            "\"output\" conflicts with \"output-dir\"",
+        ),
+        // A file named by the settings that's malformed
+        (
+            "tiers-file",
+            "tiers-file = \"tiers.toml\"\n",
+            &[("tiers.toml", "[tiers.critical]\nmax-days = 14\n")],
+            "invalid tiers file tiers.toml",
+        ),
+        // A file named by the settings that doesn't exist
+        (
+            "missing-file",
+            "channels-file = \"channels.json\"\n",
+            &[],
+            "channels.json",
+        ),
+    ];
+
+    for (name, config, files, problem) in cases {
+        let dir = dir_with(name, config, files);
+        let args: &[&str] = if *name == "missing-profile" {
+            &["--config-profile", "strict"]
+        } else {
+            &[]
+        };
+        let output = validate_config(&dir, args);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{name}: {stdout}{stderr}");
+        // Every problem is reported in validate-config's own output rather than as an error
+        assert!(stdout.contains(problem), "{name}: {stdout}{stderr}");
+        assert!(
+            stdout.ends_with("\nFound 1 problem in the config.\n"),
+            "{name}: {stdout}"
+        );
+        assert!(stderr.is_empty(), "{name}: {stderr}");
+
+        remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn help_and_version_with_malformed_config() {
+    let dir = dir_with("help", "fail-mode = [\n", &[]);
+
+    for flag in ["--help", "--version"] {
+        let output = common::flake_checker()
+            .arg(flag)
+            .current_dir(&dir)
+            .env_remove("NIX_FLAKE_CHECKER_CONFIG_PROFILE")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{flag}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    // Anything else reports the malformed config
+    let output = validate_config(&dir, &[]);
+    assert_eq!(output.status.code(), Some(2));
+
+    remove_dir_all(dir).unwrap();
+}
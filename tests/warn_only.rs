@@ -0,0 +1,51 @@
+mod common;
+
+use std::process::Output;
+
+fn flake_checker(args: &[&str]) -> Output {
+    common::flake_checker()
+        .args(["--no-telemetry", "--fail-mode"])
+        .args(args)
+        .arg("tests/flake.dirty.0.lock")
+        .env("NIX_FLAKE_CHECKER_CHECK_OUTDATED", "false")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn issues_dont_fail() {
+    assert!(!flake_checker(&[]).status.success());
+
+    let output = flake_checker(&["--warn-only", "--fail-on-warnings"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // The issues are still reported
+    assert!(String::from_utf8_lossy(&output.stdout).contains(">>> Non-supported Git branches"));
+}
+
+#[test]
+fn warnings_dont_fail() {
+    // With --fail-on-warnings alone, warnings fail
+    let severity_map = "disallowed=warning,non-upstream=warning";
+    assert!(flake_checker(&["--severity-map", severity_map])
+        .status
+        .success());
+    let output = flake_checker(&["--fail-on-warnings", "--severity-map", severity_map]);
+    assert!(!output.status.success());
+
+    // --warn-only keeps them warnings
+    let output = flake_checker(&[
+        "--warn-only",
+        "--fail-on-warnings",
+        "--severity-map",
+        severity_map,
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}